@@ -28,7 +28,8 @@ use fugit::RateExtU32;
 use hal::clocks::Clock;
 use hal::pac;
 
-use esp32_wroom_rp::network::IpAddress;
+use core::net::Ipv4Addr;
+
 use esp32_wroom_rp::wifi::ConnectionStatus;
 
 /// The linker will place this boot block at the start of our program image. We
@@ -67,7 +68,11 @@ fn main() -> ! {
     .ok()
     .unwrap();
 
-    let mut delay = cortex_m::delay::Delay::new(core.SYST, clocks.system_clock.freq().to_Hz());
+    let sys_freq_hz = clocks.system_clock.freq().to_Hz();
+    let delay = cortex_m::delay::Delay::new(core.SYST, sys_freq_hz);
+
+    // The driver owns the SysTick delay from here on, so the main loop busy-waits on its own
+    let wait_ms = |ms: u32| cortex_m::asm::delay(ms * (sys_freq_hz / 1_000));
 
     // The single-cycle I/O block controls our GPIO pins
     let sio = hal::Sio::new(pac.SIO);
@@ -97,18 +102,15 @@ fn main() -> ! {
         &MODE_0,
     );
 
-    let esp_pins = esp32_wroom_rp::gpio::EspControlPins {
-        // CS on pin x (GPIO7)
-        cs: pins.gpio7.into_mode::<hal::gpio::PushPullOutput>(),
-        // GPIO0 on pin x (GPIO2)
-        gpio0: pins.gpio2.into_mode::<hal::gpio::PushPullOutput>(),
-        // RESETn on pin x (GPIO11)
-        resetn: pins.gpio11.into_mode::<hal::gpio::PushPullOutput>(),
-        // ACK on pin x (GPIO10)
-        ack: pins.gpio10.into_mode::<hal::gpio::FloatingInput>(),
-    };
+    // CS on GPIO7, GPIO0 on GPIO2, RESETn on GPIO11 and ACK on GPIO10
+    let esp_pins = esp32_wroom_rp::pins::default_esp_control_pins(
+        pins.gpio7,
+        pins.gpio2,
+        pins.gpio11,
+        pins.gpio10,
+    );
 
-    let mut wifi = esp32_wroom_rp::wifi::Wifi::init(spi, esp_pins, &mut delay).unwrap();
+    let mut wifi = esp32_wroom_rp::wifi::Wifi::init(spi, esp_pins, delay).unwrap();
 
     let result = wifi.join(SSID, PASSPHRASE);
     defmt::info!("Join Result: {:?}", result);
@@ -121,15 +123,15 @@ fn main() -> ! {
             Ok(status) => {
                 defmt::info!("Get Connection Result: {:?}", status);
 
-                delay.delay_ms(sleep);
+                wait_ms(sleep);
 
                 if status == ConnectionStatus::Connected {
                     defmt::info!("Connected to Network: {:?}", SSID);
 
                     // The IPAddresses of two DNS servers to resolve hostnames with.
                     // Note that failover from ip1 to ip2 is fully functional.
-                    let ip1: IpAddress = [9, 9, 9, 9];
-                    let ip2: IpAddress = [8, 8, 8, 8];
+                    let ip1 = Ipv4Addr::new(9, 9, 9, 9);
+                    let ip2 = Ipv4Addr::new(8, 8, 8, 8);
                     let dns_result = wifi.set_dns(ip1, Some(ip2));
 
                     defmt::info!("set_dns result: {:?}", dns_result);
@@ -24,7 +24,6 @@ use fugit::RateExtU32;
 use hal::clocks::Clock;
 use hal::pac;
 
-use esp32_wroom_rp::gpio::EspControlPins;
 use esp32_wroom_rp::wifi::Wifi;
 
 /// The linker will place this boot block at the start of our program image. We
@@ -63,7 +62,7 @@ fn main() -> ! {
     .ok()
     .unwrap();
 
-    let mut delay = cortex_m::delay::Delay::new(core.SYST, clocks.system_clock.freq().to_Hz());
+    let delay = cortex_m::delay::Delay::new(core.SYST, clocks.system_clock.freq().to_Hz());
 
     // The single-cycle I/O block controls our GPIO pins
     let sio = hal::Sio::new(pac.SIO);
@@ -93,17 +92,14 @@ fn main() -> ! {
         &MODE_0,
     );
 
-    let esp_pins = EspControlPins {
-        // CS on pin x (GPIO7)
-        cs: pins.gpio7.into_mode::<hal::gpio::PushPullOutput>(),
-        // GPIO0 on pin x (GPIO2)
-        gpio0: pins.gpio2.into_mode::<hal::gpio::PushPullOutput>(),
-        // RESETn on pin x (GPIO11)
-        resetn: pins.gpio11.into_mode::<hal::gpio::PushPullOutput>(),
-        // ACK on pin x (GPIO10)
-        ack: pins.gpio10.into_mode::<hal::gpio::FloatingInput>(),
-    };
-    let mut wifi = Wifi::init(spi, esp_pins, &mut delay).unwrap();
+    // CS on GPIO7, GPIO0 on GPIO2, RESETn on GPIO11 and ACK on GPIO10
+    let esp_pins = esp32_wroom_rp::pins::default_esp_control_pins(
+        pins.gpio7,
+        pins.gpio2,
+        pins.gpio11,
+        pins.gpio10,
+    );
+    let mut wifi = Wifi::init(spi, esp_pins, delay).unwrap();
     let firmware_version = wifi.firmware_version();
     defmt::info!("NINA firmware version: {:?}", firmware_version);
 
@@ -66,7 +66,11 @@ fn main() -> ! {
     .ok()
     .unwrap();
 
-    let mut delay = cortex_m::delay::Delay::new(core.SYST, clocks.system_clock.freq().to_Hz());
+    let sys_freq_hz = clocks.system_clock.freq().to_Hz();
+    let delay = cortex_m::delay::Delay::new(core.SYST, sys_freq_hz);
+
+    // The driver owns the SysTick delay from here on, so the main loop busy-waits on its own
+    let wait_ms = |ms: u32| cortex_m::asm::delay(ms * (sys_freq_hz / 1_000));
 
     // The single-cycle I/O block controls our GPIO pins
     let sio = hal::Sio::new(pac.SIO);
@@ -96,18 +100,15 @@ fn main() -> ! {
         &MODE_0,
     );
 
-    let esp_pins = esp32_wroom_rp::gpio::EspControlPins {
-        // CS on pin x (GPIO7)
-        cs: pins.gpio7.into_mode::<hal::gpio::PushPullOutput>(),
-        // GPIO0 on pin x (GPIO2)
-        gpio0: pins.gpio2.into_mode::<hal::gpio::PushPullOutput>(),
-        // RESETn on pin x (GPIO11)
-        resetn: pins.gpio11.into_mode::<hal::gpio::PushPullOutput>(),
-        // ACK on pin x (GPIO10)
-        ack: pins.gpio10.into_mode::<hal::gpio::FloatingInput>(),
-    };
+    // CS on GPIO7, GPIO0 on GPIO2, RESETn on GPIO11 and ACK on GPIO10
+    let esp_pins = esp32_wroom_rp::pins::default_esp_control_pins(
+        pins.gpio7,
+        pins.gpio2,
+        pins.gpio11,
+        pins.gpio10,
+    );
 
-    let mut wifi = esp32_wroom_rp::wifi::Wifi::init(spi, esp_pins, &mut delay).unwrap();
+    let mut wifi = esp32_wroom_rp::wifi::Wifi::init(spi, esp_pins, delay).unwrap();
 
     let result = wifi.join(SSID, PASSPHRASE);
     defmt::info!("Join Result: {:?}", result);
@@ -120,13 +121,13 @@ fn main() -> ! {
             Ok(status) => {
                 defmt::info!("Get Connection Result: {:?}", status);
 
-                delay.delay_ms(sleep);
+                wait_ms(sleep);
 
                 if status == ConnectionStatus::Connected {
                     defmt::info!("Connected to Network: {:?}", SSID);
 
                     defmt::info!("Sleeping for 5 seconds before disconnecting...");
-                    delay.delay_ms(5000);
+                    wait_ms(5000);
 
                     wifi.leave().ok().unwrap();
                 } else if status == ConnectionStatus::Disconnected {
@@ -0,0 +1,119 @@
+//! # ESP32-WROOM-RP Pico Wireless Pack Example
+//!
+//! This application demonstrates how to use the ESP32-WROOM-RP crate's preset for the
+//! Pimoroni Pico Wireless Pack to light its RGB LED while its button is held.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use cortex_m_rt::entry;
+
+// Needed for debug output symbols to be linked in binary image
+use defmt_rtt as _;
+
+use panic_probe as _;
+
+// Alias for our HAL crate
+use rp2040_hal as hal;
+
+use embedded_hal::digital::v2::InputPin;
+use embedded_hal::spi::MODE_0;
+use fugit::RateExtU32;
+use hal::clocks::Clock;
+use hal::pac;
+
+use esp32_wroom_rp::boards::pico_wireless_pack;
+use esp32_wroom_rp::esp_gpio::{RgbLed, RgbLedPins};
+
+/// The linker will place this boot block at the start of our program image. We
+/// need this to help the ROM bootloader get our code up and running.
+#[link_section = ".boot2"]
+#[used]
+pub static BOOT2: [u8; 256] = rp2040_boot2::BOOT_LOADER_W25Q080;
+
+/// External high-speed crystal on the Raspberry Pi Pico board is 12 MHz. Adjust
+/// if your board has a different frequency
+const XTAL_FREQ_HZ: u32 = 12_000_000u32;
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialized.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+    let core = pac::CorePeripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        XTAL_FREQ_HZ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    let delay = cortex_m::delay::Delay::new(core.SYST, clocks.system_clock.freq().to_Hz());
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins to their default state
+    let pins = hal::gpio::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    defmt::info!("ESP32-WROOM-RP Pico Wireless Pack example");
+
+    // These are implicitly used by the spi driver if they are in the correct mode
+    let _spi_pins = pico_wireless_pack::spi_pins(pins.gpio16, pins.gpio19, pins.gpio18);
+
+    // Keep the SD card off the bus shared with the ESP32
+    let _sd_card_cs = pico_wireless_pack::sd_card_cs(pins.gpio22);
+
+    let button = pico_wireless_pack::button(pins.gpio12);
+
+    let spi = hal::Spi::<_, _, 8>::new(pac.SPI0);
+
+    // Exchange the uninitialized SPI driver for an initialized one
+    let spi = spi.init(
+        &mut pac.RESETS,
+        clocks.peripheral_clock.freq(),
+        8.MHz(),
+        &MODE_0,
+    );
+
+    let esp_pins =
+        pico_wireless_pack::esp_control_pins(pins.gpio7, pins.gpio2, pins.gpio11, pins.gpio10);
+    let mut wifi = pico_wireless_pack::init(spi, esp_pins, delay).unwrap();
+
+    let mut rgb_led = RgbLed::new(&mut wifi, RgbLedPins::pico_wireless_pack()).unwrap();
+
+    defmt::info!("Entering main loop, hold button A to light the LED");
+    let mut pressed = false;
+    loop {
+        let is_pressed = button.is_low().unwrap();
+        if is_pressed != pressed {
+            pressed = is_pressed;
+            if pressed {
+                rgb_led.set_color(0, 128, 255).unwrap();
+            } else {
+                rgb_led.off().unwrap();
+            }
+        }
+    }
+}
@@ -27,15 +27,16 @@ use embedded_hal::spi::MODE_0;
 
 use core::fmt::Write;
 
+use core::net::Ipv4Addr;
+
 use fugit::RateExtU32;
-use hal::gpio::{FloatingInput, PushPullOutput};
 use hal::{clocks::Clock, pac};
 
 use heapless::String;
 
 use esp32_wroom_rp::{
-    gpio::EspControlPins, network::IpAddress, network::Port, network::TransportMode,
-    tcp_client::Connect, tcp_client::TcpClient, wifi::ConnectionStatus, wifi::Wifi,
+    network::Port, network::TransportMode, tcp_client::Connect, tcp_client::TcpClient,
+    wifi::ConnectionStatus, wifi::Wifi,
 };
 
 const MAX_HTTP_DOC_LENGTH: usize = 4096 as usize;
@@ -76,7 +77,11 @@ fn main() -> ! {
     .ok()
     .unwrap();
 
-    let mut delay = cortex_m::delay::Delay::new(core.SYST, clocks.system_clock.freq().to_Hz());
+    let sys_freq_hz = clocks.system_clock.freq().to_Hz();
+    let delay = cortex_m::delay::Delay::new(core.SYST, sys_freq_hz);
+
+    // The driver owns the SysTick delay from here on, so the main loop busy-waits on its own
+    let wait_ms = |ms: u32| cortex_m::asm::delay(ms * (sys_freq_hz / 1_000));
 
     // The single-cycle I/O block controls our GPIO pins
     let sio = hal::Sio::new(pac.SIO);
@@ -106,18 +111,15 @@ fn main() -> ! {
         &MODE_0,
     );
 
-    let esp_pins = EspControlPins {
-        // CS on pin x (GPIO7)
-        cs: pins.gpio7.into_mode::<PushPullOutput>(),
-        // GPIO0 on pin x (GPIO2)
-        gpio0: pins.gpio2.into_mode::<PushPullOutput>(),
-        // RESETn on pin x (GPIO11)
-        resetn: pins.gpio11.into_mode::<PushPullOutput>(),
-        // ACK on pin x (GPIO10)
-        ack: pins.gpio10.into_mode::<FloatingInput>(),
-    };
+    // CS on GPIO7, GPIO0 on GPIO2, RESETn on GPIO11 and ACK on GPIO10
+    let esp_pins = esp32_wroom_rp::pins::default_esp_control_pins(
+        pins.gpio7,
+        pins.gpio2,
+        pins.gpio11,
+        pins.gpio10,
+    );
 
-    let mut wifi = Wifi::init(spi, esp_pins, &mut delay).unwrap();
+    let mut wifi = Wifi::init(spi, esp_pins, delay).unwrap();
 
     let result = wifi.join(SSID, PASSPHRASE);
     defmt::info!("Join Result: {:?}", result);
@@ -129,21 +131,21 @@ fn main() -> ! {
         match wifi.get_connection_status() {
             Ok(status) => {
                 defmt::info!("Connection status: {:?}", status);
-                delay.delay_ms(sleep);
+                wait_ms(sleep);
 
                 if status == ConnectionStatus::Connected {
                     defmt::info!("Connected to network: {:?}", SSID);
 
                     // The IPAddresses of two DNS servers to resolve hostnames with.
                     // Note that failover from ip1 to ip2 is fully functional.
-                    let ip1: IpAddress = [9, 9, 9, 9];
-                    let ip2: IpAddress = [8, 8, 8, 8];
+                    let ip1 = Ipv4Addr::new(9, 9, 9, 9);
+                    let ip2 = Ipv4Addr::new(8, 8, 8, 8);
                     let dns_result = wifi.set_dns(ip1, Some(ip2));
 
                     defmt::info!("set_dns result: {:?}", dns_result);
 
                     let hostname = "github.com";
-                    // let ip_address: IpAddress = [140, 82, 114, 3]; // github.com
+                    // let ip_address = Ipv4Addr::new(140, 82, 114, 3); // github.com
 
                     let port: Port = 80;
                     let mode: TransportMode = TransportMode::Tcp;
@@ -169,12 +171,11 @@ fn main() -> ! {
                         hostname,
                         port,
                         mode,
-                        &mut delay,
                         &mut |tcp_client| {
                             defmt::info!("TCP connection to {:?}:{:?} successful", hostname, port);
                             defmt::info!("Hostname: {:?}", tcp_client.server_hostname());
                             defmt::info!("Sending HTTP Document: {:?}", http_document.as_str());
-                            match tcp_client.send_data(&http_document) {
+                            match tcp_client.send_str(&http_document) {
                                 Ok(response) => {
                                     defmt::info!("Response: {:?}", response)
                                 }
@@ -192,7 +193,7 @@ fn main() -> ! {
                         );
                     }
 
-                    delay.delay_ms(100);
+                    wait_ms(100);
 
                     defmt::info!("Leaving network: {:?}", SSID);
                     wifi.leave().ok();
@@ -0,0 +1,71 @@
+//! Signal socket data activity, e.g. to blink a network status LED.
+//!
+//! An [`ActivityIndicator`] is invoked by the driver every time socket data is sent to or
+//! received from the ESP32 target, so that applications don't need to instrument every call
+//! site themselves.
+//!
+//! ## Usage
+//!
+//! ```no_run
+//! use esp32_wroom_core::activity::{Activity, ActivityIndicator};
+//!
+//! struct NetworkLed;
+//!
+//! impl ActivityIndicator for NetworkLed {
+//!     fn on_activity(&self, _activity: Activity) {
+//!         led::toggle();
+//!     }
+//! }
+//!
+//! wifi.set_activity_indicator(&NetworkLed);
+//! ```
+//!
+
+use core::fmt;
+
+#[cfg(feature = "defmt")]
+use defmt::{write, Format, Formatter};
+
+/// The direction of socket data activity reported to an [`ActivityIndicator`].
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "ufmt", derive(ufmt::derive::uDebug))]
+#[non_exhaustive]
+pub enum Activity {
+    /// Socket data was sent to the ESP32 target
+    Transmit,
+    /// Socket data was received from the ESP32 target
+    Receive,
+}
+
+#[cfg(feature = "defmt")]
+impl Format for Activity {
+    fn format(&self, fmt: Formatter) {
+        match self {
+            Activity::Transmit => write!(fmt, "Transmit"),
+            Activity::Receive => write!(fmt, "Receive"),
+        }
+    }
+}
+
+/// Gets notified about socket data activity.
+pub trait ActivityIndicator {
+    /// Called after socket data has been sent or received. Keep this short, as it runs
+    /// in between commands sent to the ESP32 target.
+    fn on_activity(&self, activity: Activity);
+}
+
+// Wraps an ActivityIndicator implementation so that types holding one can still derive Debug.
+#[derive(Clone, Copy)]
+pub(crate) struct ActivityHook(pub(crate) &'static dyn ActivityIndicator);
+
+impl ActivityHook {
+    pub(crate) fn on_activity(&self, activity: Activity) {
+        self.0.on_activity(activity);
+    }
+}
+
+impl fmt::Debug for ActivityHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ActivityHook")
+    }
+}
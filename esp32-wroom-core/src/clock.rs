@@ -0,0 +1,179 @@
+//! Provide the driver with the current time for timeouts and features that expire state.
+//!
+//! The driver has no timer of its own. Features that need to know how much time has passed,
+//! such as the DNS cache enabled with [`Wifi::enable_dns_cache`], read it from a [`Clock`]
+//! implemented by the application on top of e.g. the RP2040's timer peripheral.
+//!
+//! Timeouts and retries, such as waiting for a reply from the NINA firmware or for data to
+//! arrive on a [`TcpClient`], are measured with the [`Clock`] set with [`Wifi::set_clock`].
+//! Without one, they're estimated by adding up the time the driver spends in its own delays,
+//! which doesn't account for the time commands take on the bus.
+//!
+//! Times are [fugit](https://docs.rs/fugit) based [`Instant`]s and [`Duration`]s with a
+//! resolution of one millisecond.
+//!
+//! ## Usage
+//!
+//! ```no_run
+//! use esp32_wroom_core::clock::Clock;
+//! use esp32_wroom_core::dns::DnsCacheConfig;
+//!
+//! struct Rp2040Clock;
+//!
+//! impl Clock for Rp2040Clock {
+//!     fn now_ms(&self) -> u32 {
+//!         (timer::get_counter().ticks() / 1_000) as u32
+//!     }
+//! }
+//!
+//! wifi.set_clock(&Rp2040Clock);
+//! wifi.enable_dns_cache(&Rp2040Clock, DnsCacheConfig::new());
+//! ```
+//!
+//! [`Wifi::enable_dns_cache`]: crate::wifi::Wifi::enable_dns_cache
+//! [`Wifi::set_clock`]: crate::wifi::Wifi::set_clock
+//! [`TcpClient`]: crate::tcp_client::TcpClient
+//!
+
+use core::fmt;
+
+/// A point in time read from a [`Clock`], in milliseconds from an arbitrary starting point.
+pub type Instant = fugit::TimerInstantU32<1_000>;
+
+/// A span of time with a resolution of one millisecond, e.g. a timeout.
+pub type Duration = fugit::MillisDurationU32;
+
+/// A monotonic millisecond clock.
+pub trait Clock {
+    /// The current time in milliseconds from an arbitrary starting point. The value may wrap
+    /// around once it reaches `u32::MAX`.
+    fn now_ms(&self) -> u32;
+
+    /// The current time as an [`Instant`].
+    fn now(&self) -> Instant {
+        Instant::from_ticks(self.now_ms())
+    }
+}
+
+// Wraps a Clock implementation so that types holding one can still derive Debug.
+#[derive(Clone, Copy)]
+pub(crate) struct ClockHook(pub(crate) &'static dyn Clock);
+
+impl ClockHook {
+    pub(crate) fn now_ms(&self) -> u32 {
+        self.0.now_ms()
+    }
+
+    pub(crate) fn now(&self) -> Instant {
+        self.0.now()
+    }
+}
+
+impl fmt::Debug for ClockHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ClockHook")
+    }
+}
+
+// Tracks whether a timeout has passed since it was started. A timeout of None never passes.
+//
+// With a clock the time is measured, otherwise the deadline adds up the waits reported through
+// wait() and assumes nothing else takes any time.
+#[derive(Debug)]
+pub(crate) struct Deadline {
+    clock: Option<ClockHook>,
+    timeout: Option<Duration>,
+    started: Instant,
+    waited: Duration,
+}
+
+impl Deadline {
+    pub(crate) fn new(clock: Option<ClockHook>, timeout: Option<Duration>) -> Self {
+        Self {
+            clock,
+            timeout,
+            started: clock.map_or(Instant::from_ticks(0), |clock| clock.now()),
+            waited: Duration::from_ticks(0),
+        }
+    }
+
+    // Starts the timeout over, e.g. once progress has been made.
+    pub(crate) fn restart(&mut self) {
+        *self = Self::new(self.clock, self.timeout);
+    }
+
+    // Records that the driver waited for `duration`, which is what passes for time without a
+    // clock.
+    pub(crate) fn wait(&mut self, duration: Duration) {
+        self.waited = self
+            .waited
+            .checked_add(duration)
+            .unwrap_or(Duration::from_ticks(u32::MAX));
+    }
+
+    pub(crate) fn elapsed(&self) -> Duration {
+        match self.clock {
+            Some(clock) => clock
+                .now()
+                .checked_duration_since(self.started)
+                .unwrap_or(Duration::from_ticks(0)),
+            None => self.waited,
+        }
+    }
+
+    pub(crate) fn has_expired(&self) -> bool {
+        self.timeout
+            .is_some_and(|timeout| self.elapsed() >= timeout)
+    }
+}
+
+#[cfg(test)]
+mod clock_tests {
+    use super::*;
+
+    use core::sync::atomic::{AtomicU32, Ordering};
+
+    struct TestClock(AtomicU32);
+
+    impl Clock for TestClock {
+        fn now_ms(&self) -> u32 {
+            self.0.load(Ordering::Relaxed)
+        }
+    }
+
+    #[test]
+    fn deadline_without_a_clock_adds_up_waits() {
+        let mut deadline = Deadline::new(None, Some(Duration::millis(100)));
+
+        deadline.wait(Duration::millis(50));
+        assert!(!deadline.has_expired());
+
+        deadline.wait(Duration::millis(50));
+        assert!(deadline.has_expired());
+
+        deadline.restart();
+        assert!(!deadline.has_expired());
+    }
+
+    #[test]
+    fn deadline_with_a_clock_measures_time_across_a_wrap_around() {
+        static CLOCK: TestClock = TestClock(AtomicU32::new(u32::MAX - 10));
+        let mut deadline = Deadline::new(Some(ClockHook(&CLOCK)), Some(Duration::millis(100)));
+
+        // Waits don't count once time is measured
+        deadline.wait(Duration::millis(1_000));
+        assert!(!deadline.has_expired());
+
+        CLOCK.0.store(89, Ordering::Relaxed);
+        assert_eq!(deadline.elapsed(), Duration::millis(100));
+        assert!(deadline.has_expired());
+    }
+
+    #[test]
+    fn deadline_without_a_timeout_never_expires() {
+        let mut deadline = Deadline::new(None, None);
+        deadline.wait(Duration::millis(u32::MAX));
+
+        assert!(!deadline.has_expired());
+    }
+}
@@ -0,0 +1,603 @@
+//! Exchange requests and responses with a CoAP server over UDP.
+//!
+//! CoAP ([RFC 7252](https://www.rfc-editor.org/rfc/rfc7252)) is a lightweight alternative to
+//! HTTP for constrained devices, e.g. in LwM2M-adjacent deployments. A [`CoapClient`] sends
+//! every request as a confirmable message, retransmitting it with an exponentially growing
+//! timeout until the server acknowledges it. Both piggybacked and separate responses are
+//! supported.
+//!
+//! Responses larger than a single datagram are fetched block by block
+//! ([RFC 7959](https://www.rfc-editor.org/rfc/rfc7959) Block2) and passed on one block at a
+//! time, so they can be processed with constant memory. Request payloads have to fit into a
+//! single message.
+//!
+//! ## Usage
+//!
+//! ```no_run
+//! use esp32_wroom_core::coap::{CoapClient, CoapMethod, COAP_PORT};
+//!
+//! let server = Ipv4Addr::new(192, 168, 1, 10);
+//! let mut coap_client = CoapClient::build(&mut wifi).message_id(random_u16);
+//!
+//! match coap_client.request(CoapMethod::Get, server, COAP_PORT, "sensors/temp", &[], |block| {
+//!     defmt::info!("Received: {=[u8]:a}", block);
+//! }) {
+//!     Ok(code) => defmt::info!("Response code: {:?}", code),
+//!     Err(e) => defmt::error!("CoAP request failed: {:?}", e),
+//! }
+//!
+//! coap_client.close().ok();
+//! ```
+//!
+
+use core::net::{Ipv4Addr, SocketAddrV4};
+
+#[cfg(feature = "defmt")]
+use defmt::{write, Format, Formatter};
+
+use embedded_hal::blocking::delay::DelayMs;
+
+use heapless::Vec;
+
+use super::clock::Duration;
+use super::network::{NetworkError, Port, Socket, TransportMode};
+use super::protocol::{NinaProtocolHandler, ProtocolError, ProtocolInterface};
+use super::transport::Transport;
+use super::udp_client::send_datagram;
+use super::wifi::Wifi;
+use super::Error;
+
+/// The UDP port CoAP servers listen on.
+pub const COAP_PORT: Port = 5683;
+
+/// The maximum length in bytes of a single CoAP message sent or received, which is limited by
+/// the largest data transfer the NINA firmware supports.
+pub const MAX_COAP_MESSAGE_LENGTH: usize = 1024;
+
+// The local UDP port responses are received on
+const COAP_LOCAL_PORT: Port = 49154;
+
+// How long to wait for a request to be acknowledged before retransmitting it the first time,
+// and how often to retransmit it, as recommended by RFC 7252
+const ACK_TIMEOUT: Duration = Duration::millis(2_000);
+const MAX_RETRANSMIT: u32 = 4;
+// How long to wait for a separate response once a request has been acknowledged
+const SEPARATE_RESPONSE_TIMEOUT: Duration = Duration::millis(30_000);
+const POLL_INTERVAL_MS: u16 = 50;
+
+const VERSION: u8 = 1;
+const TYPE_CONFIRMABLE: u8 = 0;
+const TYPE_ACKNOWLEDGEMENT: u8 = 2;
+const TYPE_RESET: u8 = 3;
+const CODE_EMPTY: u8 = 0;
+const MAX_TOKEN_LENGTH: usize = 8;
+const PAYLOAD_MARKER: u8 = 0xff;
+
+const OPTION_URI_PATH: u16 = 11;
+const OPTION_BLOCK2: u16 = 23;
+
+// Requests blocks of 2^(4 + 5) = 512 bytes, which leaves room for the header and options
+// within MAX_COAP_MESSAGE_LENGTH
+const BLOCK_SIZE_EXPONENT: u32 = 5;
+const BLOCK_MORE: u32 = 0x8;
+
+/// The method of a CoAP request.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "ufmt", derive(ufmt::derive::uDebug))]
+#[non_exhaustive]
+#[repr(u8)]
+pub enum CoapMethod {
+    /// Retrieve a resource
+    Get = 1,
+    /// Process the payload, e.g. create a new resource
+    Post = 2,
+    /// Create or replace a resource with the payload
+    Put = 3,
+    /// Delete a resource
+    Delete = 4,
+}
+
+#[cfg(feature = "defmt")]
+impl Format for CoapMethod {
+    fn format(&self, fmt: Formatter) {
+        match self {
+            CoapMethod::Get => write!(fmt, "GET"),
+            CoapMethod::Post => write!(fmt, "POST"),
+            CoapMethod::Put => write!(fmt, "PUT"),
+            CoapMethod::Delete => write!(fmt, "DELETE"),
+        }
+    }
+}
+
+/// The response code of a CoAP server, e.g. 2.05 (Content) or 4.04 (Not Found).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "ufmt", derive(ufmt::derive::uDebug))]
+pub struct CoapCode(pub u8);
+
+impl CoapCode {
+    /// The class of the code, the digit before the dot.
+    pub fn class(&self) -> u8 {
+        self.0 >> 5
+    }
+
+    /// The detail of the code, the two digits after the dot.
+    pub fn detail(&self) -> u8 {
+        self.0 & 0x1f
+    }
+
+    /// Whether the request succeeded, which is the case for all 2.xx codes.
+    pub fn is_success(&self) -> bool {
+        self.class() == 2
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl Format for CoapCode {
+    fn format(&self, fmt: Formatter) {
+        write!(
+            fmt,
+            "{=u8}.{=u8}{=u8}",
+            self.class(),
+            self.detail() / 10,
+            self.detail() % 10
+        )
+    }
+}
+
+/// A client type that sends requests to CoAP servers. See the [module documentation](self)
+/// for details.
+///
+/// A socket is requested from the ESP32 target on the first request and reused for every
+/// request after that until [`CoapClient::close`] is called.
+pub struct CoapClient<'a, T, D> {
+    pub(crate) protocol_handler: &'a mut NinaProtocolHandler<T, D>,
+    pub(crate) socket: Option<Socket>,
+    pub(crate) message_id: u16,
+}
+
+impl<'a, T, D> CoapClient<'a, T, D>
+where
+    T: Transport,
+    D: DelayMs<u16>,
+{
+    /// Build a new instance of a [`CoapClient`] provided a [`Wifi`] instance.
+    pub fn build(wifi: &'a mut Wifi<T, D>) -> Self {
+        Self {
+            protocol_handler: wifi.protocol_handler.get_mut(),
+            socket: None,
+            message_id: 1,
+        }
+    }
+
+    /// Start numbering messages at `message_id`. Servers detect retransmissions by their
+    /// message ID, so this should be random (e.g. from a hardware RNG) to keep a quickly
+    /// rebooted device from being mistaken for one retransmitting its earlier requests.
+    pub fn message_id(mut self, message_id: u16) -> Self {
+        self.message_id = message_id;
+        self
+    }
+
+    /// Send a confirmable `method` request for the resource at `path` (e.g. `sensors/temp`)
+    /// with `payload` to `port` on the CoAP server at `server`. Every block of the response
+    /// payload is passed to `f` in order.
+    ///
+    /// Returns the code of the (last) response. Fails with [`NetworkError::CoapTimeout`] if
+    /// the server doesn't respond and with [`NetworkError::CoapReset`] if it rejects the
+    /// request.
+    #[allow(clippy::too_many_arguments)]
+    pub fn request<F: FnMut(&[u8])>(
+        &mut self,
+        method: CoapMethod,
+        server: Ipv4Addr,
+        port: Port,
+        path: &str,
+        payload: &[u8],
+        mut f: F,
+    ) -> Result<CoapCode, Error> {
+        let socket = self.open()?;
+        let mut request = [0u8; MAX_COAP_MESSAGE_LENGTH];
+        let mut response = [0u8; MAX_COAP_MESSAGE_LENGTH];
+        let mut block2 = BLOCK_SIZE_EXPONENT;
+
+        loop {
+            let message_id = self.next_message_id();
+            let token = message_id.to_be_bytes();
+            // Only the request for the first block carries the payload
+            let body = if block2 >> 4 == 0 { payload } else { &[] };
+
+            let length =
+                encode_request(method, message_id, &token, path, block2, body, &mut request)?;
+            let length = self.exchange(
+                socket,
+                SocketAddrV4::new(server, port),
+                &request[..length],
+                &mut response,
+            )?;
+            let message = decode(&response[..length])?;
+
+            f(message.payload);
+
+            match message.block2 {
+                // Ask for the next block in the block size chosen by the server
+                Some(value) if value & BLOCK_MORE != 0 => {
+                    block2 = (((value >> 4) + 1) << 4) | (value & 0x7);
+                }
+                _ => return Ok(CoapCode(message.code)),
+            }
+        }
+    }
+
+    /// Release the socket used for exchanging messages back to the ESP32 target.
+    pub fn close(&mut self) -> Result<(), Error> {
+        match self.socket.take() {
+            Some(socket) => self
+                .protocol_handler
+                .stop_client_tcp(socket, &TransportMode::Udp),
+            None => Ok(()),
+        }
+    }
+
+    // Requests a socket bound to COAP_LOCAL_PORT unless one is open already.
+    fn open(&mut self) -> Result<Socket, Error> {
+        if let Some(socket) = self.socket {
+            return Ok(socket);
+        }
+
+        let socket = self.protocol_handler.get_socket()?;
+        self.protocol_handler
+            .start_server_tcp(socket, COAP_LOCAL_PORT, &TransportMode::Udp)?;
+        self.socket = Some(socket);
+
+        Ok(socket)
+    }
+
+    fn next_message_id(&mut self) -> u16 {
+        let message_id = self.message_id;
+        self.message_id = self.message_id.wrapping_add(1);
+        message_id
+    }
+
+    // Sends the confirmable `request` until it's acknowledged and waits for the response to
+    // it, which is received into `response`. Returns the length of the response.
+    fn exchange(
+        &mut self,
+        socket: Socket,
+        server: SocketAddrV4,
+        request: &[u8],
+        response: &mut [u8],
+    ) -> Result<usize, Error> {
+        let request_message = decode(request)?;
+        let (message_id, token) = (request_message.message_id, request_message.token);
+
+        let mut retransmissions = 0;
+        let mut timeout = ACK_TIMEOUT;
+        let mut deadline = self.protocol_handler.deadline(Some(timeout));
+        let mut acknowledged = false;
+
+        send_datagram(self.protocol_handler, socket, server, request)?;
+
+        loop {
+            if let Some(length) = self.receive_datagram(socket, response)? {
+                // Datagrams that aren't well-formed CoAP messages are ignored
+                if let Ok(message) = decode(&response[..length]) {
+                    let matches_id = message.message_id == message_id;
+
+                    if matches_id && message.kind == TYPE_RESET {
+                        return Err(NetworkError::CoapReset.into());
+                    }
+                    if matches_id
+                        && message.kind == TYPE_ACKNOWLEDGEMENT
+                        && message.code == CODE_EMPTY
+                    {
+                        // The response follows separately
+                        acknowledged = true;
+                        deadline = self
+                            .protocol_handler
+                            .deadline(Some(SEPARATE_RESPONSE_TIMEOUT));
+                        continue;
+                    }
+                    if message.token == token && message.code != CODE_EMPTY {
+                        if message.kind == TYPE_CONFIRMABLE {
+                            let ack = encode_empty_ack(message.message_id);
+                            send_datagram(self.protocol_handler, socket, server, &ack)?;
+                        }
+                        return Ok(length);
+                    }
+                }
+                continue;
+            }
+
+            if deadline.has_expired() {
+                if acknowledged || retransmissions == MAX_RETRANSMIT {
+                    return Err(NetworkError::CoapTimeout.into());
+                }
+
+                send_datagram(self.protocol_handler, socket, server, request)?;
+                retransmissions += 1;
+                timeout *= 2;
+                deadline = self.protocol_handler.deadline(Some(timeout));
+            }
+
+            self.protocol_handler.delay_ms(POLL_INTERVAL_MS);
+            deadline.wait(Duration::millis(POLL_INTERVAL_MS as u32));
+        }
+    }
+
+    // Copies the next datagram received on `socket` into `buf`, if there is one.
+    fn receive_datagram(&mut self, socket: Socket, buf: &mut [u8]) -> Result<Option<usize>, Error> {
+        let available = self.protocol_handler.avail_data_tcp(socket)?;
+        if available == 0 {
+            return Ok(None);
+        }
+
+        let length = available.min(buf.len());
+        self.protocol_handler
+            .get_data_buf_tcp(socket, &mut buf[..length])
+            .map(Some)
+    }
+}
+
+// The parts of a decoded CoAP message this client makes use of.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct Message<'a> {
+    pub(crate) kind: u8,
+    pub(crate) code: u8,
+    pub(crate) message_id: u16,
+    pub(crate) token: &'a [u8],
+    pub(crate) block2: Option<u32>,
+    pub(crate) payload: &'a [u8],
+}
+
+// Encodes a confirmable `method` request for `path` asking for the block described by the
+// Block2 option value `block2` into `buf`, returning the length of the request.
+pub(crate) fn encode_request(
+    method: CoapMethod,
+    message_id: u16,
+    token: &[u8],
+    path: &str,
+    block2: u32,
+    payload: &[u8],
+    buf: &mut [u8],
+) -> Result<usize, Error> {
+    let mut encoder = Encoder {
+        buf,
+        position: 0,
+        option_number: 0,
+    };
+
+    encoder.put(&[
+        VERSION << 6 | TYPE_CONFIRMABLE << 4 | token.len() as u8,
+        method as u8,
+    ])?;
+    encoder.put(&message_id.to_be_bytes())?;
+    encoder.put(token)?;
+
+    for segment in path.split('/').filter(|segment| !segment.is_empty()) {
+        encoder.put_option(OPTION_URI_PATH, segment.as_bytes())?;
+    }
+
+    // Options carry unsigned integers in as few bytes as possible
+    let block2 = block2.to_be_bytes();
+    let first_significant = block2.iter().position(|byte| *byte != 0).unwrap_or(4);
+    encoder.put_option(OPTION_BLOCK2, &block2[first_significant..])?;
+
+    if !payload.is_empty() {
+        encoder.put(&[PAYLOAD_MARKER])?;
+        encoder.put(payload)?;
+    }
+
+    Ok(encoder.position)
+}
+
+// Encodes the empty acknowledgement of the confirmable message with `message_id`.
+fn encode_empty_ack(message_id: u16) -> [u8; 4] {
+    let [id_high, id_low] = message_id.to_be_bytes();
+
+    [
+        VERSION << 6 | TYPE_ACKNOWLEDGEMENT << 4,
+        CODE_EMPTY,
+        id_high,
+        id_low,
+    ]
+}
+
+// Decodes the CoAP `message`.
+pub(crate) fn decode(message: &[u8]) -> Result<Message<'_>, Error> {
+    let malformed = || Error::from(NetworkError::MalformedCoapMessage);
+
+    if message.len() < 4 || message[0] >> 6 != VERSION {
+        return Err(malformed());
+    }
+    let token_length = (message[0] & 0x0f) as usize;
+    if token_length > MAX_TOKEN_LENGTH {
+        return Err(malformed());
+    }
+
+    let mut decoded = Message {
+        kind: (message[0] >> 4) & 0x3,
+        code: message[1],
+        message_id: u16::from_be_bytes([message[2], message[3]]),
+        token: message.get(4..4 + token_length).ok_or_else(malformed)?,
+        block2: None,
+        payload: &[],
+    };
+
+    let mut position = 4 + token_length;
+    let mut option_number: u16 = 0;
+
+    while let Some(&byte) = message.get(position) {
+        position += 1;
+
+        if byte == PAYLOAD_MARKER {
+            decoded.payload = &message[position..];
+            if decoded.payload.is_empty() {
+                return Err(malformed());
+            }
+            break;
+        }
+
+        let delta = read_option_header_part(byte >> 4, message, &mut position)?;
+        let length = read_option_header_part(byte & 0x0f, message, &mut position)? as usize;
+        let value = message
+            .get(position..position + length)
+            .ok_or_else(malformed)?;
+        position += length;
+
+        option_number = option_number.checked_add(delta).ok_or_else(malformed)?;
+        if option_number == OPTION_BLOCK2 {
+            if value.len() > 3 {
+                return Err(malformed());
+            }
+            decoded.block2 = Some(
+                value
+                    .iter()
+                    .fold(0, |block2, byte| block2 << 8 | *byte as u32),
+            );
+        }
+    }
+
+    Ok(decoded)
+}
+
+// Reads an option delta or length from its 4 bit `nibble` and the extended bytes that follow
+// at `position` if any.
+fn read_option_header_part(nibble: u8, message: &[u8], position: &mut usize) -> Result<u16, Error> {
+    let extended_length = match nibble {
+        0..=12 => return Ok(nibble as u16),
+        13 => 1,
+        14 => 2,
+        _ => return Err(NetworkError::MalformedCoapMessage.into()),
+    };
+
+    let extended = message
+        .get(*position..*position + extended_length)
+        .ok_or(NetworkError::MalformedCoapMessage)?;
+    *position += extended_length;
+
+    if extended_length == 1 {
+        Ok(extended[0] as u16 + 13)
+    } else {
+        u16::from_be_bytes([extended[0], extended[1]])
+            .checked_add(269)
+            .ok_or_else(|| NetworkError::MalformedCoapMessage.into())
+    }
+}
+
+// Writes a CoAP message into `buf`, keeping track of the number of the last option written
+// since options are encoded relative to each other.
+struct Encoder<'a> {
+    buf: &'a mut [u8],
+    position: usize,
+    option_number: u16,
+}
+
+impl<'a> Encoder<'a> {
+    fn put(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        let end = self.position + bytes.len();
+        self.buf
+            .get_mut(self.position..end)
+            .ok_or(ProtocolError::PayloadTooLarge)?
+            .copy_from_slice(bytes);
+        self.position = end;
+        Ok(())
+    }
+
+    // Options have to be put in order of their `number`.
+    fn put_option(&mut self, number: u16, value: &[u8]) -> Result<(), Error> {
+        let (delta_nibble, delta_extended) = option_header_part(number - self.option_number);
+        let (length_nibble, length_extended) = option_header_part(value.len() as u16);
+        self.option_number = number;
+
+        self.put(&[delta_nibble << 4 | length_nibble])?;
+        self.put(&delta_extended)?;
+        self.put(&length_extended)?;
+        self.put(value)
+    }
+}
+
+// Splits an option delta or length into its 4 bit nibble and the extended bytes that follow.
+fn option_header_part(value: u16) -> (u8, Vec<u8, 2>) {
+    let mut extended = Vec::new();
+
+    if value < 13 {
+        (value as u8, extended)
+    } else if value < 269 {
+        extended.push((value - 13) as u8).ok();
+        (13, extended)
+    } else {
+        extended
+            .extend_from_slice(&(value - 269).to_be_bytes())
+            .ok();
+        (14, extended)
+    }
+}
+
+#[cfg(test)]
+mod coap_tests {
+    use super::*;
+
+    #[test]
+    fn encode_request_puts_path_segments_and_block2_in_option_order() {
+        let mut buf = [0u8; MAX_COAP_MESSAGE_LENGTH];
+
+        let length = encode_request(
+            CoapMethod::Put,
+            0x1234,
+            &[0xab, 0xcd],
+            "/sensors/temp",
+            BLOCK_SIZE_EXPONENT,
+            b"21.5",
+            &mut buf,
+        )
+        .unwrap();
+
+        assert_eq!(
+            &buf[..length],
+            &[
+                0x42, 0x03, 0x12, 0x34, 0xab, 0xcd, // header and token
+                0xb7, b's', b'e', b'n', b's', b'o', b'r', b's', // Uri-Path (11)
+                0x04, b't', b'e', b'm', b'p', // Uri-Path (delta 0)
+                0xc1, 0x05, // Block2 (delta 12): block 0, 512 byte blocks
+                0xff, b'2', b'1', b'.', b'5', // payload
+            ]
+        );
+    }
+
+    #[test]
+    fn decode_reads_block2_and_payload_of_piggybacked_response() {
+        let response = [
+            0x62, 0x45, 0x12, 0x34, 0xab, 0xcd, // ACK 2.05 (Content) with token
+            0xc0, // Content-Format (12): text/plain as zero-length uint
+            0xb1, 0x1d, // Block2 (delta 11): block 1, more, 512 byte blocks
+            0xff, b'h', b'i',
+        ];
+
+        let message = decode(&response).unwrap();
+
+        assert_eq!(
+            message,
+            Message {
+                kind: TYPE_ACKNOWLEDGEMENT,
+                code: 0x45,
+                message_id: 0x1234,
+                token: &[0xab, 0xcd],
+                block2: Some(0x1d),
+                payload: b"hi",
+            }
+        );
+        assert_eq!(CoapCode(message.code).class(), 2);
+        assert_eq!(CoapCode(message.code).detail(), 5);
+    }
+
+    #[test]
+    fn decode_rejects_truncated_options_and_empty_payloads() {
+        assert_eq!(
+            decode(&[0x60, 0x45, 0x00, 0x01, 0xd1]),
+            Err(Error::Network(NetworkError::MalformedCoapMessage))
+        );
+        assert_eq!(
+            decode(&[0x60, 0x45, 0x00, 0x01, 0xff]),
+            Err(Error::Network(NetworkError::MalformedCoapMessage))
+        );
+    }
+}
@@ -0,0 +1,400 @@
+//! A line-based command shell that can be reached over TCP for inspecting field units.
+//!
+//! A [`DebugConsole`] listens on a configurable port and accepts one client at a time, e.g.
+//! `nc <device-ip> 2323`. Every line the client sends is split into words, the first of which
+//! selects one of the registered [`ConsoleCommand`]s to run with the remaining words. The
+//! output of the command is sent back to the client followed by a new prompt.
+//!
+//! Two commands are built in: `help` lists all commands together with their description and
+//! `exit` closes the session.
+//!
+//! ## Usage
+//!
+//! ```no_run
+//! use core::fmt::Write;
+//! use core::str::SplitWhitespace;
+//!
+//! use esp32_wroom_core::console::{ConsoleCommand, DebugConsole};
+//!
+//! struct Uptime;
+//!
+//! impl ConsoleCommand for Uptime {
+//!     fn name(&self) -> &str {
+//!         "uptime"
+//!     }
+//!
+//!     fn help(&self) -> &str {
+//!         "show the time since boot"
+//!     }
+//!
+//!     fn run(&mut self, _args: SplitWhitespace, output: &mut dyn Write) -> core::fmt::Result {
+//!         writeln!(output, "{} ms", timer.now_ms())
+//!     }
+//! }
+//!
+//! let mut uptime = Uptime;
+//!
+//! let mut console = DebugConsole::build(&mut wifi);
+//! console.register(&mut uptime).unwrap();
+//! console.listen(2323).unwrap();
+//!
+//! loop {
+//!     if let Err(e) = console.poll() {
+//!         defmt::error!("Debug console failed: {:?}", e);
+//!     }
+//!
+//!     // Do other work
+//! }
+//! ```
+//!
+
+use core::fmt::{self, Write};
+use core::str::SplitWhitespace;
+
+use embedded_hal::blocking::delay::DelayMs;
+
+use heapless::{String, Vec};
+
+use super::network::{ConnectionState, NetworkError, Port, Socket};
+use super::tcp_server::TcpServer;
+use super::transport::Transport;
+use super::wifi::Wifi;
+use super::Error;
+
+/// The maximum number of commands that can be registered with a [`DebugConsole`].
+pub const MAX_CONSOLE_COMMANDS: usize = 8;
+
+/// The maximum length of a command line in bytes. Longer lines are rejected.
+pub const MAX_CONSOLE_LINE_LENGTH: usize = 128;
+
+/// The maximum length of the output of a single command in bytes. Longer output is truncated.
+pub const MAX_CONSOLE_OUTPUT_LENGTH: usize = 512;
+
+const BANNER: &str = "esp32-wroom debug console, type 'help' for a list of commands\n";
+const PROMPT: &str = "> ";
+const TRUNCATED: &str = "(output truncated)\n";
+
+// The number of received bytes that are read from the ESP32 target at once
+const RECEIVE_CHUNK_LENGTH: usize = 64;
+
+// Telnet clients send "interpret as command" sequences of this byte followed by two more bytes
+const TELNET_IAC: u8 = 0xff;
+
+/// A command that can be run from a [`DebugConsole`].
+///
+/// Commands don't have access to the [`Wifi`] instance the console was built with while they
+/// run, so any state they report has to be shared with them by the application.
+pub trait ConsoleCommand {
+    /// The word that runs this command, which must not contain whitespace.
+    fn name(&self) -> &str;
+
+    /// A short description of this command that is listed by `help`.
+    fn help(&self) -> &str;
+
+    /// Run this command with the words following its name in `args`, writing the lines to send
+    /// back to the client into `output`.
+    ///
+    /// Writing fails once the output is longer than [`MAX_CONSOLE_OUTPUT_LENGTH`], in which case
+    /// the output up to that point is sent followed by a note that it was truncated.
+    fn run(&mut self, args: SplitWhitespace, output: &mut dyn Write) -> fmt::Result;
+}
+
+/// A TCP server that serves a line-based command shell. See the
+/// [module documentation](self) for details.
+pub struct DebugConsole<'a, T, D> {
+    pub(crate) server: TcpServer<'a, T, D>,
+    commands: Vec<&'a mut dyn ConsoleCommand, MAX_CONSOLE_COMMANDS>,
+    client_socket: Option<Socket>,
+    line: LineBuffer,
+}
+
+impl<'a, T, D> DebugConsole<'a, T, D>
+where
+    T: Transport,
+    D: DelayMs<u16>,
+{
+    /// Build a new instance of a [`DebugConsole`] provided a [`Wifi`] instance.
+    pub fn build(wifi: &'a mut Wifi<T, D>) -> Self {
+        Self {
+            server: TcpServer::build(wifi),
+            commands: Vec::new(),
+            client_socket: None,
+            line: LineBuffer::default(),
+        }
+    }
+
+    /// Make `command` available to clients, failing with
+    /// [`NetworkError::TooManyConsoleCommands`] once [`MAX_CONSOLE_COMMANDS`] have been
+    /// registered.
+    pub fn register(&mut self, command: &'a mut dyn ConsoleCommand) -> Result<(), Error> {
+        self.commands
+            .push(command)
+            .map_err(|_| NetworkError::TooManyConsoleCommands.into())
+    }
+
+    /// Start listening for a client on `port`.
+    pub fn listen(&mut self, port: Port) -> Result<(), Error> {
+        self.server.bind(port)
+    }
+
+    /// Whether a client is currently connected to the console.
+    pub fn is_client_connected(&self) -> bool {
+        self.client_socket.is_some()
+    }
+
+    /// Perform one round of accepting a new client or running the commands received from the
+    /// connected client. This should be called regularly from the application's main loop.
+    pub fn poll(&mut self) -> Result<(), Error> {
+        let socket = match self.client_socket {
+            Some(socket) => socket,
+            None => {
+                if let Some(socket) = self.server.accept()? {
+                    self.client_socket = Some(socket);
+                    self.line = LineBuffer::default();
+
+                    self.send(socket, BANNER)?;
+                    self.send(socket, PROMPT)?;
+                }
+                return Ok(());
+            }
+        };
+
+        let available = self.server.available_data(socket)?;
+        if available == 0 {
+            if self.server.connection_state(socket)? != ConnectionState::Established {
+                self.close_session(socket)?;
+            }
+            return Ok(());
+        }
+
+        let mut buf = [0u8; RECEIVE_CHUNK_LENGTH];
+        let length = available.min(RECEIVE_CHUNK_LENGTH);
+        let received = self.server.receive_data(socket, &mut buf[..length])?;
+
+        for &byte in &buf[..received] {
+            if !self.line.push(byte) {
+                continue;
+            }
+
+            let mut output: String<MAX_CONSOLE_OUTPUT_LENGTH> = String::new();
+            let outcome = if self.line.overflowed {
+                writeln!(output, "line too long").ok();
+                Outcome::Done
+            } else {
+                execute(&mut self.commands, self.line.as_str(), &mut output)
+            };
+            self.line = LineBuffer::default();
+
+            self.send(socket, &output)?;
+            match outcome {
+                Outcome::Done => {}
+                Outcome::Truncated => self.send(socket, TRUNCATED)?,
+                Outcome::Exit => return self.close_session(socket),
+            }
+            self.send(socket, PROMPT)?;
+        }
+
+        Ok(())
+    }
+
+    /// Close the session of the connected client, if any, and stop listening.
+    pub fn stop(&mut self) -> Result<(), Error> {
+        if let Some(socket) = self.client_socket {
+            self.close_session(socket)?;
+        }
+        self.server.stop()
+    }
+
+    fn close_session(&mut self, socket: Socket) -> Result<(), Error> {
+        self.client_socket = None;
+        self.server.close(socket)
+    }
+
+    // Sends all of `data` to the client, failing if the ESP32 target doesn't accept any of it.
+    fn send(&mut self, socket: Socket, data: &str) -> Result<(), Error> {
+        let mut remaining = data.as_bytes();
+        while !remaining.is_empty() {
+            let sent = self.server.send_data(socket, remaining)?;
+            if sent == 0 {
+                return Err(NetworkError::SendFailed.into());
+            }
+            remaining = &remaining[sent.min(remaining.len())..];
+        }
+        Ok(())
+    }
+}
+
+// What happened when running a command line.
+#[derive(Debug, PartialEq, Eq)]
+enum Outcome {
+    Done,
+    Truncated,
+    Exit,
+}
+
+// Runs the command selected by the first word of `line`, writing its output into `output`.
+fn execute(
+    commands: &mut [&mut dyn ConsoleCommand],
+    line: &str,
+    output: &mut dyn Write,
+) -> Outcome {
+    let mut words = line.split_whitespace();
+
+    let result = match words.next() {
+        None => Ok(()),
+        Some("exit") => return Outcome::Exit,
+        Some("help") => {
+            let mut result = writeln!(output, "help - list all commands")
+                .and_then(|_| writeln!(output, "exit - close this session"));
+            for command in commands.iter() {
+                result = result
+                    .and_then(|_| writeln!(output, "{} - {}", command.name(), command.help()));
+            }
+            result
+        }
+        Some(name) => match commands.iter_mut().find(|command| command.name() == name) {
+            Some(command) => command.run(words, output),
+            None => writeln!(output, "unknown command '{}', try 'help'", name),
+        },
+    };
+
+    match result {
+        Ok(()) => Outcome::Done,
+        Err(_) => Outcome::Truncated,
+    }
+}
+
+// Collects the printable characters received from a client into a line, dropping carriage
+// returns, Telnet commands and characters that were erased with backspace.
+#[derive(Debug, Default)]
+struct LineBuffer {
+    line: String<MAX_CONSOLE_LINE_LENGTH>,
+    overflowed: bool,
+    telnet_bytes_to_skip: u8,
+}
+
+impl LineBuffer {
+    // Adds `byte` to the line, returning whether it completed the line.
+    fn push(&mut self, byte: u8) -> bool {
+        if self.telnet_bytes_to_skip > 0 {
+            self.telnet_bytes_to_skip -= 1;
+            return false;
+        }
+
+        match byte {
+            b'\n' => return true,
+            TELNET_IAC => self.telnet_bytes_to_skip = 2,
+            // Backspace and delete
+            0x08 | 0x7f => {
+                self.line.pop();
+            }
+            b' '..=b'~' => self.overflowed |= self.line.push(byte as char).is_err(),
+            _ => {}
+        }
+
+        false
+    }
+
+    fn as_str(&self) -> &str {
+        self.line.as_str()
+    }
+}
+
+#[cfg(test)]
+mod console_tests {
+    use super::*;
+
+    struct Echo;
+
+    impl ConsoleCommand for Echo {
+        fn name(&self) -> &str {
+            "echo"
+        }
+
+        fn help(&self) -> &str {
+            "print the arguments"
+        }
+
+        fn run(&mut self, args: SplitWhitespace, output: &mut dyn Write) -> fmt::Result {
+            for arg in args {
+                write!(output, "{} ", arg)?;
+            }
+            writeln!(output)
+        }
+    }
+
+    fn push_all(line: &mut LineBuffer, bytes: &[u8]) -> bool {
+        bytes.iter().fold(false, |_, &byte| line.push(byte))
+    }
+
+    #[test]
+    fn line_buffer_completes_a_line_on_newline_and_drops_carriage_returns() {
+        let mut line = LineBuffer::default();
+
+        assert!(!push_all(&mut line, b"echo hi\r"));
+        assert!(line.push(b'\n'));
+        assert_eq!(line.as_str(), "echo hi");
+    }
+
+    #[test]
+    fn line_buffer_handles_backspace_and_telnet_commands() {
+        let mut line = LineBuffer::default();
+
+        // IAC DO ECHO, then "helq" corrected to "help"
+        push_all(&mut line, &[0xff, 0xfd, 0x01]);
+        assert!(push_all(&mut line, b"helq\x7fp\n"));
+        assert_eq!(line.as_str(), "help");
+    }
+
+    #[test]
+    fn line_buffer_reports_overflow() {
+        let mut line = LineBuffer::default();
+
+        push_all(&mut line, &[b'a'; MAX_CONSOLE_LINE_LENGTH + 1]);
+        assert!(line.overflowed);
+    }
+
+    #[test]
+    fn execute_runs_the_command_named_by_the_first_word() {
+        let mut echo = Echo;
+        let mut commands: [&mut dyn ConsoleCommand; 1] = [&mut echo];
+        let mut output = std::string::String::new();
+
+        assert_eq!(
+            execute(&mut commands, "  echo  a b ", &mut output),
+            Outcome::Done
+        );
+        assert_eq!(output, "a b \n");
+    }
+
+    #[test]
+    fn execute_lists_commands_and_rejects_unknown_ones() {
+        let mut echo = Echo;
+        let mut commands: [&mut dyn ConsoleCommand; 1] = [&mut echo];
+        let mut output = std::string::String::new();
+
+        execute(&mut commands, "help", &mut output);
+        assert_eq!(
+            output,
+            "help - list all commands\nexit - close this session\necho - print the arguments\n"
+        );
+
+        output.clear();
+        execute(&mut commands, "reboot", &mut output);
+        assert_eq!(output, "unknown command 'reboot', try 'help'\n");
+
+        assert_eq!(execute(&mut commands, "exit", &mut output), Outcome::Exit);
+    }
+
+    #[test]
+    fn execute_reports_truncated_output() {
+        let mut echo = Echo;
+        let mut commands: [&mut dyn ConsoleCommand; 1] = [&mut echo];
+        let mut output: String<4> = String::new();
+
+        assert_eq!(
+            execute(&mut commands, "echo abc def", &mut output),
+            Outcome::Truncated
+        );
+    }
+}
@@ -0,0 +1,286 @@
+//! Supply WiFi network credentials on demand instead of up front.
+//!
+//! A [`CredentialProvider`] is asked for the SSID and passphrase only at the moment they are
+//! sent to the ESP32 target, and for a TLS client certificate only when connecting with
+//! [`TlsClient::connect_with_credentials`]. This allows credentials to live in a secure
+//! element, external flash or be provisioned after boot, with the driver only holding them in a
+//! short-lived stack buffer that is cleared once they have been sent.
+//!
+//! ## Usage
+//!
+//! ```no_run
+//! use esp32_wroom_core::credentials::CredentialProvider;
+//! use esp32_wroom_core::network::NetworkError;
+//! use esp32_wroom_core::Error;
+//!
+//! struct FlashCredentials {}
+//!
+//! impl CredentialProvider for FlashCredentials {
+//!     fn ssid(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+//!         // Read the SSID from flash into `buf`
+//!         read_flash(SSID_OFFSET, buf).map_err(|_| NetworkError::CredentialsUnavailable.into())
+//!     }
+//!
+//!     fn passphrase(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+//!         read_flash(PASSPHRASE_OFFSET, buf).map_err(|_| NetworkError::CredentialsUnavailable.into())
+//!     }
+//! }
+//!
+//! let result = wifi.join_with(&mut FlashCredentials {});
+//! defmt::info!("Join Result: {:?}", result);
+//! ```
+//!
+//! [`TlsClient::connect_with_credentials`]: crate::tls_client::TlsClient::connect_with_credentials
+//!
+
+use core::ptr;
+
+use super::network::NetworkError;
+use super::Error;
+
+/// The maximum length in bytes of a WiFi network SSID.
+pub const MAX_SSID_LENGTH: usize = 32;
+
+/// The maximum length in bytes of a WiFi network passphrase.
+pub const MAX_PASSPHRASE_LENGTH: usize = 64;
+
+/// Provides WiFi network credentials at the time they are needed by the driver.
+pub trait CredentialProvider {
+    /// Write the SSID of the network to join into `buf` and return the number of bytes written.
+    fn ssid(&mut self, buf: &mut [u8]) -> Result<usize, Error>;
+
+    /// Write the passphrase of the network to join into `buf` and return the number of bytes
+    /// written.
+    fn passphrase(&mut self, buf: &mut [u8]) -> Result<usize, Error>;
+
+    /// Write the PEM encoded client certificate presented for TLS connections into `buf` and
+    /// return the number of bytes written, or `None` if there is none, which is the default.
+    fn client_certificate(&mut self, _buf: &mut [u8]) -> Result<Option<usize>, Error> {
+        Ok(None)
+    }
+}
+
+/// A [`CredentialProvider`] for credentials that are already available as string slices.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct StaticCredentials<'a> {
+    /// SSID of the network to join.
+    pub ssid: &'a str,
+    /// Passphrase of the network to join.
+    pub passphrase: &'a str,
+}
+
+impl<'a> StaticCredentials<'a> {
+    /// Create a new [`StaticCredentials`] instance from an SSID and passphrase.
+    pub fn new(ssid: &'a str, passphrase: &'a str) -> Self {
+        Self { ssid, passphrase }
+    }
+}
+
+impl CredentialProvider for StaticCredentials<'_> {
+    fn ssid(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        copy_credential(self.ssid.as_bytes(), buf)
+    }
+
+    fn passphrase(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        copy_credential(self.passphrase.as_bytes(), buf)
+    }
+}
+
+/// WPA2-Enterprise (802.1X) credentials used to join a network with
+/// [`Wifi::join_enterprise`](crate::wifi::Wifi::join_enterprise).
+///
+/// Only `username` and `password` are required. The outer `identity` defaults to the username
+/// and no CA certificate is used unless one is set.
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub struct EnterpriseConfig<'a> {
+    /// Username used for the inner (phase 2) authentication.
+    pub username: &'a str,
+    /// Password used for the inner (phase 2) authentication.
+    pub password: &'a str,
+    /// Outer (phase 1) identity sent in the clear, e.g. `anonymous@example.com`.
+    pub identity: Option<&'a str>,
+    /// PEM encoded CA certificate used to verify the authentication server.
+    pub ca_certificate: Option<&'a [u8]>,
+}
+
+impl<'a> EnterpriseConfig<'a> {
+    /// Create a new [`EnterpriseConfig`] from a username and password.
+    pub fn new(username: &'a str, password: &'a str) -> Self {
+        Self {
+            username,
+            password,
+            identity: None,
+            ca_certificate: None,
+        }
+    }
+
+    /// Use `identity` as the outer identity instead of the username.
+    pub fn identity(mut self, identity: &'a str) -> Self {
+        self.identity = Some(identity);
+        self
+    }
+
+    /// Verify the authentication server against the PEM encoded `ca_certificate`.
+    pub fn ca_certificate(mut self, ca_certificate: &'a [u8]) -> Self {
+        self.ca_certificate = Some(ca_certificate);
+        self
+    }
+
+    /// The outer identity that is sent to the network, which is the username unless one has
+    /// been set with [`EnterpriseConfig::identity`].
+    pub fn outer_identity(&self) -> &'a str {
+        self.identity.unwrap_or(self.username)
+    }
+}
+
+fn copy_credential(credential: &[u8], buf: &mut [u8]) -> Result<usize, Error> {
+    let slot = buf
+        .get_mut(..credential.len())
+        .ok_or(Error::Network(NetworkError::CredentialsUnavailable))?;
+    slot.copy_from_slice(credential);
+
+    Ok(credential.len())
+}
+
+// Returns the first `length` bytes of `buf` a CredentialProvider wrote its credential to.
+// Fails with NetworkError::CredentialsUnavailable if the provider reported more bytes than
+// fit into `buf` or they aren't valid UTF-8.
+pub(crate) fn provided_str(buf: &[u8], length: usize) -> Result<&str, Error> {
+    buf.get(..length)
+        .and_then(|credential| core::str::from_utf8(credential).ok())
+        .ok_or(Error::Network(NetworkError::CredentialsUnavailable))
+}
+
+// Checks that an SSID fits into MAX_SSID_LENGTH bytes. Lengths are always counted in
+// bytes of the UTF-8 encoding, so an SSID of 11 CJK characters is already 33 bytes long.
+pub(crate) fn validate_ssid(ssid: &str) -> Result<(), Error> {
+    if ssid.len() > MAX_SSID_LENGTH {
+        return Err(NetworkError::SsidTooLong.into());
+    }
+    Ok(())
+}
+
+// Checks that a passphrase fits into MAX_PASSPHRASE_LENGTH bytes of its UTF-8 encoding.
+pub(crate) fn validate_passphrase(passphrase: &str) -> Result<(), Error> {
+    if passphrase.len() > MAX_PASSPHRASE_LENGTH {
+        return Err(NetworkError::PassphraseTooLong.into());
+    }
+    Ok(())
+}
+
+// Shortens `bytes` to at most `max_length` bytes. If `bytes` is valid UTF-8, it's cut at the
+// last character boundary within `max_length` so that no multi-byte character gets split.
+pub(crate) fn truncate_utf8(bytes: &[u8], max_length: usize) -> &[u8] {
+    if bytes.len() <= max_length {
+        return bytes;
+    }
+
+    match core::str::from_utf8(bytes) {
+        Ok(s) => {
+            let mut end = max_length;
+            while !s.is_char_boundary(end) {
+                end -= 1;
+            }
+            &bytes[..end]
+        }
+        Err(_) => &bytes[..max_length],
+    }
+}
+
+// Overwrites a buffer that held a credential. Volatile writes make sure the
+// compiler doesn't optimize away clearing a buffer that is about to go out of scope.
+pub(crate) fn clear_credential(buf: &mut [u8]) {
+    for byte in buf.iter_mut() {
+        // SAFETY: `byte` is a valid, aligned and exclusive reference into `buf`
+        unsafe { ptr::write_volatile(byte, 0) };
+    }
+}
+
+#[cfg(test)]
+mod credentials_tests {
+    use super::*;
+
+    #[test]
+    fn static_credentials_copies_credentials_into_buffer() {
+        let mut credentials = StaticCredentials::new("ssid", "passphrase");
+        let mut buf = [0u8; MAX_PASSPHRASE_LENGTH];
+
+        let length = credentials.passphrase(&mut buf).unwrap();
+
+        assert_eq!(&buf[..length], b"passphrase");
+    }
+
+    #[test]
+    fn static_credentials_returns_credentials_unavailable_error_when_buffer_is_too_small() {
+        let mut credentials = StaticCredentials::new("ssid", "passphrase");
+        let mut buf = [0u8; 2];
+
+        assert_eq!(
+            credentials.ssid(&mut buf).unwrap_err(),
+            Error::Network(NetworkError::CredentialsUnavailable)
+        )
+    }
+
+    #[test]
+    fn enterprise_config_outer_identity_defaults_to_username() {
+        let config = EnterpriseConfig::new("alice", "secret");
+        assert_eq!(config.outer_identity(), "alice");
+
+        let config = config.identity("anonymous@example.com");
+        assert_eq!(config.outer_identity(), "anonymous@example.com");
+    }
+
+    #[test]
+    fn provided_str_rejects_lengths_beyond_the_buffer_and_invalid_utf8() {
+        assert_eq!(provided_str(b"ssid", 4), Ok("ssid"));
+        assert_eq!(
+            provided_str(b"ssid", 5),
+            Err(Error::Network(NetworkError::CredentialsUnavailable))
+        );
+        assert_eq!(
+            provided_str(&[0xff, 0xfe], 2),
+            Err(Error::Network(NetworkError::CredentialsUnavailable))
+        );
+    }
+
+    #[test]
+    fn validate_ssid_counts_utf8_bytes_not_characters() {
+        // 10 CJK characters are 30 bytes, 11 are 33 bytes
+        assert_eq!(validate_ssid("無線網路無線網路無線"), Ok(()));
+        assert_eq!(
+            validate_ssid("無線網路無線網路無線網"),
+            Err(Error::Network(NetworkError::SsidTooLong))
+        );
+    }
+
+    #[test]
+    fn validate_passphrase_counts_utf8_bytes_not_characters() {
+        // 16 emoji of 4 bytes each are 64 bytes
+        assert_eq!(validate_passphrase(&"🔑".repeat(16)), Ok(()));
+        assert_eq!(
+            validate_passphrase(&"🔑".repeat(17)),
+            Err(Error::Network(NetworkError::PassphraseTooLong))
+        );
+    }
+
+    #[test]
+    fn truncate_utf8_never_splits_a_character() {
+        let ssid = "☕☕☕".as_bytes(); // 9 bytes
+
+        assert_eq!(truncate_utf8(ssid, 9), ssid);
+        assert_eq!(truncate_utf8(ssid, 8), "☕☕".as_bytes());
+        assert_eq!(truncate_utf8(ssid, 2), b"");
+        assert_eq!(truncate_utf8(&[0xff, 0xfe, 0xfd], 2), &[0xff, 0xfe]);
+    }
+
+    #[test]
+    fn clear_credential_zeroes_buffer() {
+        let mut buf = [0xAu8; 8];
+
+        clear_credential(&mut buf);
+
+        assert_eq!(buf, [0u8; 8]);
+    }
+}
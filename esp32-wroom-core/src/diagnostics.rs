@@ -0,0 +1,147 @@
+//! Built-in field diagnostics such as a TCP echo server.
+//!
+//! The echo server listens on a configurable port and writes every byte it receives
+//! straight back to the sender. This lets installers verify end-to-end connectivity
+//! and measure round-trip latency to the device using standard tools like `nc` or
+//! `telnet`, without writing any application-specific server code.
+//!
+//! ## Usage
+//!
+//! ```no_run
+//! let port: Port = 7; // The well-known echo port
+//!
+//! let result = EchoServer::build(&mut wifi).serve(port, &mut |stats| {
+//!     defmt::info!("Echo stats: {:?}", stats);
+//!     // Keep serving until 10 remote sessions have been completed
+//!     stats.sessions < 10
+//! });
+//!
+//! defmt::info!("Echo server result: {:?}", result);
+//! ```
+//!
+
+#[cfg(feature = "defmt")]
+use defmt::{write, Format, Formatter};
+
+use embedded_hal::blocking::delay::DelayMs;
+
+use super::network::{ConnectionState, Port, Socket};
+use super::protocol::ProtocolInterface;
+use super::tcp_server::TcpServer;
+use super::transport::Transport;
+use super::wifi::Wifi;
+use super::Error;
+
+// How long to wait in between polling for new clients and data
+const POLL_INTERVAL_MS: u16 = 10;
+
+/// Running totals collected while an [`EchoServer`] is serving clients, which wrap around on
+/// overflow.
+#[derive(Debug, Default, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "ufmt", derive(ufmt::derive::uDebug))]
+pub struct EchoStats {
+    /// Number of client sessions that have been closed so far.
+    pub sessions: u32,
+    /// Total number of bytes echoed back to all clients.
+    pub bytes_echoed: u32,
+}
+
+#[cfg(feature = "defmt")]
+impl Format for EchoStats {
+    fn format(&self, fmt: Formatter) {
+        write!(
+            fmt,
+            "sessions: {:?}, bytes echoed: {:?}",
+            self.sessions, self.bytes_echoed
+        );
+    }
+}
+
+/// A diagnostic TCP server that echoes back all received data to the connected client.
+pub struct EchoServer<'a, T, D> {
+    pub(crate) server: TcpServer<'a, T, D>,
+    pub(crate) stats: EchoStats,
+}
+
+impl<'a, T, D> EchoServer<'a, T, D>
+where
+    T: Transport,
+    D: DelayMs<u16>,
+{
+    /// Build a new instance of an [`EchoServer`] provided a [`Wifi`] instance.
+    pub fn build(wifi: &'a mut Wifi<T, D>) -> Self {
+        Self {
+            server: TcpServer::build(wifi),
+            stats: EchoStats::default(),
+        }
+    }
+
+    /// Listen on `port` and echo back data to one client at a time.
+    ///
+    /// After every poll of the server, `f` is invoked with the current [`EchoStats`]. The server
+    /// keeps running for as long as `f` returns `true`, after which all sockets are closed and
+    /// the final stats are returned.
+    pub fn serve<F: FnMut(&EchoStats) -> bool>(
+        &mut self,
+        port: Port,
+        f: &mut F,
+    ) -> Result<EchoStats, Error> {
+        self.server.bind(port)?;
+
+        let mut client_socket: Option<Socket> = None;
+
+        loop {
+            let result = self.poll(&mut client_socket);
+
+            if result.is_err() || !f(&self.stats) {
+                if let Some(socket) = client_socket {
+                    self.server.close(socket)?;
+                }
+                self.server.stop()?;
+
+                return result.map(|_| self.stats);
+            }
+
+            self.server.protocol_handler.delay_ms(POLL_INTERVAL_MS);
+        }
+    }
+
+    // Performs one round of accepting a new client or echoing back available data
+    // from the currently connected client.
+    fn poll(&mut self, client_socket: &mut Option<Socket>) -> Result<(), Error> {
+        let socket = match client_socket {
+            Some(socket) => *socket,
+            None => match self.server.accept()? {
+                Some(socket) => {
+                    *client_socket = Some(socket);
+                    socket
+                }
+                None => return Ok(()),
+            },
+        };
+
+        let available = self.server.available_data(socket)?;
+        if available > 0 {
+            // Data is echoed back in chunks of at most the size of the working buffer
+            let received =
+                self.server
+                    .protocol_handler
+                    .with_working_buffer(|protocol_handler, buf| {
+                        let length = available.min(buf.len());
+                        let received =
+                            protocol_handler.get_data_buf_tcp(socket, &mut buf[..length])?;
+
+                        protocol_handler.send_data(&buf[..received], socket)?;
+                        Ok::<usize, Error>(received)
+                    })?;
+
+            self.stats.bytes_echoed = self.stats.bytes_echoed.wrapping_add(received as u32);
+        } else if self.server.connection_state(socket)? != ConnectionState::Established {
+            self.server.close(socket)?;
+            *client_socket = None;
+            self.stats.sessions = self.stats.sessions.wrapping_add(1);
+        }
+
+        Ok(())
+    }
+}
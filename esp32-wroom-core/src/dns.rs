@@ -0,0 +1,574 @@
+//! A minimal DNS message codec for lookups the NINA firmware doesn't provide itself.
+//!
+//! The NINA firmware only resolves hostnames to IP addresses. Other queries, such as the
+//! reverse lookups made by [`Wifi::get_host_by_addr`], are encoded here and exchanged with
+//! a DNS server over a UDP socket.
+//!
+//! It also holds the cache of hostnames resolved by the NINA firmware, which is enabled with
+//! [`Wifi::enable_dns_cache`] so that repeatedly connecting to the same server doesn't send a
+//! query every time.
+//!
+//! [`Wifi::get_host_by_addr`]: crate::wifi::Wifi::get_host_by_addr
+//! [`Wifi::enable_dns_cache`]: crate::wifi::Wifi::enable_dns_cache
+//!
+
+use core::net::{Ipv4Addr, SocketAddrV4};
+
+use embedded_hal::blocking::delay::DelayMs;
+
+#[cfg(feature = "defmt")]
+use defmt::{write, Format, Formatter};
+
+use heapless::{String, Vec};
+
+use super::clock::ClockHook;
+use super::network::{IpAddr, NetworkError, Port};
+use super::protocol::NinaProtocolHandler;
+use super::transport::Transport;
+use super::udp_client::exchange_datagram;
+use super::Error;
+
+/// The maximum length of a DNS name in its dotted text form (e.g. `printer.example.com`).
+pub const MAX_DNS_NAME_LENGTH: usize = 253;
+
+/// The maximum number of addresses returned by [`Wifi::resolve_all`].
+///
+/// [`Wifi::resolve_all`]: crate::wifi::Wifi::resolve_all
+pub const MAX_RESOLVED_ADDRESSES: usize = 4;
+
+/// The maximum number of hostnames held in the DNS cache at once.
+pub const MAX_DNS_CACHE_ENTRIES: usize = 4;
+
+/// The UDP port DNS servers listen on.
+pub(crate) const DNS_PORT: Port = 53;
+
+// The local UDP port responses to queries are received on
+const DNS_LOCAL_PORT: Port = 49153;
+
+// How long to wait for the response to a query
+const DNS_TIMEOUT_MS: u16 = 2000;
+
+// The largest DNS message sent over UDP without EDNS
+pub(crate) const MAX_DNS_MESSAGE_LENGTH: usize = 512;
+
+const HEADER_LENGTH: usize = 12;
+const MAX_LABEL_LENGTH: usize = 63;
+const FLAG_RESPONSE: u16 = 0x8000;
+const FLAG_RECURSION_DESIRED: u16 = 0x0100;
+const RCODE_MASK: u16 = 0x000f;
+
+pub(crate) const TYPE_A: u16 = 1;
+pub(crate) const TYPE_PTR: u16 = 12;
+pub(crate) const CLASS_IN: u16 = 1;
+
+// Limits how many compression pointers are followed while decoding a single name, which
+// guards against pointer loops in malformed messages
+const MAX_COMPRESSION_POINTERS: usize = 16;
+
+/// Configures the DNS cache enabled with [`Wifi::enable_dns_cache`].
+///
+/// The NINA firmware doesn't pass on the TTL of the records it resolves, so every cached
+/// address is kept for the same configurable time instead.
+///
+/// [`Wifi::enable_dns_cache`]: crate::wifi::Wifi::enable_dns_cache
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "ufmt", derive(ufmt::derive::uDebug))]
+#[non_exhaustive]
+pub struct DnsCacheConfig {
+    /// How long a resolved address is used before the hostname is resolved again, 5 minutes
+    /// by default.
+    pub ttl_ms: u32,
+}
+
+impl DnsCacheConfig {
+    /// Create a new [`DnsCacheConfig`] with the default TTL.
+    pub fn new() -> Self {
+        Self { ttl_ms: 300_000 }
+    }
+
+    /// Use a resolved address for `ttl_ms` before resolving the hostname again.
+    pub fn ttl_ms(mut self, ttl_ms: u32) -> Self {
+        self.ttl_ms = ttl_ms;
+        self
+    }
+}
+
+impl Default for DnsCacheConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl Format for DnsCacheConfig {
+    fn format(&self, fmt: Formatter) {
+        write!(fmt, "ttl_ms: {=u32}", self.ttl_ms)
+    }
+}
+
+// A hostname resolved at `resolved_ms`.
+#[derive(Debug)]
+struct DnsCacheEntry {
+    hostname: String<MAX_DNS_NAME_LENGTH>,
+    ip: Ipv4Addr,
+    resolved_ms: u32,
+}
+
+// Remembers the addresses of recently resolved hostnames until their TTL has passed.
+#[derive(Debug)]
+pub(crate) struct DnsCache {
+    clock: ClockHook,
+    config: DnsCacheConfig,
+    entries: Vec<DnsCacheEntry, MAX_DNS_CACHE_ENTRIES>,
+}
+
+impl DnsCache {
+    pub(crate) fn new(clock: ClockHook, config: DnsCacheConfig) -> Self {
+        Self {
+            clock,
+            config,
+            entries: Vec::new(),
+        }
+    }
+
+    // Returns the cached address of `hostname` if it hasn't expired yet.
+    pub(crate) fn lookup(&mut self, hostname: &str) -> Option<Ipv4Addr> {
+        let now_ms = self.clock.now_ms();
+        let ttl_ms = self.config.ttl_ms;
+        self.entries
+            .retain(|entry| now_ms.wrapping_sub(entry.resolved_ms) < ttl_ms);
+
+        self.entries
+            .iter()
+            .find(|entry| entry.hostname == hostname)
+            .map(|entry| entry.ip)
+    }
+
+    // Caches `ip` as the address of `hostname`, replacing the oldest entry if the cache is
+    // full. Hostnames too long for a DNS name are never cached.
+    pub(crate) fn insert(&mut self, hostname: &str, ip: Ipv4Addr) {
+        let mut entry = DnsCacheEntry {
+            hostname: String::new(),
+            ip,
+            resolved_ms: self.clock.now_ms(),
+        };
+        if entry.hostname.push_str(hostname).is_err() {
+            return;
+        }
+
+        self.entries.retain(|cached| cached.hostname != hostname);
+        if self.entries.is_full() {
+            self.entries.remove(0);
+        }
+        self.entries.push(entry).ok();
+    }
+}
+
+// Sends `query` to `dns_server` from a temporary UDP socket and copies the first datagram
+// received in return into `response`, returning its length.
+pub(crate) fn exchange<T, D>(
+    protocol_handler: &mut NinaProtocolHandler<T, D>,
+    dns_server: Ipv4Addr,
+    query: &[u8],
+    response: &mut [u8],
+) -> Result<usize, Error>
+where
+    T: Transport,
+    D: DelayMs<u16>,
+{
+    exchange_datagram(
+        protocol_handler,
+        DNS_LOCAL_PORT,
+        SocketAddrV4::new(dns_server, DNS_PORT),
+        query,
+        response,
+        DNS_TIMEOUT_MS,
+    )?
+    .ok_or_else(|| NetworkError::DnsTimeout.into())
+}
+
+// Encodes a query with `id` for the PTR record of `ip` (e.g. `4.3.2.1.in-addr.arpa` for
+// 1.2.3.4) into `buf`, returning the length of the query.
+pub(crate) fn encode_ptr_query(id: u16, ip: Ipv4Addr, buf: &mut [u8]) -> Result<usize, Error> {
+    let mut writer = Writer { buf, position: 0 };
+    put_query_header(&mut writer, id)?;
+
+    for octet in ip.octets().into_iter().rev() {
+        let mut digits = [0u8; 3];
+        let digits = decimal_digits(octet, &mut digits);
+        writer.put_u8(digits.len() as u8)?;
+        writer.put_bytes(digits)?;
+    }
+    for label in [&b"in-addr"[..], &b"arpa"[..]] {
+        writer.put_u8(label.len() as u8)?;
+        writer.put_bytes(label)?;
+    }
+    writer.put_u8(0)?;
+
+    writer.put_u16(TYPE_PTR)?;
+    writer.put_u16(CLASS_IN)?;
+
+    Ok(writer.position)
+}
+
+// Encodes a query with `id` for the A records of `hostname` into `buf`, returning the length
+// of the query.
+pub(crate) fn encode_a_query(id: u16, hostname: &str, buf: &mut [u8]) -> Result<usize, Error> {
+    let mut writer = Writer { buf, position: 0 };
+    put_query_header(&mut writer, id)?;
+
+    for label in hostname.trim_end_matches('.').split('.') {
+        if label.is_empty() || label.len() > MAX_LABEL_LENGTH {
+            return Err(NetworkError::DnsResolveFailed.into());
+        }
+        writer.put_u8(label.len() as u8)?;
+        writer.put_bytes(label.as_bytes())?;
+    }
+    writer.put_u8(0)?;
+
+    writer.put_u16(TYPE_A)?;
+    writer.put_u16(CLASS_IN)?;
+
+    Ok(writer.position)
+}
+
+// Writes the header of a recursive query with `id` and a single question.
+fn put_query_header(writer: &mut Writer, id: u16) -> Result<(), Error> {
+    writer.put_u16(id)?;
+    writer.put_u16(FLAG_RECURSION_DESIRED)?;
+    writer.put_u16(1)?; // questions
+    writer.put_u16(0)?; // answers
+    writer.put_u16(0)?; // authority records
+    writer.put_u16(0) // additional records
+}
+
+// Decodes the name of the first PTR record answering the query with `id` from `response`.
+pub(crate) fn decode_ptr_response(
+    id: u16,
+    response: &[u8],
+) -> Result<String<MAX_DNS_NAME_LENGTH>, Error> {
+    let mut name = None;
+
+    for_each_answer(id, response, &mut |record_type, rdata| {
+        if record_type == TYPE_PTR && name.is_none() {
+            name = Some(decode_name(response, rdata));
+        }
+        Ok(())
+    })?;
+
+    name.unwrap_or(Err(NetworkError::DnsResolveFailed.into()))
+}
+
+// Decodes the addresses of all A records answering the query with `id` from `response`,
+// keeping at most the first `N`.
+pub(crate) fn decode_a_response<const N: usize>(
+    id: u16,
+    response: &[u8],
+) -> Result<Vec<IpAddr, N>, Error> {
+    let mut addresses = Vec::new();
+
+    for_each_answer(id, response, &mut |record_type, rdata| {
+        if record_type == TYPE_A {
+            let ip = response
+                .get(rdata..rdata + 4)
+                .ok_or(NetworkError::DnsResolveFailed)?;
+            addresses
+                .push(IpAddr::V4(Ipv4Addr::new(ip[0], ip[1], ip[2], ip[3])))
+                .ok();
+        }
+        Ok(())
+    })?;
+
+    if addresses.is_empty() {
+        Err(NetworkError::DnsResolveFailed.into())
+    } else {
+        Ok(addresses)
+    }
+}
+
+// Checks that `response` is a successful response to the query with `id` and passes the
+// type and the position of the data of each of its answers to `f`.
+fn for_each_answer<F: FnMut(u16, usize) -> Result<(), Error>>(
+    id: u16,
+    response: &[u8],
+    f: &mut F,
+) -> Result<(), Error> {
+    if read_u16(response, 0)? != id {
+        return Err(NetworkError::DnsResolveFailed.into());
+    }
+    let flags = read_u16(response, 2)?;
+    if flags & FLAG_RESPONSE == 0 || flags & RCODE_MASK != 0 {
+        return Err(NetworkError::DnsResolveFailed.into());
+    }
+    let questions = read_u16(response, 4)?;
+    let answers = read_u16(response, 6)?;
+
+    let mut position = HEADER_LENGTH;
+    for _ in 0..questions {
+        position = skip_name(response, position)? + 4; // type, class
+    }
+
+    for _ in 0..answers {
+        position = skip_name(response, position)?;
+        let record_type = read_u16(response, position)?;
+        let rdata_length = read_u16(response, position + 8)? as usize;
+        let rdata = position + 10;
+
+        f(record_type, rdata)?;
+        position = rdata + rdata_length;
+    }
+
+    Ok(())
+}
+
+// Decodes the possibly compressed name at `position` of `message` into its dotted text form.
+pub(crate) fn decode_name(
+    message: &[u8],
+    mut position: usize,
+) -> Result<String<MAX_DNS_NAME_LENGTH>, Error> {
+    let mut name: String<MAX_DNS_NAME_LENGTH> = String::new();
+    let mut pointers = 0;
+
+    loop {
+        let length = *message
+            .get(position)
+            .ok_or(NetworkError::DnsResolveFailed)? as usize;
+
+        if length & 0xc0 == 0xc0 {
+            pointers += 1;
+            if pointers > MAX_COMPRESSION_POINTERS {
+                return Err(NetworkError::DnsResolveFailed.into());
+            }
+            position = (read_u16(message, position)? & 0x3fff) as usize;
+            continue;
+        }
+        if length == 0 {
+            return Ok(name);
+        }
+
+        let label = message
+            .get(position + 1..position + 1 + length)
+            .ok_or(NetworkError::DnsResolveFailed)?;
+        let label = core::str::from_utf8(label).map_err(|_| NetworkError::DnsResolveFailed)?;
+
+        if !name.is_empty() {
+            name.push('.').map_err(|_| NetworkError::DnsResolveFailed)?;
+        }
+        name.push_str(label)
+            .map_err(|_| NetworkError::DnsResolveFailed)?;
+
+        position += 1 + length;
+    }
+}
+
+// Returns the position right after the possibly compressed name at `position`.
+pub(crate) fn skip_name(message: &[u8], mut position: usize) -> Result<usize, Error> {
+    loop {
+        let length = *message
+            .get(position)
+            .ok_or(NetworkError::DnsResolveFailed)? as usize;
+
+        if length & 0xc0 == 0xc0 {
+            return Ok(position + 2);
+        }
+        if length == 0 {
+            return Ok(position + 1);
+        }
+        position += 1 + length;
+    }
+}
+
+pub(crate) fn read_u16(message: &[u8], position: usize) -> Result<u16, Error> {
+    match message.get(position..position + 2) {
+        Some(bytes) => Ok(u16::from_be_bytes([bytes[0], bytes[1]])),
+        None => Err(NetworkError::DnsResolveFailed.into()),
+    }
+}
+
+// Writes the decimal digits of `value` into `digits`, returning the ones in use.
+fn decimal_digits(mut value: u8, digits: &mut [u8; 3]) -> &[u8] {
+    let mut start = digits.len();
+    loop {
+        start -= 1;
+        digits[start] = b'0' + value % 10;
+        value /= 10;
+        if value == 0 {
+            return &digits[start..];
+        }
+    }
+}
+
+// Appends big-endian encoded fields to a DNS message.
+pub(crate) struct Writer<'a> {
+    pub(crate) buf: &'a mut [u8],
+    pub(crate) position: usize,
+}
+
+impl<'a> Writer<'a> {
+    pub(crate) fn put_bytes(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        let end = self.position + bytes.len();
+        self.buf
+            .get_mut(self.position..end)
+            .ok_or(NetworkError::DnsResolveFailed)?
+            .copy_from_slice(bytes);
+        self.position = end;
+        Ok(())
+    }
+
+    pub(crate) fn put_u8(&mut self, value: u8) -> Result<(), Error> {
+        self.put_bytes(&[value])
+    }
+
+    pub(crate) fn put_u16(&mut self, value: u16) -> Result<(), Error> {
+        self.put_bytes(&value.to_be_bytes())
+    }
+}
+
+#[cfg(test)]
+mod dns_tests {
+    use super::*;
+
+    use core::sync::atomic::{AtomicU32, Ordering};
+
+    use crate::clock::Clock;
+
+    #[test]
+    fn encode_ptr_query_reverses_the_octets_in_the_question_name() {
+        let mut buf = [0u8; MAX_DNS_MESSAGE_LENGTH];
+
+        let length = encode_ptr_query(0x1234, Ipv4Addr::new(192, 168, 1, 42), &mut buf).unwrap();
+
+        assert_eq!(
+            &buf[..length],
+            &[
+                0x12, 0x34, 0x01, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 2, b'4',
+                b'2', 1, b'1', 3, b'1', b'6', b'8', 3, b'1', b'9', b'2', 7, b'i', b'n', b'-', b'a',
+                b'd', b'd', b'r', 4, b'a', b'r', b'p', b'a', 0, 0x00, 0x0c, 0x00, 0x01,
+            ][..]
+        );
+    }
+
+    #[test]
+    fn decode_ptr_response_follows_compression_pointers() {
+        let mut response = [0u8; MAX_DNS_MESSAGE_LENGTH];
+        let query_length =
+            encode_ptr_query(0x1234, Ipv4Addr::new(10, 0, 0, 7), &mut response).unwrap();
+
+        // Turn the query into a response with a single answer
+        response[2] = 0x81;
+        response[3] = 0x80;
+        response[7] = 1;
+
+        let answer: &[u8] = &[
+            0xc0, 0x0c, // name: pointer to the question name
+            0x00, 0x0c, 0x00, 0x01, // type PTR, class IN
+            0x00, 0x00, 0x0e, 0x10, // TTL
+            0x00, 0x0e, // rdata length
+            7, b'p', b'r', b'i', b'n', b't', b'e', b'r', 3, b'l', b'a', b'n', 0xc0, 0x0c,
+        ];
+        response[query_length..query_length + answer.len()].copy_from_slice(answer);
+
+        let name = decode_ptr_response(0x1234, &response[..query_length + answer.len()]).unwrap();
+
+        assert_eq!(name, "printer.lan.7.0.0.10.in-addr.arpa");
+    }
+
+    #[test]
+    fn decode_ptr_response_returns_dns_resolve_failed_error_for_error_responses() {
+        let mut response = [0u8; MAX_DNS_MESSAGE_LENGTH];
+        let length = encode_ptr_query(0x1234, Ipv4Addr::new(10, 0, 0, 7), &mut response).unwrap();
+
+        // NXDOMAIN
+        response[2] = 0x81;
+        response[3] = 0x83;
+
+        assert_eq!(
+            decode_ptr_response(0x1234, &response[..length]),
+            Err(Error::Network(NetworkError::DnsResolveFailed))
+        );
+    }
+
+    #[test]
+    fn decode_a_response_returns_every_a_record() {
+        let mut response = [0u8; MAX_DNS_MESSAGE_LENGTH];
+        let query_length = encode_a_query(0x4321, "mqtt.lan", &mut response).unwrap();
+
+        assert_eq!(
+            &response[HEADER_LENGTH..query_length],
+            &[4, b'm', b'q', b't', b't', 3, b'l', b'a', b'n', 0, 0x00, 0x01, 0x00, 0x01][..]
+        );
+
+        // Turn the query into a response with two answers
+        response[2] = 0x81;
+        response[3] = 0x80;
+        response[7] = 2;
+
+        let answers: &[u8] = &[
+            0xc0, 0x0c, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x00, 0x3c, 0x00, 0x04, 10, 0, 0, 1,
+            0xc0, 0x0c, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x00, 0x3c, 0x00, 0x04, 10, 0, 0, 2,
+        ];
+        response[query_length..query_length + answers.len()].copy_from_slice(answers);
+
+        let addresses: Vec<IpAddr, MAX_RESOLVED_ADDRESSES> =
+            decode_a_response(0x4321, &response[..query_length + answers.len()]).unwrap();
+
+        assert_eq!(
+            &addresses[..],
+            &[
+                IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+                IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2))
+            ][..]
+        );
+    }
+
+    struct TestClock(AtomicU32);
+
+    impl Clock for TestClock {
+        fn now_ms(&self) -> u32 {
+            self.0.load(Ordering::Relaxed)
+        }
+    }
+
+    #[test]
+    fn dns_cache_forgets_addresses_once_their_ttl_has_passed() {
+        static CLOCK: TestClock = TestClock(AtomicU32::new(0));
+        let mut cache = DnsCache::new(ClockHook(&CLOCK), DnsCacheConfig::new().ttl_ms(1_000));
+
+        cache.insert("broker.example.com", Ipv4Addr::new(10, 0, 0, 1));
+        CLOCK.0.store(999, Ordering::Relaxed);
+
+        assert_eq!(
+            cache.lookup("broker.example.com"),
+            Some(Ipv4Addr::new(10, 0, 0, 1))
+        );
+        assert_eq!(cache.lookup("other.example.com"), None);
+
+        CLOCK.0.store(1_000, Ordering::Relaxed);
+
+        assert_eq!(cache.lookup("broker.example.com"), None);
+    }
+
+    #[test]
+    fn dns_cache_replaces_the_oldest_entry_when_full() {
+        static CLOCK: TestClock = TestClock(AtomicU32::new(0));
+        let mut cache = DnsCache::new(ClockHook(&CLOCK), DnsCacheConfig::new());
+
+        let hostnames = ["a.lan", "b.lan", "c.lan", "d.lan", "e.lan"];
+        for (index, hostname) in hostnames.iter().enumerate() {
+            cache.insert(hostname, Ipv4Addr::new(10, 0, 0, index as u8));
+        }
+
+        assert_eq!(cache.lookup("a.lan"), None);
+        assert_eq!(cache.lookup("e.lan"), Some(Ipv4Addr::new(10, 0, 0, 4)));
+    }
+
+    #[test]
+    fn decode_name_rejects_compression_pointer_loops() {
+        let message = [0xc0, 0x00];
+
+        assert_eq!(
+            decode_name(&message, 0),
+            Err(Error::Network(NetworkError::DnsResolveFailed))
+        );
+    }
+}
@@ -0,0 +1,295 @@
+//! Use the ESP32 target's spare pins as extra I/O of the RP2040 and associated types.
+//!
+//! The NINA firmware can drive the pins of the ESP32 that aren't used for the
+//! connection to the RP2040. An [`EspGpio`] borrows a [`Wifi`] instance and passes
+//! pin operations to the ESP32 target over SPI.
+//!
+//! ## Usage
+//!
+//! ```no_run
+//! use esp32_wroom_core::esp_gpio::{AdcAttenuation, AnalogPin, EspGpio, PinMode};
+//!
+//! let mut esp_gpio = EspGpio::build(&mut wifi);
+//!
+//! let reading = esp_gpio.analog_read(AnalogPin::Gpio34, AdcAttenuation::Db11).unwrap();
+//! defmt::info!("ESP32 GPIO34 reads: {:?}", reading);
+//!
+//! esp_gpio.pin_mode(5, PinMode::Output).unwrap();
+//! esp_gpio.digital_write(5, true).unwrap();
+//!
+//! // Dim an LED on GPIO26 to a quarter of its brightness
+//! esp_gpio.analog_write(26, 64).unwrap();
+//! ```
+//!
+//! Boards that route an RGB LED through the ESP32 target, such as the Arduino Nano RP2040
+//! Connect, can set its color with an [`RgbLed`]:
+//!
+//! ```no_run
+//! use esp32_wroom_core::esp_gpio::{RgbLed, RgbLedPins};
+//!
+//! let mut rgb_led = RgbLed::new(&mut wifi, RgbLedPins::nano_rp2040_connect()).unwrap();
+//!
+//! rgb_led.set_color(255, 128, 0).unwrap(); // Orange
+//! ```
+//!
+
+#[cfg(feature = "defmt")]
+use defmt::{write, Format, Formatter};
+
+use embedded_hal::blocking::delay::DelayMs;
+
+use super::protocol::{NinaProtocolHandler, ProtocolInterface};
+use super::transport::Transport;
+use super::wifi::Wifi;
+use super::Error;
+
+/// The direction and pull resistor configuration of an ESP32 pin.
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "ufmt", derive(ufmt::derive::uDebug))]
+pub enum PinMode {
+    /// A floating input
+    Input = 0,
+    /// A push-pull output
+    Output = 1,
+    /// An input with the internal pull-up resistor enabled
+    InputPullUp = 2,
+}
+
+#[cfg(feature = "defmt")]
+impl Format for PinMode {
+    fn format(&self, fmt: Formatter) {
+        match self {
+            PinMode::Input => write!(fmt, "Input"),
+            PinMode::Output => write!(fmt, "Output"),
+            PinMode::InputPullUp => write!(fmt, "InputPullUp"),
+        }
+    }
+}
+
+/// The ESP32 pins that can be read with its ADC1 unit.
+///
+/// ADC2 pins can't be used, as ADC2 is shared with the WiFi radio.
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "ufmt", derive(ufmt::derive::uDebug))]
+pub enum AnalogPin {
+    /// GPIO32, ADC1 channel 4
+    Gpio32 = 32,
+    /// GPIO33, ADC1 channel 5
+    Gpio33 = 33,
+    /// GPIO34, ADC1 channel 6 (input only)
+    Gpio34 = 34,
+    /// GPIO35, ADC1 channel 7 (input only)
+    Gpio35 = 35,
+    /// GPIO36 (SENSOR_VP), ADC1 channel 0 (input only)
+    Gpio36 = 36,
+    /// GPIO39 (SENSOR_VN), ADC1 channel 3 (input only)
+    Gpio39 = 39,
+}
+
+impl AnalogPin {
+    /// The ADC1 channel the pin is read with, which is what the NINA firmware expects rather
+    /// than the GPIO number.
+    pub fn adc1_channel(self) -> u8 {
+        match self {
+            AnalogPin::Gpio32 => 4,
+            AnalogPin::Gpio33 => 5,
+            AnalogPin::Gpio34 => 6,
+            AnalogPin::Gpio35 => 7,
+            AnalogPin::Gpio36 => 0,
+            AnalogPin::Gpio39 => 3,
+        }
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl Format for AnalogPin {
+    fn format(&self, fmt: Formatter) {
+        write!(fmt, "GPIO{=u8}", *self as u8)
+    }
+}
+
+/// How much an analog input is attenuated before it's measured, which sets the range of
+/// voltages that can be read.
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "ufmt", derive(ufmt::derive::uDebug))]
+pub enum AdcAttenuation {
+    /// No attenuation, reads up to about 1.1 V
+    Db0 = 0,
+    /// 2.5 dB attenuation, reads up to about 1.5 V
+    Db2_5 = 1,
+    /// 6 dB attenuation, reads up to about 2.2 V
+    Db6 = 2,
+    /// 11 dB attenuation, reads up to about 3.9 V (limited by the 3.3 V supply)
+    Db11 = 3,
+}
+
+#[cfg(feature = "defmt")]
+impl Format for AdcAttenuation {
+    fn format(&self, fmt: Formatter) {
+        match self {
+            AdcAttenuation::Db0 => write!(fmt, "0 dB"),
+            AdcAttenuation::Db2_5 => write!(fmt, "2.5 dB"),
+            AdcAttenuation::Db6 => write!(fmt, "6 dB"),
+            AdcAttenuation::Db11 => write!(fmt, "11 dB"),
+        }
+    }
+}
+
+/// Controls the spare pins of the ESP32 target.
+pub struct EspGpio<'a, T, D> {
+    pub(crate) protocol_handler: &'a mut NinaProtocolHandler<T, D>,
+}
+
+impl<'a, T, D> EspGpio<'a, T, D>
+where
+    T: Transport,
+    D: DelayMs<u16>,
+{
+    /// Build a new instance of an [`EspGpio`] provided a [`Wifi`] instance.
+    pub fn build(wifi: &'a mut Wifi<T, D>) -> Self {
+        Self {
+            protocol_handler: wifi.protocol_handler.get_mut(),
+        }
+    }
+
+    /// Configure the ESP32's GPIO `pin` (e.g. `5` for GPIO5) as an input or output.
+    ///
+    /// Only pins not used for the connection to the RP2040 should be configured. Which ones
+    /// are free depends on the board, see its schematic.
+    pub fn pin_mode(&mut self, pin: u8, mode: PinMode) -> Result<(), Error> {
+        self.protocol_handler.set_pin_mode(pin, mode as u8)
+    }
+
+    /// Drive output `pin` high if `high` is true and low otherwise.
+    pub fn digital_write(&mut self, pin: u8, high: bool) -> Result<(), Error> {
+        self.protocol_handler.set_digital_write(pin, high as u8)
+    }
+
+    /// Read whether input `pin` is high.
+    pub fn digital_read(&mut self, pin: u8) -> Result<bool, Error> {
+        Ok(self.protocol_handler.get_digital_read(pin)? != 0)
+    }
+
+    /// Output a PWM signal on `pin` that is high for `duty_cycle` out of every 255 steps, e.g.
+    /// to dim an LED. A `duty_cycle` of 0 keeps the pin low and one of 255 keeps it high.
+    pub fn analog_write(&mut self, pin: u8, duty_cycle: u8) -> Result<(), Error> {
+        self.protocol_handler.set_analog_write(pin, duty_cycle)
+    }
+
+    /// Read the raw 12-bit value (0 to 4095) of analog `pin` with `attenuation` applied.
+    pub fn analog_read(
+        &mut self,
+        pin: AnalogPin,
+        attenuation: AdcAttenuation,
+    ) -> Result<u16, Error> {
+        self.protocol_handler
+            .get_analog_read(pin.adc1_channel(), attenuation as u8)
+    }
+}
+
+/// The ESP32 GPIO pins an RGB LED is connected to.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "ufmt", derive(ufmt::derive::uDebug))]
+#[non_exhaustive]
+pub struct RgbLedPins {
+    /// The pin of the red LED
+    pub red: u8,
+    /// The pin of the green LED
+    pub green: u8,
+    /// The pin of the blue LED
+    pub blue: u8,
+    /// Whether the LEDs light up when their pin is driven low (e.g. a common anode LED)
+    pub active_low: bool,
+}
+
+impl RgbLedPins {
+    /// Create a new [`RgbLedPins`] for an LED lit by driving the `red`, `green` and `blue`
+    /// pins high.
+    pub fn new(red: u8, green: u8, blue: u8) -> Self {
+        Self {
+            red,
+            green,
+            blue,
+            active_low: false,
+        }
+    }
+
+    /// The RGB LED of the Arduino Nano RP2040 Connect.
+    pub fn nano_rp2040_connect() -> Self {
+        Self::new(27, 25, 26)
+    }
+
+    /// The RGB LED of the Pimoroni Pico Wireless Pack.
+    pub fn pico_wireless_pack() -> Self {
+        Self::new(25, 26, 27).active_low(true)
+    }
+
+    /// Whether the LEDs light up when their pin is driven low.
+    pub fn active_low(mut self, active_low: bool) -> Self {
+        self.active_low = active_low;
+        self
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl Format for RgbLedPins {
+    fn format(&self, fmt: Formatter) {
+        write!(
+            fmt,
+            "red: GPIO{=u8}, green: GPIO{=u8}, blue: GPIO{=u8}, active_low: {=bool}",
+            self.red, self.green, self.blue, self.active_low
+        )
+    }
+}
+
+/// An RGB LED connected to the ESP32 target, dimmed with PWM.
+pub struct RgbLed<'a, T, D> {
+    esp_gpio: EspGpio<'a, T, D>,
+    pins: RgbLedPins,
+}
+
+impl<'a, T, D> RgbLed<'a, T, D>
+where
+    T: Transport,
+    D: DelayMs<u16>,
+{
+    /// Create a new [`RgbLed`] provided a [`Wifi`] instance and the `pins` of the LED, which
+    /// are configured as outputs. The LED starts out off.
+    pub fn new(wifi: &'a mut Wifi<T, D>, pins: RgbLedPins) -> Result<Self, Error> {
+        let mut rgb_led = Self {
+            esp_gpio: EspGpio::build(wifi),
+            pins,
+        };
+
+        for pin in [pins.red, pins.green, pins.blue] {
+            rgb_led.esp_gpio.pin_mode(pin, PinMode::Output)?;
+        }
+        rgb_led.off()?;
+
+        Ok(rgb_led)
+    }
+
+    /// Set the brightness of the `red`, `green` and `blue` LEDs, from 0 (off) to 255.
+    pub fn set_color(&mut self, red: u8, green: u8, blue: u8) -> Result<(), Error> {
+        let pins = self.pins;
+
+        for (pin, brightness) in [(pins.red, red), (pins.green, green), (pins.blue, blue)] {
+            let duty_cycle = if pins.active_low {
+                255 - brightness
+            } else {
+                brightness
+            };
+            self.esp_gpio.analog_write(pin, duty_cycle)?;
+        }
+
+        Ok(())
+    }
+
+    /// Turn all LEDs off.
+    pub fn off(&mut self) -> Result<(), Error> {
+        self.set_color(0, 0, 0)
+    }
+}
@@ -0,0 +1,172 @@
+//! React to changes of the WiFi connection without scattering status checks everywhere.
+//!
+//! A [`WifiEventHandler`] set with [`Wifi::set_event_handler`] is notified with a [`WifiEvent`]
+//! whenever the connection status read from the ESP32 target differs from the one read before.
+//! Transitions are detected every time the status is read, i.e. by
+//! [`Wifi::get_connection_status`] and anything built on top of it such as a
+//! [`Reconnector`], so polling either of those is enough to drive the handler.
+//!
+//! ## Usage
+//!
+//! ```no_run
+//! use esp32_wroom_core::events::{WifiEvent, WifiEventHandler};
+//!
+//! struct StatusLed;
+//!
+//! impl WifiEventHandler for StatusLed {
+//!     fn on_event(&self, event: WifiEvent) {
+//!         match event {
+//!             WifiEvent::GotIp(_) => led::on(),
+//!             WifiEvent::Disconnected | WifiEvent::ConnectFailed => led::off(),
+//!             _ => {}
+//!         }
+//!     }
+//! }
+//!
+//! wifi.set_event_handler(&StatusLed);
+//!
+//! loop {
+//!     wifi.get_connection_status().ok();
+//! }
+//! ```
+//!
+//! [`Wifi::set_event_handler`]: crate::wifi::Wifi::set_event_handler
+//! [`Wifi::get_connection_status`]: crate::wifi::Wifi::get_connection_status
+//! [`Reconnector`]: crate::reconnect::Reconnector
+//!
+
+use core::fmt;
+use core::net::Ipv4Addr;
+
+#[cfg(feature = "defmt")]
+use defmt::{write, Format, Formatter};
+
+use super::wifi::ConnectionStatus;
+
+/// A change of the WiFi connection reported to a [`WifiEventHandler`].
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[non_exhaustive]
+pub enum WifiEvent {
+    /// The ESP32 target connected to a WiFi network
+    Connected,
+    /// The ESP32 target got the contained IP address after connecting
+    GotIp(Ipv4Addr),
+    /// The ESP32 target is no longer connected to the WiFi network it was connected to
+    Disconnected,
+    /// The ESP32 target failed to connect to a WiFi network
+    ConnectFailed,
+}
+
+#[cfg(feature = "defmt")]
+impl Format for WifiEvent {
+    fn format(&self, fmt: Formatter) {
+        match self {
+            WifiEvent::Connected => write!(fmt, "Connected"),
+            WifiEvent::GotIp(ip) => write!(fmt, "GotIp({})", ip),
+            WifiEvent::Disconnected => write!(fmt, "Disconnected"),
+            WifiEvent::ConnectFailed => write!(fmt, "ConnectFailed"),
+        }
+    }
+}
+
+#[cfg(feature = "ufmt")]
+impl ufmt::uDebug for WifiEvent {
+    fn fmt<W: ufmt::uWrite + ?Sized>(
+        &self,
+        f: &mut ufmt::Formatter<'_, W>,
+    ) -> Result<(), W::Error> {
+        match self {
+            WifiEvent::Connected => f.write_str("Connected"),
+            WifiEvent::GotIp(ip) => {
+                f.write_str("GotIp(")?;
+                super::network::uwrite_ipv4(f, *ip)?;
+                f.write_str(")")
+            }
+            WifiEvent::Disconnected => f.write_str("Disconnected"),
+            WifiEvent::ConnectFailed => f.write_str("ConnectFailed"),
+        }
+    }
+}
+
+/// Gets notified about changes of the WiFi connection.
+pub trait WifiEventHandler {
+    /// Called after a change has been detected. Keep this short, as it runs in between
+    /// commands sent to the ESP32 target.
+    fn on_event(&self, event: WifiEvent);
+}
+
+// Wraps a WifiEventHandler implementation so that types holding one can still derive Debug.
+#[derive(Clone, Copy)]
+pub(crate) struct EventHook(pub(crate) &'static dyn WifiEventHandler);
+
+impl EventHook {
+    pub(crate) fn on_event(&self, event: WifiEvent) {
+        self.0.on_event(event);
+    }
+}
+
+impl fmt::Debug for EventHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("EventHook")
+    }
+}
+
+// Returns the event caused by the connection status changing from `previous` to `current`, if
+// any. The GotIp event that follows Connected is left for the caller to add, as it needs the
+// IP address to be read from the ESP32 target.
+pub(crate) fn transition_event(
+    previous: Option<ConnectionStatus>,
+    current: ConnectionStatus,
+) -> Option<WifiEvent> {
+    let was_connected = previous == Some(ConnectionStatus::Connected);
+
+    match current {
+        ConnectionStatus::Connected if !was_connected => Some(WifiEvent::Connected),
+        ConnectionStatus::Connected => None,
+        _ if was_connected => Some(WifiEvent::Disconnected),
+        ConnectionStatus::Failed if previous != Some(ConnectionStatus::Failed) => {
+            Some(WifiEvent::ConnectFailed)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod events_tests {
+    use super::*;
+
+    #[test]
+    fn transition_event_reports_connecting_and_disconnecting_once() {
+        assert_eq!(
+            transition_event(None, ConnectionStatus::Connected),
+            Some(WifiEvent::Connected)
+        );
+        assert_eq!(
+            transition_event(
+                Some(ConnectionStatus::Connected),
+                ConnectionStatus::Connected
+            ),
+            None
+        );
+        assert_eq!(
+            transition_event(Some(ConnectionStatus::Connected), ConnectionStatus::Lost),
+            Some(WifiEvent::Disconnected)
+        );
+        assert_eq!(
+            transition_event(Some(ConnectionStatus::Lost), ConnectionStatus::Disconnected),
+            None
+        );
+    }
+
+    #[test]
+    fn transition_event_reports_a_failed_connection_once() {
+        assert_eq!(
+            transition_event(Some(ConnectionStatus::Invalid), ConnectionStatus::Failed),
+            Some(WifiEvent::ConnectFailed)
+        );
+        assert_eq!(
+            transition_event(Some(ConnectionStatus::Failed), ConnectionStatus::Failed),
+            None
+        );
+    }
+}
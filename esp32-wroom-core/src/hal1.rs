@@ -0,0 +1,177 @@
+//! Adapters for driving the ESP32 target over an [embedded-hal 1.0](https://github.com/rust-embedded/embedded-hal)
+//! SPI bus or device, enabled by the `embedded-hal-1` feature.
+//!
+//! The crate is still built on the embedded-hal 0.2 `Transfer` trait. Until it has migrated,
+//! wrapping an embedded-hal 1.0 [`SpiDevice`] in a [`SpiDeviceAdapter`] or an [`SpiBus`] in a
+//! [`SpiBusAdapter`] lets current rp2040-hal releases and the bus sharing primitives of
+//! [embedded-hal-bus](https://github.com/rust-embedded/embedded-hal/tree/master/embedded-hal-bus)
+//! be passed to [`Wifi::init`](super::wifi::Wifi::init).
+//!
+//! The NINA firmware expects CS to stay asserted for a whole protocol command, while an
+//! [`SpiDevice`] asserts its own CS for every single transfer. So CS remains driven by
+//! [`EspControlPins`](super::gpio::EspControlPins) and the [`SpiDevice`] should be created with
+//! a no-op CS pin, e.g. from the [dummy-pin](https://crates.io/crates/dummy-pin) crate. Other
+//! devices sharing the bus must not be used while a command is in flight, e.g. from an
+//! interrupt handler.
+//!
+//! ## Usage
+//!
+//! ```no_run
+//! use dummy_pin::DummyPin;
+//! use embedded_hal_bus::spi::{ExclusiveDevice, NoDelay};
+//! use esp32_wroom_core::hal1::SpiDeviceAdapter;
+//!
+//! let spi = hal::Spi::new(pac.SPI0, (mosi, miso, sclk)).init(
+//!     &mut pac.RESETS,
+//!     clocks.peripheral_clock.freq(),
+//!     8.MHz(),
+//!     embedded_hal::spi::MODE_0,
+//! );
+//! let spi_device = ExclusiveDevice::new(spi, DummyPin::new_high(), NoDelay).unwrap();
+//!
+//! let mut wifi = Wifi::init(SpiDeviceAdapter::new(spi_device), esp_pins, &mut delay).unwrap();
+//! ```
+//!
+
+use embedded_hal::blocking::spi::Transfer;
+
+use embedded_hal_1::spi::{SpiBus, SpiDevice};
+
+/// Drives the ESP32 target through an embedded-hal 1.0 [`SpiDevice`].
+pub struct SpiDeviceAdapter<S> {
+    device: S,
+}
+
+impl<S> SpiDeviceAdapter<S>
+where
+    S: SpiDevice<u8>,
+{
+    /// Wrap `device` so it can be passed to [`Wifi::init`](super::wifi::Wifi::init).
+    pub fn new(device: S) -> Self {
+        Self { device }
+    }
+
+    /// Consumes the adapter and returns the wrapped [`SpiDevice`].
+    pub fn destroy(self) -> S {
+        self.device
+    }
+}
+
+impl<S> Transfer<u8> for SpiDeviceAdapter<S>
+where
+    S: SpiDevice<u8>,
+{
+    type Error = S::Error;
+
+    fn transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8], S::Error> {
+        self.device.transfer_in_place(words)?;
+        Ok(words)
+    }
+}
+
+/// Drives the ESP32 target through an embedded-hal 1.0 [`SpiBus`] that isn't shared with other
+/// devices.
+pub struct SpiBusAdapter<S> {
+    bus: S,
+}
+
+impl<S> SpiBusAdapter<S>
+where
+    S: SpiBus<u8>,
+{
+    /// Wrap `bus` so it can be passed to [`Wifi::init`](super::wifi::Wifi::init).
+    pub fn new(bus: S) -> Self {
+        Self { bus }
+    }
+
+    /// Consumes the adapter and returns the wrapped [`SpiBus`].
+    pub fn destroy(self) -> S {
+        self.bus
+    }
+}
+
+impl<S> Transfer<u8> for SpiBusAdapter<S>
+where
+    S: SpiBus<u8>,
+{
+    type Error = S::Error;
+
+    /// Transfer `words` and wait for the bus to finish, so CS can be deasserted right after.
+    fn transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8], S::Error> {
+        self.bus.transfer_in_place(words)?;
+        self.bus.flush()?;
+        Ok(words)
+    }
+}
+
+#[cfg(test)]
+mod hal1_tests {
+    use super::*;
+
+    use core::convert::Infallible;
+
+    use embedded_hal_1::spi::{ErrorType, Operation};
+
+    // Echoes every byte incremented by one and counts the calls it received.
+    #[derive(Default)]
+    struct IncrementingSpi {
+        transactions: usize,
+        flushes: usize,
+    }
+
+    impl ErrorType for IncrementingSpi {
+        type Error = Infallible;
+    }
+
+    impl SpiDevice<u8> for IncrementingSpi {
+        fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Infallible> {
+            self.transactions += 1;
+            for operation in operations {
+                if let Operation::TransferInPlace(words) = operation {
+                    words.iter_mut().for_each(|word| *word += 1);
+                }
+            }
+            Ok(())
+        }
+    }
+
+    impl SpiBus<u8> for IncrementingSpi {
+        fn read(&mut self, _words: &mut [u8]) -> Result<(), Infallible> {
+            Ok(())
+        }
+
+        fn write(&mut self, _words: &[u8]) -> Result<(), Infallible> {
+            Ok(())
+        }
+
+        fn transfer(&mut self, _read: &mut [u8], _write: &[u8]) -> Result<(), Infallible> {
+            Ok(())
+        }
+
+        fn transfer_in_place(&mut self, words: &mut [u8]) -> Result<(), Infallible> {
+            words.iter_mut().for_each(|word| *word += 1);
+            Ok(())
+        }
+
+        fn flush(&mut self) -> Result<(), Infallible> {
+            self.flushes += 1;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn spi_device_adapter_transfers_words_in_a_single_transaction() {
+        let mut adapter = SpiDeviceAdapter::new(IncrementingSpi::default());
+
+        assert_eq!(adapter.transfer(&mut [0x1, 0x2]).unwrap(), &[0x2, 0x3]);
+        assert_eq!(adapter.destroy().transactions, 1);
+    }
+
+    #[test]
+    fn spi_bus_adapter_flushes_after_transferring_words() {
+        let mut adapter = SpiBusAdapter::new(IncrementingSpi::default());
+
+        assert_eq!(adapter.transfer(&mut [0x1, 0x2]).unwrap(), &[0x2, 0x3]);
+        assert_eq!(adapter.destroy().flushes, 1);
+    }
+}
@@ -0,0 +1,295 @@
+//! Drive commands from interrupts, e.g. an [RTIC](https://rtic.rs) task woken by the GPIO
+//! interrupt of the ACK pin.
+//!
+//! Every [`Wifi`] method spins on the ACK pin while it waits for the NINA firmware, which keeps
+//! the CPU busy for as long as the ESP32 target takes to answer. A [`PendingCommand`] instead
+//! advances as far as it can each time [`PendingCommand::poll`] is called and returns
+//! [`CommandProgress::Pending`] as soon as it would have to wait for the ACK pin. Calling
+//! [`PendingCommand::poll`] again after the next edge of the ACK pin picks up where it left
+//! off.
+//!
+//! The ACK pin is owned by the driver, so the application clears its pending interrupt through
+//! the PAC. A [`PendingCommand`] borrows the [`Wifi`] for as long as it exists, so no other
+//! command can be sent while it awaits its reply. Dropping it part way, e.g. when a task gives
+//! up waiting, cancels it with [`PendingCommand::cancel`].
+//!
+//! ## Usage
+//!
+//! ```no_run
+//! use esp32_wroom_core::interrupt::{CommandProgress, PendingCommand, Reply, Request};
+//!
+//! #[task(binds = IO_IRQ_BANK0)]
+//! fn esp_ack_edge(_cx: esp_ack_edge::Context) {
+//!     clear_ack_pin_interrupt();
+//!     ACK_EDGE.signal(());
+//! }
+//!
+//! #[task(local = [wifi])]
+//! async fn connection_status(cx: connection_status::Context) {
+//!     let mut command = PendingCommand::new(cx.local.wifi, Request::ConnectionStatus);
+//!
+//!     loop {
+//!         match command.poll() {
+//!             Ok(CommandProgress::Pending) => ACK_EDGE.wait().await,
+//!             Ok(CommandProgress::Done(Reply::ConnectionStatus(status))) => {
+//!                 defmt::info!("Connection status: {:?}", status);
+//!                 break;
+//!             }
+//!             Ok(_) => break,
+//!             Err(e) => {
+//!                 defmt::error!("Getting the connection status failed: {:?}", e);
+//!                 break;
+//!             }
+//!         }
+//!     }
+//! }
+//! ```
+//!
+
+#[cfg(feature = "defmt")]
+use defmt::{write, Format, Formatter};
+
+use embedded_hal::blocking::delay::DelayMs;
+
+use super::network::{ConnectionState, NetworkError, Socket};
+use super::protocol::operation::Operation;
+use super::protocol::{NinaAbstractParam, NinaByteParam, NinaCommand, NinaConcreteParam};
+use super::transport::Transport;
+use super::wifi::{ConnectionStatus, Wifi};
+use super::Error;
+
+/// A command a [`PendingCommand`] sends to the ESP32 target.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "ufmt", derive(ufmt::derive::uDebug))]
+#[non_exhaustive]
+pub enum Request {
+    /// Get the WiFi network [`ConnectionStatus`].
+    ConnectionStatus,
+    /// Get the [`ConnectionState`] of the TCP connection on a socket.
+    ClientState(Socket),
+    /// Get the number of bytes received on a socket that are ready to be read.
+    DataAvailable(Socket),
+    /// Close the client connection on a socket.
+    StopClient(Socket),
+}
+
+#[cfg(feature = "defmt")]
+impl Format for Request {
+    fn format(&self, fmt: Formatter) {
+        match self {
+            Request::ConnectionStatus => write!(fmt, "ConnectionStatus"),
+            Request::ClientState(socket) => write!(fmt, "ClientState({=u8})", socket),
+            Request::DataAvailable(socket) => write!(fmt, "DataAvailable({=u8})", socket),
+            Request::StopClient(socket) => write!(fmt, "StopClient({=u8})", socket),
+        }
+    }
+}
+
+/// The answer of the ESP32 target to a [`Request`].
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "ufmt", derive(ufmt::derive::uDebug))]
+#[non_exhaustive]
+pub enum Reply {
+    /// The answer to [`Request::ConnectionStatus`].
+    ConnectionStatus(ConnectionStatus),
+    /// The answer to [`Request::ClientState`].
+    ClientState(ConnectionState),
+    /// The answer to [`Request::DataAvailable`].
+    DataAvailable(usize),
+    /// The client connection of [`Request::StopClient`] has been closed.
+    StopClient,
+}
+
+#[cfg(feature = "defmt")]
+impl Format for Reply {
+    fn format(&self, fmt: Formatter) {
+        match self {
+            Reply::ConnectionStatus(status) => write!(fmt, "ConnectionStatus({})", status),
+            Reply::ClientState(state) => write!(fmt, "ClientState({})", state),
+            Reply::DataAvailable(length) => write!(fmt, "DataAvailable({=usize})", length),
+            Reply::StopClient => write!(fmt, "StopClient"),
+        }
+    }
+}
+
+/// What a single [`PendingCommand::poll`] achieved.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "ufmt", derive(ufmt::derive::uDebug))]
+pub enum CommandProgress {
+    /// The ESP32 target is busy, poll again on the next edge of the ACK pin.
+    Pending,
+    /// The command has completed with the given [`Reply`].
+    Done(Reply),
+}
+
+#[cfg(feature = "defmt")]
+impl Format for CommandProgress {
+    fn format(&self, fmt: Formatter) {
+        match self {
+            CommandProgress::Pending => write!(fmt, "Pending"),
+            CommandProgress::Done(reply) => write!(fmt, "Done({})", reply),
+        }
+    }
+}
+
+// The steps of sending a command and receiving its reply, each of which waits for the ACK pin.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Stage {
+    // Waiting for the NINA firmware to be ready before sending the command
+    SendReady,
+    // Selected the NINA firmware, waiting for it to acknowledge before sending the command
+    SendAck,
+    // Waiting for the NINA firmware to be ready to send the reply
+    ReceiveReady,
+    // Selected the NINA firmware, waiting for it to acknowledge before receiving the reply
+    ReceiveAck,
+}
+
+/// A [`Request`] that is sent to the ESP32 target of a borrowed [`Wifi`] and answered over a
+/// series of [`PendingCommand::poll`] calls. See the [module documentation](self) for details.
+pub struct PendingCommand<'a, T, D>
+where
+    T: Transport,
+    D: DelayMs<u16>,
+{
+    wifi: &'a mut Wifi<T, D>,
+    request: Request,
+    stage: Stage,
+}
+
+impl<'a, T, D> PendingCommand<'a, T, D>
+where
+    T: Transport,
+    D: DelayMs<u16>,
+{
+    /// Create a new [`PendingCommand`] that sends `request` to the ESP32 target of `wifi` on its
+    /// first poll.
+    pub fn new(wifi: &'a mut Wifi<T, D>, request: Request) -> Self {
+        Self {
+            wifi,
+            request,
+            stage: Stage::SendReady,
+        }
+    }
+
+    /// The [`Request`] this command sends.
+    pub fn request(&self) -> Request {
+        self.request
+    }
+
+    /// Advance the command as far as the ESP32 target allows without waiting. Once it's done,
+    /// the next poll sends the same [`Request`] again, so a periodic request can keep reusing
+    /// the same [`PendingCommand`].
+    ///
+    /// Unlike [`Wifi::get_connection_status`], a [`Request::ConnectionStatus`] doesn't notify
+    /// the event handler set with [`Wifi::set_event_handler`].
+    pub fn poll(&mut self) -> Result<CommandProgress, Error> {
+        let operation = self.operation()?;
+        let protocol_handler = self.wifi.protocol_handler.get_mut();
+
+        loop {
+            match self.stage {
+                Stage::SendReady | Stage::ReceiveReady => {
+                    if !protocol_handler.transport.is_ready() {
+                        return Ok(CommandProgress::Pending);
+                    }
+
+                    if self.stage == Stage::SendReady {
+                        protocol_handler.begin_transaction()?;
+                        self.stage = Stage::SendAck;
+                    } else {
+                        self.stage = Stage::ReceiveAck;
+                    }
+                    protocol_handler.transport.select();
+                }
+                Stage::SendAck => {
+                    if !protocol_handler.transport.is_acknowledged() {
+                        return Ok(CommandProgress::Pending);
+                    }
+
+                    let result = protocol_handler.send_operation(&operation);
+                    protocol_handler.transport.deselect();
+                    if let Err(error) = result {
+                        protocol_handler.end_transaction();
+                        self.stage = Stage::SendReady;
+                        return Err(error);
+                    }
+
+                    self.stage = Stage::ReceiveReady;
+                }
+                Stage::ReceiveAck => {
+                    if !protocol_handler.transport.is_acknowledged() {
+                        return Ok(CommandProgress::Pending);
+                    }
+
+                    protocol_handler.end_transaction();
+                    let result = protocol_handler.read_reply(&operation.command, 1);
+                    protocol_handler.transport.deselect();
+                    self.stage = Stage::SendReady;
+
+                    let result = result?;
+                    let reply = match self.request {
+                        Request::ConnectionStatus => {
+                            Reply::ConnectionStatus(ConnectionStatus::from(result[0]))
+                        }
+                        Request::ClientState(_) => {
+                            Reply::ClientState(ConnectionState::from(result[0]))
+                        }
+                        // The number of available bytes is sent back as a little-endian u16
+                        Request::DataAvailable(_) => {
+                            Reply::DataAvailable(u16::from_le_bytes([result[0], result[1]]) as usize)
+                        }
+                        Request::StopClient(socket) => {
+                            if result[0] != 1 {
+                                return Err(NetworkError::DisconnectFailed.into());
+                            }
+                            protocol_handler.mark_socket_closed(socket);
+                            Reply::StopClient
+                        }
+                    };
+
+                    return Ok(CommandProgress::Done(reply));
+                }
+            }
+        }
+    }
+
+    /// Abandon the command part way, deselecting the ESP32 target and allowing other commands
+    /// to be sent again. The next poll sends the [`Request`] again from the start.
+    ///
+    /// A reply the ESP32 target is already preparing for a command that has been sent is left
+    /// unread. Cancelling does nothing if the command hasn't been started or has completed.
+    pub fn cancel(&mut self) {
+        let protocol_handler = self.wifi.protocol_handler.get_mut();
+
+        match self.stage {
+            Stage::SendReady => return,
+            Stage::SendAck | Stage::ReceiveAck => protocol_handler.transport.deselect(),
+            Stage::ReceiveReady => {}
+        }
+        protocol_handler.end_transaction();
+        self.stage = Stage::SendReady;
+    }
+
+    fn operation(&self) -> Result<Operation<NinaAbstractParam>, Error> {
+        Ok(match self.request {
+            Request::ConnectionStatus => Operation::new(NinaCommand::GetConnStatus),
+            Request::ClientState(socket) => Operation::new(NinaCommand::GetClientStateTcp)
+                .param(NinaByteParam::from_bytes(&[socket])?),
+            Request::DataAvailable(socket) => Operation::new(NinaCommand::AvailDataTcp)
+                .param(NinaByteParam::from_bytes(&[socket])?),
+            Request::StopClient(socket) => Operation::new(NinaCommand::StopClientTcp)
+                .param(NinaByteParam::from_bytes(&[socket])?),
+        })
+    }
+}
+
+impl<T, D> Drop for PendingCommand<'_, T, D>
+where
+    T: Transport,
+    D: DelayMs<u16>,
+{
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
@@ -0,0 +1,122 @@
+//! Implementations of the [embedded-io](https://github.com/rust-embedded/embedded-hal/tree/master/embedded-io)
+//! `Read` and `Write` traits for [`TcpClient`], enabled by the `embedded-io` feature.
+//!
+//! This lets parsers and serializers written against embedded-io streams (e.g. line readers or
+//! serde transports) read from and write to a connected server directly.
+//!
+//! Both traits block until at least one byte could be read or written. In between two polls of
+//! the ESP32 target control is handed to the application's scheduler if one was set with
+//! [`Wifi::set_yield`](super::wifi::Wifi::set_yield). Read and write timeouts only apply to the
+//! inherent [`TcpClient`] methods such as [`TcpClient::receive_data`].
+//!
+//! ## Usage
+//!
+//! ```no_run
+//! use embedded_io::{Read, Write};
+//!
+//! let mut tcp_client = TcpClient::build(&mut wifi);
+//! // ... connect tcp_client to a server ...
+//!
+//! tcp_client.write_all(b"GET / HTTP/1.1\r\nHost: example.com\r\n\r\n").unwrap();
+//!
+//! let mut status_line = [0u8; 16];
+//! tcp_client.read_exact(&mut status_line).unwrap();
+//! ```
+//!
+
+use embedded_hal::blocking::delay::DelayMs;
+
+use embedded_io::{ErrorKind, ErrorType, Read, Write};
+
+use super::network::NetworkError;
+use super::protocol::{ProtocolInterface, MAX_NINA_LARGE_ARRAY_PARAM_BUFFER_LENGTH};
+use super::tcp_client::TcpClient;
+use super::transport::Transport;
+use super::Error;
+
+impl embedded_io::Error for Error {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Network(NetworkError::ConnectionLost) => ErrorKind::ConnectionReset,
+            Error::Network(NetworkError::ConnectFailed) => ErrorKind::ConnectionRefused,
+            Error::Network(NetworkError::ConnectionTimeout)
+            | Error::Network(NetworkError::ReadTimeout)
+            | Error::Network(NetworkError::WriteTimeout) => ErrorKind::TimedOut,
+            Error::Network(NetworkError::UdpSocketNotConnected) => ErrorKind::NotConnected,
+            Error::Network(NetworkError::Ipv6Unsupported) | Error::UnsupportedOperation => {
+                ErrorKind::Unsupported
+            }
+            _ => ErrorKind::Other,
+        }
+    }
+}
+
+impl<T, D> ErrorType for TcpClient<'_, T, D> {
+    type Error = Error;
+}
+
+impl<T, D> Read for TcpClient<'_, T, D>
+where
+    T: Transport,
+    D: DelayMs<u16>,
+{
+    /// Wait for data from the connected server and read it into `buf`, returning the number
+    /// of bytes read. Returns 0 once the server has closed the connection and all of its data
+    /// has been read.
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        loop {
+            let available = self.bytes_available()?;
+            if available > 0 {
+                let length = available.min(buf.len());
+                let socket = self.socket.unwrap_or_default();
+                return self
+                    .protocol_handler
+                    .get_data_buf_tcp(socket, &mut buf[..length]);
+            }
+
+            if !self.is_connected()? {
+                return Ok(0);
+            }
+            self.protocol_handler.yield_now();
+        }
+    }
+}
+
+impl<T, D> Write for TcpClient<'_, T, D>
+where
+    T: Transport,
+    D: DelayMs<u16>,
+{
+    /// Send as much of `buf` as the ESP32 target accepts at once, waiting while it has no
+    /// room to accept any. Fails with [`NetworkError::SendFailed`] if the ESP32 target
+    /// couldn't confirm the data was sent.
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        let socket = self.socket.unwrap_or_default();
+        let length = buf.len().min(MAX_NINA_LARGE_ARRAY_PARAM_BUFFER_LENGTH);
+
+        loop {
+            let written = self.protocol_handler.send_data(&buf[..length], socket)?;
+            if written > 0 {
+                if !self.protocol_handler.check_data_sent(socket)? {
+                    return Err(NetworkError::SendFailed.into());
+                }
+                return Ok(written);
+            }
+            self.protocol_handler.yield_now();
+        }
+    }
+
+    /// Every [`Write::write`] is confirmed to have been sent by the ESP32 target, so there is
+    /// nothing left to flush.
+    fn flush(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+}
@@ -0,0 +1,79 @@
+//! Keep idle connections alive from a client's poll loop.
+//!
+//! Protocols such as MQTT drop a session as soon as the client misses a single keep-alive
+//! window. A [`KeepAlive`] registered for a connected [`TcpClient`] builds the ping message to
+//! send, and [`TcpClient::poll`] sends it whenever its interval has elapsed since the previous
+//! ping, no matter what else the application is doing in between polls.
+//!
+//! ## Usage
+//!
+//! ```no_run
+//! use esp32_wroom_core::keep_alive::KeepAlive;
+//!
+//! struct MqttPing;
+//!
+//! impl KeepAlive for MqttPing {
+//!     fn interval_ms(&self) -> u32 {
+//!         30_000
+//!     }
+//!
+//!     fn ping(&self, buf: &mut [u8]) -> usize {
+//!         // MQTT PINGREQ
+//!         buf[..2].copy_from_slice(&[0xc0, 0x00]);
+//!         2
+//!     }
+//! }
+//!
+//! static MQTT_PING: MqttPing = MqttPing;
+//!
+//! TcpClient::build(&mut wifi).connect(hostname, 1883, TransportMode::Tcp, &mut |tcp_client| {
+//!     tcp_client.set_keep_alive(&MQTT_PING).ok();
+//!
+//!     loop {
+//!         tcp_client.poll(timer.now_ms()).ok();
+//!
+//!         // Publish, receive, etc.
+//!     }
+//! });
+//! ```
+//!
+//! [`TcpClient`]: crate::tcp_client::TcpClient
+//! [`TcpClient::poll`]: crate::tcp_client::TcpClient::poll
+//!
+
+use core::fmt;
+
+use super::network::Socket;
+
+/// The maximum length in bytes of a single keep-alive ping message.
+pub const MAX_KEEP_ALIVE_LENGTH: usize = 64;
+
+/// The maximum number of sockets that can have a [`KeepAlive`] registered at once.
+pub const MAX_KEEP_ALIVES: usize = 4;
+
+/// Builds the keep-alive ping message that is periodically sent on a connected socket.
+pub trait KeepAlive {
+    /// How many milliseconds may pass in between two pings.
+    fn interval_ms(&self) -> u32;
+
+    /// Write the ping message into `buf` and return the number of bytes written. `buf` holds
+    /// [`MAX_KEEP_ALIVE_LENGTH`] bytes.
+    fn ping(&self, buf: &mut [u8]) -> usize;
+}
+
+// A KeepAlive registered for a socket along with the time its last ping was sent.
+#[derive(Clone, Copy)]
+pub(crate) struct KeepAliveEntry {
+    pub(crate) socket: Socket,
+    pub(crate) keep_alive: &'static dyn KeepAlive,
+    pub(crate) last_sent_ms: Option<u32>,
+}
+
+impl fmt::Debug for KeepAliveEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeepAliveEntry")
+            .field("socket", &self.socket)
+            .field("last_sent_ms", &self.last_sent_ms)
+            .finish()
+    }
+}
@@ -0,0 +1,428 @@
+//! esp32-wroom-core
+//!
+//! This crate is the MCU-agnostic core of the Espressif ESP32-WROOM WiFi module communications
+//! driver implemented in Rust. It speaks the NINA protocol of recent versions of most
+//! [Arduino-derived WiFiNINA firmwares](https://www.arduino.cc/reference/en/libraries/wifinina/)
+//! that run on an ESP32-WROOM-XX or u-blox NINA-W10x WiFi module, e.g. Adafruit's
+//! [Airlift](https://www.adafruit.com/product/4201) with its [firmware](https://github.com/adafruit/nina-fw).
+//!
+//! The driver is implemented on top of [embedded-hal](https://github.com/rust-embedded/embedded-hal/)
+//! traits only, so it can drive the ESP32 target from any microcontroller with a HAL that
+//! implements them, e.g. an STM32, an nRF52 or another ESP32 acting as the host. Users of an
+//! RP2040 board will want the [esp32-wroom-rp](https://docs.rs/esp32-wroom-rp) crate instead,
+//! which re-exports this crate and adds pin presets and examples for RP2040 based boards.
+//!
+//! All communication with the WiFi board occurs via a SPI bus, so an application needs an
+//! instance of its HAL's SPI type that implements `embedded_hal::blocking::spi::Transfer<u8>`.
+//! It also needs to reserve 4 GPIO pins (3 output, 1 input) that are used to mediate
+//! communication between the two boards, passed to the driver in an instance of
+//! [`gpio::EspControlPins`].
+//!
+//! **NOTE:** This crate is still under active development. This API will remain volatile until 1.0.0.
+//! To soften that, public error and status enums are marked `#[non_exhaustive]` and configuration types
+//! are created through constructors or builders with defaults rather than struct literals, so that new
+//! variants and options can be added without breaking existing code.
+//!
+//! ## Crate layout
+//!
+//! The crate is split into three layers:
+//!
+//! * The NINA protocol core: the command set and the wire codec (`protocol`), the handler that
+//!   frames commands and parses replies (`spi`) and the error types. It only depends on the
+//!   [embedded-hal](https://github.com/rust-embedded/embedded-hal/) `DelayMs` trait and a
+//!   [`transport::Transport`] that carries the bytes, so it doesn't pull in any MCU or
+//!   Cortex-M specific crates and can be driven from any host.
+//! * The transport layer: [`transport::SpiTransport`], which carries the protocol over an SPI
+//!   bus implementing `Transfer` paced by [`gpio::EspControlInterface`] pins. Other links to
+//!   the ESP32 target plug in as further [`transport::Transport`] implementations.
+//! * The board layer: [`gpio::EspControlPins`] and the high-level [`wifi::Wifi`], client and
+//!   server types built on top of the core.
+//!
+//! ## Logging
+//!
+//! The driver doesn't log anything by default. Enable the `defmt` feature to log through
+//! [defmt](https://defmt.ferrous-systems.com) and implement `defmt::Format` for the crate's
+//! public types, or the `log` feature to log through the [log](https://docs.rs/log) crate.
+//!
+//! ## Usage
+//!
+//! ```no_run
+//! use esp32_wroom_core::gpio::EspControlPins;
+//! use esp32_wroom_core::wifi::Wifi;
+//!
+//! // `spi` implements Transfer<u8>, the pins OutputPin/InputPin and `delay` DelayMs<u16>,
+//! // all provided by the HAL of the host microcontroller
+//! let esp_pins = EspControlPins {
+//!     cs,
+//!     gpio0,
+//!     resetn,
+//!     ack,
+//! };
+//! let mut wifi = Wifi::init(spi, esp_pins, delay).unwrap();
+//! let firmware_version = wifi.firmware_version();
+//! ```
+//!
+
+#![doc(html_root_url = "https://docs.rs/esp32-wroom-core")]
+#![doc(issue_tracker_base_url = "https://github.com/Jim-Hodapp-Coaching/esp32-wroom-rp/issues")]
+#![warn(missing_docs)]
+#![cfg_attr(not(any(test, feature = "std")), no_std)]
+
+pub mod activity;
+pub mod clock;
+pub mod coap;
+pub mod console;
+pub mod credentials;
+pub mod diagnostics;
+pub mod dns;
+pub mod esp_gpio;
+pub mod events;
+pub mod framing;
+pub mod gpio;
+#[cfg(feature = "embedded-hal-1")]
+pub mod hal1;
+pub mod interrupt;
+#[cfg(feature = "embedded-io")]
+pub mod io;
+pub mod keep_alive;
+pub mod mdns;
+pub mod multicore;
+#[cfg(feature = "embedded-nal")]
+pub mod nal;
+#[cfg(feature = "async")]
+pub mod nal_async;
+pub mod network;
+pub mod offline_queue;
+pub mod protocol;
+pub mod reconnect;
+pub mod scheduler;
+#[cfg(feature = "std")]
+pub mod sim;
+pub mod sntp;
+pub mod ssdp;
+pub mod stats;
+#[cfg(feature = "embedded-svc")]
+pub mod svc;
+pub mod tcp_client;
+pub mod tcp_server;
+pub mod tls_client;
+pub mod transport;
+pub mod udp_client;
+pub mod udp_server;
+pub mod wifi;
+#[cfg(feature = "async-hal")]
+pub mod wifi_async;
+
+#[macro_use]
+mod logging;
+mod spi;
+#[cfg(feature = "async-hal")]
+mod spi_async;
+
+use core::fmt;
+
+#[cfg(feature = "defmt")]
+use defmt::{write, Format, Formatter};
+
+use heapless::String;
+
+use framing::FramingError;
+
+use network::NetworkError;
+
+use protocol::ProtocolError;
+
+use transport::SpiClockError;
+
+use wifi::InitStage;
+
+/// Highest level error types for this crate.
+#[derive(Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "ufmt", derive(ufmt::derive::uDebug))]
+#[non_exhaustive]
+pub enum Error {
+    /// SPI/I2C related communications error with the ESP32 WiFi target
+    Bus,
+    /// Protocol error in communicating with the ESP32 WiFi target
+    Protocol(ProtocolError),
+
+    /// Network related error
+    Network(NetworkError),
+
+    /// A stage of initializing the ESP32 WiFi target did not complete in time
+    InitTimeout(InitStage),
+
+    /// Application framing related error
+    Framing(FramingError),
+
+    /// A command was attempted while another one is still awaiting its response from the ESP32
+    /// WiFi target
+    Busy,
+
+    /// The requested operation isn't supported by the NINA firmware on the ESP32 WiFi target
+    UnsupportedOperation,
+
+    /// A TCP only operation was attempted on a UDP socket, or a UDP only operation on a TCP
+    /// socket
+    WrongTransportMode,
+
+    /// The SPI bus is clocked at a frequency the NINA firmware can't keep up with
+    SpiClock(SpiClockError),
+}
+
+#[cfg(feature = "defmt")]
+impl Format for Error {
+    fn format(&self, fmt: Formatter) {
+        match self {
+            Error::Bus => write!(fmt, "Bus error"),
+            Error::Protocol(e) => write!(
+                fmt,
+                "Communication protocol error with ESP32 WiFi target: {}",
+                e
+            ),
+            Error::Network(e) => write!(fmt, "Network error: {}", e),
+            Error::InitTimeout(stage) => write!(
+                fmt,
+                "Timed out initializing ESP32 WiFi target during stage: {}",
+                stage
+            ),
+            Error::Framing(e) => write!(fmt, "Framing error: {}", e),
+            Error::Busy => write!(
+                fmt,
+                "Another command is still awaiting its response from the ESP32 WiFi target"
+            ),
+            Error::UnsupportedOperation => write!(
+                fmt,
+                "Operation is not supported by the NINA firmware on the ESP32 WiFi target"
+            ),
+            Error::WrongTransportMode => write!(
+                fmt,
+                "Operation is not supported by the transport mode of the socket"
+            ),
+            Error::SpiClock(e) => write!(fmt, "SPI clock error: {}", e),
+        }
+    }
+}
+
+impl From<protocol::ProtocolError> for Error {
+    fn from(err: protocol::ProtocolError) -> Self {
+        Error::Protocol(err)
+    }
+}
+
+impl From<network::NetworkError> for Error {
+    fn from(err: network::NetworkError) -> Self {
+        Error::Network(err)
+    }
+}
+
+impl From<framing::FramingError> for Error {
+    fn from(err: framing::FramingError) -> Self {
+        Error::Framing(err)
+    }
+}
+
+/// The maximum length of the suffix tag of a [`FirmwareVersion`] (e.g. `-beta1`).
+pub const MAX_FIRMWARE_VERSION_SUFFIX_LENGTH: usize = 16;
+
+/// A structured representation of a connected NINA firmware device's version number (e.g. 1.7.4).
+///
+/// Besides the numeric components, any suffix tag following the patch number (e.g. the `-beta1`
+/// in `1.8.0-beta1`) is kept as well. It's displayed in its original form (e.g. `1.8.0-beta1`).
+#[derive(Debug, Default, Eq, PartialEq, Clone)]
+pub struct FirmwareVersion {
+    major: u8,
+    minor: u8,
+    patch: u8,
+    suffix: String<MAX_FIRMWARE_VERSION_SUFFIX_LENGTH>,
+}
+
+impl FirmwareVersion {
+    fn new(version: &[u8]) -> FirmwareVersion {
+        Self::parse(version)
+    }
+
+    // Takes in a version string of any length (e.g. 1.7.4 or 1.10.0-beta1) that may be NUL
+    // terminated and returns a FirmwareVersion instance. Missing components are left as 0.
+    fn parse(version: &[u8]) -> FirmwareVersion {
+        let length = version
+            .iter()
+            .position(|byte| *byte == 0)
+            .unwrap_or(version.len());
+        let version = &version[..length];
+
+        let mut components = [0u8; 3];
+        let mut position = 0;
+
+        for (index, component) in components.iter_mut().enumerate() {
+            let digits = version[position..]
+                .iter()
+                .take_while(|byte| byte.is_ascii_digit())
+                .count();
+
+            *component = version[position..position + digits]
+                .iter()
+                .fold(0u8, |number, digit| {
+                    number.saturating_mul(10).saturating_add(digit - b'0')
+                });
+            position += digits;
+
+            // Components are separated by a '.', anything else starts the suffix tag
+            if index < 2 && version.get(position) == Some(&b'.') {
+                position += 1;
+            } else {
+                break;
+            }
+        }
+
+        let mut suffix = String::new();
+        for byte in version[position..]
+            .iter()
+            .take_while(|byte| byte.is_ascii_graphic())
+        {
+            if suffix.push(*byte as char).is_err() {
+                break;
+            }
+        }
+
+        FirmwareVersion {
+            major: components[0],
+            minor: components[1],
+            patch: components[2],
+            suffix,
+        }
+    }
+
+    /// The major version number (e.g. the 1 in 1.7.4).
+    pub fn major(&self) -> u8 {
+        self.major
+    }
+
+    /// The minor version number (e.g. the 7 in 1.7.4).
+    pub fn minor(&self) -> u8 {
+        self.minor
+    }
+
+    /// The patch version number (e.g. the 4 in 1.7.4).
+    pub fn patch(&self) -> u8 {
+        self.patch
+    }
+
+    /// The suffix tag following the patch number (e.g. `-beta1`), or an empty string if there
+    /// is none.
+    pub fn suffix(&self) -> &str {
+        self.suffix.as_str()
+    }
+}
+
+impl fmt::Display for FirmwareVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        core::write!(
+            f,
+            "{}.{}.{}{}",
+            self.major,
+            self.minor,
+            self.patch,
+            self.suffix
+        )
+    }
+}
+
+#[cfg(feature = "ufmt")]
+impl ufmt::uDisplay for FirmwareVersion {
+    fn fmt<W: ufmt::uWrite + ?Sized>(
+        &self,
+        f: &mut ufmt::Formatter<'_, W>,
+    ) -> Result<(), W::Error> {
+        ufmt::uwrite!(
+            f,
+            "{}.{}.{}{}",
+            self.major,
+            self.minor,
+            self.patch,
+            self.suffix.as_str()
+        )
+    }
+}
+
+#[cfg(feature = "ufmt")]
+impl ufmt::uDebug for FirmwareVersion {
+    fn fmt<W: ufmt::uWrite + ?Sized>(
+        &self,
+        f: &mut ufmt::Formatter<'_, W>,
+    ) -> Result<(), W::Error> {
+        ufmt::uDisplay::fmt(self, f)
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl Format for FirmwareVersion {
+    fn format(&self, fmt: Formatter) {
+        write!(
+            fmt,
+            "Major: {:?}, Minor: {:?}, Patch: {:?}",
+            self.major, self.minor, self.patch
+        );
+        if !self.suffix.is_empty() {
+            write!(fmt, ", Suffix: {=str}", self.suffix.as_str());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn firmware_new_returns_a_populated_firmware_struct() {
+        let firmware_version: FirmwareVersion = FirmwareVersion::new(b"1.7.4");
+
+        assert_eq!(
+            firmware_version,
+            FirmwareVersion {
+                major: 1,
+                minor: 7,
+                patch: 4,
+                suffix: String::new()
+            }
+        )
+    }
+
+    #[test]
+    fn firmware_new_stops_at_nul_terminator() {
+        let firmware_version = FirmwareVersion::new(b"1.7.7\0\xff\xff");
+
+        assert_eq!(
+            (
+                firmware_version.major(),
+                firmware_version.minor(),
+                firmware_version.patch()
+            ),
+            (1, 7, 7)
+        );
+        assert_eq!(firmware_version.suffix(), "");
+    }
+
+    #[test]
+    fn firmware_new_parses_multi_digit_components_and_suffix_tag() {
+        let firmware_version = FirmwareVersion::new(b"12.10.255-beta1\0");
+
+        assert_eq!(
+            (
+                firmware_version.major(),
+                firmware_version.minor(),
+                firmware_version.patch()
+            ),
+            (12, 10, 255)
+        );
+        assert_eq!(firmware_version.suffix(), "-beta1");
+        assert_eq!(format!("{}", firmware_version), "12.10.255-beta1");
+    }
+
+    #[test]
+    fn firmware_new_leaves_missing_components_as_zero() {
+        let firmware_version = FirmwareVersion::new(b"2.0rc");
+
+        assert_eq!(format!("{}", firmware_version), "2.0.0rc");
+    }
+}
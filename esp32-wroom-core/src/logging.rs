@@ -0,0 +1,29 @@
+// Logging macros that forward to whichever logging backend the crate is built with.
+//
+// With the `defmt` feature they forward to the defmt macros, with the `log` feature to the
+// log crate, and without either they only evaluate their arguments so the driver can be used
+// in projects that don't link a logger at all. The format strings are limited to the `{}` and
+// `{:?}` parameters both backends understand. The module is declared with `#[macro_use]` ahead
+// of the modules that log, so the macros are in scope there without an import.
+
+macro_rules! debug {
+    ($s:literal $(, $x:expr)* $(,)?) => {{
+        #[cfg(feature = "defmt")]
+        ::defmt::debug!($s $(, $x)*);
+        #[cfg(feature = "log")]
+        ::log::debug!($s $(, $x)*);
+        #[cfg(not(any(feature = "defmt", feature = "log")))]
+        let _ = ($(&$x,)*);
+    }};
+}
+
+macro_rules! warn {
+    ($s:literal $(, $x:expr)* $(,)?) => {{
+        #[cfg(feature = "defmt")]
+        ::defmt::warn!($s $(, $x)*);
+        #[cfg(feature = "log")]
+        ::log::warn!($s $(, $x)*);
+        #[cfg(not(any(feature = "defmt", feature = "log")))]
+        let _ = ($(&$x,)*);
+    }};
+}
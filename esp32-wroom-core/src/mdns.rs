@@ -0,0 +1,774 @@
+//! Make the ESP32 target reachable as `<hostname>.local`, advertise its services via mDNS and
+//! discover the services of other hosts.
+//!
+//! The NINA firmware has no mDNS support of its own, so an [`MdnsResponder`] joins the mDNS
+//! multicast group over a UDP socket and answers queries for its hostname and for the
+//! [`MdnsService`]s added to it, including their TXT records (DNS-SD). Queries are only
+//! answered while [`MdnsResponder::poll`] is called regularly.
+//!
+//! An [`MdnsBrowser`] looks up the instances of a service type on the local network instead,
+//! e.g. to find an MQTT broker without a hard-coded address.
+//!
+//! ## Usage
+//!
+//! ```no_run
+//! use esp32_wroom_core::mdns::{MdnsResponder, MdnsService};
+//!
+//! let mut mdns = MdnsResponder::build(&mut wifi);
+//! mdns.add_service(MdnsService::new("Pico sensor", "_http._tcp", 80).txt_records(&["path=/"]))
+//!     .unwrap();
+//! mdns.start("pico-sensor").unwrap();
+//!
+//! loop {
+//!     mdns.poll().ok();
+//!     timer.delay_ms(100);
+//! }
+//! ```
+//!
+//! ```no_run
+//! use esp32_wroom_core::mdns::MdnsBrowser;
+//!
+//! for service in MdnsBrowser::build(&mut wifi).browse("_mqtt._tcp", 1000).unwrap() {
+//!     defmt::info!("Found {=str} at {:?}:{=u16}", service.instance_name.as_str(), service.ip, service.port);
+//! }
+//! ```
+//!
+
+use core::fmt::Write;
+use core::net::{Ipv4Addr, SocketAddrV4};
+
+use embedded_hal::blocking::delay::DelayMs;
+
+use heapless::{String, Vec};
+
+use super::clock::Duration;
+use super::dns::{
+    decode_name, read_u16, skip_name, Writer, CLASS_IN, MAX_DNS_MESSAGE_LENGTH,
+    MAX_DNS_NAME_LENGTH, TYPE_PTR,
+};
+use super::network::{validate_hostname, NetworkError, Port, Socket, TransportMode};
+use super::protocol::{NinaProtocolHandler, ProtocolInterface};
+use super::transport::Transport;
+use super::udp_client::send_datagram;
+use super::wifi::Wifi;
+use super::Error;
+
+/// The maximum number of [`MdnsService`]s a single [`MdnsResponder`] advertises.
+pub const MAX_MDNS_SERVICES: usize = 4;
+
+/// The maximum number of service instances returned by [`MdnsBrowser::browse`].
+pub const MAX_DISCOVERED_SERVICES: usize = 4;
+
+const MDNS_GROUP: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(224, 0, 0, 251), 5353);
+
+// How long other hosts may cache the records, as recommended by RFC 6762
+const TTL_SECONDS: u32 = 120;
+
+const TYPE_A: u16 = 1;
+const TYPE_TXT: u16 = 16;
+const TYPE_SRV: u16 = 33;
+const TYPE_ANY: u16 = 255;
+
+const FLAG_RESPONSE: u16 = 0x8000;
+const FLAG_AUTHORITATIVE: u16 = 0x0400;
+// Marks records only this host owns, so that other hosts replace any cached copies
+const CLASS_CACHE_FLUSH: u16 = 0x8000;
+
+const SERVICE_ENUMERATION: &str = "_services._dns-sd._udp.local";
+
+// How often MdnsBrowser::browse() checks for received responses
+const BROWSE_POLL_INTERVAL_MS: u16 = 50;
+
+/// A service advertised by an [`MdnsResponder`] via DNS-SD.
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub struct MdnsService<'a> {
+    /// Human readable name of this service instance, e.g. `"Pico sensor"`. It must not
+    /// contain dots.
+    pub instance_name: &'a str,
+    /// Service type and transport protocol, e.g. `"_http._tcp"`.
+    pub service_type: &'a str,
+    /// Local port the service is listening on.
+    pub port: Port,
+    /// `key=value` pairs published in the service's TXT record.
+    pub txt_records: &'a [&'a str],
+}
+
+impl<'a> MdnsService<'a> {
+    /// Create a new [`MdnsService`] without any TXT records.
+    pub fn new(instance_name: &'a str, service_type: &'a str, port: Port) -> Self {
+        Self {
+            instance_name,
+            service_type,
+            port,
+            txt_records: &[],
+        }
+    }
+
+    /// Publish `txt_records`, each a `key=value` pair of at most 255 bytes.
+    pub fn txt_records(mut self, txt_records: &'a [&'a str]) -> Self {
+        self.txt_records = txt_records;
+        self
+    }
+}
+
+/// Answers mDNS queries for a hostname and the [`MdnsService`]s added to it.
+pub struct MdnsResponder<'a, T, D> {
+    pub(crate) protocol_handler: &'a mut NinaProtocolHandler<T, D>,
+    pub(crate) socket: Option<Socket>,
+    hostname: &'a str,
+    ip: Ipv4Addr,
+    services: Vec<MdnsService<'a>, MAX_MDNS_SERVICES>,
+}
+
+impl<'a, T, D> MdnsResponder<'a, T, D>
+where
+    T: Transport,
+    D: DelayMs<u16>,
+{
+    /// Build a new instance of an [`MdnsResponder`] provided a [`Wifi`] instance.
+    pub fn build(wifi: &'a mut Wifi<T, D>) -> Self {
+        Self {
+            protocol_handler: wifi.protocol_handler.get_mut(),
+            socket: None,
+            hostname: "",
+            ip: Ipv4Addr::UNSPECIFIED,
+            services: Vec::new(),
+        }
+    }
+
+    /// Advertise `service`, failing with [`NetworkError::TooManyMdnsServices`] once
+    /// [`MAX_MDNS_SERVICES`] have been added.
+    pub fn add_service(&mut self, service: MdnsService<'a>) -> Result<(), Error> {
+        self.services
+            .push(service)
+            .map_err(|_| NetworkError::TooManyMdnsServices.into())
+    }
+
+    /// Start answering queries for `<hostname>.local` with the current IP address of the
+    /// ESP32 target and announce the hostname and all services once. The hostname follows
+    /// the rules of [`Wifi::set_hostname`].
+    pub fn start(&mut self, hostname: &'a str) -> Result<(), Error> {
+        validate_hostname(hostname)?;
+
+        self.hostname = hostname;
+        self.ip = self.protocol_handler.get_ip_config()?.ip;
+
+        let socket = self.protocol_handler.get_socket()?;
+        self.protocol_handler
+            .start_server_multicast(socket, MDNS_GROUP)?;
+        self.socket = Some(socket);
+
+        let mut response = [0u8; MAX_DNS_MESSAGE_LENGTH];
+        let length = encode_announcement(self.hostname, self.ip, &self.services, &mut response)?;
+
+        send_datagram(
+            self.protocol_handler,
+            socket,
+            MDNS_GROUP,
+            &response[..length],
+        )
+    }
+
+    /// Poll once for a received query and answer it if it's about the hostname or one of the
+    /// services. Returns whether a query was answered.
+    pub fn poll(&mut self) -> Result<bool, Error> {
+        let socket = self.socket.ok_or(NetworkError::ServerNotBound)?;
+
+        let available = self.protocol_handler.avail_data_tcp(socket)?;
+        if available == 0 {
+            return Ok(false);
+        }
+
+        let mut query = [0u8; MAX_DNS_MESSAGE_LENGTH];
+        let length = available.min(query.len());
+        let length = self
+            .protocol_handler
+            .get_data_buf_tcp(socket, &mut query[..length])?;
+
+        let mut response = [0u8; MAX_DNS_MESSAGE_LENGTH];
+        // Malformed queries from other hosts are simply not answered
+        match answer_query(
+            &query[..length],
+            self.hostname,
+            self.ip,
+            &self.services,
+            &mut response,
+        ) {
+            Ok(Some(length)) => {
+                send_datagram(
+                    self.protocol_handler,
+                    socket,
+                    MDNS_GROUP,
+                    &response[..length],
+                )?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Stop answering queries and release the socket back to the ESP32 target.
+    pub fn stop(&mut self) -> Result<(), Error> {
+        match self.socket.take() {
+            Some(socket) => self
+                .protocol_handler
+                .stop_client_tcp(socket, &TransportMode::UdpMulticast),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A service instance found by an [`MdnsBrowser`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct DiscoveredService {
+    /// Human readable name of the service instance, e.g. `"Living room broker"`.
+    pub instance_name: String<MAX_DNS_NAME_LENGTH>,
+    /// IP address of the host the service is on.
+    pub ip: Ipv4Addr,
+    /// Port the service is listening on.
+    pub port: Port,
+}
+
+/// Discovers the instances of a service type on the local network via DNS-SD.
+pub struct MdnsBrowser<'a, T, D> {
+    pub(crate) protocol_handler: &'a mut NinaProtocolHandler<T, D>,
+}
+
+impl<'a, T, D> MdnsBrowser<'a, T, D>
+where
+    T: Transport,
+    D: DelayMs<u16>,
+{
+    /// Build a new instance of an [`MdnsBrowser`] provided a [`Wifi`] instance.
+    pub fn build(wifi: &'a mut Wifi<T, D>) -> Self {
+        Self {
+            protocol_handler: wifi.protocol_handler.get_mut(),
+        }
+    }
+
+    /// Ask the local network for instances of `service_type` (e.g. `"_mqtt._tcp"`) and
+    /// collect the responses received within `timeout_ms`. Only instances whose responses
+    /// include the port and address of their host are returned, which is the case for most
+    /// responders, up to [`MAX_DISCOVERED_SERVICES`] of them.
+    ///
+    /// The mDNS multicast group is joined on a socket of its own, so browsing while an
+    /// [`MdnsResponder`] is running isn't supported.
+    pub fn browse(
+        &mut self,
+        service_type: &str,
+        timeout_ms: u16,
+    ) -> Result<Vec<DiscoveredService, MAX_DISCOVERED_SERVICES>, Error> {
+        let mut service: String<MAX_DNS_NAME_LENGTH> = String::new();
+        write!(service, "{}.local", service_type).map_err(|_| NetworkError::InvalidHostname)?;
+
+        let mut query = [0u8; MAX_DNS_MESSAGE_LENGTH];
+        let length = encode_ptr_query(&service, &mut query)?;
+
+        let socket = self.protocol_handler.get_socket()?;
+        self.protocol_handler
+            .start_server_multicast(socket, MDNS_GROUP)?;
+
+        let protocol_handler = &mut *self.protocol_handler;
+        let mut discover = || -> Result<Discovery, Error> {
+            send_datagram(protocol_handler, socket, MDNS_GROUP, &query[..length])?;
+
+            let mut discovery = Discovery::default();
+            let mut response = [0u8; MAX_DNS_MESSAGE_LENGTH];
+            let mut deadline = protocol_handler.deadline(Some(Duration::millis(timeout_ms as u32)));
+
+            while !deadline.has_expired() {
+                let available = protocol_handler.avail_data_tcp(socket)?;
+                if available == 0 {
+                    protocol_handler.delay_ms(BROWSE_POLL_INTERVAL_MS);
+                    deadline.wait(Duration::millis(BROWSE_POLL_INTERVAL_MS as u32));
+                    continue;
+                }
+
+                let length = available.min(response.len());
+                let length = protocol_handler.get_data_buf_tcp(socket, &mut response[..length])?;
+                // Malformed responses from other hosts are simply skipped
+                discovery.add_response(&response[..length], &service).ok();
+            }
+
+            Ok(discovery)
+        };
+        let result = discover();
+
+        self.protocol_handler
+            .stop_client_tcp(socket, &TransportMode::UdpMulticast)?;
+
+        Ok(result?.services(&service))
+    }
+}
+
+// The records about a browsed service type collected from all responses received so far.
+#[derive(Default)]
+pub(crate) struct Discovery {
+    instances: Vec<String<MAX_DNS_NAME_LENGTH>, MAX_DISCOVERED_SERVICES>,
+    // The port and target host of each instance
+    targets: Vec<
+        (
+            String<MAX_DNS_NAME_LENGTH>,
+            Port,
+            String<MAX_DNS_NAME_LENGTH>,
+        ),
+        MAX_DISCOVERED_SERVICES,
+    >,
+    addresses: Vec<(String<MAX_DNS_NAME_LENGTH>, Ipv4Addr), MAX_DISCOVERED_SERVICES>,
+}
+
+impl Discovery {
+    // Collects the PTR records for `service` and any SRV and A records of `response`. Records
+    // that don't fit anymore are dropped.
+    pub(crate) fn add_response(&mut self, response: &[u8], service: &str) -> Result<(), Error> {
+        if read_u16(response, 2)? & FLAG_RESPONSE == 0 {
+            return Ok(());
+        }
+
+        let mut position = 12;
+        for _ in 0..read_u16(response, 4)? {
+            position = skip_name(response, position)? + 4; // type, class
+        }
+
+        // Answer, authority and additional records
+        let records = read_u16(response, 6)? as usize
+            + read_u16(response, 8)? as usize
+            + read_u16(response, 10)? as usize;
+
+        for _ in 0..records {
+            let name = decode_name(response, position)?;
+            position = skip_name(response, position)?;
+            let record_type = read_u16(response, position)?;
+            let rdata = position + 10;
+            position = rdata + read_u16(response, position + 8)? as usize;
+
+            match record_type {
+                TYPE_PTR if name.eq_ignore_ascii_case(service) => {
+                    let instance = decode_name(response, rdata)?;
+                    if !self.instances.contains(&instance) {
+                        self.instances.push(instance).ok();
+                    }
+                }
+                TYPE_SRV => {
+                    let port = read_u16(response, rdata + 4)?;
+                    let target = decode_name(response, rdata + 6)?;
+                    if !self
+                        .targets
+                        .iter()
+                        .any(|(instance, _, _)| *instance == name)
+                    {
+                        self.targets.push((name, port, target)).ok();
+                    }
+                }
+                TYPE_A => {
+                    let ip: [u8; 4] = response
+                        .get(rdata..rdata + 4)
+                        .and_then(|ip| ip.try_into().ok())
+                        .ok_or(NetworkError::DnsResolveFailed)?;
+                    let ip = Ipv4Addr::from(ip);
+                    if !self.addresses.iter().any(|(host, _)| *host == name) {
+                        self.addresses.push((name, ip)).ok();
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    // Pairs up every instance of `service` with the port and address of its host.
+    pub(crate) fn services(
+        &self,
+        service: &str,
+    ) -> Vec<DiscoveredService, MAX_DISCOVERED_SERVICES> {
+        let mut services = Vec::new();
+
+        for instance in &self.instances {
+            let target = self
+                .targets
+                .iter()
+                .find(|(name, _, _)| name.eq_ignore_ascii_case(instance));
+            let address = target.and_then(|(_, _, host)| {
+                self.addresses
+                    .iter()
+                    .find(|(name, _)| name.eq_ignore_ascii_case(host))
+            });
+            let (port, ip) = match (target, address) {
+                (Some((_, port, _)), Some((_, ip))) => (port, ip),
+                _ => continue,
+            };
+
+            // The instance name is the label in front of the service name
+            let instance_name = instance
+                .len()
+                .checked_sub(service.len() + 1)
+                .and_then(|length| instance.get(..length))
+                .unwrap_or(instance);
+
+            services
+                .push(DiscoveredService {
+                    instance_name: instance_name.into(),
+                    ip: *ip,
+                    port: *port,
+                })
+                .ok();
+        }
+
+        services
+    }
+}
+
+// Encodes a query for the PTR records of `service`, i.e. its instances, into `buf`.
+fn encode_ptr_query(service: &str, buf: &mut [u8]) -> Result<usize, Error> {
+    let mut writer = Writer { buf, position: 0 };
+
+    for field in [0, 0, 1, 0, 0, 0] {
+        // ID, flags, one question, no records
+        writer.put_u16(field)?;
+    }
+    put_name(&mut writer, &[service])?;
+    writer.put_u16(TYPE_PTR)?;
+    writer.put_u16(CLASS_IN)?;
+
+    Ok(writer.position)
+}
+
+// Which records a response is made up of
+#[derive(Default)]
+struct Answers {
+    address: bool,
+    service_types: bool,
+    // Bitmasks of indexes into the services
+    service_pointers: u8,
+    service_details: u8,
+}
+
+// Encodes an unsolicited response announcing all records into `buf`.
+pub(crate) fn encode_announcement(
+    hostname: &str,
+    ip: Ipv4Addr,
+    services: &[MdnsService],
+    buf: &mut [u8],
+) -> Result<usize, Error> {
+    let all_services = ((1u16 << services.len()) - 1) as u8;
+    let answers = Answers {
+        address: true,
+        service_types: false,
+        service_pointers: all_services,
+        service_details: all_services,
+    };
+
+    encode_response(&answers, hostname, ip, services, buf)
+}
+
+// Encodes the response to `query` into `buf` and returns its length, or None if the query
+// isn't about any of the records of `hostname` and `services`.
+pub(crate) fn answer_query(
+    query: &[u8],
+    hostname: &str,
+    ip: Ipv4Addr,
+    services: &[MdnsService],
+    buf: &mut [u8],
+) -> Result<Option<usize>, Error> {
+    let flags = read_u16(query, 2)?;
+    if flags & FLAG_RESPONSE != 0 {
+        return Ok(None);
+    }
+
+    let mut answers = Answers::default();
+    let mut position = 12;
+
+    for _ in 0..read_u16(query, 4)? {
+        let name = decode_name(query, position)?;
+        position = skip_name(query, position)?;
+        let record_type = read_u16(query, position)?;
+        position += 4; // type, class
+
+        let is = |wanted: u16| record_type == wanted || record_type == TYPE_ANY;
+
+        if is(TYPE_A) && name_matches(&name, &[hostname, "local"]) {
+            answers.address = true;
+        }
+        if is(TYPE_PTR) && name.eq_ignore_ascii_case(SERVICE_ENUMERATION) {
+            answers.service_types = true;
+        }
+        for (index, service) in services.iter().enumerate() {
+            if is(TYPE_PTR) && name_matches(&name, &[service.service_type, "local"]) {
+                answers.service_pointers |= 1 << index;
+            }
+            if (is(TYPE_SRV) || is(TYPE_TXT))
+                && name_matches(
+                    &name,
+                    &[service.instance_name, service.service_type, "local"],
+                )
+            {
+                answers.service_details |= 1 << index;
+            }
+        }
+    }
+
+    // Resolving a service instance also needs the address of the host it's on
+    if answers.service_pointers != 0 || answers.service_details != 0 {
+        answers.service_details |= answers.service_pointers;
+        answers.address = true;
+    }
+
+    if !answers.address && !answers.service_types {
+        return Ok(None);
+    }
+
+    encode_response(&answers, hostname, ip, services, buf).map(Some)
+}
+
+fn encode_response(
+    answers: &Answers,
+    hostname: &str,
+    ip: Ipv4Addr,
+    services: &[MdnsService],
+    buf: &mut [u8],
+) -> Result<usize, Error> {
+    let mut writer = Writer { buf, position: 0 };
+    let mut count = 0u16;
+
+    writer.put_u16(0)?; // mDNS responses carry no ID
+    writer.put_u16(FLAG_RESPONSE | FLAG_AUTHORITATIVE)?;
+    writer.put_u16(0)?; // questions
+    writer.put_u16(0)?; // answers, set once all records are written
+    writer.put_u16(0)?; // authority records
+    writer.put_u16(0)?; // additional records
+
+    let host = [hostname, "local"];
+
+    for (index, service) in services.iter().enumerate() {
+        let instance = [service.instance_name, service.service_type, "local"];
+
+        if answers.service_types {
+            put_record(&mut writer, &[SERVICE_ENUMERATION], TYPE_PTR, false, |w| {
+                put_name(w, &[service.service_type, "local"])
+            })?;
+            count += 1;
+        }
+        if answers.service_pointers & (1 << index) != 0 {
+            put_record(
+                &mut writer,
+                &[service.service_type, "local"],
+                TYPE_PTR,
+                false,
+                |w| put_name(w, &instance),
+            )?;
+            count += 1;
+        }
+        if answers.service_details & (1 << index) != 0 {
+            put_record(&mut writer, &instance, TYPE_SRV, true, |w| {
+                w.put_u16(0)?; // priority
+                w.put_u16(0)?; // weight
+                w.put_u16(service.port)?;
+                put_name(w, &host)
+            })?;
+            put_record(&mut writer, &instance, TYPE_TXT, true, |w| {
+                // An empty TXT record still holds a single empty string
+                if service.txt_records.is_empty() {
+                    return w.put_u8(0);
+                }
+                for txt_record in service.txt_records {
+                    let length = u8::try_from(txt_record.len())
+                        .map_err(|_| NetworkError::DnsResolveFailed)?;
+                    w.put_u8(length)?;
+                    w.put_bytes(txt_record.as_bytes())?;
+                }
+                Ok(())
+            })?;
+            count += 2;
+        }
+    }
+
+    if answers.address {
+        put_record(&mut writer, &host, TYPE_A, true, |w| {
+            w.put_bytes(&ip.octets())
+        })?;
+        count += 1;
+    }
+
+    writer.buf[6..8].copy_from_slice(&count.to_be_bytes());
+
+    Ok(writer.position)
+}
+
+// Writes a resource record whose data is written by `rdata`.
+fn put_record<F: FnOnce(&mut Writer) -> Result<(), Error>>(
+    writer: &mut Writer,
+    name: &[&str],
+    record_type: u16,
+    unique: bool,
+    rdata: F,
+) -> Result<(), Error> {
+    put_name(writer, name)?;
+    writer.put_u16(record_type)?;
+    writer.put_u16(if unique {
+        CLASS_IN | CLASS_CACHE_FLUSH
+    } else {
+        CLASS_IN
+    })?;
+    writer.put_bytes(&TTL_SECONDS.to_be_bytes())?;
+
+    let length_position = writer.position;
+    writer.put_u16(0)?;
+    rdata(writer)?;
+
+    let length = (writer.position - length_position - 2) as u16;
+    writer.buf[length_position..length_position + 2].copy_from_slice(&length.to_be_bytes());
+
+    Ok(())
+}
+
+// Writes the name made up of `parts`, each of which may hold several dot separated labels.
+fn put_name(writer: &mut Writer, parts: &[&str]) -> Result<(), Error> {
+    for label in parts.iter().flat_map(|part| part.split('.')) {
+        if label.is_empty() {
+            continue;
+        }
+        let length = u8::try_from(label.len()).map_err(|_| NetworkError::DnsResolveFailed)?;
+        writer.put_u8(length)?;
+        writer.put_bytes(label.as_bytes())?;
+    }
+    writer.put_u8(0)
+}
+
+fn name_matches(name: &str, parts: &[&str]) -> bool {
+    let mut expected: String<MAX_DNS_NAME_LENGTH> = String::new();
+    for part in parts {
+        if !expected.is_empty() && expected.push('.').is_err() {
+            return false;
+        }
+        if expected.write_str(part).is_err() {
+            return false;
+        }
+    }
+
+    name.eq_ignore_ascii_case(&expected)
+}
+
+#[cfg(test)]
+mod mdns_tests {
+    use super::*;
+
+    fn query(name: &[&str], record_type: u16) -> std::vec::Vec<u8> {
+        let mut buf = [0u8; MAX_DNS_MESSAGE_LENGTH];
+        let mut writer = Writer {
+            buf: &mut buf,
+            position: 0,
+        };
+        for field in [0, 0, 1, 0, 0, 0] {
+            writer.put_u16(field).unwrap();
+        }
+        put_name(&mut writer, name).unwrap();
+        writer.put_u16(record_type).unwrap();
+        writer.put_u16(CLASS_IN).unwrap();
+
+        let length = writer.position;
+        buf[..length].to_vec()
+    }
+
+    #[test]
+    fn answer_query_answers_address_queries_for_the_hostname() {
+        let mut buf = [0u8; MAX_DNS_MESSAGE_LENGTH];
+
+        let length = answer_query(
+            &query(&["Pico-Sensor", "local"], TYPE_A),
+            "pico-sensor",
+            Ipv4Addr::new(192, 168, 1, 42),
+            &[],
+            &mut buf,
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(read_u16(&buf, 6).unwrap(), 1);
+        assert_eq!(&buf[length - 4..length], &[192, 168, 1, 42]);
+    }
+
+    #[test]
+    fn answer_query_answers_service_queries_with_srv_txt_and_address_records() {
+        let services = [MdnsService::new("Pico sensor", "_http._tcp", 80).txt_records(&["path=/"])];
+        let mut buf = [0u8; MAX_DNS_MESSAGE_LENGTH];
+
+        let length = answer_query(
+            &query(&["_http._tcp", "local"], TYPE_PTR),
+            "pico-sensor",
+            Ipv4Addr::new(192, 168, 1, 42),
+            &services,
+            &mut buf,
+        )
+        .unwrap()
+        .unwrap();
+
+        // PTR, SRV, TXT and A
+        assert_eq!(read_u16(&buf, 6).unwrap(), 4);
+        assert!(buf[..length].windows(7).any(|w| w == b"\x06path=/"));
+    }
+
+    #[test]
+    fn discovery_pairs_instances_with_port_and_address_of_their_host() {
+        let services = [MdnsService::new("Living room broker", "_mqtt._tcp", 1883)];
+        let mut announcement = [0u8; MAX_DNS_MESSAGE_LENGTH];
+        let length = encode_announcement(
+            "hub",
+            Ipv4Addr::new(192, 168, 1, 7),
+            &services,
+            &mut announcement,
+        )
+        .unwrap();
+
+        let mut discovery = Discovery::default();
+        discovery
+            .add_response(&announcement[..length], "_mqtt._tcp.local")
+            .unwrap();
+
+        let discovered = discovery.services("_mqtt._tcp.local");
+
+        assert_eq!(discovered.len(), 1);
+        assert_eq!(discovered[0].instance_name, "Living room broker");
+        assert_eq!(discovered[0].ip, Ipv4Addr::new(192, 168, 1, 7));
+        assert_eq!(discovered[0].port, 1883);
+    }
+
+    #[test]
+    fn discovery_skips_instances_without_host_address() {
+        let services = [MdnsService::new("Printer", "_ipp._tcp", 631)];
+        let mut announcement = [0u8; MAX_DNS_MESSAGE_LENGTH];
+        let length = encode_announcement(
+            "printer",
+            Ipv4Addr::new(192, 168, 1, 9),
+            &services,
+            &mut announcement,
+        )
+        .unwrap();
+
+        let mut discovery = Discovery::default();
+        discovery
+            .add_response(&announcement[..length], "_ipp._tcp.local")
+            .unwrap();
+        discovery.addresses.clear();
+
+        assert!(discovery.services("_ipp._tcp.local").is_empty());
+    }
+
+    #[test]
+    fn answer_query_ignores_queries_for_other_names() {
+        let mut buf = [0u8; MAX_DNS_MESSAGE_LENGTH];
+
+        let answer = answer_query(
+            &query(&["printer", "local"], TYPE_A),
+            "pico-sensor",
+            Ipv4Addr::new(192, 168, 1, 42),
+            &[],
+            &mut buf,
+        )
+        .unwrap();
+
+        assert_eq!(answer, None);
+    }
+}
@@ -0,0 +1,340 @@
+//! Offload the SPI bus or the whole NINA protocol to the RP2040's second core (core1).
+//!
+//! ## Servicing the bus from core1
+//!
+//! A [`BusChannel`] is split into a [`BusClient`] and a [`BusServer`]. The [`BusClient`]
+//! implements the embedded-hal `Transfer` trait, so it's handed to [`Wifi::init`] in place of
+//! the SPI instance and the public API stays exactly the same. Every transfer the driver makes
+//! is passed through the channel to core1, where the [`BusServer`] owns the real SPI instance
+//! and performs it, leaving core0's SPI peripheral timing and interrupt latency unaffected by
+//! the ESP32 target.
+//!
+//! Note that the driver itself, including its waits on the ESP32 control pins, still runs on
+//! the core that owns the [`Wifi`] instance; only the bus transfers move to core1. Core0 also
+//! spins for as long as each transfer takes on core1, so it isn't freed up for real-time work.
+//! Only a [`CommandChannel`] does that.
+//!
+//! ```no_run
+//! use esp32_wroom_core::multicore::BusChannel;
+//!
+//! static mut BUS_CHANNEL: BusChannel<16> = BusChannel::new();
+//!
+//! let (bus_client, mut bus_server) = unsafe { BUS_CHANNEL.split() };
+//!
+//! core1.spawn(unsafe { &mut CORE1_STACK.mem }, move || bus_server.run(&mut spi)).unwrap();
+//!
+//! let mut wifi = Wifi::init(bus_client, esp_pins, delay).unwrap();
+//! ```
+//!
+//! ## Running the protocol on core1
+//!
+//! A [`CommandChannel`] moves the whole driver to core1 instead, so core0 never stalls on the
+//! ESP32's handshakes or on its multi-hundred-millisecond resets. It's split into a
+//! [`CommandClient`] and a [`CommandServer`], connected by a queue of commands to core1 and a
+//! queue of responses back to core0.
+//!
+//! The commands and responses are types defined by the application, typically enums with one
+//! variant per operation it needs. Core1 initializes and owns the [`Wifi`] and runs the
+//! [`CommandServer`] with a handler that performs a command on it and returns the response.
+//! Core0 [`send`](CommandClient::send)s commands and checks for their responses with
+//! [`try_receive`](CommandClient::try_receive), neither of which ever blocks. Responses are
+//! returned in the order the commands were sent.
+//!
+//! ```no_run
+//! use esp32_wroom_core::multicore::CommandChannel;
+//! use esp32_wroom_core::wifi::ConnectionStatus;
+//! use esp32_wroom_core::Error;
+//!
+//! enum Command {
+//!     Join(&'static str, &'static str),
+//!     ConnectionStatus,
+//! }
+//!
+//! enum Response {
+//!     Joined(Result<(), Error>),
+//!     ConnectionStatus(Result<ConnectionStatus, Error>),
+//! }
+//!
+//! static mut COMMAND_CHANNEL: CommandChannel<Command, Response, 4> = CommandChannel::new();
+//!
+//! let (mut client, mut server) = unsafe { COMMAND_CHANNEL.split() };
+//!
+//! core1
+//!     .spawn(unsafe { &mut CORE1_STACK.mem }, move || {
+//!         let mut wifi = Wifi::init(spi, esp_pins, delay).unwrap();
+//!         server.run(&mut wifi, |wifi, command| match command {
+//!             Command::Join(ssid, passphrase) => Response::Joined(wifi.join(ssid, passphrase)),
+//!             Command::ConnectionStatus => {
+//!                 Response::ConnectionStatus(wifi.get_connection_status())
+//!             }
+//!         })
+//!     })
+//!     .unwrap();
+//!
+//! client.send(Command::Join("ssid", "passphrase")).ok();
+//! loop {
+//!     if let Some(Response::Joined(result)) = client.try_receive() {
+//!         // ...
+//!     }
+//!     // Keep doing the application's work in the meantime
+//! }
+//! ```
+//!
+//! ## Requirements
+//!
+//! The channels are lock-free and only rely on atomic loads and stores, which the RP2040's
+//! Cortex-M0+ cores support. They don't depend on rp2040-hal, so core1 is launched by the
+//! application, e.g. with `rp2040_hal::multicore::Multicore`.
+//!
+//! [`Wifi`]: crate::wifi::Wifi
+//! [`Wifi::init`]: crate::wifi::Wifi::init
+//!
+
+use core::cell::UnsafeCell;
+use core::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+
+use embedded_hal::blocking::spi::Transfer;
+
+use heapless::spsc::{Consumer, Producer, Queue};
+
+use super::Error;
+
+// States of the single transfer slot shared by a BusClient and its BusServer
+const IDLE: u8 = 0;
+const REQUESTED: u8 = 1;
+const DONE: u8 = 2;
+const FAILED: u8 = 3;
+
+/// A single transfer slot shared between the two cores, holding up to `N` bytes per transfer.
+///
+/// Create it as a `static` and [`split`](BusChannel::split) it once into the [`BusClient`]
+/// used on core0 and the [`BusServer`] run on core1.
+pub struct BusChannel<const N: usize> {
+    state: AtomicU8,
+    length: AtomicUsize,
+    buffer: UnsafeCell<[u8; N]>,
+}
+
+// The buffer is only ever accessed by the side the state hands it to: the BusClient while
+// IDLE, the BusServer while REQUESTED and the BusClient again once DONE or FAILED.
+unsafe impl<const N: usize> Sync for BusChannel<N> {}
+
+impl<const N: usize> BusChannel<N> {
+    /// Create a new, empty [`BusChannel`].
+    pub const fn new() -> Self {
+        Self {
+            state: AtomicU8::new(IDLE),
+            length: AtomicUsize::new(0),
+            buffer: UnsafeCell::new([0; N]),
+        }
+    }
+
+    /// Split the channel into its [`BusClient`] and [`BusServer`] halves.
+    pub fn split(&mut self) -> (BusClient<'_, N>, BusServer<'_, N>) {
+        let channel: &Self = self;
+        (BusClient { channel }, BusServer { channel })
+    }
+}
+
+impl<const N: usize> Default for BusChannel<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The core0 half of a [`BusChannel`], used as the bus of a [`Wifi`](crate::wifi::Wifi).
+///
+/// Each transfer blocks until the [`BusServer`] on core1 has performed it. Transfers longer
+/// than the channel's `N` bytes are passed to core1 in pieces of `N` bytes. A transfer that
+/// fails on core1 fails with [`Error::Bus`].
+pub struct BusClient<'a, const N: usize> {
+    channel: &'a BusChannel<N>,
+}
+
+impl<'a, const N: usize> Transfer<u8> for BusClient<'a, N> {
+    type Error = Error;
+
+    fn transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8], Error> {
+        for chunk in words.chunks_mut(N) {
+            // Safety: the slot is IDLE, so the BusServer doesn't access the buffer
+            unsafe { (&mut *self.channel.buffer.get())[..chunk.len()].copy_from_slice(chunk) };
+            self.channel.length.store(chunk.len(), Ordering::Relaxed);
+            self.channel.state.store(REQUESTED, Ordering::Release);
+
+            let state = loop {
+                match self.channel.state.load(Ordering::Acquire) {
+                    REQUESTED => core::hint::spin_loop(),
+                    state => break state,
+                }
+            };
+            if state == FAILED {
+                self.channel.state.store(IDLE, Ordering::Release);
+                return Err(Error::Bus);
+            }
+
+            // Safety: the slot is DONE, so the BusServer is done with the buffer
+            chunk.copy_from_slice(unsafe { &(&*self.channel.buffer.get())[..chunk.len()] });
+            self.channel.state.store(IDLE, Ordering::Release);
+        }
+
+        Ok(words)
+    }
+}
+
+/// The core1 half of a [`BusChannel`] that owns the real bus and performs the transfers
+/// requested by the [`BusClient`].
+pub struct BusServer<'a, const N: usize> {
+    channel: &'a BusChannel<N>,
+}
+
+impl<'a, const N: usize> BusServer<'a, N> {
+    /// Perform the pending transfer on `bus`, if there is one, and return whether one was
+    /// performed. Bus errors are passed back to the caller and make the transfer of the
+    /// [`BusClient`] fail with [`Error::Bus`].
+    pub fn poll<B: Transfer<u8>>(&mut self, bus: &mut B) -> Result<bool, B::Error> {
+        if self.channel.state.load(Ordering::Acquire) != REQUESTED {
+            return Ok(false);
+        }
+
+        let length = self.channel.length.load(Ordering::Relaxed);
+        // Safety: the slot is REQUESTED, so the BusClient doesn't access the buffer
+        let buffer = unsafe { &mut (&mut *self.channel.buffer.get())[..length] };
+        // The trait doesn't guarantee that the received words end up in place, so they're
+        // copied back from wherever the bus returns them
+        let mut received = [0u8; N];
+        let result = bus.transfer(buffer).map(|words| {
+            let length = words.len().min(length);
+            received[..length].copy_from_slice(&words[..length]);
+        });
+        let state = match result {
+            Ok(_) => {
+                buffer.copy_from_slice(&received[..length]);
+                DONE
+            }
+            Err(_) => FAILED,
+        };
+
+        self.channel.state.store(state, Ordering::Release);
+
+        result.map(|_| true)
+    }
+
+    /// Serve transfers on `bus` forever. This is meant to be the entry function of core1.
+    pub fn run<B: Transfer<u8>>(&mut self, bus: &mut B) -> ! {
+        loop {
+            // A failed transfer is already reported to the BusClient
+            self.poll(bus).ok();
+        }
+    }
+}
+
+/// The command and response queues shared between the two cores, each holding up to `N - 1`
+/// entries.
+///
+/// Create it as a `static` and [`split`](CommandChannel::split) it once into the
+/// [`CommandClient`] used on core0 and the [`CommandServer`] run on core1.
+pub struct CommandChannel<C, R, const N: usize> {
+    commands: Queue<C, N>,
+    responses: Queue<R, N>,
+}
+
+impl<C, R, const N: usize> CommandChannel<C, R, N> {
+    /// Create a new [`CommandChannel`] with empty queues.
+    pub const fn new() -> Self {
+        Self {
+            commands: Queue::new(),
+            responses: Queue::new(),
+        }
+    }
+
+    /// Split the channel into its [`CommandClient`] and [`CommandServer`] halves.
+    pub fn split(&mut self) -> (CommandClient<'_, C, R, N>, CommandServer<'_, C, R, N>) {
+        let (commands, pending) = self.commands.split();
+        let (completed, responses) = self.responses.split();
+        (
+            CommandClient {
+                commands,
+                responses,
+            },
+            CommandServer {
+                commands: pending,
+                responses: completed,
+            },
+        )
+    }
+}
+
+impl<C, R, const N: usize> Default for CommandChannel<C, R, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The core0 half of a [`CommandChannel`], which sends commands to the [`CommandServer`] on
+/// core1 and receives their responses without ever blocking.
+pub struct CommandClient<'a, C, R, const N: usize> {
+    commands: Producer<'a, C, N>,
+    responses: Consumer<'a, R, N>,
+}
+
+impl<'a, C, R, const N: usize> CommandClient<'a, C, R, N> {
+    /// Queue `command` for core1. If the command queue is full, the command is handed back.
+    pub fn send(&mut self, command: C) -> Result<(), C> {
+        self.commands.enqueue(command)
+    }
+
+    /// Take the response to the oldest outstanding command, if core1 has completed it.
+    pub fn try_receive(&mut self) -> Option<R> {
+        self.responses.dequeue()
+    }
+
+    /// Whether there's room in the command queue for another command.
+    pub fn is_ready(&self) -> bool {
+        self.commands.ready()
+    }
+}
+
+/// The core1 half of a [`CommandChannel`], which performs the commands sent by the
+/// [`CommandClient`] on the driver it owns.
+pub struct CommandServer<'a, C, R, const N: usize> {
+    commands: Consumer<'a, C, N>,
+    responses: Producer<'a, R, N>,
+}
+
+impl<'a, C, R, const N: usize> CommandServer<'a, C, R, N> {
+    /// Perform the oldest pending command, if there is one, by passing it to `handler` together
+    /// with `driver`, and queue the response it returns. Returns whether a command was
+    /// performed.
+    ///
+    /// Commands are left queued while the response queue is full, so no response is ever
+    /// dropped.
+    pub fn poll<W, F>(&mut self, driver: &mut W, mut handler: F) -> bool
+    where
+        F: FnMut(&mut W, C) -> R,
+    {
+        if !self.responses.ready() {
+            return false;
+        }
+
+        match self.commands.dequeue() {
+            Some(command) => {
+                let response = handler(driver, command);
+                // Can't fail, this is the only producer and there was room before the command
+                self.responses.enqueue(response).ok();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Serve commands on `driver` with `handler` forever. This is meant to be the last call of
+    /// core1's entry function, after it has initialized the driver.
+    pub fn run<W, F>(&mut self, driver: &mut W, mut handler: F) -> !
+    where
+        F: FnMut(&mut W, C) -> R,
+    {
+        loop {
+            self.poll(driver, &mut handler);
+        }
+    }
+}
@@ -0,0 +1,301 @@
+//! Implementations of the [embedded-nal](https://github.com/rust-embedded-community/embedded-nal)
+//! TCP and UDP network stack traits for [`Wifi`], enabled by the `embedded-nal` feature.
+//!
+//! This lets crates written against embedded-nal (e.g. MQTT or HTTP clients) use the ESP32
+//! target without any glue code. All operations are non-blocking: while a connection is still
+//! being established or no data has arrived yet, `nb::Error::WouldBlock` is returned and the
+//! operation should simply be retried later.
+//!
+//! ## Usage
+//!
+//! ```no_run
+//! use core::net::{SocketAddr, SocketAddrV4};
+//!
+//! use embedded_nal::{nb::block, TcpClientStack};
+//!
+//! let mut socket = wifi.socket().unwrap();
+//! block!(wifi.connect(&mut socket, SocketAddr::from((Ipv4Addr::new(10, 0, 1, 3), 4000)))).unwrap();
+//! block!(wifi.send(&mut socket, b"Hello, server!")).unwrap();
+//! wifi.close(socket).unwrap();
+//! ```
+//!
+//! ```no_run
+//! use core::net::{SocketAddr, SocketAddrV4};
+//!
+//! use embedded_nal::{nb::block, UdpClientStack};
+//!
+//! let mut socket = wifi.socket().unwrap();
+//! wifi.connect(&mut socket, SocketAddr::from((Ipv4Addr::new(10, 0, 1, 3), 123))).unwrap();
+//! block!(wifi.send(&mut socket, &ntp_request)).unwrap();
+//!
+//! let mut buf = [0u8; 48];
+//! let (length, sender) = block!(wifi.receive(&mut socket, &mut buf)).unwrap();
+//! ```
+//!
+
+use core::net::{SocketAddr, SocketAddrV4};
+
+use embedded_hal::blocking::delay::DelayMs;
+
+use embedded_nal::{nb, TcpClientStack, TcpError, TcpErrorKind, UdpClientStack, UdpFullStack};
+
+use super::network::{
+    to_socket_addr_v4, ConnectionState, NetworkError, Port, Socket, TransportMode,
+};
+use super::protocol::{ProtocolInterface, MAX_NINA_LARGE_ARRAY_PARAM_BUFFER_LENGTH};
+use super::transport::Transport;
+use super::udp_client::{send_datagram, UDP_EPHEMERAL_PORT_BASE};
+use super::wifi::Wifi;
+use super::Error;
+
+/// A TCP socket handed out by the [`TcpClientStack`] implementation of [`Wifi`].
+#[derive(Debug, PartialEq, Eq)]
+pub struct TcpSocket {
+    socket: Socket,
+    // Whether the ESP32 target has been asked to connect this socket to a server
+    connecting: bool,
+}
+
+impl TcpSocket {
+    /// The `Socket` handle of the ESP32 target this socket wraps.
+    pub fn socket(&self) -> Socket {
+        self.socket
+    }
+}
+
+/// A UDP socket handed out by the [`UdpClientStack`] implementation of [`Wifi`].
+#[derive(Debug, PartialEq, Eq)]
+pub struct UdpSocket {
+    socket: Socket,
+    local_port: Option<Port>,
+    remote: Option<SocketAddrV4>,
+}
+
+impl UdpSocket {
+    /// The `Socket` handle of the ESP32 target this socket wraps.
+    pub fn socket(&self) -> Socket {
+        self.socket
+    }
+
+    /// The local [`Port`] this socket receives datagrams on, once it has been bound or
+    /// connected.
+    pub fn local_port(&self) -> Option<Port> {
+        self.local_port
+    }
+}
+
+impl TcpError for Error {
+    fn kind(&self) -> TcpErrorKind {
+        match self {
+            Error::Network(NetworkError::ConnectionLost) => TcpErrorKind::PipeClosed,
+            _ => TcpErrorKind::Other,
+        }
+    }
+}
+
+impl<T, D> TcpClientStack for Wifi<T, D>
+where
+    T: Transport,
+    D: DelayMs<u16>,
+{
+    type TcpSocket = TcpSocket;
+    type Error = Error;
+
+    fn socket(&mut self) -> Result<TcpSocket, Error> {
+        let socket = self.protocol_handler.get_mut().get_socket()?;
+
+        Ok(TcpSocket {
+            socket,
+            connecting: false,
+        })
+    }
+
+    /// Start connecting `socket` to `remote` and poll until the connection is established.
+    /// IPv6 addresses fail with [`NetworkError::Ipv6Unsupported`], while a connection that
+    /// can't be established fails with [`NetworkError::ConnectFailed`] after closing `socket`.
+    fn connect(&mut self, socket: &mut TcpSocket, remote: SocketAddr) -> nb::Result<(), Error> {
+        let protocol_handler = self.protocol_handler.get_mut();
+
+        if !socket.connecting {
+            let remote = to_socket_addr_v4(remote)?;
+            protocol_handler.start_client_tcp(socket.socket, remote, &TransportMode::Tcp)?;
+            socket.connecting = true;
+        }
+
+        match protocol_handler.get_client_state_tcp(socket.socket)? {
+            ConnectionState::Established => Ok(()),
+            ConnectionState::Listening
+            | ConnectionState::SynSent
+            | ConnectionState::SynReceived => Err(nb::Error::WouldBlock),
+            _ => {
+                socket.connecting = false;
+                protocol_handler.stop_client_tcp(socket.socket, &TransportMode::Tcp)?;
+                Err(nb::Error::Other(NetworkError::ConnectFailed.into()))
+            }
+        }
+    }
+
+    /// Send as much of `buffer` as the ESP32 target accepts at once, returning the number of
+    /// bytes sent.
+    fn send(&mut self, socket: &mut TcpSocket, buffer: &[u8]) -> nb::Result<usize, Error> {
+        let protocol_handler = self.protocol_handler.get_mut();
+
+        let length = buffer.len().min(MAX_NINA_LARGE_ARRAY_PARAM_BUFFER_LENGTH);
+        let sent = protocol_handler.send_data(&buffer[..length], socket.socket)?;
+        if sent == 0 {
+            return Err(nb::Error::WouldBlock);
+        }
+
+        if !protocol_handler.check_data_sent(socket.socket)? {
+            return Err(nb::Error::Other(NetworkError::SendFailed.into()));
+        }
+
+        Ok(sent)
+    }
+
+    /// Read the data received on `socket` into `buffer`, returning the number of bytes read.
+    /// Fails with [`NetworkError::ConnectionLost`] once the server has closed the connection
+    /// and all of its data has been read.
+    fn receive(&mut self, socket: &mut TcpSocket, buffer: &mut [u8]) -> nb::Result<usize, Error> {
+        let protocol_handler = self.protocol_handler.get_mut();
+
+        let available = protocol_handler.avail_data_tcp(socket.socket)?;
+        if available > 0 {
+            let length = available.min(buffer.len());
+            return Ok(protocol_handler.get_data_buf_tcp(socket.socket, &mut buffer[..length])?);
+        }
+
+        match protocol_handler.get_client_state_tcp(socket.socket)? {
+            ConnectionState::Established => Err(nb::Error::WouldBlock),
+            _ => Err(nb::Error::Other(NetworkError::ConnectionLost.into())),
+        }
+    }
+
+    fn close(&mut self, socket: TcpSocket) -> Result<(), Error> {
+        self.protocol_handler
+            .get_mut()
+            .stop_client_tcp(socket.socket, &TransportMode::Tcp)
+    }
+}
+
+impl<T, D> UdpClientStack for Wifi<T, D>
+where
+    T: Transport,
+    D: DelayMs<u16>,
+{
+    type UdpSocket = UdpSocket;
+    type Error = Error;
+
+    fn socket(&mut self) -> Result<UdpSocket, Error> {
+        let socket = self.protocol_handler.get_mut().get_socket()?;
+
+        Ok(UdpSocket {
+            socket,
+            local_port: None,
+            remote: None,
+        })
+    }
+
+    /// Send datagrams written with `send` to `remote`. Unless `socket` has already been bound
+    /// with [`UdpFullStack::bind`], it's bound to a local port that is picked by its socket
+    /// number so that responses can be received.
+    ///
+    /// The NINA firmware doesn't filter received datagrams by sender, so `receive` also
+    /// returns datagrams of other peers sent to the same local port.
+    fn connect(&mut self, socket: &mut UdpSocket, remote: SocketAddr) -> Result<(), Error> {
+        let remote = to_socket_addr_v4(remote)?;
+
+        if socket.local_port.is_none() {
+            let port = UDP_EPHEMERAL_PORT_BASE + socket.socket as Port;
+            self.bind(socket, port)?;
+        }
+        socket.remote = Some(remote);
+
+        Ok(())
+    }
+
+    /// Send `buffer` as a single datagram to the remote address `socket` is connected to,
+    /// failing with [`NetworkError::UdpSocketNotConnected`] if it isn't.
+    fn send(&mut self, socket: &mut UdpSocket, buffer: &[u8]) -> nb::Result<(), Error> {
+        let remote = socket
+            .remote
+            .ok_or(NetworkError::UdpSocketNotConnected)
+            .map_err(Error::from)?;
+
+        Ok(send_datagram(
+            self.protocol_handler.get_mut(),
+            socket.socket,
+            remote,
+            buffer,
+        )?)
+    }
+
+    /// Copy a received datagram into `buffer`, returning its length and sender. `buffer`
+    /// should be large enough to hold a whole datagram, any bytes that don't fit are returned
+    /// by the next call. Fails with [`NetworkError::ServerNotBound`] for sockets that are
+    /// neither bound nor connected.
+    fn receive(
+        &mut self,
+        socket: &mut UdpSocket,
+        buffer: &mut [u8],
+    ) -> nb::Result<(usize, SocketAddr), Error> {
+        if socket.local_port.is_none() {
+            return Err(nb::Error::Other(NetworkError::ServerNotBound.into()));
+        }
+
+        let protocol_handler = self.protocol_handler.get_mut();
+
+        let available = protocol_handler.avail_data_tcp(socket.socket)?;
+        if available == 0 {
+            return Err(nb::Error::WouldBlock);
+        }
+
+        let length = available.min(buffer.len());
+        let length = protocol_handler.get_data_buf_tcp(socket.socket, &mut buffer[..length])?;
+        let sender = protocol_handler.get_remote_data(socket.socket)?;
+
+        Ok((length, SocketAddr::V4(sender)))
+    }
+
+    fn close(&mut self, socket: UdpSocket) -> Result<(), Error> {
+        self.protocol_handler
+            .get_mut()
+            .stop_client_tcp(socket.socket, &TransportMode::Udp)
+    }
+}
+
+impl<T, D> UdpFullStack for Wifi<T, D>
+where
+    T: Transport,
+    D: DelayMs<u16>,
+{
+    /// Start receiving datagrams sent to `local_port` on `socket`.
+    fn bind(&mut self, socket: &mut UdpSocket, local_port: u16) -> Result<(), Error> {
+        self.protocol_handler.get_mut().start_server_tcp(
+            socket.socket,
+            local_port,
+            &TransportMode::Udp,
+        )?;
+        socket.local_port = Some(local_port);
+
+        Ok(())
+    }
+
+    /// Send `buffer` as a single datagram to `remote`, from the local port `socket` is bound
+    /// to if it is.
+    fn send_to(
+        &mut self,
+        socket: &mut UdpSocket,
+        remote: SocketAddr,
+        buffer: &[u8],
+    ) -> nb::Result<(), Error> {
+        let remote = to_socket_addr_v4(remote)?;
+
+        Ok(send_datagram(
+            self.protocol_handler.get_mut(),
+            socket.socket,
+            remote,
+            buffer,
+        )?)
+    }
+}
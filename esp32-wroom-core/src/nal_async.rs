@@ -0,0 +1,428 @@
+//! Implementations of the [embedded-nal-async](https://github.com/rust-embedded-community/embedded-nal)
+//! network stack traits for [`Wifi`], enabled by the `async` feature.
+//!
+//! This lets async applications (e.g. on embassy) and crates written against embedded-nal-async
+//! use the ESP32 target through standard trait bounds. The NINA firmware can't notify the host
+//! about new data, so every future polls the ESP32 target and yields back to the executor in
+//! between two polls. None of them time out on their own, which is left to the executor's
+//! timer, e.g. with `embassy_time::with_timeout`.
+//!
+//! ## Usage
+//!
+//! ```no_run
+//! use core::net::{Ipv4Addr, SocketAddr};
+//!
+//! use embedded_io_async::{Read, Write};
+//! use embedded_nal_async::TcpConnect;
+//!
+//! let mut connection = wifi
+//!     .connect(SocketAddr::from((Ipv4Addr::new(10, 0, 1, 3), 4000)))
+//!     .await
+//!     .unwrap();
+//! connection.write_all(b"Hello, server!").await.unwrap();
+//!
+//! let mut buf = [0u8; 64];
+//! let length = connection.read(&mut buf).await.unwrap();
+//! // The connection is closed when it's dropped
+//! ```
+//!
+
+use core::future::poll_fn;
+use core::net::{IpAddr, SocketAddr};
+use core::task::Poll;
+
+use embedded_hal::blocking::delay::DelayMs;
+
+use embedded_io_async::{ErrorType, Read, Write};
+use embedded_nal_async::{AddrType, ConnectedUdp, Dns, TcpConnect, UdpStack, UnconnectedUdp};
+
+use super::network::{
+    to_socket_addr_v4, ConnectionState, NetworkError, Port, Socket, TransportMode,
+};
+use super::protocol::{ProtocolInterface, MAX_NINA_LARGE_ARRAY_PARAM_BUFFER_LENGTH};
+use super::transport::Transport;
+use super::udp_client::{send_datagram, UDP_EPHEMERAL_PORT_BASE};
+use super::wifi::Wifi;
+use super::Error;
+
+/// A TCP connection made with the [`TcpConnect`] implementation of [`Wifi`], which is closed
+/// when it's dropped.
+pub struct TcpConnection<'a, T, D>
+where
+    T: Transport,
+    D: DelayMs<u16>,
+{
+    wifi: &'a Wifi<T, D>,
+    socket: Socket,
+}
+
+impl<T, D> TcpConnection<'_, T, D>
+where
+    T: Transport,
+    D: DelayMs<u16>,
+{
+    /// The `Socket` handle of the ESP32 target this connection uses.
+    pub fn socket(&self) -> Socket {
+        self.socket
+    }
+}
+
+impl<T, D> ErrorType for TcpConnection<'_, T, D>
+where
+    T: Transport,
+    D: DelayMs<u16>,
+{
+    type Error = Error;
+}
+
+impl<T, D> Read for TcpConnection<'_, T, D>
+where
+    T: Transport,
+    D: DelayMs<u16>,
+{
+    /// Wait for data from the server and read it into `buf`, returning the number of bytes
+    /// read. Once the server has closed the connection and all of its data has been read, `0`
+    /// is returned.
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        loop {
+            {
+                let mut protocol_handler = self.wifi.borrow_protocol_handler()?;
+
+                let available = protocol_handler.avail_data_tcp(self.socket)?;
+                if available > 0 {
+                    let length = available.min(buf.len());
+                    return protocol_handler.get_data_buf_tcp(self.socket, &mut buf[..length]);
+                }
+
+                if protocol_handler.get_client_state_tcp(self.socket)?
+                    != ConnectionState::Established
+                {
+                    return Ok(0);
+                }
+            }
+
+            yield_now().await;
+        }
+    }
+}
+
+impl<T, D> Write for TcpConnection<'_, T, D>
+where
+    T: Transport,
+    D: DelayMs<u16>,
+{
+    /// Wait until the ESP32 target accepts some of `buf` to send, returning the number of
+    /// bytes sent.
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        let length = buf.len().min(MAX_NINA_LARGE_ARRAY_PARAM_BUFFER_LENGTH);
+
+        loop {
+            {
+                let mut protocol_handler = self.wifi.borrow_protocol_handler()?;
+
+                let sent = protocol_handler.send_data(&buf[..length], self.socket)?;
+                if sent > 0 {
+                    if !protocol_handler.check_data_sent(self.socket)? {
+                        return Err(NetworkError::SendFailed.into());
+                    }
+                    return Ok(sent);
+                }
+            }
+
+            yield_now().await;
+        }
+    }
+}
+
+impl<T, D> Drop for TcpConnection<'_, T, D>
+where
+    T: Transport,
+    D: DelayMs<u16>,
+{
+    fn drop(&mut self) {
+        if let Ok(mut protocol_handler) = self.wifi.protocol_handler.try_borrow_mut() {
+            protocol_handler
+                .stop_client_tcp(self.socket, &TransportMode::Tcp)
+                .ok();
+        }
+    }
+}
+
+impl<T, D> TcpConnect for Wifi<T, D>
+where
+    T: Transport,
+    D: DelayMs<u16>,
+{
+    type Error = Error;
+    type Connection<'a>
+        = TcpConnection<'a, T, D>
+    where
+        Self: 'a;
+
+    /// Connect to `remote`, waiting until the connection is established. IPv6 addresses fail
+    /// with [`NetworkError::Ipv6Unsupported`], while a connection that can't be established
+    /// fails with [`NetworkError::ConnectFailed`].
+    async fn connect<'a>(&'a self, remote: SocketAddr) -> Result<TcpConnection<'a, T, D>, Error> {
+        let remote = to_socket_addr_v4(remote)?;
+
+        let socket = {
+            let mut protocol_handler = self.borrow_protocol_handler()?;
+
+            let socket = protocol_handler.get_socket()?;
+            protocol_handler.start_client_tcp(socket, remote, &TransportMode::Tcp)?;
+            socket
+        };
+        // From here on dropping the connection closes the socket, also when connecting fails
+        let connection = TcpConnection { wifi: self, socket };
+
+        loop {
+            let state = self
+                .borrow_protocol_handler()?
+                .get_client_state_tcp(socket)?;
+
+            match state {
+                ConnectionState::Established => return Ok(connection),
+                ConnectionState::Listening
+                | ConnectionState::SynSent
+                | ConnectionState::SynReceived => yield_now().await,
+                _ => return Err(NetworkError::ConnectFailed.into()),
+            }
+        }
+    }
+}
+
+impl<T, D> Dns for Wifi<T, D>
+where
+    T: Transport,
+    D: DelayMs<u16>,
+{
+    type Error = Error;
+
+    /// Resolve `host` with the DNS server(s) of the ESP32 target. Only IPv4 addresses can be
+    /// resolved, so asking for [`AddrType::IPv6`] fails with [`NetworkError::Ipv6Unsupported`].
+    async fn get_host_by_name(&self, host: &str, addr_type: AddrType) -> Result<IpAddr, Error> {
+        if addr_type == AddrType::IPv6 {
+            return Err(NetworkError::Ipv6Unsupported.into());
+        }
+
+        let ip = self.borrow_protocol_handler()?.resolve(host)?;
+
+        Ok(IpAddr::V4(ip))
+    }
+
+    /// The NINA firmware can't look up the hostname of an address, so this always fails with
+    /// [`Error::UnsupportedOperation`]. Use [`Wifi::get_host_by_addr`] with the address of a
+    /// DNS server instead.
+    async fn get_host_by_address(&self, _addr: IpAddr, _result: &mut [u8]) -> Result<usize, Error> {
+        Err(Error::UnsupportedOperation)
+    }
+}
+
+/// A UDP socket bound to a local port by the [`UdpStack`] implementation of [`Wifi`], which is
+/// released when it's dropped.
+///
+/// The NINA firmware doesn't filter received datagrams by sender, so a connected socket also
+/// receives the datagrams of other peers sent to its local port.
+pub struct UdpSocket<'a, T, D>
+where
+    T: Transport,
+    D: DelayMs<u16>,
+{
+    wifi: &'a Wifi<T, D>,
+    socket: Socket,
+    local: SocketAddr,
+    remote: Option<SocketAddr>,
+}
+
+impl<'a, T, D> UdpSocket<'a, T, D>
+where
+    T: Transport,
+    D: DelayMs<u16>,
+{
+    // Requests a socket and binds it to the port of `local`, or to an ephemeral port if that
+    // is 0.
+    fn bind(
+        wifi: &'a Wifi<T, D>,
+        local: SocketAddr,
+        remote: Option<SocketAddr>,
+    ) -> Result<Self, Error> {
+        to_socket_addr_v4(local)?;
+
+        let mut protocol_handler = wifi.borrow_protocol_handler()?;
+
+        let socket = protocol_handler.get_socket()?;
+        let port = match local.port() {
+            0 => UDP_EPHEMERAL_PORT_BASE + socket as Port,
+            port => port,
+        };
+        protocol_handler.start_server_tcp(socket, port, &TransportMode::Udp)?;
+
+        Ok(Self {
+            wifi,
+            socket,
+            local: SocketAddr::new(local.ip(), port),
+            remote,
+        })
+    }
+
+    fn send_to(&mut self, remote: SocketAddr, data: &[u8]) -> Result<(), Error> {
+        let remote = to_socket_addr_v4(remote)?;
+
+        send_datagram(
+            &mut *self.wifi.borrow_protocol_handler()?,
+            self.socket,
+            remote,
+            data,
+        )
+    }
+
+    // Waits for a datagram and copies it into `buffer`, returning its length and sender.
+    async fn receive_from(&mut self, buffer: &mut [u8]) -> Result<(usize, SocketAddr), Error> {
+        loop {
+            {
+                let mut protocol_handler = self.wifi.borrow_protocol_handler()?;
+
+                let available = protocol_handler.avail_data_tcp(self.socket)?;
+                if available > 0 {
+                    let length = available.min(buffer.len());
+                    let length =
+                        protocol_handler.get_data_buf_tcp(self.socket, &mut buffer[..length])?;
+                    let sender = protocol_handler.get_remote_data(self.socket)?;
+
+                    return Ok((length, SocketAddr::V4(sender)));
+                }
+            }
+
+            yield_now().await;
+        }
+    }
+}
+
+impl<T, D> Drop for UdpSocket<'_, T, D>
+where
+    T: Transport,
+    D: DelayMs<u16>,
+{
+    fn drop(&mut self) {
+        if let Ok(mut protocol_handler) = self.wifi.protocol_handler.try_borrow_mut() {
+            protocol_handler
+                .stop_client_tcp(self.socket, &TransportMode::Udp)
+                .ok();
+        }
+    }
+}
+
+impl<T, D> ConnectedUdp for UdpSocket<'_, T, D>
+where
+    T: Transport,
+    D: DelayMs<u16>,
+{
+    type Error = Error;
+
+    /// Send `data` as a single datagram to the remote address this socket is connected to,
+    /// failing with [`NetworkError::UdpSocketNotConnected`] for sockets that were only bound.
+    async fn send(&mut self, data: &[u8]) -> Result<(), Error> {
+        let remote = self.remote.ok_or(NetworkError::UdpSocketNotConnected)?;
+
+        self.send_to(remote, data)
+    }
+
+    async fn receive_into(&mut self, buffer: &mut [u8]) -> Result<usize, Error> {
+        let (length, _) = self.receive_from(buffer).await?;
+
+        Ok(length)
+    }
+}
+
+impl<T, D> UnconnectedUdp for UdpSocket<'_, T, D>
+where
+    T: Transport,
+    D: DelayMs<u16>,
+{
+    type Error = Error;
+
+    /// Send `data` as a single datagram to `remote`. Datagrams are always sent from the port
+    /// this socket is bound to, whatever `local` is.
+    async fn send(
+        &mut self,
+        _local: SocketAddr,
+        remote: SocketAddr,
+        data: &[u8],
+    ) -> Result<(), Error> {
+        self.send_to(remote, data)
+    }
+
+    async fn receive_into(
+        &mut self,
+        buffer: &mut [u8],
+    ) -> Result<(usize, SocketAddr, SocketAddr), Error> {
+        let (length, remote) = self.receive_from(buffer).await?;
+
+        Ok((length, self.local, remote))
+    }
+}
+
+impl<'a, T, D> UdpStack for &'a Wifi<T, D>
+where
+    T: Transport,
+    D: DelayMs<u16>,
+{
+    type Error = Error;
+    type Connected = UdpSocket<'a, T, D>;
+    type UniquelyBound = UdpSocket<'a, T, D>;
+    type MultiplyBound = UdpSocket<'a, T, D>;
+
+    /// Create a socket that sends datagrams to `remote`, bound to the port of `local` or to an
+    /// ephemeral port if that is 0.
+    async fn connect_from(
+        &self,
+        local: SocketAddr,
+        remote: SocketAddr,
+    ) -> Result<(SocketAddr, UdpSocket<'a, T, D>), Error> {
+        to_socket_addr_v4(remote)?;
+
+        let socket = UdpSocket::bind(self, local, Some(remote))?;
+
+        Ok((socket.local, socket))
+    }
+
+    /// Create a socket bound to the port of `local`, or to an ephemeral port if that is 0.
+    async fn bind_single(
+        &self,
+        local: SocketAddr,
+    ) -> Result<(SocketAddr, UdpSocket<'a, T, D>), Error> {
+        let socket = UdpSocket::bind(self, local, None)?;
+
+        Ok((socket.local, socket))
+    }
+
+    /// The NINA firmware can only bind one socket to a port, so this always fails with
+    /// [`Error::UnsupportedOperation`].
+    async fn bind_multiple(&self, _local: SocketAddr) -> Result<UdpSocket<'a, T, D>, Error> {
+        Err(Error::UnsupportedOperation)
+    }
+}
+
+// Lets other tasks run once before the ESP32 target is polled again.
+async fn yield_now() {
+    let mut yielded = false;
+
+    poll_fn(|cx| {
+        if yielded {
+            Poll::Ready(())
+        } else {
+            yielded = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    })
+    .await
+}
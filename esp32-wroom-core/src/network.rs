@@ -0,0 +1,774 @@
+//! Defines common network functions, types and error definitions.
+//!
+
+use core::fmt;
+use core::net::Ipv4Addr;
+use core::str::FromStr;
+
+#[cfg(feature = "defmt")]
+use defmt::{write, Format, Formatter};
+
+use super::Error;
+
+/// A sixteen byte array type alias representing an IPv6 address.
+pub type Ipv6Address = [u8; 16];
+
+/// Either an IPv4 or an IPv6 address.
+///
+/// The NINA firmware only supports IPv4 so far, so operations given a [`IpAddr::V6`] address
+/// fail with [`NetworkError::Ipv6Unsupported`]. Accepting this type already lets IPv6 support
+/// be added later without changing the signatures that take it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum IpAddr {
+    /// An IPv4 address
+    V4(Ipv4Addr),
+    /// An IPv6 address
+    V6(Ipv6Address),
+}
+
+impl IpAddr {
+    /// Whether this is an IPv6 address.
+    pub fn is_ipv6(&self) -> bool {
+        matches!(self, IpAddr::V6(_))
+    }
+
+    // Returns the IPv4 address the ESP32 target can use or an error for IPv6 addresses.
+    pub(crate) fn to_ipv4(self) -> Result<Ipv4Addr, Error> {
+        match self {
+            IpAddr::V4(ip) => Ok(ip),
+            IpAddr::V6(_) => Err(NetworkError::Ipv6Unsupported.into()),
+        }
+    }
+}
+
+impl From<Ipv4Addr> for IpAddr {
+    fn from(ip: Ipv4Addr) -> Self {
+        IpAddr::V4(ip)
+    }
+}
+
+impl From<[u8; 4]> for IpAddr {
+    fn from(ip: [u8; 4]) -> Self {
+        IpAddr::V4(Ipv4Addr::from(ip))
+    }
+}
+
+impl From<Ipv6Address> for IpAddr {
+    fn from(ip: Ipv6Address) -> Self {
+        IpAddr::V6(ip)
+    }
+}
+
+impl From<core::net::IpAddr> for IpAddr {
+    fn from(ip: core::net::IpAddr) -> Self {
+        match ip {
+            core::net::IpAddr::V4(ip) => IpAddr::V4(ip),
+            core::net::IpAddr::V6(ip) => IpAddr::V6(ip.octets()),
+        }
+    }
+}
+
+// Returns the IPv4 socket address the ESP32 target can use or an error for IPv6 addresses.
+#[cfg(any(feature = "embedded-nal", feature = "async"))]
+pub(crate) fn to_socket_addr_v4(
+    remote: core::net::SocketAddr,
+) -> Result<core::net::SocketAddrV4, Error> {
+    match remote {
+        core::net::SocketAddr::V4(remote) => Ok(remote),
+        core::net::SocketAddr::V6(_) => Err(NetworkError::Ipv6Unsupported.into()),
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl Format for IpAddr {
+    fn format(&self, fmt: Formatter) {
+        match self {
+            IpAddr::V4(ip) => write!(fmt, "{}", ip),
+            IpAddr::V6(ip) => {
+                for (index, group) in ip.chunks(2).enumerate() {
+                    if index > 0 {
+                        write!(fmt, ":");
+                    }
+                    write!(fmt, "{=u16:x}", u16::from_be_bytes([group[0], group[1]]));
+                }
+            }
+        }
+    }
+}
+
+/// A named string slice type representing a network hostname.
+pub type Hostname<'a> = &'a str;
+
+/// A TCP/UDP network port.
+pub type Port = u16;
+
+pub(crate) type Socket = u8;
+
+/// The maximum length in bytes of the hostname the ESP32 target announces via DHCP.
+pub const MAX_DHCP_HOSTNAME_LENGTH: usize = 32;
+
+// Checks that `hostname` is a valid DHCP hostname label (RFC 1123): 1 to
+// MAX_DHCP_HOSTNAME_LENGTH ASCII letters, digits and hyphens, not starting or ending with one.
+pub(crate) fn validate_hostname(hostname: &str) -> Result<(), Error> {
+    let bytes = hostname.as_bytes();
+
+    let valid = !bytes.is_empty()
+        && bytes.len() <= MAX_DHCP_HOSTNAME_LENGTH
+        && bytes
+            .iter()
+            .all(|b| b.is_ascii_alphanumeric() || *b == b'-')
+        && bytes.first() != Some(&b'-')
+        && bytes.last() != Some(&b'-');
+
+    if valid {
+        Ok(())
+    } else {
+        Err(NetworkError::InvalidHostname.into())
+    }
+}
+
+/// The limited broadcast address, which reaches every host on the local network.
+pub const BROADCAST_ADDRESS: Ipv4Addr = Ipv4Addr::BROADCAST;
+
+/// The subnet mask an [`IpConfig`] uses unless another one is set.
+pub const DEFAULT_SUBNET_MASK: Ipv4Addr = Ipv4Addr::new(255, 255, 255, 0);
+
+/// An IPv4 configuration, either assigned statically with
+/// [`Wifi::set_ip_config`](crate::wifi::Wifi::set_ip_config) or queried with
+/// [`Wifi::get_ip_config`](crate::wifi::Wifi::get_ip_config).
+///
+/// Only the IP address is required. Like the Arduino WiFiNINA library, the gateway defaults to
+/// the first address of the IP address's /24 network (e.g. `192.168.1.1` for `192.168.1.42`)
+/// and the subnet mask to [`DEFAULT_SUBNET_MASK`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct IpConfig {
+    /// IP address of the ESP32 target.
+    pub ip: Ipv4Addr,
+    /// IP address of the network's default gateway.
+    pub gateway: Ipv4Addr,
+    /// Subnet mask of the network.
+    pub subnet_mask: Ipv4Addr,
+}
+
+impl IpConfig {
+    /// Create a new [`IpConfig`] for the static IP address `ip`.
+    pub fn new(ip: Ipv4Addr) -> Self {
+        let [a, b, c, _] = ip.octets();
+
+        Self {
+            ip,
+            gateway: Ipv4Addr::new(a, b, c, 1),
+            subnet_mask: DEFAULT_SUBNET_MASK,
+        }
+    }
+
+    /// Route traffic leaving the local network through `gateway`.
+    pub fn gateway(mut self, gateway: Ipv4Addr) -> Self {
+        self.gateway = gateway;
+        self
+    }
+
+    /// Use `subnet_mask` for the local network.
+    pub fn subnet_mask(mut self, subnet_mask: Ipv4Addr) -> Self {
+        self.subnet_mask = subnet_mask;
+        self
+    }
+
+    /// The directed broadcast address of the local network (e.g. `192.168.1.255` for
+    /// `192.168.1.42/24`), which reaches every host on it.
+    pub fn broadcast_address(&self) -> Ipv4Addr {
+        self.ip | !self.subnet_mask
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl Format for IpConfig {
+    fn format(&self, fmt: Formatter) {
+        write!(
+            fmt,
+            "ip: {}, gateway: {}, subnet_mask: {}",
+            self.ip, self.gateway, self.subnet_mask
+        );
+    }
+}
+
+#[cfg(feature = "ufmt")]
+impl ufmt::uDebug for IpConfig {
+    fn fmt<W: ufmt::uWrite + ?Sized>(
+        &self,
+        f: &mut ufmt::Formatter<'_, W>,
+    ) -> Result<(), W::Error> {
+        f.write_str("IpConfig { ip: ")?;
+        uwrite_ipv4(f, self.ip)?;
+        f.write_str(", gateway: ")?;
+        uwrite_ipv4(f, self.gateway)?;
+        f.write_str(", subnet_mask: ")?;
+        uwrite_ipv4(f, self.subnet_mask)?;
+        f.write_str(" }")
+    }
+}
+
+// Writes `ip` in dotted decimal notation, as ufmt has no impls for core::net types.
+#[cfg(feature = "ufmt")]
+pub(crate) fn uwrite_ipv4<W: ufmt::uWrite + ?Sized>(
+    f: &mut ufmt::Formatter<'_, W>,
+    ip: Ipv4Addr,
+) -> Result<(), W::Error> {
+    let [a, b, c, d] = ip.octets();
+    ufmt::uwrite!(f, "{}.{}.{}.{}", a, b, c, d)
+}
+
+/// The number of bytes in a [`MacAddress`].
+pub const MAC_ADDRESS_LENGTH: usize = 6;
+
+/// A 6 byte hardware (MAC) address of a network interface, such as the ESP32's own WiFi
+/// interface or the BSSID of an access point.
+///
+/// A [`MacAddress`] can be parsed from a string of hex byte pairs separated by either colons
+/// or dashes (e.g. `"a4:cf:12:0b:3c:5e"` or `"A4-CF-12-0B-3C-5E"`) and is displayed in the
+/// lowercase colon separated form.
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Default, Hash)]
+pub struct MacAddress([u8; MAC_ADDRESS_LENGTH]);
+
+impl MacAddress {
+    /// Create a new [`MacAddress`] from its 6 bytes in transmission order.
+    pub const fn new(bytes: [u8; MAC_ADDRESS_LENGTH]) -> Self {
+        Self(bytes)
+    }
+
+    /// Get the bytes of this [`MacAddress`] in transmission order.
+    pub const fn octets(&self) -> [u8; MAC_ADDRESS_LENGTH] {
+        self.0
+    }
+}
+
+impl From<[u8; MAC_ADDRESS_LENGTH]> for MacAddress {
+    fn from(bytes: [u8; MAC_ADDRESS_LENGTH]) -> Self {
+        Self(bytes)
+    }
+}
+
+impl From<MacAddress> for [u8; MAC_ADDRESS_LENGTH] {
+    fn from(mac_address: MacAddress) -> Self {
+        mac_address.0
+    }
+}
+
+impl AsRef<[u8]> for MacAddress {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl FromStr for MacAddress {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let separator = match s.as_bytes().get(2) {
+            Some(b':') => ':',
+            Some(b'-') => '-',
+            _ => return Err(NetworkError::InvalidMacAddress.into()),
+        };
+
+        let mut bytes = [0u8; MAC_ADDRESS_LENGTH];
+        let mut octets = s.split(separator);
+
+        for byte in bytes.iter_mut() {
+            let octet = octets.next().ok_or(NetworkError::InvalidMacAddress)?;
+            if octet.len() != 2 {
+                return Err(NetworkError::InvalidMacAddress.into());
+            }
+            *byte = u8::from_str_radix(octet, 16).map_err(|_| NetworkError::InvalidMacAddress)?;
+        }
+
+        if octets.next().is_some() {
+            return Err(NetworkError::InvalidMacAddress.into());
+        }
+
+        Ok(Self(bytes))
+    }
+}
+
+impl fmt::Display for MacAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [a, b, c, d, e, g] = self.0;
+        core::write!(
+            f,
+            "{:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}",
+            a,
+            b,
+            c,
+            d,
+            e,
+            g
+        )
+    }
+}
+
+#[cfg(feature = "ufmt")]
+impl ufmt::uDisplay for MacAddress {
+    fn fmt<W: ufmt::uWrite + ?Sized>(
+        &self,
+        f: &mut ufmt::Formatter<'_, W>,
+    ) -> Result<(), W::Error> {
+        const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+        for (index, byte) in self.0.iter().enumerate() {
+            if index > 0 {
+                f.write_char(':')?;
+            }
+            f.write_char(HEX_DIGITS[usize::from(byte >> 4)] as char)?;
+            f.write_char(HEX_DIGITS[usize::from(byte & 0x0f)] as char)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "ufmt")]
+impl ufmt::uDebug for MacAddress {
+    fn fmt<W: ufmt::uWrite + ?Sized>(
+        &self,
+        f: &mut ufmt::Formatter<'_, W>,
+    ) -> Result<(), W::Error> {
+        ufmt::uDisplay::fmt(self, f)
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl Format for MacAddress {
+    fn format(&self, fmt: Formatter) {
+        let [a, b, c, d, e, g] = self.0;
+        write!(
+            fmt,
+            "{=u8:02x}:{=u8:02x}:{=u8:02x}:{=u8:02x}:{=u8:02x}:{=u8:02x}",
+            a, b, c, d, e, g
+        );
+    }
+}
+
+/// Defines the mode types that the ESP32 firmware can be put into when starting
+/// a new client or server instance
+#[repr(u8)]
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+#[cfg_attr(feature = "ufmt", derive(ufmt::derive::uDebug))]
+#[non_exhaustive]
+pub enum TransportMode {
+    /// TCP mode
+    Tcp = 0,
+    /// UDP mode
+    Udp = 1,
+    /// TLS mode
+    Tls = 2,
+    /// UDP multicast mode
+    UdpMulticast = 3,
+    /// TLS BearSSL mode
+    TlsBearSsl = 4,
+}
+
+impl TransportMode {
+    /// Whether data is encrypted with TLS by the NINA firmware in this mode.
+    pub fn is_tls(&self) -> bool {
+        matches!(self, TransportMode::Tls | TransportMode::TlsBearSsl)
+    }
+
+    /// Whether data is sent as individual datagrams instead of a stream in this mode.
+    pub fn is_datagram(&self) -> bool {
+        matches!(self, TransportMode::Udp | TransportMode::UdpMulticast)
+    }
+}
+
+/// Defines all possible TCP connection states for a client or server instance.
+#[repr(u8)]
+#[derive(PartialEq, PartialOrd, Debug)]
+#[cfg_attr(feature = "ufmt", derive(ufmt::derive::uDebug))]
+pub enum ConnectionState {
+    /// Closed
+    Closed = 0,
+    /// Listening
+    Listening = 1,
+    /// SynSent
+    SynSent = 2,
+    /// SynReceived
+    SynReceived = 3,
+    /// Established
+    Established = 4,
+    /// FinWait1
+    FinWait1 = 5,
+    /// Finwait2
+    FinWait2 = 6,
+    /// CloseWait
+    CloseWait = 7,
+    /// Closing
+    Closing = 8,
+    /// LastAck
+    LastAck = 9,
+    /// TimeWait
+    TimeWait = 10,
+}
+
+impl From<u8> for ConnectionState {
+    fn from(state: u8) -> ConnectionState {
+        match state {
+            0 => ConnectionState::Closed,
+            1 => ConnectionState::Listening,
+            2 => ConnectionState::SynSent,
+            3 => ConnectionState::SynReceived,
+            4 => ConnectionState::Established,
+            5 => ConnectionState::FinWait1,
+            6 => ConnectionState::FinWait2,
+            7 => ConnectionState::CloseWait,
+            8 => ConnectionState::Closing,
+            9 => ConnectionState::LastAck,
+            10 => ConnectionState::TimeWait,
+            _ => ConnectionState::Closed,
+        }
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl Format for ConnectionState {
+    fn format(&self, fmt: Formatter) {
+        match self {
+            ConnectionState::Closed => write!(fmt, "Connection Closed"),
+            ConnectionState::Listening => write!(fmt, "Connection Listening"),
+            ConnectionState::SynSent => write!(fmt, "Connection SynSent"),
+            ConnectionState::SynReceived => write!(fmt, "Connection SynRecieved"),
+            ConnectionState::Established => write!(fmt, "Connection Established"),
+            ConnectionState::FinWait1 => write!(fmt, "Connection FinWait1"),
+            ConnectionState::FinWait2 => write!(fmt, "Connection FinWait2"),
+            ConnectionState::CloseWait => write!(fmt, "Connection CloseWait"),
+            ConnectionState::Closing => write!(fmt, "Connection Closing"),
+            ConnectionState::LastAck => write!(fmt, "Connection LastAck"),
+            ConnectionState::TimeWait => write!(fmt, "Connection TimeWait"),
+        }
+    }
+}
+
+/// Errors that occur due to issues involving communication over
+/// WiFi network.
+#[derive(PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "ufmt", derive(ufmt::derive::uDebug))]
+#[non_exhaustive]
+pub enum NetworkError {
+    /// Failed to resolve a hostname for the provided IP address.
+    DnsResolveFailed,
+    /// Timed out while trying to connect to remote TCP server.
+    ConnectionTimeout,
+    /// Failed to connect to remote TCP server.
+    ConnectFailed,
+    /// Failed to disconnect from remote TCP server.
+    DisconnectFailed,
+    /// Failed to start up a new TCP server instance.
+    StartServerFailed,
+    /// Attempted to accept clients on a TCP server that isn't bound to a port.
+    ServerNotBound,
+    /// Failed to retrieve network credentials from a credential provider.
+    CredentialsUnavailable,
+    /// Failed to parse a string as a MAC address.
+    InvalidMacAddress,
+    /// Failed to start a scan for nearby WiFi networks.
+    ScanFailed,
+    /// Failed to send a UDP datagram or data over a TCP connection.
+    SendFailed,
+    /// The ESP32 target rejected a TLS client certificate or private key.
+    TlsProvisioningFailed,
+    /// No room is left to register a keep-alive for another socket.
+    TooManyKeepAlives,
+    /// Failed to start hosting a WiFi network in Access Point mode.
+    StartAccessPointFailed,
+    /// The UTF-8 encoding of an SSID is longer than `MAX_SSID_LENGTH` bytes.
+    SsidTooLong,
+    /// The UTF-8 encoding of a passphrase is longer than `MAX_PASSPHRASE_LENGTH` bytes.
+    PassphraseTooLong,
+    /// A hostname is empty, longer than [`MAX_DHCP_HOSTNAME_LENGTH`] bytes or contains other
+    /// characters than ASCII letters, digits and inner hyphens.
+    InvalidHostname,
+    /// The ESP32 target failed to set its DHCP hostname.
+    SetHostnameFailed,
+    /// Data doesn't fit into the free space left in an `OfflineQueue`.
+    OfflineQueueFull,
+    /// The ESP32 target hasn't synchronized its clock via SNTP yet.
+    TimeUnavailable,
+    /// A DNS server didn't respond to a query in time.
+    DnsTimeout,
+    /// An `MdnsResponder` already advertises `MAX_MDNS_SERVICES` services.
+    TooManyMdnsServices,
+    /// The NINA firmware doesn't support IPv6 addresses.
+    Ipv6Unsupported,
+    /// No data was received from a remote server within the read timeout.
+    ReadTimeout,
+    /// The ESP32 target didn't accept more data to send within the write timeout.
+    WriteTimeout,
+    /// A connection that was established before has been closed or reset.
+    ConnectionLost,
+    /// A CoAP server didn't respond to a request in time.
+    CoapTimeout,
+    /// A CoAP server rejected a request with a reset message.
+    CoapReset,
+    /// A CoAP message is malformed.
+    MalformedCoapMessage,
+    /// An SNTP server didn't respond to a request in time.
+    SntpTimeout,
+    /// An SNTP server responded without a usable time.
+    InvalidSntpResponse,
+    /// A `DebugConsole` already has `MAX_CONSOLE_COMMANDS` commands registered.
+    TooManyConsoleCommands,
+    /// A datagram was sent on a UDP socket that isn't connected to a remote address.
+    UdpSocketNotConnected,
+    /// `SvcWifi` was asked to connect before a client configuration was set.
+    ClientNotConfigured,
+    /// A scan found more than `MAX_SCAN_RESULTS` networks, so not all of them were returned.
+    ScanResultsTruncated,
+}
+
+#[cfg(feature = "defmt")]
+impl Format for NetworkError {
+    fn format(&self, fmt: Formatter) {
+        match self {
+            NetworkError::DnsResolveFailed => {
+                write!(
+                    fmt,
+                    "Failed to resolve a hostname for the provided IP address"
+                )
+            }
+            NetworkError::ConnectionTimeout => {
+                write!(fmt, "Timed out while trying connect the remote TCP server")
+            }
+            NetworkError::ConnectFailed => {
+                write!(fmt, "Failed to connect to remote TCP server")
+            }
+            NetworkError::DisconnectFailed => {
+                write!(fmt, "Failed to start up a new TCP/UDP client instance")
+            }
+            NetworkError::StartServerFailed => {
+                write!(fmt, "Failed to start up a new TCP server instance")
+            }
+            NetworkError::ServerNotBound => {
+                write!(fmt, "TCP server is not bound to a port")
+            }
+            NetworkError::CredentialsUnavailable => {
+                write!(
+                    fmt,
+                    "Failed to retrieve network credentials from a credential provider"
+                )
+            }
+            NetworkError::InvalidMacAddress => {
+                write!(fmt, "Failed to parse a string as a MAC address")
+            }
+            NetworkError::ScanFailed => {
+                write!(fmt, "Failed to start a scan for nearby WiFi networks")
+            }
+            NetworkError::SendFailed => {
+                write!(fmt, "Failed to send a UDP datagram or TCP data")
+            }
+            NetworkError::TlsProvisioningFailed => {
+                write!(
+                    fmt,
+                    "The ESP32 target rejected a TLS client certificate or private key"
+                )
+            }
+            NetworkError::TooManyKeepAlives => {
+                write!(
+                    fmt,
+                    "No room is left to register a keep-alive for another socket"
+                )
+            }
+            NetworkError::StartAccessPointFailed => {
+                write!(
+                    fmt,
+                    "Failed to start hosting a WiFi network in Access Point mode"
+                )
+            }
+            NetworkError::SsidTooLong => {
+                write!(fmt, "SSID is longer than the maximum number of bytes")
+            }
+            NetworkError::PassphraseTooLong => {
+                write!(fmt, "Passphrase is longer than the maximum number of bytes")
+            }
+            NetworkError::InvalidHostname => {
+                write!(fmt, "Hostname is not a valid DHCP hostname")
+            }
+            NetworkError::SetHostnameFailed => {
+                write!(fmt, "Failed to set the DHCP hostname of the ESP32 target")
+            }
+            NetworkError::OfflineQueueFull => {
+                write!(fmt, "Not enough free space left in the offline queue")
+            }
+            NetworkError::TimeUnavailable => {
+                write!(fmt, "The ESP32 target hasn't synchronized its clock yet")
+            }
+            NetworkError::DnsTimeout => {
+                write!(fmt, "Timed out waiting for the response of a DNS server")
+            }
+            NetworkError::TooManyMdnsServices => {
+                write!(
+                    fmt,
+                    "The mDNS responder already advertises the maximum number of services"
+                )
+            }
+            NetworkError::Ipv6Unsupported => {
+                write!(fmt, "The NINA firmware doesn't support IPv6 addresses")
+            }
+            NetworkError::ReadTimeout => {
+                write!(
+                    fmt,
+                    "Timed out while waiting for data from the remote server"
+                )
+            }
+            NetworkError::WriteTimeout => {
+                write!(
+                    fmt,
+                    "Timed out while waiting to send data to the remote server"
+                )
+            }
+            NetworkError::ConnectionLost => {
+                write!(fmt, "The connection to the remote server was lost")
+            }
+            NetworkError::CoapTimeout => {
+                write!(fmt, "A CoAP server didn't respond to a request in time")
+            }
+            NetworkError::CoapReset => {
+                write!(fmt, "A CoAP server rejected a request with a reset message")
+            }
+            NetworkError::MalformedCoapMessage => {
+                write!(fmt, "A CoAP message is malformed")
+            }
+            NetworkError::SntpTimeout => {
+                write!(fmt, "An SNTP server didn't respond to a request in time")
+            }
+            NetworkError::InvalidSntpResponse => {
+                write!(fmt, "An SNTP server responded without a usable time")
+            }
+            NetworkError::TooManyConsoleCommands => {
+                write!(
+                    fmt,
+                    "The debug console already has the maximum number of commands registered"
+                )
+            }
+            NetworkError::UdpSocketNotConnected => {
+                write!(
+                    fmt,
+                    "A datagram was sent on a UDP socket without a remote address"
+                )
+            }
+            NetworkError::ClientNotConfigured => {
+                write!(
+                    fmt,
+                    "No client configuration was set before connecting to a WiFi network"
+                )
+            }
+            NetworkError::ScanResultsTruncated => {
+                write!(
+                    fmt,
+                    "A scan found more networks than the maximum number of scan results"
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod network_tests {
+    use super::*;
+
+    #[test]
+    fn ip_addr_to_ipv4_returns_ipv6_unsupported_error_for_ipv6_addresses() {
+        let ipv6 = IpAddr::from([0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+
+        assert!(ipv6.is_ipv6());
+        assert_eq!(
+            ipv6.to_ipv4(),
+            Err(Error::Network(NetworkError::Ipv6Unsupported))
+        );
+        assert_eq!(
+            IpAddr::from([10, 0, 0, 1]).to_ipv4(),
+            Ok(Ipv4Addr::new(10, 0, 0, 1))
+        );
+    }
+
+    #[test]
+    fn mac_address_from_str_parses_colon_separated_octets() {
+        let mac_address: MacAddress = "a4:cf:12:0b:3c:5e".parse().unwrap();
+
+        assert_eq!(
+            mac_address,
+            MacAddress::new([0xa4, 0xcf, 0x12, 0x0b, 0x3c, 0x5e])
+        )
+    }
+
+    #[test]
+    fn mac_address_from_str_parses_dash_separated_octets() {
+        let mac_address: MacAddress = "A4-CF-12-0B-3C-5E".parse().unwrap();
+
+        assert_eq!(
+            mac_address,
+            MacAddress::new([0xa4, 0xcf, 0x12, 0x0b, 0x3c, 0x5e])
+        )
+    }
+
+    #[test]
+    fn mac_address_from_str_returns_invalid_mac_address_error_for_malformed_strings() {
+        for s in [
+            "",
+            "a4:cf:12:0b:3c",
+            "a4:cf:12:0b:3c:5e:00",
+            "a4:cf-12:0b:3c:5e",
+            "a4:cf:12:0b:3c:5g",
+            "a4:cf:12:0b:3c:5",
+        ] {
+            assert_eq!(
+                s.parse::<MacAddress>().unwrap_err(),
+                Error::Network(NetworkError::InvalidMacAddress)
+            )
+        }
+    }
+
+    #[test]
+    fn mac_address_display_formats_lowercase_colon_separated_octets() {
+        let mac_address = MacAddress::from([0xa4, 0xcf, 0x12, 0x0b, 0x3c, 0x5e]);
+
+        assert_eq!(mac_address.to_string(), "a4:cf:12:0b:3c:5e")
+    }
+
+    #[test]
+    fn ip_config_new_defaults_gateway_and_subnet_mask_to_the_ips_24_network() {
+        let ip_config = IpConfig::new(Ipv4Addr::new(192, 168, 1, 42));
+
+        assert_eq!(ip_config.gateway, Ipv4Addr::new(192, 168, 1, 1));
+        assert_eq!(ip_config.subnet_mask, Ipv4Addr::new(255, 255, 255, 0));
+    }
+
+    #[test]
+    fn validate_hostname_accepts_letters_digits_and_inner_hyphens() {
+        assert_eq!(validate_hostname("pico-sensor-01"), Ok(()));
+    }
+
+    #[test]
+    fn validate_hostname_rejects_invalid_hostnames() {
+        for hostname in [
+            "",
+            "-pico",
+            "pico-",
+            "pico_sensor",
+            "pico.local",
+            "pico sensor",
+            "piço",
+            "a23456789012345678901234567890123",
+        ] {
+            assert_eq!(
+                validate_hostname(hostname),
+                Err(Error::Network(NetworkError::InvalidHostname))
+            )
+        }
+    }
+
+    #[test]
+    fn ip_config_broadcast_address_sets_all_host_bits() {
+        let ip_config =
+            IpConfig::new(Ipv4Addr::new(10, 1, 2, 3)).subnet_mask(Ipv4Addr::new(255, 255, 240, 0));
+
+        assert_eq!(ip_config.broadcast_address(), Ipv4Addr::new(10, 1, 15, 255));
+    }
+}
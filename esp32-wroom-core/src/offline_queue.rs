@@ -0,0 +1,174 @@
+//! Buffer outbound data while a connection is down and send it once it's back.
+//!
+//! An [`OfflineQueue`] is a bounded ring of bytes owned by the application. Data written with
+//! [`TcpClient::send_data_or_queue`] is sent right away while the connection is established
+//! and stored in the queue otherwise. The queued bytes are sent, oldest first, ahead of the
+//! next write made over an established connection, or explicitly with
+//! [`TcpClient::flush_queue`] right after reconnecting. When a [`Reconnector`] is polled with
+//! [`Reconnector::poll_and_flush`], the queue is also flushed on a registered socket as soon
+//! as the network has been joined again. This way brief WiFi dropouts don't lose e.g.
+//! telemetry readings.
+//!
+//! ## Usage
+//!
+//! ```no_run
+//! use esp32_wroom_core::offline_queue::OfflineQueue;
+//!
+//! let mut queue: OfflineQueue<2048> = OfflineQueue::new();
+//!
+//! TcpClient::build(&mut wifi).connect(hostname, port, TransportMode::Tcp, &mut |tcp_client| {
+//!     loop {
+//!         tcp_client.send_data_or_queue(read_sensor().as_bytes(), &mut queue).ok();
+//!     }
+//! });
+//! ```
+//!
+//! [`TcpClient::send_data_or_queue`]: crate::tcp_client::TcpClient::send_data_or_queue
+//! [`TcpClient::flush_queue`]: crate::tcp_client::TcpClient::flush_queue
+//! [`Reconnector`]: crate::reconnect::Reconnector
+//! [`Reconnector::poll_and_flush`]: crate::reconnect::Reconnector::poll_and_flush
+//!
+
+use heapless::Deque;
+
+use super::network::NetworkError;
+use super::protocol::MAX_NINA_LARGE_ARRAY_PARAM_BUFFER_LENGTH;
+use super::Error;
+
+/// A bounded store-and-forward queue holding up to `N` bytes of outbound data.
+#[derive(Debug)]
+pub struct OfflineQueue<const N: usize> {
+    bytes: Deque<u8, N>,
+}
+
+impl<const N: usize> OfflineQueue<N> {
+    /// Create a new, empty [`OfflineQueue`].
+    pub const fn new() -> Self {
+        Self {
+            bytes: Deque::new(),
+        }
+    }
+
+    /// Append `data` to the queue. Fails with [`NetworkError::OfflineQueueFull`] without
+    /// queueing any of `data` if it doesn't fit in completely.
+    pub fn push(&mut self, data: &[u8]) -> Result<(), Error> {
+        if data.len() > N - self.bytes.len() {
+            return Err(NetworkError::OfflineQueueFull.into());
+        }
+
+        for byte in data {
+            self.bytes.push_back(*byte).ok();
+        }
+
+        Ok(())
+    }
+
+    /// The number of bytes waiting to be sent.
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// Whether there's no data waiting to be sent.
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Discard all queued data.
+    pub fn clear(&mut self) {
+        self.bytes.clear();
+    }
+
+    // Passes the queued bytes to `send` in order, in chunks no larger than a single NINA
+    // command can carry. `send` returns how many bytes of a chunk were accepted and only those
+    // are removed from the queue. A failed `send`, or one that accepted nothing, fails with
+    // everything not yet accepted still queued.
+    pub(crate) fn flush<F: FnMut(&[u8]) -> Result<usize, Error>>(
+        &mut self,
+        send: &mut F,
+    ) -> Result<(), Error> {
+        loop {
+            let (front, _) = self.bytes.as_slices();
+            if front.is_empty() {
+                return Ok(());
+            }
+
+            let length = front.len().min(MAX_NINA_LARGE_ARRAY_PARAM_BUFFER_LENGTH);
+            let sent = send(&front[..length])?.min(length);
+            if sent == 0 {
+                return Err(NetworkError::SendFailed.into());
+            }
+
+            for _ in 0..sent {
+                self.bytes.pop_front();
+            }
+        }
+    }
+}
+
+impl<const N: usize> Default for OfflineQueue<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod offline_queue_tests {
+    use super::*;
+
+    #[test]
+    fn push_rejects_data_that_does_not_fit_completely() {
+        let mut queue: OfflineQueue<4> = OfflineQueue::new();
+
+        queue.push(b"abc").unwrap();
+
+        assert_eq!(
+            queue.push(b"de"),
+            Err(Error::Network(NetworkError::OfflineQueueFull))
+        );
+        assert_eq!(queue.len(), 3);
+    }
+
+    #[test]
+    fn flush_keeps_data_queued_when_sending_fails() {
+        let mut queue: OfflineQueue<8> = OfflineQueue::new();
+        queue.push(b"abcd").unwrap();
+
+        let result = queue.flush(&mut |_| Err(Error::Bus));
+
+        assert_eq!(result, Err(Error::Bus));
+        assert_eq!(queue.len(), 4);
+
+        let mut sent = Vec::new();
+        queue
+            .flush(&mut |chunk| {
+                sent.extend_from_slice(chunk);
+                Ok(chunk.len())
+            })
+            .unwrap();
+
+        assert_eq!(sent, b"abcd");
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn flush_only_removes_the_bytes_that_were_accepted() {
+        let mut queue: OfflineQueue<8> = OfflineQueue::new();
+        queue.push(b"abcd").unwrap();
+
+        let mut accepted = [3, 0].into_iter();
+        let result = queue.flush(&mut |_| Ok(accepted.next().unwrap()));
+
+        assert_eq!(result, Err(Error::Network(NetworkError::SendFailed)));
+        assert_eq!(queue.len(), 1);
+
+        let mut sent = Vec::new();
+        queue
+            .flush(&mut |chunk| {
+                sent.extend_from_slice(chunk);
+                Ok(chunk.len())
+            })
+            .unwrap();
+
+        assert_eq!(sent, b"d");
+    }
+}
@@ -3,16 +3,25 @@
 
 pub(crate) mod operation;
 
-use core::cell::RefCell;
+use core::net::{Ipv4Addr, SocketAddrV4};
+use core::sync::atomic::{AtomicBool, Ordering};
 
+#[cfg(feature = "defmt")]
 use defmt::{write, Format, Formatter};
 
-use embedded_hal::blocking::delay::DelayMs;
-
 use heapless::{String, Vec};
 
-use super::network::{ConnectionState, IpAddress, Port, Socket, TransportMode};
-use super::wifi::ConnectionStatus;
+use super::activity::{Activity, ActivityHook};
+use super::clock::{ClockHook, Deadline, Duration};
+use super::dns::DnsCache;
+use super::events::EventHook;
+use super::keep_alive::{KeepAlive, KeepAliveEntry, MAX_KEEP_ALIVES};
+use super::network::{
+    ConnectionState, IpConfig, MacAddress, NetworkError, Port, Socket, TransportMode,
+};
+use super::scheduler::YieldHook;
+use super::stats::RecoveryStats;
+use super::wifi::{ConnectionStatus, EncryptionType};
 use super::{Error, FirmwareVersion};
 
 // The maximum number of NINA param u8 bytes in a command send/receive byte stream
@@ -26,6 +35,9 @@ pub(crate) const MAX_NINA_LARGE_ARRAY_PARAM_BUFFER_LENGTH: usize = 1024;
 // The maximum length that a 2-byte length NINA response can be
 pub(crate) const MAX_NINA_RESPONSE_LENGTH: usize = 1024;
 
+// The length of the temporary working buffer used when the application doesn't supply one
+pub(crate) const DEFAULT_WORKING_BUFFER_LENGTH: usize = MAX_NINA_RESPONSE_LENGTH;
+
 // TODO: unalias this type and turn into a full wrapper struct
 /// Provides a byte buffer to hold responses returned from NINA-FW
 pub type NinaResponseBuffer = [u8; MAX_NINA_RESPONSE_LENGTH];
@@ -33,18 +45,57 @@ pub type NinaResponseBuffer = [u8; MAX_NINA_RESPONSE_LENGTH];
 #[repr(u8)]
 #[derive(Copy, Clone, Debug)]
 pub(crate) enum NinaCommand {
+    SetNet = 0x10u8,
     SetPassphrase = 0x11u8,
+    SetIPConfig = 0x14u8,
     SetDNSConfig = 0x15u8,
+    SetHostname = 0x16u8,
+    SetPowerMode = 0x17u8,
+    SetApNet = 0x18u8,
+    SetApPassphrase = 0x19u8,
+    SetDebug = 0x1au8,
+    GetTemperature = 0x1bu8,
     GetConnStatus = 0x20u8,
+    GetIpAddr = 0x21u8,
+    GetMacAddr = 0x22u8,
+    GetCurrSsid = 0x23u8,
+    GetCurrBssid = 0x24u8,
+    GetCurrRssi = 0x25u8,
+    GetCurrEnct = 0x26u8,
+    ScanNetworks = 0x27u8,
+    StartServerTcp = 0x28u8,
+    CheckDataSent = 0x2au8,
+    AvailDataTcp = 0x2bu8,
     StartClientTcp = 0x2du8,
     StopClientTcp = 0x2eu8,
     GetClientStateTcp = 0x2fu8,
     Disconnect = 0x30u8,
+    GetIdxRssi = 0x32u8,
+    GetIdxEnct = 0x33u8,
     ReqHostByName = 0x34u8,
     GetHostByName = 0x35u8,
+    StartScanNetworks = 0x36u8,
     GetFwVersion = 0x37u8,
+    SendUdpData = 0x39u8,
+    GetRemoteData = 0x3au8,
+    GetTime = 0x3bu8,
+    GetIdxBssid = 0x3cu8,
+    GetIdxChannel = 0x3du8,
     GetSocket = 0x3fu8,
+    SetCliCert = 0x40,
+    SetPk = 0x41,
     SendDataTcp = 0x44,
+    GetDataBufTcp = 0x45,
+    InsertDataBuf = 0x46,
+    SetEntIdent = 0x4a,
+    SetEntUname = 0x4b,
+    SetEntPasswd = 0x4c,
+    SetEntEnable = 0x4f,
+    SetPinMode = 0x50,
+    SetDigitalWrite = 0x51,
+    SetAnalogWrite = 0x52,
+    GetDigitalRead = 0x53,
+    GetAnalogRead = 0x54,
 }
 
 pub(crate) trait NinaConcreteParam
@@ -385,40 +436,287 @@ impl Default for NinaLargeArrayParam {
 
 pub(crate) trait ProtocolInterface {
     fn init(&mut self);
-    fn reset<D: DelayMs<u16>>(&mut self, delay: &mut D);
+    fn reset(&mut self);
+    fn wait_for_esp_ready(&mut self, timeout_ms: u16) -> bool;
     fn get_fw_version(&mut self) -> Result<FirmwareVersion, Error>;
-    fn set_passphrase(&mut self, ssid: &str, passphrase: &str) -> Result<(), Error>;
+    fn set_network(&mut self, ssid: &[u8]) -> Result<(), Error>;
+    fn set_passphrase(&mut self, ssid: &[u8], passphrase: &[u8]) -> Result<(), Error>;
+    fn set_ap_net(&mut self, ssid: &[u8], channel: u8) -> Result<(), Error>;
+    fn set_ap_passphrase(
+        &mut self,
+        ssid: &[u8],
+        passphrase: &[u8],
+        channel: u8,
+    ) -> Result<(), Error>;
+    fn set_ent_identity(&mut self, identity: &[u8]) -> Result<(), Error>;
+    fn set_ent_username(&mut self, username: &[u8]) -> Result<(), Error>;
+    fn set_ent_password(&mut self, password: &[u8]) -> Result<(), Error>;
+    fn set_ent_enable(&mut self) -> Result<(), Error>;
     fn disconnect(&mut self) -> Result<(), Error>;
     fn get_conn_status(&mut self) -> Result<ConnectionStatus, Error>;
-    fn set_dns_config(&mut self, dns1: IpAddress, dns2: Option<IpAddress>) -> Result<(), Error>;
+    fn set_ip_config(
+        &mut self,
+        ip: Ipv4Addr,
+        gateway: Ipv4Addr,
+        subnet_mask: Ipv4Addr,
+    ) -> Result<(), Error>;
+    fn get_ip_config(&mut self) -> Result<IpConfig, Error>;
+    fn get_mac_address(&mut self) -> Result<MacAddress, Error>;
+    fn get_curr_ssid<F: FnMut(&[u8])>(&mut self, f: &mut F) -> Result<(), Error>;
+    fn get_curr_bssid(&mut self) -> Result<MacAddress, Error>;
+    fn get_curr_rssi(&mut self) -> Result<i32, Error>;
+    fn get_curr_enct(&mut self) -> Result<EncryptionType, Error>;
+    fn set_dns_config(&mut self, dns1: Ipv4Addr, dns2: Option<Ipv4Addr>) -> Result<(), Error>;
+    fn set_hostname(&mut self, hostname: &str) -> Result<(), Error>;
+    fn set_power_mode(&mut self, low_power: bool) -> Result<(), Error>;
+    fn set_debug(&mut self, enabled: bool) -> Result<(), Error>;
     fn req_host_by_name(&mut self, hostname: &str) -> Result<u8, Error>;
     fn get_host_by_name(&mut self) -> Result<[u8; MAX_NINA_RESPONSE_LENGTH], Error>;
-    fn resolve(&mut self, hostname: &str) -> Result<IpAddress, Error>;
+    fn resolve(&mut self, hostname: &str) -> Result<Ipv4Addr, Error>;
+    fn get_time(&mut self) -> Result<u32, Error>;
+    fn get_temperature(&mut self) -> Result<f32, Error>;
     fn get_socket(&mut self) -> Result<Socket, Error>;
     fn start_client_tcp(
         &mut self,
         socket: Socket,
-        ip: IpAddress,
+        remote: SocketAddrV4,
+        mode: &TransportMode,
+    ) -> Result<(), Error>;
+    fn start_client_tcp_by_hostname(
+        &mut self,
+        socket: Socket,
+        hostname: &str,
         port: Port,
         mode: &TransportMode,
     ) -> Result<(), Error>;
     fn stop_client_tcp(&mut self, socket: Socket, _mode: &TransportMode) -> Result<(), Error>;
     fn get_client_state_tcp(&mut self, socket: Socket) -> Result<ConnectionState, Error>;
-    fn send_data(&mut self, data: &str, socket: Socket) -> Result<[u8; 1], Error>;
+    fn send_data(&mut self, data: &[u8], socket: Socket) -> Result<usize, Error>;
+    fn check_data_sent(&mut self, socket: Socket) -> Result<bool, Error>;
+    fn start_server_tcp(
+        &mut self,
+        socket: Socket,
+        port: Port,
+        mode: &TransportMode,
+    ) -> Result<(), Error>;
+    fn start_server_multicast(&mut self, socket: Socket, group: SocketAddrV4) -> Result<(), Error>;
+    fn avail_server_tcp(&mut self, socket: Socket) -> Result<Option<Socket>, Error>;
+    fn avail_data_tcp(&mut self, socket: Socket) -> Result<usize, Error>;
+    fn get_data_buf_tcp(&mut self, socket: Socket, buf: &mut [u8]) -> Result<usize, Error>;
+    fn insert_data_buf(&mut self, data: &[u8], socket: Socket) -> Result<(), Error>;
+    fn send_udp_data(&mut self, socket: Socket) -> Result<(), Error>;
+    fn get_remote_data(&mut self, socket: Socket) -> Result<SocketAddrV4, Error>;
+    fn set_client_cert(&mut self, certificate: &[u8]) -> Result<(), Error>;
+    fn set_private_key(&mut self, private_key: &[u8]) -> Result<(), Error>;
+    fn start_scan_networks(&mut self) -> Result<(), Error>;
+    fn scan_networks<F: FnMut(&[u8])>(&mut self, f: &mut F) -> Result<u8, Error>;
+    fn get_idx_rssi(&mut self, index: u8) -> Result<i32, Error>;
+    fn get_idx_enct(&mut self, index: u8) -> Result<EncryptionType, Error>;
+    fn get_idx_bssid(&mut self, index: u8) -> Result<MacAddress, Error>;
+    fn get_idx_channel(&mut self, index: u8) -> Result<u8, Error>;
+    fn set_pin_mode(&mut self, pin: u8, mode: u8) -> Result<(), Error>;
+    fn set_digital_write(&mut self, pin: u8, value: u8) -> Result<(), Error>;
+    fn set_analog_write(&mut self, pin: u8, value: u8) -> Result<(), Error>;
+    fn get_digital_read(&mut self, pin: u8) -> Result<u8, Error>;
+    fn get_analog_read(&mut self, channel: u8, attenuation: u8) -> Result<u16, Error>;
 }
 
+// The maximum number of sockets that can be tracked as open at once
+const MAX_OPEN_SOCKETS: u8 = 32;
+
 #[derive(Debug)]
-pub(crate) struct NinaProtocolHandler<B, C> {
-    /// A Spi or I2c instance
-    pub bus: RefCell<B>,
-    /// An EspControlPins instance
-    pub control_pins: C,
+pub(crate) struct NinaProtocolHandler<T, D> {
+    /// The transport that carries commands to, and replies from, the NINA firmware
+    pub transport: T,
+    /// The delay source used for all timing, e.g. resetting the ESP32 target
+    pub delay: D,
+    /// A bitmask of sockets that have been started and not yet stopped
+    pub open_sockets: u32,
+    /// A bitmask of the open sockets that were started in a datagram transport mode
+    pub datagram_sockets: u32,
+    /// Counters of recovery actions taken while communicating with NINA firmware
+    pub recovery_stats: RecoveryStats,
+    /// Whether a WiFi network has been joined before
+    pub joined: bool,
+    /// An application supplied buffer used for holding data received on behalf of the application
+    pub working_buffer: Option<&'static mut [u8]>,
+    /// An application supplied hook that lets a scheduler run other tasks
+    pub yield_hook: Option<YieldHook>,
+    /// An application supplied clock that timeouts are measured with
+    pub clock: Option<ClockHook>,
+    /// An application supplied hook that is notified about socket data activity
+    pub activity_hook: Option<ActivityHook>,
+    /// An application supplied hook that is notified about changes of the WiFi connection
+    pub event_hook: Option<EventHook>,
+    /// The connection status read most recently, used to detect changes of the connection
+    pub last_connection_status: Option<ConnectionStatus>,
+    /// Whether a command has been sent whose response hasn't been received yet
+    pub transaction_in_progress: AtomicBool,
+    /// Bounds every wait for the NINA firmware to be selected while it's set, e.g. during init
+    pub select_deadline: Option<Deadline>,
+    /// Application supplied keep-alives of connected sockets
+    pub keep_alives: Vec<KeepAliveEntry, MAX_KEEP_ALIVES>,
+    /// The ID of the next DNS query sent by the driver itself
+    pub next_dns_id: u16,
+    /// Addresses of recently resolved hostnames, if the application enabled caching them
+    pub dns_cache: Option<DnsCache>,
+}
+
+impl<T, D> NinaProtocolHandler<T, D> {
+    pub(crate) fn new(transport: T, delay: D) -> Self {
+        Self {
+            transport,
+            delay,
+            open_sockets: 0,
+            datagram_sockets: 0,
+            recovery_stats: RecoveryStats::default(),
+            joined: false,
+            working_buffer: None,
+            yield_hook: None,
+            clock: None,
+            activity_hook: None,
+            event_hook: None,
+            last_connection_status: None,
+            transaction_in_progress: AtomicBool::new(false),
+            select_deadline: None,
+            keep_alives: Vec::new(),
+            next_dns_id: 1,
+            dns_cache: None,
+        }
+    }
+
+    // Marks the start of sending a command, failing if a previous command is still
+    // awaiting its response so that the bytes of both never interleave on the bus. The flag is
+    // checked and set in a critical section, since the Cortex-M0+ has no atomic swap.
+    pub(crate) fn begin_transaction(&self) -> Result<(), Error> {
+        critical_section::with(|_| {
+            if self.transaction_in_progress.load(Ordering::Acquire) {
+                return Err(Error::Busy);
+            }
+            self.transaction_in_progress.store(true, Ordering::Release);
+            Ok(())
+        })
+    }
+
+    // Marks that the response to the command sent last is being received.
+    pub(crate) fn end_transaction(&self) {
+        self.transaction_in_progress.store(false, Ordering::Release);
+    }
+
+    // Starts a deadline that passes after `timeout`, measured with the application's clock if
+    // one has been set.
+    pub(crate) fn deadline(&self, timeout: Option<Duration>) -> Deadline {
+        Deadline::new(self.clock, timeout)
+    }
+
+    // Hands control back to the application's scheduler, if one has been set.
+    pub(crate) fn yield_now(&self) {
+        if let Some(yield_hook) = self.yield_hook {
+            yield_hook.yield_now();
+        }
+    }
+
+    // Notifies the application's activity indicator, if one has been set.
+    pub(crate) fn record_activity(&self, activity: Activity) {
+        if let Some(activity_hook) = self.activity_hook {
+            activity_hook.on_activity(activity);
+        }
+    }
+
+    // Invokes `f` with the application supplied working buffer, falling back to a
+    // temporary buffer on the stack when the application didn't supply one.
+    pub(crate) fn with_working_buffer<R, F: FnOnce(&mut Self, &mut [u8]) -> R>(
+        &mut self,
+        f: F,
+    ) -> R {
+        match self.working_buffer.take() {
+            Some(buffer) => {
+                let result = f(self, &mut *buffer);
+                self.working_buffer = Some(buffer);
+                result
+            }
+            None => {
+                let mut buffer = [0u8; DEFAULT_WORKING_BUFFER_LENGTH];
+                f(self, &mut buffer)
+            }
+        }
+    }
+
+    // Records that a client or server has been started on `socket` using transport `mode`.
+    pub(crate) fn mark_socket_open(&mut self, socket: Socket, mode: &TransportMode) {
+        if socket < MAX_OPEN_SOCKETS {
+            self.open_sockets |= 1 << socket;
+            if mode.is_datagram() {
+                self.datagram_sockets |= 1 << socket;
+            } else {
+                self.datagram_sockets &= !(1 << socket);
+            }
+        }
+    }
+
+    // Records that `socket` has been stopped.
+    pub(crate) fn mark_socket_closed(&mut self, socket: Socket) {
+        if socket < MAX_OPEN_SOCKETS {
+            self.open_sockets &= !(1 << socket);
+            self.datagram_sockets &= !(1 << socket);
+        }
+        self.keep_alives.retain(|entry| entry.socket != socket);
+    }
+
+    // Registers `keep_alive` for `socket`, replacing any keep-alive it already had.
+    pub(crate) fn set_keep_alive(
+        &mut self,
+        socket: Socket,
+        keep_alive: &'static dyn KeepAlive,
+    ) -> Result<(), Error> {
+        let entry = KeepAliveEntry {
+            socket,
+            keep_alive,
+            last_sent_ms: None,
+        };
+
+        match self.keep_alives.iter_mut().find(|e| e.socket == socket) {
+            Some(existing) => *existing = entry,
+            None => self
+                .keep_alives
+                .push(entry)
+                .map_err(|_| NetworkError::TooManyKeepAlives)?,
+        }
+
+        Ok(())
+    }
+
+    // Fails with Error::WrongTransportMode if `socket` is open in a transport mode whose
+    // datagram-ness differs from `datagram`, before a command is sent the NINA firmware would
+    // never answer. Sockets that aren't tracked as open are passed through to the firmware.
+    pub(crate) fn check_transport_mode(&self, socket: Socket, datagram: bool) -> Result<(), Error> {
+        if socket >= MAX_OPEN_SOCKETS || self.open_sockets & (1 << socket) == 0 {
+            return Ok(());
+        }
+
+        if (self.datagram_sockets & (1 << socket) != 0) == datagram {
+            Ok(())
+        } else {
+            Err(Error::WrongTransportMode)
+        }
+    }
+
+    // Returns the first socket that is still open, if any.
+    pub(crate) fn first_open_socket(&self) -> Option<Socket> {
+        if self.open_sockets == 0 {
+            None
+        } else {
+            Some(self.open_sockets.trailing_zeros() as Socket)
+        }
+    }
 }
 
 // TODO: look at Nina Firmware code to understand conditions
 // that lead to NinaProtocolVersionMismatch
 /// Errors related to communication with NINA firmware
 #[derive(Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "ufmt", derive(ufmt::derive::uDebug))]
+#[non_exhaustive]
 pub enum ProtocolError {
     /// TODO: look at Nina Firmware code to understand conditions
     /// that lead to NinaProtocolVersionMismatch
@@ -436,6 +734,7 @@ pub enum ProtocolError {
     PayloadTooLarge,
 }
 
+#[cfg(feature = "defmt")]
 impl Format for ProtocolError {
     fn format(&self, fmt: Formatter) {
         match self {
@@ -454,6 +753,70 @@ mod protocol_tests {
     use super::*;
     use core::str;
 
+    #[test]
+    fn nina_protocol_handler_tracks_open_sockets() {
+        let mut protocol_handler = NinaProtocolHandler::new((), ());
+
+        protocol_handler.mark_socket_open(3, &TransportMode::Tcp);
+        protocol_handler.mark_socket_open(1, &TransportMode::Udp);
+        assert_eq!(protocol_handler.first_open_socket(), Some(1));
+
+        protocol_handler.mark_socket_closed(1);
+        assert_eq!(protocol_handler.first_open_socket(), Some(3));
+
+        protocol_handler.mark_socket_closed(3);
+        assert_eq!(protocol_handler.first_open_socket(), None);
+    }
+
+    #[test]
+    fn nina_protocol_handler_rejects_operations_for_the_wrong_transport_mode() {
+        let mut protocol_handler = NinaProtocolHandler::new((), ());
+
+        protocol_handler.mark_socket_open(0, &TransportMode::Tls);
+        protocol_handler.mark_socket_open(1, &TransportMode::Udp);
+
+        assert_eq!(protocol_handler.check_transport_mode(0, false), Ok(()));
+        assert_eq!(
+            protocol_handler.check_transport_mode(0, true),
+            Err(Error::WrongTransportMode)
+        );
+        assert_eq!(protocol_handler.check_transport_mode(1, true), Ok(()));
+        assert_eq!(
+            protocol_handler.check_transport_mode(1, false),
+            Err(Error::WrongTransportMode)
+        );
+
+        // Sockets that aren't open are left to the NINA firmware
+        protocol_handler.mark_socket_closed(1);
+        assert_eq!(protocol_handler.check_transport_mode(1, false), Ok(()));
+    }
+
+    #[test]
+    fn nina_protocol_handler_is_busy_until_transaction_ends() {
+        let protocol_handler = NinaProtocolHandler::new((), ());
+
+        assert_eq!(protocol_handler.begin_transaction(), Ok(()));
+        assert_eq!(protocol_handler.begin_transaction(), Err(Error::Busy));
+
+        protocol_handler.end_transaction();
+        assert_eq!(protocol_handler.begin_transaction(), Ok(()));
+    }
+
+    #[test]
+    fn nina_protocol_handler_lends_out_and_keeps_working_buffer() {
+        let mut protocol_handler = NinaProtocolHandler::new((), ());
+
+        let length = protocol_handler.with_working_buffer(|_, buf| buf.len());
+        assert_eq!(length, DEFAULT_WORKING_BUFFER_LENGTH);
+
+        protocol_handler.working_buffer = Some(Box::leak(Box::new([0u8; 16])));
+
+        protocol_handler.with_working_buffer(|_, buf| buf[0] = 0xA);
+        let first_byte = protocol_handler.with_working_buffer(|_, buf| buf[0]);
+        assert_eq!(first_byte, 0xA);
+        assert_eq!(protocol_handler.working_buffer.as_ref().unwrap().len(), 16);
+    }
+
     #[test]
     fn nina_byte_param_new_returns_payload_too_large_error_when_given_too_many_bytes() {
         let str_slice: &str = "too many bytes";
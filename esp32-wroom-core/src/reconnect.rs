@@ -0,0 +1,384 @@
+//! Rejoin a WiFi network automatically after the connection drops.
+//!
+//! A [`Reconnector`] is polled from the application's main loop. Whenever the ESP32 target
+//! reports that it's no longer connected, the network is joined again with the credentials of
+//! a [`CredentialProvider`]. Failed attempts are retried with an exponentially growing delay
+//! in between, randomized by a jitter so that a fleet of devices doesn't hammer an access
+//! point that just came back in lockstep.
+//!
+//! Polling with [`Reconnector::poll_and_flush`] instead registers a socket together with an
+//! [`OfflineQueue`], whose data is sent on that socket once the network has been joined again.
+//!
+//! ## Usage
+//!
+//! ```no_run
+//! use esp32_wroom_core::credentials::StaticCredentials;
+//! use esp32_wroom_core::reconnect::{ReconnectConfig, Reconnector};
+//!
+//! let mut credentials = StaticCredentials::new(SSID, PASSPHRASE);
+//! let mut reconnector = Reconnector::new(ReconnectConfig::new().seed(device_serial_number));
+//!
+//! loop {
+//!     match reconnector.poll(&mut wifi, &mut credentials, timer.now_ms()) {
+//!         Ok(state) => defmt::debug!("WiFi: {:?}", state),
+//!         Err(e) => defmt::error!("Reconnecting failed: {:?}", e),
+//!     }
+//!
+//!     // Do other work
+//! }
+//! ```
+//!
+//! [`CredentialProvider`]: crate::credentials::CredentialProvider
+//! [`OfflineQueue`]: crate::offline_queue::OfflineQueue
+//!
+
+#[cfg(feature = "defmt")]
+use defmt::{write, Format, Formatter};
+
+use embedded_hal::blocking::delay::DelayMs;
+
+use super::credentials::CredentialProvider;
+use super::network::{ConnectionState, Socket};
+use super::offline_queue::OfflineQueue;
+use super::protocol::ProtocolInterface;
+use super::transport::Transport;
+use super::wifi::{ConnectionStatus, Wifi};
+use super::Error;
+
+/// Configures the delays in between the join attempts of a [`Reconnector`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "ufmt", derive(ufmt::derive::uDebug))]
+#[non_exhaustive]
+pub struct ReconnectConfig {
+    /// How long to wait after the first join attempt before trying again. Joining usually takes
+    /// a few seconds, so this shouldn't be much shorter than the default of 5 seconds.
+    pub initial_backoff_ms: u32,
+    /// The longest delay in between two join attempts, 5 minutes by default.
+    pub max_backoff_ms: u32,
+    /// How much each delay is randomly shortened or lengthened, in percent of the delay.
+    pub jitter_percent: u8,
+    /// The seed of the jitter, which should differ in between devices (e.g. a serial number).
+    pub seed: u32,
+}
+
+impl ReconnectConfig {
+    /// Create a new [`ReconnectConfig`] with the default delays and a jitter of 25%.
+    pub fn new() -> Self {
+        Self {
+            initial_backoff_ms: 5_000,
+            max_backoff_ms: 300_000,
+            jitter_percent: 25,
+            seed: 1,
+        }
+    }
+
+    /// Wait `initial_backoff_ms` after the first join attempt before trying again.
+    pub fn initial_backoff_ms(mut self, initial_backoff_ms: u32) -> Self {
+        self.initial_backoff_ms = initial_backoff_ms;
+        self
+    }
+
+    /// Never wait longer than `max_backoff_ms` in between two join attempts.
+    pub fn max_backoff_ms(mut self, max_backoff_ms: u32) -> Self {
+        self.max_backoff_ms = max_backoff_ms;
+        self
+    }
+
+    /// Randomly shorten or lengthen each delay by up to `jitter_percent` percent (at most 100).
+    pub fn jitter_percent(mut self, jitter_percent: u8) -> Self {
+        self.jitter_percent = jitter_percent.min(100);
+        self
+    }
+
+    /// Seed the jitter with `seed`.
+    pub fn seed(mut self, seed: u32) -> Self {
+        self.seed = seed;
+        self
+    }
+}
+
+impl Default for ReconnectConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl Format for ReconnectConfig {
+    fn format(&self, fmt: Formatter) {
+        write!(
+            fmt,
+            "initial_backoff_ms: {=u32}, max_backoff_ms: {=u32}, jitter_percent: {=u8}",
+            self.initial_backoff_ms, self.max_backoff_ms, self.jitter_percent
+        )
+    }
+}
+
+/// What a [`Reconnector`] found or did during a single poll.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "ufmt", derive(ufmt::derive::uDebug))]
+#[non_exhaustive]
+pub enum ReconnectState {
+    /// The ESP32 target is connected to the network
+    Connected,
+    /// The network was just joined again, for the `attempt`-th time since the connection dropped
+    Joining {
+        /// The number of join attempts since the connection dropped, starting at 1
+        attempt: u32,
+    },
+    /// The ESP32 target isn't connected and the next join attempt is still backed off
+    Waiting,
+}
+
+#[cfg(feature = "defmt")]
+impl Format for ReconnectState {
+    fn format(&self, fmt: Formatter) {
+        match self {
+            ReconnectState::Connected => write!(fmt, "Connected"),
+            ReconnectState::Joining { attempt } => write!(fmt, "Joining (attempt {=u32})", attempt),
+            ReconnectState::Waiting => write!(fmt, "Waiting"),
+        }
+    }
+}
+
+/// Watches the connection status and rejoins the network with exponential backoff when it
+/// drops. See the [module documentation](self) for details.
+#[derive(Debug)]
+pub struct Reconnector {
+    config: ReconnectConfig,
+    attempts: u32,
+    backoff_ms: u32,
+    last_attempt_ms: u32,
+    delay_ms: u32,
+    rng_state: u32,
+    flush_pending: bool,
+}
+
+impl Reconnector {
+    /// Create a new [`Reconnector`] with `config`.
+    pub fn new(config: ReconnectConfig) -> Self {
+        Self {
+            config,
+            attempts: 0,
+            backoff_ms: config.initial_backoff_ms,
+            last_attempt_ms: 0,
+            delay_ms: 0,
+            // xorshift gets stuck at 0
+            rng_state: config.seed.max(1),
+            flush_pending: false,
+        }
+    }
+
+    /// Check the connection status of `wifi` and join the network with the credentials of
+    /// `provider` if it isn't connected and no earlier attempt is still backed off. `now_ms`
+    /// is the current time of a monotonic millisecond timer, which may wrap around.
+    ///
+    /// Errors from reading the connection status or requesting the credentials are returned,
+    /// after which polling can simply continue.
+    pub fn poll<T, D, P>(
+        &mut self,
+        wifi: &mut Wifi<T, D>,
+        provider: &mut P,
+        now_ms: u32,
+    ) -> Result<ReconnectState, Error>
+    where
+        T: Transport,
+        D: DelayMs<u16>,
+        P: CredentialProvider,
+    {
+        let connected = wifi.get_connection_status()? == ConnectionStatus::Connected;
+
+        let state = self.next_state(connected, now_ms);
+        if let ReconnectState::Joining { .. } = state {
+            wifi.join_with(provider)?;
+        }
+
+        Ok(state)
+    }
+
+    /// Poll like [`Reconnector::poll`] and, once the network has been joined again, send the
+    /// data waiting in `queue` on `socket` if the ESP32 target still considers that connection
+    /// established.
+    ///
+    /// A flush that fails is retried on the following polls, with the unsent data staying
+    /// queued. If the connection on `socket` didn't survive the dropout, the data is left in
+    /// `queue` to be sent ahead of the next write made with
+    /// [`TcpClient::send_data_or_queue`](crate::tcp_client::TcpClient::send_data_or_queue).
+    pub fn poll_and_flush<T, D, P, const N: usize>(
+        &mut self,
+        wifi: &mut Wifi<T, D>,
+        provider: &mut P,
+        now_ms: u32,
+        socket: Socket,
+        queue: &mut OfflineQueue<N>,
+    ) -> Result<ReconnectState, Error>
+    where
+        T: Transport,
+        D: DelayMs<u16>,
+        P: CredentialProvider,
+    {
+        let state = self.poll(wifi, provider, now_ms)?;
+        if state != ReconnectState::Connected || !self.flush_pending {
+            return Ok(state);
+        }
+
+        let protocol_handler = wifi.protocol_handler.get_mut();
+        if protocol_handler.get_client_state_tcp(socket)? == ConnectionState::Established {
+            queue.flush(&mut |chunk| protocol_handler.send_data(chunk, socket))?;
+        }
+        self.flush_pending = false;
+
+        Ok(state)
+    }
+
+    /// The number of join attempts made since the connection dropped.
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    // Decides what to do given whether the ESP32 target is `connected` at `now_ms`, scheduling
+    // the attempt after a join that is due.
+    fn next_state(&mut self, connected: bool, now_ms: u32) -> ReconnectState {
+        if connected {
+            self.flush_pending |= self.attempts > 0;
+            self.attempts = 0;
+            self.backoff_ms = self.config.initial_backoff_ms;
+            return ReconnectState::Connected;
+        }
+
+        if self.attempts > 0 && now_ms.wrapping_sub(self.last_attempt_ms) < self.delay_ms {
+            return ReconnectState::Waiting;
+        }
+
+        self.attempts = self.attempts.saturating_add(1);
+        self.last_attempt_ms = now_ms;
+        self.delay_ms = self.jittered(self.backoff_ms);
+        self.backoff_ms = self
+            .backoff_ms
+            .saturating_mul(2)
+            .min(self.config.max_backoff_ms);
+
+        ReconnectState::Joining {
+            attempt: self.attempts,
+        }
+    }
+
+    // Randomly shortens or lengthens `delay_ms` by up to the configured jitter percentage.
+    fn jittered(&mut self, delay_ms: u32) -> u32 {
+        let jitter_ms = (delay_ms as u64 * self.config.jitter_percent as u64 / 100) as u32;
+        if jitter_ms == 0 {
+            return delay_ms;
+        }
+
+        // xorshift32
+        self.rng_state ^= self.rng_state << 13;
+        self.rng_state ^= self.rng_state >> 17;
+        self.rng_state ^= self.rng_state << 5;
+
+        let offset_ms = self.rng_state % (2 * jitter_ms + 1);
+        (delay_ms - jitter_ms).saturating_add(offset_ms)
+    }
+}
+
+#[cfg(test)]
+mod reconnect_tests {
+    use super::*;
+
+    #[test]
+    fn next_state_backs_off_exponentially_up_to_the_maximum() {
+        let config = ReconnectConfig::new()
+            .initial_backoff_ms(1_000)
+            .max_backoff_ms(3_000)
+            .jitter_percent(0);
+        let mut reconnector = Reconnector::new(config);
+
+        assert_eq!(
+            reconnector.next_state(false, 0),
+            ReconnectState::Joining { attempt: 1 }
+        );
+        assert_eq!(reconnector.next_state(false, 999), ReconnectState::Waiting);
+        assert_eq!(
+            reconnector.next_state(false, 1_000),
+            ReconnectState::Joining { attempt: 2 }
+        );
+        assert_eq!(
+            reconnector.next_state(false, 2_999),
+            ReconnectState::Waiting
+        );
+        assert_eq!(
+            reconnector.next_state(false, 3_000),
+            ReconnectState::Joining { attempt: 3 }
+        );
+        assert_eq!(
+            reconnector.next_state(false, 5_999),
+            ReconnectState::Waiting
+        );
+        assert_eq!(
+            reconnector.next_state(false, 6_000),
+            ReconnectState::Joining { attempt: 4 }
+        );
+    }
+
+    #[test]
+    fn next_state_starts_over_once_connected_again() {
+        let config = ReconnectConfig::new()
+            .initial_backoff_ms(1_000)
+            .jitter_percent(0);
+        let mut reconnector = Reconnector::new(config);
+
+        reconnector.next_state(false, 0);
+        reconnector.next_state(false, 1_000);
+
+        assert_eq!(
+            reconnector.next_state(true, 2_000),
+            ReconnectState::Connected
+        );
+        assert_eq!(reconnector.attempts(), 0);
+        assert_eq!(
+            reconnector.next_state(false, 2_001),
+            ReconnectState::Joining { attempt: 1 }
+        );
+        assert_eq!(
+            reconnector.next_state(false, 3_000),
+            ReconnectState::Waiting
+        );
+    }
+
+    #[test]
+    fn next_state_only_schedules_a_flush_after_reconnecting() {
+        let mut reconnector = Reconnector::new(ReconnectConfig::new());
+
+        reconnector.next_state(true, 0);
+        assert!(!reconnector.flush_pending);
+
+        reconnector.next_state(false, 1_000);
+        reconnector.next_state(true, 2_000);
+        assert!(reconnector.flush_pending);
+    }
+
+    #[test]
+    fn jittered_stays_within_the_jitter_percentage() {
+        let mut reconnector = Reconnector::new(ReconnectConfig::new().jitter_percent(10).seed(42));
+
+        for _ in 0..1_000 {
+            let delay_ms = reconnector.jittered(10_000);
+            assert!((9_000..=11_000).contains(&delay_ms));
+        }
+    }
+
+    #[test]
+    fn next_state_handles_timer_wrap_around() {
+        let config = ReconnectConfig::new()
+            .initial_backoff_ms(1_000)
+            .jitter_percent(0);
+        let mut reconnector = Reconnector::new(config);
+
+        reconnector.next_state(false, u32::MAX - 499);
+
+        assert_eq!(reconnector.next_state(false, 499), ReconnectState::Waiting);
+        assert_eq!(
+            reconnector.next_state(false, 500),
+            ReconnectState::Joining { attempt: 2 }
+        );
+    }
+}
@@ -0,0 +1,48 @@
+//! Integrate the driver with a cooperative scheduler or RTOS.
+//!
+//! Operations such as joining a network, connecting to a server or scanning for networks keep
+//! the driver busy waiting on the ESP32 target for a long time. A [`Yield`] implementation is
+//! invoked at safe points in between the commands of these operations, when no SPI transaction
+//! is in progress, so that other tasks get a chance to run without the driver depending on a
+//! particular executor.
+//!
+//! ## Usage
+//!
+//! ```no_run
+//! use esp32_wroom_core::scheduler::Yield;
+//!
+//! struct RtosYield;
+//!
+//! impl Yield for RtosYield {
+//!     fn yield_now(&self) {
+//!         rtos::task_yield();
+//!     }
+//! }
+//!
+//! wifi.set_yield(&RtosYield);
+//! ```
+//!
+
+use core::fmt;
+
+/// Hands control back to a scheduler from within long running driver operations.
+pub trait Yield {
+    /// Let other tasks run before the driver continues with its next command.
+    fn yield_now(&self);
+}
+
+// Wraps a Yield implementation so that types holding one can still derive Debug.
+#[derive(Clone, Copy)]
+pub(crate) struct YieldHook(pub(crate) &'static dyn Yield);
+
+impl YieldHook {
+    pub(crate) fn yield_now(&self) {
+        self.0.yield_now();
+    }
+}
+
+impl fmt::Debug for YieldHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("YieldHook")
+    }
+}
@@ -0,0 +1,516 @@
+//! A simulated ESP32 target for exercising the full driver on a host, enabled by the `std`
+//! feature.
+//!
+//! A [`Simulator`] hands out a [`SimulatedBus`] implementing the embedded-hal `Transfer` trait
+//! and [`SimulatedControlPins`] implementing [`EspControlInterface`], which are passed to
+//! [`Wifi::init`] in place of the SPI instance and [`EspControlPins`]. Behind them, an
+//! in-process fake of the NINA firmware decodes every protocol command the driver sends and
+//! answers it, so applications can test their use of the [`Wifi`] API in unit tests and CI
+//! without any hardware.
+//!
+//! The simulated firmware knows about the WiFi networks added with [`Simulator::add_network`],
+//! resolves the hostnames added with [`Simulator::add_host`] and accepts TCP connections to the
+//! servers added with [`Simulator::add_echo_server`], which send back whatever they receive.
+//! Commands outside of joining a network, DNS and TCP client sockets are answered with the
+//! error reply of the NINA firmware.
+//!
+//! ## Usage
+//!
+//! ```no_run
+//! use esp32_wroom_core::sim::{SimulatedDelay, Simulator};
+//! use esp32_wroom_core::wifi::{ConnectionStatus, Wifi};
+//!
+//! let simulator = Simulator::new();
+//! simulator.add_network("Home", Some("secret123"));
+//!
+//! let mut wifi = Wifi::init(simulator.bus(), simulator.control_pins(), SimulatedDelay).unwrap();
+//! wifi.join("Home", "secret123").unwrap();
+//!
+//! assert_eq!(wifi.get_connection_status().unwrap(), ConnectionStatus::Connected);
+//! ```
+//!
+//! [`Wifi`]: crate::wifi::Wifi
+//! [`Wifi::init`]: crate::wifi::Wifi::init
+//! [`EspControlPins`]: crate::gpio::EspControlPins
+//!
+
+use core::cell::RefCell;
+use core::convert::Infallible;
+use core::net::{Ipv4Addr, SocketAddrV4};
+
+use std::collections::{HashMap, VecDeque};
+use std::rc::Rc;
+use std::string::{String, ToString};
+use std::vec::Vec;
+
+use embedded_hal::blocking::delay::DelayMs;
+use embedded_hal::blocking::spi::Transfer;
+
+use super::gpio::EspControlInterface;
+
+const START: u8 = 0xe0;
+const END: u8 = 0xee;
+const ERROR: u8 = 0xef;
+const REPLY: u8 = 0x80;
+const DUMMY: u8 = 0xff;
+
+// The NINA firmware command codes the simulated firmware answers
+const SET_NET: u8 = 0x10;
+const SET_PASSPHRASE: u8 = 0x11;
+const SET_DNS_CONFIG: u8 = 0x15;
+const GET_CONN_STATUS: u8 = 0x20;
+const CHECK_DATA_SENT: u8 = 0x2a;
+const AVAIL_DATA_TCP: u8 = 0x2b;
+const START_CLIENT_TCP: u8 = 0x2d;
+const STOP_CLIENT_TCP: u8 = 0x2e;
+const GET_CLIENT_STATE_TCP: u8 = 0x2f;
+const DISCONNECT: u8 = 0x30;
+const REQ_HOST_BY_NAME: u8 = 0x34;
+const GET_HOST_BY_NAME: u8 = 0x35;
+const GET_FW_VERSION: u8 = 0x37;
+const GET_SOCKET: u8 = 0x3f;
+const SEND_DATA_TCP: u8 = 0x44;
+const GET_DATA_BUF_TCP: u8 = 0x45;
+
+// Commands whose params have their length encoded in 2 bytes instead of 1
+const LARGE_PARAM_COMMANDS: [u8; 5] = [0x40, 0x41, SEND_DATA_TCP, GET_DATA_BUF_TCP, 0x46];
+
+// WiFi network ConnectionStatus values
+const STATUS_NO_ACTIVE_SSID: u8 = 1;
+const STATUS_CONNECTED: u8 = 3;
+const STATUS_FAILED: u8 = 4;
+const STATUS_DISCONNECTED: u8 = 6;
+
+// TCP ConnectionState values
+const STATE_CLOSED: u8 = 0;
+const STATE_ESTABLISHED: u8 = 4;
+
+const MAX_SOCKETS: u8 = 10;
+const NO_SOCKET_AVAIL: u8 = 255;
+
+/// An in-process ESP32 target running a fake of the NINA firmware. See the
+/// [module documentation](self) for details.
+#[derive(Clone, Default)]
+pub struct Simulator {
+    nina: Rc<RefCell<SimulatedNina>>,
+}
+
+impl Simulator {
+    /// Create a new [`Simulator`] that knows about no networks, hosts or servers yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The SPI bus to pass to [`Wifi::init`](crate::wifi::Wifi::init).
+    pub fn bus(&self) -> SimulatedBus {
+        SimulatedBus {
+            nina: self.nina.clone(),
+        }
+    }
+
+    /// The control pins to pass to [`Wifi::init`](crate::wifi::Wifi::init).
+    pub fn control_pins(&self) -> SimulatedControlPins {
+        SimulatedControlPins {
+            nina: self.nina.clone(),
+        }
+    }
+
+    /// Make a WiFi network named `ssid` available to join, secured with `passphrase` or open if
+    /// it's `None`.
+    pub fn add_network(&self, ssid: &str, passphrase: Option<&str>) {
+        self.nina
+            .borrow_mut()
+            .networks
+            .insert(ssid.to_string(), passphrase.map(str::to_string));
+    }
+
+    /// Make `hostname` resolve to `ip_address`.
+    pub fn add_host(&self, hostname: &str, ip_address: Ipv4Addr) {
+        self.nina
+            .borrow_mut()
+            .hosts
+            .insert(hostname.to_string(), ip_address);
+    }
+
+    /// Accept TCP connections to `address` and send back all data received on them.
+    pub fn add_echo_server(&self, address: SocketAddrV4) {
+        self.nina.borrow_mut().echo_servers.push(address);
+    }
+
+    /// Report `version` (e.g. 1.7.4) as the NINA firmware version.
+    pub fn set_firmware_version(&self, version: &str) {
+        self.nina.borrow_mut().firmware_version = version.to_string();
+    }
+
+    /// The number of protocol commands the simulated firmware has received so far.
+    pub fn commands_received(&self) -> usize {
+        self.nina.borrow().commands_received
+    }
+}
+
+/// The SPI bus of a [`Simulator`].
+pub struct SimulatedBus {
+    nina: Rc<RefCell<SimulatedNina>>,
+}
+
+impl Transfer<u8> for SimulatedBus {
+    type Error = Infallible;
+
+    fn transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8], Infallible> {
+        let mut nina = self.nina.borrow_mut();
+        for word in words.iter_mut() {
+            *word = nina.exchange(*word);
+        }
+        Ok(words)
+    }
+}
+
+/// The control pins of a [`Simulator`], whose simulated firmware is always ready and
+/// acknowledges right away.
+pub struct SimulatedControlPins {
+    nina: Rc<RefCell<SimulatedNina>>,
+}
+
+impl EspControlInterface for SimulatedControlPins {
+    fn init(&mut self) {}
+
+    fn reset<D: DelayMs<u16>>(&mut self, _delay: &mut D) {
+        self.nina.borrow_mut().reset();
+    }
+
+    fn esp_select(&mut self) {}
+
+    fn esp_deselect(&mut self) {
+        self.nina.borrow_mut().deselect();
+    }
+
+    fn get_esp_ready(&self) -> bool {
+        true
+    }
+
+    fn get_esp_ack(&self) -> bool {
+        true
+    }
+
+    fn wait_for_esp_ready(&self) {}
+
+    fn wait_for_esp_ack(&self) {}
+
+    fn wait_for_esp_select(&mut self) {}
+
+    fn hold_in_reset(&mut self) {
+        self.nina.borrow_mut().reset();
+    }
+
+    fn enter_bootloader<D: DelayMs<u16>>(&mut self, _delay: &mut D) {
+        self.nina.borrow_mut().reset();
+    }
+}
+
+/// A delay that returns right away, as no time needs to pass for a [`Simulator`].
+#[derive(Copy, Clone, Debug, Default)]
+pub struct SimulatedDelay;
+
+impl DelayMs<u16> for SimulatedDelay {
+    fn delay_ms(&mut self, _ms: u16) {}
+}
+
+impl DelayMs<u32> for SimulatedDelay {
+    fn delay_ms(&mut self, _ms: u32) {}
+}
+
+struct SimulatedSocket {
+    state: u8,
+    received: VecDeque<u8>,
+}
+
+struct SimulatedNina {
+    firmware_version: String,
+    networks: HashMap<String, Option<String>>,
+    hosts: HashMap<String, Ipv4Addr>,
+    echo_servers: Vec<SocketAddrV4>,
+    status: u8,
+    resolved: Option<Ipv4Addr>,
+    sockets: HashMap<u8, SimulatedSocket>,
+    commands_received: usize,
+    // The bytes of the command currently being received
+    command: Vec<u8>,
+    // The bytes of the reply waiting to be read, and whether reading it has begun
+    reply: VecDeque<u8>,
+    replying: bool,
+}
+
+impl Default for SimulatedNina {
+    fn default() -> Self {
+        Self {
+            firmware_version: "1.7.4".to_string(),
+            networks: HashMap::new(),
+            hosts: HashMap::new(),
+            echo_servers: Vec::new(),
+            status: STATUS_DISCONNECTED,
+            resolved: None,
+            sockets: HashMap::new(),
+            commands_received: 0,
+            command: Vec::new(),
+            reply: VecDeque::new(),
+            replying: false,
+        }
+    }
+}
+
+impl SimulatedNina {
+    fn reset(&mut self) {
+        self.status = STATUS_DISCONNECTED;
+        self.resolved = None;
+        self.sockets.clear();
+        self.command.clear();
+        self.reply.clear();
+        self.replying = false;
+    }
+
+    // A reply that isn't read completely is dropped once chip select is deasserted, so the
+    // next command starts from a clean state.
+    fn deselect(&mut self) {
+        if self.replying {
+            self.reply.clear();
+            self.replying = false;
+        }
+    }
+
+    // Clocks one byte in each direction, like a single SPI transfer
+    fn exchange(&mut self, word: u8) -> u8 {
+        if let Some(byte) = self.reply.pop_front() {
+            self.replying = !self.reply.is_empty();
+            return byte;
+        }
+
+        // Anything in between commands, e.g. the dummy bytes sent while waiting for a reply
+        if self.command.is_empty() && word != START {
+            return DUMMY;
+        }
+
+        self.command.push(word);
+        if let Some((command, params)) = self.decode_command() {
+            self.command.clear();
+            self.commands_received += 1;
+            self.reply = self.handle(command, &params).into();
+        }
+
+        DUMMY
+    }
+
+    // Decodes the command received so far once all of it, including the padding to a multiple
+    // of 4 bytes, has arrived
+    fn decode_command(&self) -> Option<(u8, Vec<Vec<u8>>)> {
+        let bytes = &self.command;
+        let command = *bytes.get(1)?;
+        let number_of_params = *bytes.get(2)?;
+        let large_params = LARGE_PARAM_COMMANDS.contains(&command);
+
+        let mut position = 3;
+        let mut params = Vec::new();
+        for _ in 0..number_of_params {
+            let length = if large_params {
+                let length = u16::from_be_bytes([*bytes.get(position)?, *bytes.get(position + 1)?]);
+                position += 2;
+                length as usize
+            } else {
+                position += 1;
+                *bytes.get(position - 1)? as usize
+            };
+
+            params.push(bytes.get(position..position + length)?.to_vec());
+            position += length;
+        }
+
+        if *bytes.get(position)? != END {
+            return None;
+        }
+
+        let length = position + 1;
+        if bytes.len() < length.next_multiple_of(4) {
+            return None;
+        }
+
+        Some((command, params))
+    }
+
+    fn handle(&mut self, command: u8, params: &[Vec<u8>]) -> Vec<u8> {
+        let param = |index: usize| params.get(index).map(Vec::as_slice).unwrap_or_default();
+
+        match command {
+            GET_FW_VERSION => {
+                let mut version = self.firmware_version.as_bytes().to_vec();
+                version.push(0);
+                reply(command, &[&version])
+            }
+            SET_NET => {
+                self.status = self.join(param(0), None);
+                reply(command, &[&[1]])
+            }
+            SET_PASSPHRASE => {
+                self.status = self.join(param(0), Some(param(1)));
+                reply(command, &[&[1]])
+            }
+            GET_CONN_STATUS => reply(command, &[&[self.status]]),
+            DISCONNECT => {
+                self.status = STATUS_DISCONNECTED;
+                reply(command, &[&[1]])
+            }
+            SET_DNS_CONFIG => reply(command, &[&[1]]),
+            REQ_HOST_BY_NAME => {
+                self.resolved = core::str::from_utf8(param(0))
+                    .ok()
+                    .and_then(|hostname| self.hosts.get(hostname).copied());
+                reply(command, &[&[1]])
+            }
+            GET_HOST_BY_NAME => {
+                let ip_address = self.resolved.take().unwrap_or(Ipv4Addr::BROADCAST);
+                reply(command, &[&ip_address.octets()])
+            }
+            GET_SOCKET => {
+                let socket = (0..MAX_SOCKETS)
+                    .find(|socket| !self.sockets.contains_key(socket))
+                    .unwrap_or(NO_SOCKET_AVAIL);
+                if socket != NO_SOCKET_AVAIL {
+                    self.sockets.insert(
+                        socket,
+                        SimulatedSocket {
+                            state: STATE_CLOSED,
+                            received: VecDeque::new(),
+                        },
+                    );
+                }
+                reply(command, &[&[socket]])
+            }
+            START_CLIENT_TCP => reply(command, &[&[self.start_client(params) as u8]]),
+            GET_CLIENT_STATE_TCP => {
+                let state = self
+                    .sockets
+                    .get(&first_byte(param(0)))
+                    .map_or(STATE_CLOSED, |socket| socket.state);
+                reply(command, &[&[state]])
+            }
+            STOP_CLIENT_TCP => {
+                self.sockets.remove(&first_byte(param(0)));
+                reply(command, &[&[1]])
+            }
+            SEND_DATA_TCP => {
+                let written = match self.sockets.get_mut(&first_byte(param(0))) {
+                    Some(socket) if socket.state == STATE_ESTABLISHED => {
+                        socket.received.extend(param(1));
+                        param(1).len() as u16
+                    }
+                    _ => 0,
+                };
+                // The number of bytes written is sent back as a little-endian u16
+                reply(command, &[&written.to_le_bytes()])
+            }
+            CHECK_DATA_SENT => reply(command, &[&[1]]),
+            AVAIL_DATA_TCP => {
+                let available = self
+                    .sockets
+                    .get(&first_byte(param(0)))
+                    .map_or(0, |socket| socket.received.len() as u16);
+                reply(command, &[&available.to_le_bytes()])
+            }
+            GET_DATA_BUF_TCP => {
+                let length = match param(1) {
+                    [lsb, msb] => u16::from_le_bytes([*lsb, *msb]) as usize,
+                    _ => 0,
+                };
+                let data: Vec<u8> = match self.sockets.get_mut(&first_byte(param(0))) {
+                    Some(socket) => {
+                        let length = length.min(socket.received.len());
+                        socket.received.drain(..length).collect()
+                    }
+                    None => Vec::new(),
+                };
+                data_reply(command, &data)
+            }
+            _ => vec![ERROR, 0x00, END],
+        }
+    }
+
+    fn join(&self, ssid: &[u8], passphrase: Option<&[u8]>) -> u8 {
+        let network = core::str::from_utf8(ssid)
+            .ok()
+            .and_then(|ssid| self.networks.get(ssid));
+
+        match network {
+            None => STATUS_NO_ACTIVE_SSID,
+            Some(expected) if expected.as_deref().map(str::as_bytes) == passphrase => {
+                STATUS_CONNECTED
+            }
+            Some(_) => STATUS_FAILED,
+        }
+    }
+
+    // Takes either the IP address, port, socket and transport mode params of a connection to an
+    // IP address, or a hostname followed by those of a connection to a hostname
+    fn start_client(&mut self, params: &[Vec<u8>]) -> bool {
+        let (ip_address, params) = match params {
+            [hostname, _, rest @ ..] if rest.len() == 3 => {
+                let ip_address = core::str::from_utf8(hostname)
+                    .ok()
+                    .and_then(|hostname| self.hosts.get(hostname).copied());
+                (ip_address, rest)
+            }
+            [ip_address, rest @ ..] => (
+                <[u8; 4]>::try_from(ip_address.as_slice())
+                    .ok()
+                    .map(Ipv4Addr::from),
+                rest,
+            ),
+            [] => (None, params),
+        };
+
+        let (port, socket) = match params {
+            [port, socket, ..] if port.len() == 2 => {
+                (u16::from_be_bytes([port[0], port[1]]), first_byte(socket))
+            }
+            _ => return false,
+        };
+
+        let remote = match ip_address {
+            Some(ip_address) => SocketAddrV4::new(ip_address, port),
+            None => return false,
+        };
+
+        if self.status != STATUS_CONNECTED || !self.echo_servers.contains(&remote) {
+            return false;
+        }
+
+        match self.sockets.get_mut(&socket) {
+            Some(socket) => {
+                socket.state = STATE_ESTABLISHED;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+fn first_byte(param: &[u8]) -> u8 {
+    param.first().copied().unwrap_or(NO_SOCKET_AVAIL)
+}
+
+// A reply made up of params whose length is encoded in 1 byte
+fn reply(command: u8, params: &[&[u8]]) -> Vec<u8> {
+    let mut bytes = vec![START, command | REPLY, params.len() as u8];
+    for param in params {
+        bytes.push(param.len() as u8);
+        bytes.extend_from_slice(param);
+    }
+    bytes.push(END);
+    bytes
+}
+
+// A reply made up of a single param whose length is encoded in 2 bytes
+fn data_reply(command: u8, data: &[u8]) -> Vec<u8> {
+    let mut bytes = vec![START, command | REPLY, 1];
+    bytes.extend_from_slice(&(data.len() as u16).to_be_bytes());
+    bytes.extend_from_slice(data);
+    bytes.push(END);
+    bytes
+}
@@ -0,0 +1,404 @@
+//! Keep track of the wall-clock time by synchronizing with an SNTP server periodically.
+//!
+//! [`Wifi::get_time`] reads the time the NINA firmware synchronized once after joining a
+//! network. An [`SntpClient`] instead queries an SNTP server itself whenever its resync
+//! interval has elapsed and extrapolates the time in between from the application's
+//! [`Clock`], so that timestamps and TLS certificate validity checks stay correct over weeks of
+//! uptime while the local clock drifts.
+//!
+//! The client is polled from the application's main loop while connected. The first poll
+//! synchronizes right away, and [`SntpClient::request_sync`] makes the next poll synchronize
+//! again, e.g. after the network has been joined again.
+//!
+//! ## Usage
+//!
+//! ```no_run
+//! use esp32_wroom_core::sntp::{SntpClient, SntpConfig};
+//!
+//! let mut sntp = SntpClient::new(&RP2040_CLOCK, SntpConfig::new("pool.ntp.org"));
+//!
+//! loop {
+//!     if let Err(e) = sntp.poll(&mut wifi) {
+//!         defmt::error!("Synchronizing the time failed: {:?}", e);
+//!     }
+//!
+//!     if let Some(unix_seconds) = sntp.now() {
+//!         defmt::info!("Unix time: {=u32}", unix_seconds);
+//!     }
+//! }
+//! ```
+//!
+//! [`Wifi::get_time`]: crate::wifi::Wifi::get_time
+//!
+
+use core::net::SocketAddrV4;
+
+#[cfg(feature = "defmt")]
+use defmt::{write, Format, Formatter};
+
+use embedded_hal::blocking::delay::DelayMs;
+
+use super::clock::{Clock, ClockHook};
+use super::network::{NetworkError, Port};
+use super::transport::Transport;
+use super::udp_client::exchange_datagram;
+use super::wifi::Wifi;
+use super::Error;
+
+/// The UDP port SNTP servers listen on.
+pub const SNTP_PORT: Port = 123;
+
+// The local UDP port responses are received on
+const SNTP_LOCAL_PORT: Port = 49155;
+
+// How long to wait for the response to a request
+const SNTP_TIMEOUT_MS: u16 = 2000;
+
+const PACKET_LENGTH: usize = 48;
+// LI 0 (no warning), version 4, mode 3 (client)
+const CLIENT_REQUEST: u8 = 0x23;
+const MODE_SERVER: u8 = 4;
+const MAX_STRATUM: u8 = 15;
+const ORIGINATE_TIMESTAMP: usize = 24;
+const TRANSMIT_TIMESTAMP: usize = 40;
+// The seconds in between the NTP epoch (1900) and the Unix epoch (1970)
+const UNIX_EPOCH_NTP_SECONDS: u32 = 2_208_988_800;
+
+/// Configures the server and intervals of an [`SntpClient`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct SntpConfig {
+    /// The hostname (or dotted IP address) of the SNTP server, `pool.ntp.org` by default.
+    pub server: &'static str,
+    /// How long to wait after a successful synchronization before synchronizing again, 1 hour
+    /// by default.
+    pub resync_interval_ms: u32,
+    /// How long to wait after a failed synchronization before trying again, 1 minute by
+    /// default.
+    pub retry_interval_ms: u32,
+}
+
+impl SntpConfig {
+    /// Create a new [`SntpConfig`] for `server` with the default intervals.
+    pub fn new(server: &'static str) -> Self {
+        Self {
+            server,
+            resync_interval_ms: 3_600_000,
+            retry_interval_ms: 60_000,
+        }
+    }
+
+    /// Synchronize again `resync_interval_ms` after each successful synchronization.
+    pub fn resync_interval_ms(mut self, resync_interval_ms: u32) -> Self {
+        self.resync_interval_ms = resync_interval_ms;
+        self
+    }
+
+    /// Try again `retry_interval_ms` after each failed synchronization.
+    pub fn retry_interval_ms(mut self, retry_interval_ms: u32) -> Self {
+        self.retry_interval_ms = retry_interval_ms;
+        self
+    }
+}
+
+impl Default for SntpConfig {
+    fn default() -> Self {
+        Self::new("pool.ntp.org")
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl Format for SntpConfig {
+    fn format(&self, fmt: Formatter) {
+        write!(
+            fmt,
+            "server: {=str}, resync_interval_ms: {=u32}, retry_interval_ms: {=u32}",
+            self.server, self.resync_interval_ms, self.retry_interval_ms
+        )
+    }
+}
+
+/// The result of the last successful synchronization of an [`SntpClient`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "ufmt", derive(ufmt::derive::uDebug))]
+#[non_exhaustive]
+pub struct SntpSync {
+    /// The whole seconds since the Unix epoch the server reported
+    pub unix_seconds: u32,
+    /// The milliseconds past `unix_seconds` the server reported
+    pub fraction_ms: u16,
+    /// The time of the [`Clock`] when the response was received
+    pub synced_at_ms: u32,
+    /// How many hops away the server is from a reference clock, 1 being directly attached
+    pub stratum: u8,
+}
+
+#[cfg(feature = "defmt")]
+impl Format for SntpSync {
+    fn format(&self, fmt: Formatter) {
+        write!(
+            fmt,
+            "unix_seconds: {=u32}, synced_at_ms: {=u32}, stratum: {=u8}",
+            self.unix_seconds, self.synced_at_ms, self.stratum
+        )
+    }
+}
+
+/// Periodically synchronizes the time with an SNTP server. See the
+/// [module documentation](self) for details.
+#[derive(Debug)]
+pub struct SntpClient {
+    config: SntpConfig,
+    clock: ClockHook,
+    last_sync: Option<SntpSync>,
+    last_attempt_ms: Option<u32>,
+    next_interval_ms: u32,
+}
+
+impl SntpClient {
+    /// Create a new [`SntpClient`] that measures the time in between synchronizations with
+    /// `clock`.
+    pub fn new(clock: &'static dyn Clock, config: SntpConfig) -> Self {
+        Self {
+            config,
+            clock: ClockHook(clock),
+            last_sync: None,
+            last_attempt_ms: None,
+            next_interval_ms: 0,
+        }
+    }
+
+    /// Synchronize with the SNTP server if this is the first poll, a synchronization was
+    /// requested with [`SntpClient::request_sync`] or the resync (or after a failure, the
+    /// retry) interval has elapsed. Returns whether a synchronization succeeded.
+    pub fn poll<T, D>(&mut self, wifi: &mut Wifi<T, D>) -> Result<bool, Error>
+    where
+        T: Transport,
+        D: DelayMs<u16>,
+    {
+        if !self.is_due(self.clock.now_ms()) {
+            return Ok(false);
+        }
+
+        self.sync(wifi).map(|_| true)
+    }
+
+    /// Synchronize with the SNTP server right away.
+    ///
+    /// Fails with [`NetworkError::SntpTimeout`] if the server doesn't respond and with
+    /// [`NetworkError::InvalidSntpResponse`] if it doesn't report a usable time.
+    pub fn sync<T, D>(&mut self, wifi: &mut Wifi<T, D>) -> Result<SntpSync, Error>
+    where
+        T: Transport,
+        D: DelayMs<u16>,
+    {
+        let started_ms = self.clock.now_ms();
+        self.last_attempt_ms = Some(started_ms);
+        self.next_interval_ms = self.config.retry_interval_ms;
+
+        let server = wifi.resolve(self.config.server)?;
+
+        // The server echoes the transmit timestamp of the request back, which tells its
+        // response apart from stray datagrams
+        let mut request = [0u8; PACKET_LENGTH];
+        request[0] = CLIENT_REQUEST;
+        request[TRANSMIT_TIMESTAMP + 4..].copy_from_slice(&started_ms.to_be_bytes());
+
+        let mut response = [0u8; PACKET_LENGTH];
+        let length = exchange_datagram(
+            wifi.protocol_handler.get_mut(),
+            SNTP_LOCAL_PORT,
+            SocketAddrV4::new(server, SNTP_PORT),
+            &request,
+            &mut response,
+            SNTP_TIMEOUT_MS,
+        )?
+        .ok_or(NetworkError::SntpTimeout)?;
+
+        let sync = decode_response(&request, &response[..length], self.clock.now_ms())?;
+        self.last_sync = Some(sync);
+        self.next_interval_ms = self.config.resync_interval_ms;
+
+        Ok(sync)
+    }
+
+    /// Make the next poll synchronize regardless of the intervals, e.g. right after the
+    /// network has been joined again.
+    pub fn request_sync(&mut self) {
+        self.last_attempt_ms = None;
+    }
+
+    /// The current time in seconds since the Unix epoch, extrapolated from the last
+    /// synchronization. `None` until the first synchronization succeeded.
+    pub fn now(&self) -> Option<u32> {
+        self.last_sync.map(|sync| {
+            let elapsed_ms = self.clock.now_ms().wrapping_sub(sync.synced_at_ms) as u64
+                + sync.fraction_ms as u64;
+            sync.unix_seconds.wrapping_add((elapsed_ms / 1000) as u32)
+        })
+    }
+
+    /// The result of the last successful synchronization, if there was one.
+    pub fn last_sync(&self) -> Option<SntpSync> {
+        self.last_sync
+    }
+
+    /// How many milliseconds have passed since the last successful synchronization, if there
+    /// was one.
+    pub fn since_last_sync_ms(&self) -> Option<u32> {
+        self.last_sync
+            .map(|sync| self.clock.now_ms().wrapping_sub(sync.synced_at_ms))
+    }
+
+    fn is_due(&self, now_ms: u32) -> bool {
+        match self.last_attempt_ms {
+            Some(last_attempt_ms) => now_ms.wrapping_sub(last_attempt_ms) >= self.next_interval_ms,
+            None => true,
+        }
+    }
+}
+
+// Checks that `response` answers `request` and reads the time it reports, which was received
+// at `received_ms`.
+fn decode_response(request: &[u8], response: &[u8], received_ms: u32) -> Result<SntpSync, Error> {
+    if response.len() < PACKET_LENGTH
+        || response[0] & 0x7 != MODE_SERVER
+        || !(1..=MAX_STRATUM).contains(&response[1])
+        || response[ORIGINATE_TIMESTAMP..ORIGINATE_TIMESTAMP + 8]
+            != request[TRANSMIT_TIMESTAMP..TRANSMIT_TIMESTAMP + 8]
+    {
+        return Err(NetworkError::InvalidSntpResponse.into());
+    }
+
+    let timestamp = &response[TRANSMIT_TIMESTAMP..TRANSMIT_TIMESTAMP + 8];
+    let ntp_seconds = u32::from_be_bytes([timestamp[0], timestamp[1], timestamp[2], timestamp[3]]);
+    let fraction = u32::from_be_bytes([timestamp[4], timestamp[5], timestamp[6], timestamp[7]]);
+    if ntp_seconds == 0 {
+        return Err(NetworkError::InvalidSntpResponse.into());
+    }
+
+    Ok(SntpSync {
+        // Wrapping maps timestamps of the next NTP era (from 2036 on) onto the right Unix time
+        unix_seconds: ntp_seconds.wrapping_sub(UNIX_EPOCH_NTP_SECONDS),
+        fraction_ms: ((fraction as u64 * 1000) >> 32) as u16,
+        synced_at_ms: received_ms,
+        stratum: response[1],
+    })
+}
+
+#[cfg(test)]
+mod sntp_tests {
+    use super::*;
+
+    use core::sync::atomic::{AtomicU32, Ordering};
+
+    struct TestClock(AtomicU32);
+
+    impl Clock for TestClock {
+        fn now_ms(&self) -> u32 {
+            self.0.load(Ordering::Relaxed)
+        }
+    }
+
+    fn request() -> [u8; PACKET_LENGTH] {
+        let mut request = [0u8; PACKET_LENGTH];
+        request[0] = CLIENT_REQUEST;
+        request[TRANSMIT_TIMESTAMP + 4..].copy_from_slice(&[0x12, 0x34, 0x56, 0x78]);
+        request
+    }
+
+    fn response(ntp_seconds: u32, fraction: u32) -> [u8; PACKET_LENGTH] {
+        let mut response = [0u8; PACKET_LENGTH];
+        response[0] = 0x24; // version 4, mode 4 (server)
+        response[1] = 2;
+        response[ORIGINATE_TIMESTAMP..ORIGINATE_TIMESTAMP + 8]
+            .copy_from_slice(&request()[TRANSMIT_TIMESTAMP..]);
+        response[TRANSMIT_TIMESTAMP..TRANSMIT_TIMESTAMP + 4]
+            .copy_from_slice(&ntp_seconds.to_be_bytes());
+        response[TRANSMIT_TIMESTAMP + 4..].copy_from_slice(&fraction.to_be_bytes());
+        response
+    }
+
+    #[test]
+    fn decode_response_converts_ntp_timestamps_of_both_eras_to_unix_time() {
+        // 2024-01-01T00:00:00.5Z
+        let sync = decode_response(&request(), &response(3_913_056_000, 1 << 31), 42).unwrap();
+
+        assert_eq!(sync.unix_seconds, 1_704_067_200);
+        assert_eq!(sync.fraction_ms, 500);
+        assert_eq!(sync.synced_at_ms, 42);
+        assert_eq!(sync.stratum, 2);
+
+        // 2040-01-01T00:00:00Z, 4 years into the second NTP era
+        let sync = decode_response(&request(), &response(123_010_304, 0), 0).unwrap();
+
+        assert_eq!(sync.unix_seconds, 2_208_988_800);
+    }
+
+    #[test]
+    fn decode_response_rejects_kiss_of_death_and_unrelated_responses() {
+        let mut kiss_of_death = response(3_913_056_000, 0);
+        kiss_of_death[1] = 0;
+
+        assert_eq!(
+            decode_response(&request(), &kiss_of_death, 0),
+            Err(Error::Network(NetworkError::InvalidSntpResponse))
+        );
+
+        let mut unrelated = response(3_913_056_000, 0);
+        unrelated[ORIGINATE_TIMESTAMP + 7] ^= 0xff;
+
+        assert_eq!(
+            decode_response(&request(), &unrelated, 0),
+            Err(Error::Network(NetworkError::InvalidSntpResponse))
+        );
+    }
+
+    #[test]
+    fn now_extrapolates_from_the_last_sync_with_the_clock() {
+        static CLOCK: TestClock = TestClock(AtomicU32::new(0));
+        let mut sntp = SntpClient::new(&CLOCK, SntpConfig::new("pool.ntp.org"));
+
+        assert_eq!(sntp.now(), None);
+
+        sntp.last_sync =
+            Some(decode_response(&request(), &response(3_913_056_000, 1 << 31), 1_000).unwrap());
+        CLOCK.0.store(2_499, Ordering::Relaxed);
+
+        assert_eq!(sntp.now(), Some(1_704_067_200 + 1));
+        assert_eq!(sntp.since_last_sync_ms(), Some(1_499));
+
+        CLOCK.0.store(2_500, Ordering::Relaxed);
+
+        assert_eq!(sntp.now(), Some(1_704_067_200 + 2));
+    }
+
+    #[test]
+    fn is_due_waits_for_the_resync_or_retry_interval() {
+        static CLOCK: TestClock = TestClock(AtomicU32::new(0));
+        let config = SntpConfig::new("pool.ntp.org")
+            .resync_interval_ms(10_000)
+            .retry_interval_ms(1_000);
+        let mut sntp = SntpClient::new(&CLOCK, config);
+
+        assert!(sntp.is_due(0));
+
+        // A successful sync at 0
+        sntp.last_attempt_ms = Some(0);
+        sntp.next_interval_ms = config.resync_interval_ms;
+
+        assert!(!sntp.is_due(9_999));
+        assert!(sntp.is_due(10_000));
+
+        // A failed sync at 10_000
+        sntp.last_attempt_ms = Some(10_000);
+        sntp.next_interval_ms = config.retry_interval_ms;
+
+        assert!(!sntp.is_due(10_999));
+        assert!(sntp.is_due(11_000));
+
+        sntp.request_sync();
+
+        assert!(sntp.is_due(10_001));
+    }
+}
@@ -0,0 +1,1376 @@
+//! WifiNINA protocol communication
+//!
+//! Implements the WifiNINA protocol on top of a [`Transport`], which carries the bytes of each
+//! command and reply. Historically this only ever ran over SPI, hence the name of the module.
+//!
+//! Note: Currently everything in this file is private and considered internal to the crate.
+//!
+use core::convert::Infallible;
+use core::net::{Ipv4Addr, SocketAddrV4};
+
+use embedded_hal::blocking::delay::DelayMs;
+
+use super::activity::Activity;
+use super::clock::Duration;
+use super::keep_alive::MAX_KEEP_ALIVE_LENGTH;
+use super::network::{
+    ConnectionState, IpConfig, MacAddress, NetworkError, Port, Socket, TransportMode,
+    MAC_ADDRESS_LENGTH,
+};
+use super::protocol::operation::Operation;
+use super::protocol::{
+    NinaByteParam, NinaCommand, NinaConcreteParam, NinaLargeArrayParam, NinaParam,
+    NinaProtocolHandler, NinaResponseBuffer, NinaSmallArrayParam, NinaWordParam, ProtocolError,
+    ProtocolInterface, MAX_NINA_PARAMS, MAX_NINA_RESPONSE_LENGTH,
+    MAX_NINA_SMALL_ARRAY_PARAM_BUFFER_LENGTH,
+};
+use super::tls_client::{MAX_CLIENT_CERTIFICATE_LENGTH, MAX_PRIVATE_KEY_LENGTH};
+use super::transport::{Transport, MAX_TRANSFER_LENGTH};
+use super::wifi::{ConnectionStatus, EncryptionType};
+use super::{Error, FirmwareVersion};
+
+#[repr(u8)]
+#[derive(Debug)]
+pub(crate) enum ControlByte {
+    Start = 0xE0u8,
+    End = 0xEEu8,
+    Reply = 1u8 << 7u8,
+    Dummy = 0xFFu8,
+    Error = 0xEFu8,
+}
+
+// Returned by NINA firmware in place of a socket handle when none is available
+const NO_SOCKET_AVAIL: u8 = 255;
+
+// How long the NINA firmware has to start sending a reply
+const REPLY_TIMEOUT: Duration = Duration::millis(1_000);
+
+// How long reading a single byte is assumed to take without a clock, which limits waiting for a
+// reply to 1000 bytes
+const BYTE_READ_ESTIMATE: Duration = Duration::millis(1);
+
+// Builds a MacAddress from the first MAC_ADDRESS_LENGTH bytes of `bytes`, which hold it in
+// reverse transmission order.
+fn mac_address_from_reversed(bytes: &[u8]) -> MacAddress {
+    let mut octets = [0u8; MAC_ADDRESS_LENGTH];
+    octets
+        .iter_mut()
+        .zip(bytes[..MAC_ADDRESS_LENGTH].iter().rev())
+        .for_each(|(to, from)| *to = *from);
+
+    MacAddress::new(octets)
+}
+
+// Stages the bytes of a command, so that they're written to the transport in as few writes as
+// possible rather than one per field. The staged bytes are written whenever the buffer fills up
+// and by finish().
+struct CommandWriter<'a, T: Transport> {
+    transport: &'a mut T,
+    buffer: [u8; MAX_TRANSFER_LENGTH],
+    staged: usize,
+}
+
+impl<'a, T: Transport> CommandWriter<'a, T> {
+    // Starts writing `cmd` with `num_params` params.
+    fn new(transport: &'a mut T, cmd: &NinaCommand, num_params: u8) -> Self {
+        let mut writer = Self {
+            transport,
+            buffer: [0; MAX_TRANSFER_LENGTH],
+            staged: 0,
+        };
+        writer.write(&[
+            ControlByte::Start as u8,
+            (*cmd as u8) & !(ControlByte::Reply as u8),
+            num_params,
+        ]);
+        writer
+    }
+
+    fn write(&mut self, mut bytes: &[u8]) {
+        while !bytes.is_empty() {
+            if self.staged == MAX_TRANSFER_LENGTH {
+                self.flush();
+            }
+            let length = bytes.len().min(MAX_TRANSFER_LENGTH - self.staged);
+            self.buffer[self.staged..self.staged + length].copy_from_slice(&bytes[..length]);
+            self.staged += length;
+            bytes = &bytes[length..];
+        }
+    }
+
+    fn write_param<P: NinaParam>(&mut self, param: &P) {
+        let length = param.length_as_bytes();
+        self.write(&length[..param.length_size() as usize]);
+        self.write(param.data());
+    }
+
+    fn flush(&mut self) {
+        self.transport.write(&self.buffer[..self.staged]);
+        self.staged = 0;
+    }
+
+    // Writes the end byte and lets the transport complete the command of `command_length`
+    // bytes.
+    fn finish(mut self, command_length: u16) {
+        self.write(&[ControlByte::End as u8]);
+        self.flush();
+        self.transport.end_command(command_length);
+    }
+}
+
+impl<T, D> ProtocolInterface for NinaProtocolHandler<T, D>
+where
+    T: Transport,
+    D: DelayMs<u16>,
+{
+    fn init(&mut self) {
+        self.transport.init();
+    }
+
+    fn reset(&mut self) {
+        self.transport.reset(&mut self.delay);
+        self.recovery_stats.record_hard_reset();
+    }
+
+    fn wait_for_esp_ready(&mut self, timeout_ms: u16) -> bool {
+        let mut deadline = self.deadline(Some(Duration::millis(timeout_ms as u32)));
+
+        while !deadline.has_expired() {
+            if self.transport.is_ready() {
+                return true;
+            }
+            self.delay.delay_ms(1);
+            deadline.wait(Duration::millis(1));
+        }
+        self.transport.is_ready()
+    }
+
+    fn get_fw_version(&mut self) -> Result<FirmwareVersion, Error> {
+        let operation = Operation::new(NinaCommand::GetFwVersion);
+
+        self.execute(&operation)?;
+
+        // The version is sent back as a NUL terminated string of any length (e.g. 1.7.4)
+        let mut version = FirmwareVersion::default();
+        let number_of_params = self.receive_each_param(&operation, &mut |param| {
+            version = FirmwareVersion::new(param)
+        })?;
+
+        if number_of_params != 1 {
+            return Err(ProtocolError::InvalidNumberOfParameters.into());
+        }
+
+        Ok(version)
+    }
+
+    fn set_network(&mut self, ssid: &[u8]) -> Result<(), Error> {
+        let operation =
+            Operation::new(NinaCommand::SetNet).param(NinaSmallArrayParam::from_bytes(ssid)?);
+
+        self.execute(&operation)?;
+
+        self.receive(&operation, 1)?;
+        Ok(())
+    }
+
+    fn set_passphrase(&mut self, ssid: &[u8], passphrase: &[u8]) -> Result<(), Error> {
+        let operation = Operation::new(NinaCommand::SetPassphrase)
+            .param(NinaSmallArrayParam::from_bytes(ssid)?)
+            .param(NinaSmallArrayParam::from_bytes(passphrase)?);
+
+        self.execute(&operation)?;
+
+        self.receive(&operation, 1)?;
+        Ok(())
+    }
+
+    fn set_ap_net(&mut self, ssid: &[u8], channel: u8) -> Result<(), Error> {
+        let operation = Operation::new(NinaCommand::SetApNet)
+            .param(NinaSmallArrayParam::from_bytes(ssid)?)
+            .param(NinaSmallArrayParam::from_bytes(&[channel])?);
+
+        self.execute(&operation)?;
+
+        let result = self.receive(&operation, 1)?;
+        if result[0] == 1 {
+            Ok(())
+        } else {
+            Err(NetworkError::StartAccessPointFailed.into())
+        }
+    }
+
+    fn set_ap_passphrase(
+        &mut self,
+        ssid: &[u8],
+        passphrase: &[u8],
+        channel: u8,
+    ) -> Result<(), Error> {
+        let operation = Operation::new(NinaCommand::SetApPassphrase)
+            .param(NinaSmallArrayParam::from_bytes(ssid)?)
+            .param(NinaSmallArrayParam::from_bytes(passphrase)?)
+            .param(NinaSmallArrayParam::from_bytes(&[channel])?);
+
+        self.execute(&operation)?;
+
+        let result = self.receive(&operation, 1)?;
+        if result[0] == 1 {
+            Ok(())
+        } else {
+            Err(NetworkError::StartAccessPointFailed.into())
+        }
+    }
+
+    fn set_ent_identity(&mut self, identity: &[u8]) -> Result<(), Error> {
+        let operation = Operation::new(NinaCommand::SetEntIdent)
+            .param(NinaSmallArrayParam::from_bytes(identity)?);
+
+        self.execute(&operation)?;
+
+        self.receive(&operation, 1)?;
+        Ok(())
+    }
+
+    fn set_ent_username(&mut self, username: &[u8]) -> Result<(), Error> {
+        let operation = Operation::new(NinaCommand::SetEntUname)
+            .param(NinaSmallArrayParam::from_bytes(username)?);
+
+        self.execute(&operation)?;
+
+        self.receive(&operation, 1)?;
+        Ok(())
+    }
+
+    fn set_ent_password(&mut self, password: &[u8]) -> Result<(), Error> {
+        let operation = Operation::new(NinaCommand::SetEntPasswd)
+            .param(NinaSmallArrayParam::from_bytes(password)?);
+
+        self.execute(&operation)?;
+
+        self.receive(&operation, 1)?;
+        Ok(())
+    }
+
+    fn set_ent_enable(&mut self) -> Result<(), Error> {
+        let operation = Operation::new(NinaCommand::SetEntEnable);
+
+        self.execute(&operation)?;
+
+        self.receive(&operation, 1)?;
+        Ok(())
+    }
+
+    fn get_conn_status(&mut self) -> Result<ConnectionStatus, Error> {
+        let operation = Operation::new(NinaCommand::GetConnStatus);
+
+        self.execute(&operation)?;
+
+        let result = self.receive(&operation, 1)?;
+
+        Ok(ConnectionStatus::from(result[0]))
+    }
+
+    fn disconnect(&mut self) -> Result<(), Error> {
+        let dummy_param = NinaByteParam::from_bytes(&[ControlByte::Dummy as u8]);
+        let operation =
+            Operation::new(NinaCommand::Disconnect).param(dummy_param.unwrap_or_default());
+
+        self.execute(&operation)?;
+
+        self.receive(&operation, 1)?;
+
+        Ok(())
+    }
+
+    fn set_ip_config(
+        &mut self,
+        ip: Ipv4Addr,
+        gateway: Ipv4Addr,
+        subnet_mask: Ipv4Addr,
+    ) -> Result<(), Error> {
+        let operation = Operation::new(NinaCommand::SetIPConfig)
+            // The number of valid addresses that follow: IP address, gateway and subnet mask
+            .param(NinaByteParam::from_bytes(&[3])?)
+            .param(NinaSmallArrayParam::from_bytes(&ip.octets())?)
+            .param(NinaSmallArrayParam::from_bytes(&gateway.octets())?)
+            .param(NinaSmallArrayParam::from_bytes(&subnet_mask.octets())?);
+
+        self.execute(&operation)?;
+
+        self.receive(&operation, 1)?;
+
+        Ok(())
+    }
+
+    // The NINA firmware sends back the IP address, subnet mask and gateway in that order
+    fn get_ip_config(&mut self) -> Result<IpConfig, Error> {
+        let operation = Operation::new(NinaCommand::GetIpAddr)
+            .param(NinaByteParam::from_bytes(&[ControlByte::Dummy as u8])?);
+
+        self.execute(&operation)?;
+
+        let mut addresses = [[0u8; 4]; 3];
+        let mut index = 0;
+
+        let number_of_params = self.receive_each_param(&operation, &mut |param| {
+            if let Some(address) = addresses.get_mut(index) {
+                address
+                    .iter_mut()
+                    .zip(param)
+                    .for_each(|(to, from)| *to = *from);
+            }
+            index += 1;
+        })?;
+
+        if number_of_params != 3 {
+            return Err(ProtocolError::InvalidNumberOfParameters.into());
+        }
+
+        let [ip, subnet_mask, gateway] = addresses.map(Ipv4Addr::from);
+        Ok(IpConfig::new(ip).gateway(gateway).subnet_mask(subnet_mask))
+    }
+
+    fn get_mac_address(&mut self) -> Result<MacAddress, Error> {
+        let operation = Operation::new(NinaCommand::GetMacAddr)
+            .param(NinaByteParam::from_bytes(&[ControlByte::Dummy as u8])?);
+
+        self.execute(&operation)?;
+
+        let result = self.receive(&operation, 1)?;
+        // The MAC address is sent back with its bytes in reverse transmission order
+        Ok(mac_address_from_reversed(&result))
+    }
+
+    fn get_curr_ssid<F: FnMut(&[u8])>(&mut self, f: &mut F) -> Result<(), Error> {
+        let operation = Operation::new(NinaCommand::GetCurrSsid)
+            .param(NinaByteParam::from_bytes(&[ControlByte::Dummy as u8])?);
+
+        self.execute(&operation)?;
+
+        // An SSID can be longer than receive() allows for
+        let number_of_params = self.receive_each_param(&operation, f)?;
+        if number_of_params != 1 {
+            return Err(ProtocolError::InvalidNumberOfParameters.into());
+        }
+
+        Ok(())
+    }
+
+    fn get_curr_bssid(&mut self) -> Result<MacAddress, Error> {
+        let operation = Operation::new(NinaCommand::GetCurrBssid)
+            .param(NinaByteParam::from_bytes(&[ControlByte::Dummy as u8])?);
+
+        self.execute(&operation)?;
+
+        let result = self.receive(&operation, 1)?;
+        // The BSSID is sent back with its bytes in reverse transmission order
+        Ok(mac_address_from_reversed(&result))
+    }
+
+    fn get_curr_rssi(&mut self) -> Result<i32, Error> {
+        let operation = Operation::new(NinaCommand::GetCurrRssi)
+            .param(NinaByteParam::from_bytes(&[ControlByte::Dummy as u8])?);
+
+        self.execute(&operation)?;
+
+        let result = self.receive(&operation, 1)?;
+        // The RSSI is sent back as a little-endian i32
+        Ok(i32::from_le_bytes([
+            result[0], result[1], result[2], result[3],
+        ]))
+    }
+
+    fn get_curr_enct(&mut self) -> Result<EncryptionType, Error> {
+        let operation = Operation::new(NinaCommand::GetCurrEnct)
+            .param(NinaByteParam::from_bytes(&[ControlByte::Dummy as u8])?);
+
+        self.execute(&operation)?;
+
+        let result = self.receive(&operation, 1)?;
+        Ok(EncryptionType::from(result[0]))
+    }
+
+    fn set_dns_config(&mut self, ip1: Ipv4Addr, ip2: Option<Ipv4Addr>) -> Result<(), Error> {
+        // FIXME: refactor Operation so it can take different NinaParam types
+        let operation = Operation::new(NinaCommand::SetDNSConfig)
+            // FIXME: first param should be able to be a NinaByteParam:
+            .param(NinaByteParam::from_bytes(&[1])?)
+            .param(NinaSmallArrayParam::from_bytes(&ip1.octets())?)
+            .param(NinaSmallArrayParam::from_bytes(
+                &ip2.unwrap_or(Ipv4Addr::UNSPECIFIED).octets(),
+            )?);
+
+        self.execute(&operation)?;
+
+        self.receive(&operation, 1)?;
+
+        Ok(())
+    }
+
+    fn set_hostname(&mut self, hostname: &str) -> Result<(), Error> {
+        let operation =
+            Operation::new(NinaCommand::SetHostname).param(NinaSmallArrayParam::new(hostname)?);
+
+        self.execute(&operation)?;
+
+        let result = self.receive(&operation, 1)?;
+        if result[0] == 1 {
+            Ok(())
+        } else {
+            Err(NetworkError::SetHostnameFailed.into())
+        }
+    }
+
+    fn set_power_mode(&mut self, low_power: bool) -> Result<(), Error> {
+        let operation = Operation::new(NinaCommand::SetPowerMode)
+            .param(NinaByteParam::from_bytes(&[low_power as u8])?);
+
+        self.execute(&operation)?;
+
+        self.receive(&operation, 1)?;
+
+        Ok(())
+    }
+
+    fn set_debug(&mut self, enabled: bool) -> Result<(), Error> {
+        let operation = Operation::new(NinaCommand::SetDebug)
+            .param(NinaByteParam::from_bytes(&[enabled as u8])?);
+
+        self.execute(&operation)?;
+
+        self.receive(&operation, 1)?;
+
+        Ok(())
+    }
+
+    fn req_host_by_name(&mut self, hostname: &str) -> Result<u8, Error> {
+        let operation =
+            Operation::new(NinaCommand::ReqHostByName).param(NinaSmallArrayParam::new(hostname)?);
+
+        self.execute(&operation)?;
+
+        let result = self.receive(&operation, 1)?;
+
+        if result[0] != 1u8 {
+            return Err(NetworkError::DnsResolveFailed.into());
+        }
+
+        Ok(result[0])
+    }
+
+    fn get_host_by_name(&mut self) -> Result<NinaResponseBuffer, Error> {
+        let operation = Operation::new(NinaCommand::GetHostByName);
+
+        self.execute(&operation)?;
+
+        let result = self.receive(&operation, 1)?;
+
+        Ok(result)
+    }
+
+    fn resolve(&mut self, hostname: &str) -> Result<Ipv4Addr, Error> {
+        if let Some(ip_address) = self
+            .dns_cache
+            .as_mut()
+            .and_then(|dns_cache| dns_cache.lookup(hostname))
+        {
+            return Ok(ip_address);
+        }
+
+        self.req_host_by_name(hostname)?;
+
+        let dummy = Ipv4Addr::BROADCAST;
+
+        let result = self.get_host_by_name()?;
+        let ip_address = Ipv4Addr::new(result[0], result[1], result[2], result[3]);
+
+        if ip_address != dummy {
+            if let Some(dns_cache) = self.dns_cache.as_mut() {
+                dns_cache.insert(hostname, ip_address);
+            }
+            Ok(ip_address)
+        } else {
+            Err(NetworkError::DnsResolveFailed.into())
+        }
+    }
+
+    fn get_time(&mut self) -> Result<u32, Error> {
+        let operation = Operation::new(NinaCommand::GetTime)
+            .param(NinaByteParam::from_bytes(&[ControlByte::Dummy as u8])?);
+
+        self.execute(&operation)?;
+
+        let result = self.receive(&operation, 1)?;
+        // The time is sent back as a little-endian u32, which is 0 until SNTP has synchronized
+        let time = u32::from_le_bytes([result[0], result[1], result[2], result[3]]);
+        if time == 0 {
+            Err(NetworkError::TimeUnavailable.into())
+        } else {
+            Ok(time)
+        }
+    }
+
+    fn get_temperature(&mut self) -> Result<f32, Error> {
+        let operation = Operation::new(NinaCommand::GetTemperature)
+            .param(NinaByteParam::from_bytes(&[ControlByte::Dummy as u8])?);
+
+        self.execute(&operation)?;
+
+        let result = self.receive(&operation, 1)?;
+        // The temperature is sent back in degrees Celsius as a little-endian f32
+        Ok(f32::from_le_bytes([
+            result[0], result[1], result[2], result[3],
+        ]))
+    }
+
+    fn get_socket(&mut self) -> Result<Socket, Error> {
+        let operation = Operation::new(NinaCommand::GetSocket);
+
+        self.execute(&operation)?;
+
+        let result = self.receive(&operation, 1)?;
+
+        Ok(result[0])
+    }
+
+    fn start_client_tcp(
+        &mut self,
+        socket: Socket,
+        remote: SocketAddrV4,
+        mode: &TransportMode,
+    ) -> Result<(), Error> {
+        let operation = Operation::new(NinaCommand::StartClientTcp)
+            .param(NinaSmallArrayParam::from_bytes(&remote.ip().octets())?)
+            .param(NinaWordParam::from_bytes(&remote.port().to_be_bytes())?)
+            .param(NinaByteParam::from_bytes(&[socket])?)
+            .param(NinaByteParam::from_bytes(&[*mode as u8])?);
+
+        self.execute(&operation)?;
+
+        let result = self.receive(&operation, 1)?;
+        if result[0] == 1 {
+            self.mark_socket_open(socket, mode);
+            Ok(())
+        } else {
+            Err(NetworkError::ConnectFailed.into())
+        }
+    }
+
+    // Passes the hostname along to the NINA firmware, which needs it to verify the server's
+    // certificate and for SNI when `mode` is a TLS transport mode. The firmware resolves the
+    // hostname itself, so no IP address is sent.
+    fn start_client_tcp_by_hostname(
+        &mut self,
+        socket: Socket,
+        hostname: &str,
+        port: Port,
+        mode: &TransportMode,
+    ) -> Result<(), Error> {
+        let port_as_bytes = [((port & 0xff00) >> 8) as u8, (port & 0xff) as u8];
+        let operation = Operation::new(NinaCommand::StartClientTcp)
+            .param(NinaSmallArrayParam::new(hostname)?)
+            .param(NinaSmallArrayParam::from_bytes(
+                &Ipv4Addr::UNSPECIFIED.octets(),
+            )?)
+            .param(NinaWordParam::from_bytes(&port_as_bytes)?)
+            .param(NinaByteParam::from_bytes(&[socket])?)
+            .param(NinaByteParam::from_bytes(&[*mode as u8])?);
+
+        self.execute(&operation)?;
+
+        let result = self.receive(&operation, 1)?;
+        if result[0] == 1 {
+            self.mark_socket_open(socket, mode);
+            Ok(())
+        } else {
+            Err(NetworkError::ConnectFailed.into())
+        }
+    }
+
+    // TODO: passing in TransportMode but not using, for now. It will become a way
+    // of stopping the right kind of client (e.g. TCP, vs UDP)
+    fn stop_client_tcp(&mut self, socket: Socket, _mode: &TransportMode) -> Result<(), Error> {
+        let operation =
+            Operation::new(NinaCommand::StopClientTcp).param(NinaByteParam::from_bytes(&[socket])?);
+
+        self.execute(&operation)?;
+
+        let result = self.receive(&operation, 1)?;
+        if result[0] == 1 {
+            self.mark_socket_closed(socket);
+            Ok(())
+        } else {
+            Err(NetworkError::DisconnectFailed.into())
+        }
+    }
+
+    fn get_client_state_tcp(&mut self, socket: Socket) -> Result<ConnectionState, Error> {
+        let operation = Operation::new(NinaCommand::GetClientStateTcp)
+            .param(NinaByteParam::from_bytes(&[socket])?);
+
+        self.execute(&operation)?;
+
+        let result = self.receive(&operation, 1)?;
+        // TODO: Determine whether or not any ConnectionState variants should be considered
+        // an error.
+        Ok(ConnectionState::from(result[0]))
+    }
+
+    fn send_data(&mut self, data: &[u8], socket: Socket) -> Result<usize, Error> {
+        self.check_transport_mode(socket, false)?;
+
+        let operation = Operation::new(NinaCommand::SendDataTcp)
+            .param(NinaLargeArrayParam::from_bytes(&[socket])?)
+            .param(NinaLargeArrayParam::from_bytes(data)?);
+
+        self.execute(&operation)?;
+
+        let result = self.receive(&operation, 1)?;
+        self.record_activity(Activity::Transmit);
+
+        // The number of bytes written is sent back as a little-endian u16
+        Ok(u16::from_le_bytes([result[0], result[1]]) as usize)
+    }
+
+    // NINA firmware waits briefly for the data written to `socket` to be acknowledged before
+    // answering whether it was.
+    fn check_data_sent(&mut self, socket: Socket) -> Result<bool, Error> {
+        let operation =
+            Operation::new(NinaCommand::CheckDataSent).param(NinaByteParam::from_bytes(&[socket])?);
+
+        self.execute(&operation)?;
+
+        let result = self.receive(&operation, 1)?;
+
+        Ok(result[0] == 1)
+    }
+
+    fn start_server_tcp(
+        &mut self,
+        socket: Socket,
+        port: Port,
+        mode: &TransportMode,
+    ) -> Result<(), Error> {
+        let port_as_bytes = [((port & 0xff00) >> 8) as u8, (port & 0xff) as u8];
+        let operation = Operation::new(NinaCommand::StartServerTcp)
+            .param(NinaWordParam::from_bytes(&port_as_bytes)?)
+            .param(NinaByteParam::from_bytes(&[socket])?)
+            .param(NinaByteParam::from_bytes(&[*mode as u8])?);
+
+        self.execute(&operation)?;
+
+        let result = self.receive(&operation, 1)?;
+        if result[0] == 1 {
+            self.mark_socket_open(socket, mode);
+            Ok(())
+        } else {
+            Err(NetworkError::StartServerFailed.into())
+        }
+    }
+
+    // Passing an IP address before the other params makes the NINA firmware join `group`
+    // instead of just listening on `port`.
+    fn start_server_multicast(&mut self, socket: Socket, group: SocketAddrV4) -> Result<(), Error> {
+        let mode = &TransportMode::UdpMulticast;
+        let operation = Operation::new(NinaCommand::StartServerTcp)
+            .param(NinaSmallArrayParam::from_bytes(&group.ip().octets())?)
+            .param(NinaWordParam::from_bytes(&group.port().to_be_bytes())?)
+            .param(NinaByteParam::from_bytes(&[socket])?)
+            .param(NinaByteParam::from_bytes(&[*mode as u8])?);
+
+        self.execute(&operation)?;
+
+        let result = self.receive(&operation, 1)?;
+        if result[0] == 1 {
+            self.mark_socket_open(socket, mode);
+            Ok(())
+        } else {
+            Err(NetworkError::StartServerFailed.into())
+        }
+    }
+
+    // When queried with a listening server socket, NINA firmware answers the
+    // AvailDataTcp command with the socket of a newly connected client instead
+    // of a count of available bytes.
+    fn avail_server_tcp(&mut self, socket: Socket) -> Result<Option<Socket>, Error> {
+        let client_socket = self.avail_data_tcp(socket)?;
+
+        if client_socket == NO_SOCKET_AVAIL as usize {
+            Ok(None)
+        } else {
+            self.mark_socket_open(client_socket as Socket, &TransportMode::Tcp);
+            Ok(Some(client_socket as Socket))
+        }
+    }
+
+    fn avail_data_tcp(&mut self, socket: Socket) -> Result<usize, Error> {
+        let operation =
+            Operation::new(NinaCommand::AvailDataTcp).param(NinaByteParam::from_bytes(&[socket])?);
+
+        self.execute(&operation)?;
+
+        let result = self.receive(&operation, 1)?;
+        // The number of available bytes is sent back as a little-endian u16
+        Ok(u16::from_le_bytes([result[0], result[1]]) as usize)
+    }
+
+    fn get_data_buf_tcp(&mut self, socket: Socket, buf: &mut [u8]) -> Result<usize, Error> {
+        let length = buf.len().min(MAX_NINA_RESPONSE_LENGTH) as u16;
+        let operation = Operation::new(NinaCommand::GetDataBufTcp)
+            .param(NinaLargeArrayParam::from_bytes(&[socket])?)
+            .param(NinaLargeArrayParam::from_bytes(&length.to_le_bytes())?);
+
+        self.execute(&operation)?;
+
+        let length = self.receive_data(&operation, buf)?;
+        if length > 0 {
+            self.record_activity(Activity::Receive);
+        }
+
+        Ok(length)
+    }
+
+    // Appends data to the datagram that is sent once send_udp_data() is called
+    fn insert_data_buf(&mut self, data: &[u8], socket: Socket) -> Result<(), Error> {
+        self.check_transport_mode(socket, true)?;
+
+        let operation = Operation::new(NinaCommand::InsertDataBuf)
+            .param(NinaLargeArrayParam::from_bytes(&[socket])?)
+            .param(NinaLargeArrayParam::from_bytes(data)?);
+
+        self.execute(&operation)?;
+
+        let result = self.receive(&operation, 1)?;
+        if result[0] == 1 {
+            self.record_activity(Activity::Transmit);
+            Ok(())
+        } else {
+            Err(NetworkError::SendFailed.into())
+        }
+    }
+
+    fn send_udp_data(&mut self, socket: Socket) -> Result<(), Error> {
+        self.check_transport_mode(socket, true)?;
+
+        let operation =
+            Operation::new(NinaCommand::SendUdpData).param(NinaByteParam::from_bytes(&[socket])?);
+
+        self.execute(&operation)?;
+
+        let result = self.receive(&operation, 1)?;
+        if result[0] == 1 {
+            Ok(())
+        } else {
+            Err(NetworkError::SendFailed.into())
+        }
+    }
+
+    // Returns the IP address and port of the remote peer of a TCP/TLS socket, or of the sender
+    // of the most recently received datagram on a UDP socket
+    fn get_remote_data(&mut self, socket: Socket) -> Result<SocketAddrV4, Error> {
+        let operation =
+            Operation::new(NinaCommand::GetRemoteData).param(NinaByteParam::from_bytes(&[socket])?);
+
+        self.execute(&operation)?;
+
+        let mut ip = [0u8; 4];
+        let mut port: Port = 0;
+        let mut index = 0;
+
+        let number_of_params = self.receive_each_param(&operation, &mut |param| {
+            match index {
+                0 => ip.iter_mut().zip(param).for_each(|(to, from)| *to = *from),
+                1 if param.len() == 2 => port = u16::from_be_bytes([param[0], param[1]]),
+                _ => {}
+            }
+            index += 1;
+        })?;
+
+        if number_of_params != 2 {
+            return Err(ProtocolError::InvalidNumberOfParameters.into());
+        }
+
+        Ok(SocketAddrV4::new(Ipv4Addr::from(ip), port))
+    }
+
+    fn set_client_cert(&mut self, certificate: &[u8]) -> Result<(), Error> {
+        if certificate.len() > MAX_CLIENT_CERTIFICATE_LENGTH {
+            return Err(ProtocolError::PayloadTooLarge.into());
+        }
+
+        self.execute_with_data(NinaCommand::SetCliCert, certificate)?;
+
+        let result = self.receive(&Operation::new(NinaCommand::SetCliCert), 1)?;
+        if result[0] == 1 {
+            Ok(())
+        } else {
+            Err(NetworkError::TlsProvisioningFailed.into())
+        }
+    }
+
+    fn set_private_key(&mut self, private_key: &[u8]) -> Result<(), Error> {
+        if private_key.len() > MAX_PRIVATE_KEY_LENGTH {
+            return Err(ProtocolError::PayloadTooLarge.into());
+        }
+
+        self.execute_with_data(NinaCommand::SetPk, private_key)?;
+
+        let result = self.receive(&Operation::new(NinaCommand::SetPk), 1)?;
+        if result[0] == 1 {
+            Ok(())
+        } else {
+            Err(NetworkError::TlsProvisioningFailed.into())
+        }
+    }
+
+    fn start_scan_networks(&mut self) -> Result<(), Error> {
+        let operation = Operation::new(NinaCommand::StartScanNetworks);
+
+        self.execute(&operation)?;
+
+        let result = self.receive(&operation, 1)?;
+        if result[0] == 1 {
+            Ok(())
+        } else {
+            Err(NetworkError::ScanFailed.into())
+        }
+    }
+
+    fn scan_networks<F: FnMut(&[u8])>(&mut self, f: &mut F) -> Result<u8, Error> {
+        let operation = Operation::new(NinaCommand::ScanNetworks);
+
+        self.execute(&operation)?;
+
+        // Each scanned network's SSID is sent back as its own param
+        self.receive_each_param(&operation, f)
+    }
+
+    fn get_idx_rssi(&mut self, index: u8) -> Result<i32, Error> {
+        let operation =
+            Operation::new(NinaCommand::GetIdxRssi).param(NinaByteParam::from_bytes(&[index])?);
+
+        self.execute(&operation)?;
+
+        let result = self.receive(&operation, 1)?;
+        // The RSSI is sent back as a little-endian i32
+        Ok(i32::from_le_bytes([
+            result[0], result[1], result[2], result[3],
+        ]))
+    }
+
+    fn get_idx_enct(&mut self, index: u8) -> Result<EncryptionType, Error> {
+        let operation =
+            Operation::new(NinaCommand::GetIdxEnct).param(NinaByteParam::from_bytes(&[index])?);
+
+        self.execute(&operation)?;
+
+        let result = self.receive(&operation, 1)?;
+        Ok(EncryptionType::from(result[0]))
+    }
+
+    fn get_idx_bssid(&mut self, index: u8) -> Result<MacAddress, Error> {
+        let operation =
+            Operation::new(NinaCommand::GetIdxBssid).param(NinaByteParam::from_bytes(&[index])?);
+
+        self.execute(&operation)?;
+
+        let result = self.receive(&operation, 1)?;
+        // The BSSID is sent back with its bytes in reverse transmission order
+        Ok(mac_address_from_reversed(&result))
+    }
+
+    fn get_idx_channel(&mut self, index: u8) -> Result<u8, Error> {
+        let operation =
+            Operation::new(NinaCommand::GetIdxChannel).param(NinaByteParam::from_bytes(&[index])?);
+
+        self.execute(&operation)?;
+
+        let result = self.receive(&operation, 1)?;
+        Ok(result[0])
+    }
+
+    fn set_pin_mode(&mut self, pin: u8, mode: u8) -> Result<(), Error> {
+        let operation = Operation::new(NinaCommand::SetPinMode)
+            .param(NinaByteParam::from_bytes(&[pin])?)
+            .param(NinaByteParam::from_bytes(&[mode])?);
+
+        self.execute(&operation)?;
+
+        self.receive(&operation, 1)?;
+
+        Ok(())
+    }
+
+    fn set_digital_write(&mut self, pin: u8, value: u8) -> Result<(), Error> {
+        let operation = Operation::new(NinaCommand::SetDigitalWrite)
+            .param(NinaByteParam::from_bytes(&[pin])?)
+            .param(NinaByteParam::from_bytes(&[value])?);
+
+        self.execute(&operation)?;
+
+        self.receive(&operation, 1)?;
+
+        Ok(())
+    }
+
+    fn set_analog_write(&mut self, pin: u8, value: u8) -> Result<(), Error> {
+        let operation = Operation::new(NinaCommand::SetAnalogWrite)
+            .param(NinaByteParam::from_bytes(&[pin])?)
+            .param(NinaByteParam::from_bytes(&[value])?);
+
+        self.execute(&operation)?;
+
+        self.receive(&operation, 1)?;
+
+        Ok(())
+    }
+
+    fn get_digital_read(&mut self, pin: u8) -> Result<u8, Error> {
+        let operation =
+            Operation::new(NinaCommand::GetDigitalRead).param(NinaByteParam::from_bytes(&[pin])?);
+
+        self.execute(&operation)?;
+
+        let result = self.receive(&operation, 1)?;
+        Ok(result[0])
+    }
+
+    fn get_analog_read(&mut self, channel: u8, attenuation: u8) -> Result<u16, Error> {
+        let operation = Operation::new(NinaCommand::GetAnalogRead)
+            .param(NinaByteParam::from_bytes(&[channel])?)
+            .param(NinaByteParam::from_bytes(&[attenuation])?);
+
+        self.execute(&operation)?;
+
+        let result = self.receive(&operation, 1)?;
+        // The reading is sent back as a little-endian u16
+        Ok(u16::from_le_bytes([result[0], result[1]]))
+    }
+}
+
+impl<T, D> NinaProtocolHandler<T, D>
+where
+    T: Transport,
+    D: DelayMs<u16>,
+{
+    // Sends the ping of every registered keep-alive whose interval has elapsed by `now_ms`.
+    // The interval of a newly registered keep-alive starts at the first poll.
+    pub(crate) fn poll_keep_alives(&mut self, now_ms: u32) -> Result<(), Error> {
+        for index in 0..self.keep_alives.len() {
+            let entry = self.keep_alives[index];
+
+            match entry.last_sent_ms {
+                Some(last_sent_ms)
+                    if now_ms.wrapping_sub(last_sent_ms) < entry.keep_alive.interval_ms() => {}
+                Some(_) => {
+                    let mut ping = [0u8; MAX_KEEP_ALIVE_LENGTH];
+                    let length = entry.keep_alive.ping(&mut ping).min(MAX_KEEP_ALIVE_LENGTH);
+
+                    self.send_data(&ping[..length], entry.socket)?;
+                    self.keep_alives[index].last_sent_ms = Some(now_ms);
+                }
+                None => self.keep_alives[index].last_sent_ms = Some(now_ms),
+            }
+        }
+
+        Ok(())
+    }
+
+    // Waits `ms` milliseconds using the delay source the driver was initialized with.
+    pub(crate) fn delay_ms(&mut self, ms: u16) {
+        self.delay.delay_ms(ms);
+    }
+
+    // Waits for the NINA firmware to be ready and selects it once it acknowledges. This only
+    // gives up, with ProtocolError::CommunicationTimeout, once a select_deadline has been set
+    // and passes.
+    fn wait_for_select(&mut self) -> Result<(), Error> {
+        let deadline = match self.select_deadline.as_mut() {
+            Some(deadline) => deadline,
+            None => {
+                self.transport.wait_for_select();
+                return Ok(());
+            }
+        };
+
+        while !self.transport.is_ready() {
+            if deadline.has_expired() {
+                return Err(ProtocolError::CommunicationTimeout.into());
+            }
+            self.delay.delay_ms(1);
+            deadline.wait(Duration::millis(1));
+        }
+
+        self.transport.select();
+        while !self.transport.is_acknowledged() {
+            if deadline.has_expired() {
+                self.transport.deselect();
+                return Err(ProtocolError::CommunicationTimeout.into());
+            }
+            self.delay.delay_ms(1);
+            deadline.wait(Duration::millis(1));
+        }
+
+        Ok(())
+    }
+
+    fn execute<P: NinaParam>(&mut self, operation: &Operation<P>) -> Result<(), Error> {
+        self.begin_transaction()?;
+
+        // In between transactions is a safe point to let other tasks run
+        self.yield_now();
+        if let Err(error) = self.wait_for_select() {
+            self.end_transaction();
+            return Err(error);
+        }
+        let result = self.send_operation(operation);
+        self.transport.deselect();
+
+        if result.is_err() {
+            self.end_transaction();
+        }
+
+        result
+    }
+
+    // Sends the command and params of `operation` to the NINA firmware, which must already be
+    // selected and have acknowledged that.
+    pub(crate) fn send_operation<P: NinaParam>(
+        &mut self,
+        operation: &Operation<P>,
+    ) -> Result<(), Error> {
+        let mut total_params_length: u16 = 0;
+        let mut total_params_length_size: u16 = 0;
+
+        let number_of_params = operation.params.len() as u8;
+
+        let mut command =
+            CommandWriter::new(&mut self.transport, &operation.command, number_of_params);
+        operation.params.iter().for_each(|param| {
+            command.write_param(param);
+
+            total_params_length += param.length();
+            total_params_length_size += param.length_size() as u16;
+        });
+
+        // This is to make sure we align correctly
+        // 4 (start byte, command byte, number of params as byte, end byte)
+        // + the number of bytes to represent the param length (1 or 2)
+        // + the sum of all param lengths
+        // See https://github.com/arduino/nina-fw/blob/master/main/CommandHandler.cpp#L2153 for the actual equation.
+        command.finish(4u16 + total_params_length_size + total_params_length);
+
+        Ok(())
+    }
+
+    // Sends a command with a single 2-byte length param straight from `data`. Unlike
+    // execute(), this doesn't copy the param into a fixed size buffer first, so it can
+    // send payloads larger than MAX_NINA_LARGE_ARRAY_PARAM_BUFFER_LENGTH.
+    fn execute_with_data(&mut self, command: NinaCommand, data: &[u8]) -> Result<(), Error> {
+        let length: u16 = data
+            .len()
+            .try_into()
+            .map_err(|_| ProtocolError::PayloadTooLarge)?;
+
+        self.begin_transaction()?;
+
+        self.yield_now();
+        if let Err(error) = self.wait_for_select() {
+            self.end_transaction();
+            return Err(error);
+        }
+
+        let mut command = CommandWriter::new(&mut self.transport, &command, 1);
+        command.write(&length.to_be_bytes());
+        command.write(data);
+        // 4 (start byte, command byte, number of params as byte, end byte)
+        // + 2 bytes to represent the param length + the param length
+        command.finish(4u16 + 2 + length);
+
+        self.transport.deselect();
+
+        Ok(())
+    }
+
+    fn receive<P: NinaParam>(
+        &mut self,
+        operation: &Operation<P>,
+        expected_num_params: u8,
+    ) -> Result<NinaResponseBuffer, Error> {
+        // The response is being picked up, so a new command may be sent after this one
+        self.end_transaction();
+        self.yield_now();
+        self.wait_for_select()?;
+
+        let result = self.read_reply(&operation.command, expected_num_params)?;
+
+        self.transport.deselect();
+
+        Ok(result)
+    }
+
+    // Reads the reply to `cmd` from the NINA firmware, which must already be selected and have
+    // acknowledged that.
+    pub(crate) fn read_reply(
+        &mut self,
+        cmd: &NinaCommand,
+        expected_num_params: u8,
+    ) -> Result<NinaResponseBuffer, Error> {
+        self.check_response_ready(cmd, expected_num_params)?;
+
+        self.read_response()
+    }
+
+    // Reads and drops `remaining` bytes of a reply, using the application supplied working
+    // buffer when there is one and a small buffer on the stack otherwise.
+    fn discard(&mut self, mut remaining: usize) {
+        let mut fallback = [0u8; MAX_TRANSFER_LENGTH];
+        let mut lent = self.working_buffer.take();
+        let discarded: &mut [u8] = match lent.as_deref_mut() {
+            Some(buffer) if !buffer.is_empty() => buffer,
+            _ => &mut fallback,
+        };
+        while remaining > 0 {
+            let length = remaining.min(discarded.len());
+            self.transport.read(&mut discarded[..length]);
+            remaining -= length;
+        }
+        self.working_buffer = lent;
+    }
+
+    // Receives a single response param whose length is encoded in 2 bytes, as
+    // used by NINA data commands, and copies its bytes into `buf`. Returns the
+    // number of bytes received.
+    fn receive_data<P: NinaParam>(
+        &mut self,
+        operation: &Operation<P>,
+        buf: &mut [u8],
+    ) -> Result<usize, Error> {
+        self.end_transaction();
+        self.yield_now();
+        self.wait_for_select()?;
+
+        self.check_response_ready(&operation.command, 1)?;
+
+        let mut length = [0u8; 2];
+        self.transport.read(&mut length);
+        let response_length_in_bytes = u16::from_be_bytes(length) as usize;
+
+        let fitting_length = response_length_in_bytes.min(buf.len());
+        self.transport.read(&mut buf[..fitting_length]);
+
+        // Always consume the full response so that the next command stays aligned
+        self.discard(response_length_in_bytes - fitting_length);
+
+        let control_byte: u8 = ControlByte::End as u8;
+        self.read_and_check_byte(&control_byte).ok();
+
+        self.transport.deselect();
+
+        if response_length_in_bytes > buf.len() {
+            warn!(
+                "Reply of {} bytes doesn't fit a buffer of {} bytes",
+                response_length_in_bytes,
+                buf.len()
+            );
+            self.recovery_stats.record_protocol_resync();
+            return Err(ProtocolError::PayloadTooLarge.into());
+        }
+
+        Ok(response_length_in_bytes)
+    }
+
+    // Receives a response made up of a variable number of params, each with a 1 byte
+    // length, and passes each param to `f` in order. Returns the number of params received.
+    fn receive_each_param<P: NinaParam, F: FnMut(&[u8])>(
+        &mut self,
+        operation: &Operation<P>,
+        f: &mut F,
+    ) -> Result<u8, Error> {
+        self.end_transaction();
+        self.yield_now();
+        self.wait_for_select()?;
+
+        self.check_start_cmd()?;
+        let byte_to_check: u8 = operation.command as u8 | ControlByte::Reply as u8;
+        if !self.read_and_check_byte(&byte_to_check).ok().unwrap() {
+            return Err(ProtocolError::InvalidCommand.into());
+        }
+
+        let number_of_params = self.get_byte().ok().unwrap();
+        let mut param_buffer = [0u8; MAX_NINA_SMALL_ARRAY_PARAM_BUFFER_LENGTH];
+
+        for _ in 0..number_of_params {
+            let param_length = self.get_byte().ok().unwrap() as usize;
+            self.transport.read(&mut param_buffer[..param_length]);
+            f(&param_buffer[..param_length]);
+        }
+
+        let control_byte: u8 = ControlByte::End as u8;
+        self.read_and_check_byte(&control_byte).ok();
+
+        self.transport.deselect();
+
+        Ok(number_of_params)
+    }
+
+    fn read_response(&mut self) -> Result<NinaResponseBuffer, Error> {
+        let response_length_in_bytes = self.get_byte().ok().unwrap() as usize;
+
+        if response_length_in_bytes > MAX_NINA_PARAMS {
+            return Err(ProtocolError::TooManyParameters.into());
+        }
+
+        let mut response_param_buffer: NinaResponseBuffer = [0; MAX_NINA_RESPONSE_LENGTH];
+        if response_length_in_bytes > 0 {
+            response_param_buffer =
+                self.read_response_bytes(response_param_buffer, response_length_in_bytes)?;
+        }
+
+        let control_byte: u8 = ControlByte::End as u8;
+        self.read_and_check_byte(&control_byte).ok();
+
+        Ok(response_param_buffer)
+    }
+
+    fn check_response_ready(&mut self, cmd: &NinaCommand, num_params: u8) -> Result<(), Error> {
+        self.check_start_cmd()?;
+        let byte_to_check: u8 = *cmd as u8 | ControlByte::Reply as u8;
+        let result = self.read_and_check_byte(&byte_to_check).ok().unwrap();
+        // Ensure we see a cmd byte
+        if !result {
+            return Err(ProtocolError::InvalidCommand.into());
+        }
+
+        let result = self.read_and_check_byte(&num_params).unwrap();
+        // Ensure we see the number of params we expected to receive back
+        if !result {
+            return Err(ProtocolError::InvalidNumberOfParameters.into());
+        }
+        Ok(())
+    }
+
+    fn read_response_bytes(
+        &mut self,
+        mut response_param_buffer: NinaResponseBuffer,
+        response_length_in_bytes: usize,
+    ) -> Result<NinaResponseBuffer, Error> {
+        self.transport
+            .read(&mut response_param_buffer[..response_length_in_bytes]);
+        Ok(response_param_buffer)
+    }
+
+    fn get_byte(&mut self) -> Result<u8, Infallible> {
+        Ok(self.transport.read_byte())
+    }
+
+    fn wait_for_byte(&mut self, wait_byte: u8) -> Result<bool, Error> {
+        let mut deadline = self.deadline(Some(REPLY_TIMEOUT));
+
+        while !deadline.has_expired() {
+            let byte_read = self.get_byte().ok().unwrap();
+            if byte_read == ControlByte::Error as u8 {
+                // consume remaining bytes after error: 0x00, 0xEE
+                self.get_byte().ok();
+                self.get_byte().ok();
+                warn!("NINA firmware replied with an error byte");
+                self.recovery_stats.record_protocol_resync();
+                return Err(ProtocolError::NinaProtocolVersionMismatch.into());
+            } else if byte_read == wait_byte {
+                return Ok(true);
+            }
+            deadline.wait(BYTE_READ_ESTIMATE);
+        }
+        debug!("Timed out waiting for byte {:?}", wait_byte);
+        Err(ProtocolError::CommunicationTimeout.into())
+    }
+
+    fn check_start_cmd(&mut self) -> Result<bool, Error> {
+        self.wait_for_byte(ControlByte::Start as u8)
+    }
+
+    fn read_and_check_byte(&mut self, check_byte: &u8) -> Result<bool, Infallible> {
+        let byte = self.get_byte().ok().unwrap();
+        Ok(&byte == check_byte)
+    }
+}
+
+#[cfg(test)]
+mod spi_tests {
+    use super::*;
+
+    use crate::gpio::EspControlPins;
+    use crate::transport::SpiTransport;
+    use crate::Error;
+    use core::str;
+    use embedded_hal::blocking::spi::Transfer;
+    use embedded_hal::digital::v2::{InputPin, OutputPin, PinState};
+    use embedded_hal_mock::delay::MockNoop;
+
+    struct TransferMock {}
+
+    impl Transfer<u8> for TransferMock {
+        type Error = Error;
+        fn transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8], Error> {
+            Ok(words)
+        }
+    }
+
+    struct OutputPinMock {}
+
+    impl OutputPin for OutputPinMock {
+        type Error = Error;
+
+        fn set_low(&mut self) -> Result<(), Self::Error> {
+            Ok(())
+        }
+
+        fn set_high(&mut self) -> Result<(), Self::Error> {
+            Ok(())
+        }
+
+        fn set_state(&mut self, _state: PinState) -> Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    struct InputPinMock {}
+
+    impl InputPin for InputPinMock {
+        type Error = Error;
+
+        fn is_high(&self) -> Result<bool, Self::Error> {
+            Ok(true)
+        }
+
+        fn is_low(&self) -> Result<bool, Self::Error> {
+            Ok(true)
+        }
+    }
+
+    #[test]
+    fn too_large_of_a_nina_param_throws_error() {
+        let bytes = [0xA; 256];
+        let str_slice: &str = str::from_utf8(&bytes).unwrap();
+
+        let control_pins = EspControlPins {
+            cs: OutputPinMock {},
+            gpio0: OutputPinMock {},
+            resetn: OutputPinMock {},
+            ack: InputPinMock {},
+        };
+
+        let transfer_mock = TransferMock {};
+
+        let mut protocol_handler = NinaProtocolHandler::new(
+            SpiTransport::new(transfer_mock, control_pins),
+            MockNoop::new(),
+        );
+
+        let result = protocol_handler.set_passphrase(str_slice.as_bytes(), b"");
+
+        assert_eq!(
+            result.unwrap_err(),
+            Error::Protocol(ProtocolError::PayloadTooLarge)
+        )
+    }
+}
@@ -0,0 +1,489 @@
+//! Asynchronous counterpart of the SPI transport in `spi`, enabled by the `async-hal` feature.
+//!
+//! Commands are framed exactly as by `NinaProtocolHandler`. Every wait for the NINA firmware is
+//! awaited instead of spun on though, so the executor can run other tasks while the ESP32 target
+//! is busy, e.g. for the hundreds of milliseconds it takes to answer a DNS request.
+//!
+//! Note: Currently everything in this file is private and considered internal to the crate.
+//!
+use core::net::{Ipv4Addr, SocketAddrV4};
+
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_async::spi::SpiBus;
+
+use super::gpio::AsyncEspControlInterface;
+use super::network::{ConnectionState, NetworkError, Socket, TransportMode};
+use super::protocol::operation::Operation;
+use super::protocol::{
+    NinaByteParam, NinaCommand, NinaConcreteParam, NinaLargeArrayParam, NinaParam,
+    NinaResponseBuffer, NinaSmallArrayParam, NinaWordParam, ProtocolError, MAX_NINA_PARAMS,
+    MAX_NINA_RESPONSE_LENGTH, MAX_NINA_SMALL_ARRAY_PARAM_BUFFER_LENGTH,
+};
+use super::spi::ControlByte;
+use super::wifi::ConnectionStatus;
+use super::{Error, FirmwareVersion};
+
+// Handles the NINA protocol for AsyncWifi. Exclusive access to the bus is guaranteed by `&mut
+// self`, so unlike NinaProtocolHandler no transaction needs to be tracked.
+pub(crate) struct AsyncNinaProtocolHandler<B, C> {
+    /// A Spi instance
+    pub bus: B,
+    /// An AsyncEspControlPins instance
+    pub control_pins: C,
+}
+
+impl<B, C> AsyncNinaProtocolHandler<B, C>
+where
+    B: SpiBus<u8>,
+    C: AsyncEspControlInterface,
+{
+    pub(crate) fn new(bus: B, control_pins: C) -> Self {
+        Self { bus, control_pins }
+    }
+
+    pub(crate) async fn reset<D: DelayNs>(&mut self, delay: &mut D) {
+        self.control_pins.init();
+        self.control_pins.reset(delay).await;
+    }
+
+    pub(crate) async fn get_fw_version(&mut self) -> Result<FirmwareVersion, Error> {
+        let operation = Operation::new(NinaCommand::GetFwVersion);
+
+        self.execute(&operation).await?;
+
+        // The version is sent back as a NUL terminated string of any length (e.g. 1.7.4)
+        let mut version = FirmwareVersion::default();
+        let number_of_params = self
+            .receive_each_param(&operation, &mut |param| {
+                version = FirmwareVersion::new(param)
+            })
+            .await?;
+
+        if number_of_params != 1 {
+            return Err(ProtocolError::InvalidNumberOfParameters.into());
+        }
+
+        Ok(version)
+    }
+
+    pub(crate) async fn set_network(&mut self, ssid: &[u8]) -> Result<(), Error> {
+        let operation =
+            Operation::new(NinaCommand::SetNet).param(NinaSmallArrayParam::from_bytes(ssid)?);
+
+        self.execute(&operation).await?;
+
+        self.receive(&operation, 1).await?;
+        Ok(())
+    }
+
+    pub(crate) async fn set_passphrase(
+        &mut self,
+        ssid: &[u8],
+        passphrase: &[u8],
+    ) -> Result<(), Error> {
+        let operation = Operation::new(NinaCommand::SetPassphrase)
+            .param(NinaSmallArrayParam::from_bytes(ssid)?)
+            .param(NinaSmallArrayParam::from_bytes(passphrase)?);
+
+        self.execute(&operation).await?;
+
+        self.receive(&operation, 1).await?;
+        Ok(())
+    }
+
+    pub(crate) async fn get_conn_status(&mut self) -> Result<ConnectionStatus, Error> {
+        let operation = Operation::new(NinaCommand::GetConnStatus);
+
+        self.execute(&operation).await?;
+
+        let result = self.receive(&operation, 1).await?;
+
+        Ok(ConnectionStatus::from(result[0]))
+    }
+
+    pub(crate) async fn disconnect(&mut self) -> Result<(), Error> {
+        let operation = Operation::new(NinaCommand::Disconnect)
+            .param(NinaByteParam::from_bytes(&[ControlByte::Dummy as u8])?);
+
+        self.execute(&operation).await?;
+
+        self.receive(&operation, 1).await?;
+
+        Ok(())
+    }
+
+    pub(crate) async fn set_dns_config(
+        &mut self,
+        ip1: Ipv4Addr,
+        ip2: Option<Ipv4Addr>,
+    ) -> Result<(), Error> {
+        let operation = Operation::new(NinaCommand::SetDNSConfig)
+            .param(NinaByteParam::from_bytes(&[1])?)
+            .param(NinaSmallArrayParam::from_bytes(&ip1.octets())?)
+            .param(NinaSmallArrayParam::from_bytes(
+                &ip2.unwrap_or(Ipv4Addr::UNSPECIFIED).octets(),
+            )?);
+
+        self.execute(&operation).await?;
+
+        self.receive(&operation, 1).await?;
+
+        Ok(())
+    }
+
+    pub(crate) async fn resolve(&mut self, hostname: &str) -> Result<Ipv4Addr, Error> {
+        let operation =
+            Operation::new(NinaCommand::ReqHostByName).param(NinaSmallArrayParam::new(hostname)?);
+
+        self.execute(&operation).await?;
+
+        let result = self.receive(&operation, 1).await?;
+        if result[0] != 1u8 {
+            return Err(NetworkError::DnsResolveFailed.into());
+        }
+
+        let operation = Operation::new(NinaCommand::GetHostByName);
+
+        self.execute(&operation).await?;
+
+        let result = self.receive(&operation, 1).await?;
+        let ip_address = Ipv4Addr::new(result[0], result[1], result[2], result[3]);
+
+        // NINA firmware answers with the broadcast address if the hostname couldn't be resolved
+        if ip_address != Ipv4Addr::BROADCAST {
+            Ok(ip_address)
+        } else {
+            Err(NetworkError::DnsResolveFailed.into())
+        }
+    }
+
+    pub(crate) async fn get_socket(&mut self) -> Result<Socket, Error> {
+        let operation = Operation::new(NinaCommand::GetSocket);
+
+        self.execute(&operation).await?;
+
+        let result = self.receive(&operation, 1).await?;
+
+        Ok(result[0])
+    }
+
+    pub(crate) async fn start_client_tcp(
+        &mut self,
+        socket: Socket,
+        remote: SocketAddrV4,
+        mode: &TransportMode,
+    ) -> Result<(), Error> {
+        let operation = Operation::new(NinaCommand::StartClientTcp)
+            .param(NinaSmallArrayParam::from_bytes(&remote.ip().octets())?)
+            .param(NinaWordParam::from_bytes(&remote.port().to_be_bytes())?)
+            .param(NinaByteParam::from_bytes(&[socket])?)
+            .param(NinaByteParam::from_bytes(&[*mode as u8])?);
+
+        self.execute(&operation).await?;
+
+        let result = self.receive(&operation, 1).await?;
+        if result[0] == 1 {
+            Ok(())
+        } else {
+            Err(NetworkError::ConnectFailed.into())
+        }
+    }
+
+    pub(crate) async fn stop_client_tcp(&mut self, socket: Socket) -> Result<(), Error> {
+        let operation =
+            Operation::new(NinaCommand::StopClientTcp).param(NinaByteParam::from_bytes(&[socket])?);
+
+        self.execute(&operation).await?;
+
+        let result = self.receive(&operation, 1).await?;
+        if result[0] == 1 {
+            Ok(())
+        } else {
+            Err(NetworkError::DisconnectFailed.into())
+        }
+    }
+
+    pub(crate) async fn get_client_state_tcp(
+        &mut self,
+        socket: Socket,
+    ) -> Result<ConnectionState, Error> {
+        let operation = Operation::new(NinaCommand::GetClientStateTcp)
+            .param(NinaByteParam::from_bytes(&[socket])?);
+
+        self.execute(&operation).await?;
+
+        let result = self.receive(&operation, 1).await?;
+
+        Ok(ConnectionState::from(result[0]))
+    }
+
+    pub(crate) async fn send_data(&mut self, data: &[u8], socket: Socket) -> Result<usize, Error> {
+        let operation = Operation::new(NinaCommand::SendDataTcp)
+            .param(NinaLargeArrayParam::from_bytes(&[socket])?)
+            .param(NinaLargeArrayParam::from_bytes(data)?);
+
+        self.execute(&operation).await?;
+
+        let result = self.receive(&operation, 1).await?;
+
+        // The number of bytes written is sent back as a little-endian u16
+        Ok(u16::from_le_bytes([result[0], result[1]]) as usize)
+    }
+
+    pub(crate) async fn check_data_sent(&mut self, socket: Socket) -> Result<bool, Error> {
+        let operation =
+            Operation::new(NinaCommand::CheckDataSent).param(NinaByteParam::from_bytes(&[socket])?);
+
+        self.execute(&operation).await?;
+
+        let result = self.receive(&operation, 1).await?;
+
+        Ok(result[0] == 1)
+    }
+
+    pub(crate) async fn avail_data_tcp(&mut self, socket: Socket) -> Result<usize, Error> {
+        let operation =
+            Operation::new(NinaCommand::AvailDataTcp).param(NinaByteParam::from_bytes(&[socket])?);
+
+        self.execute(&operation).await?;
+
+        let result = self.receive(&operation, 1).await?;
+        // The number of available bytes is sent back as a little-endian u16
+        Ok(u16::from_le_bytes([result[0], result[1]]) as usize)
+    }
+
+    pub(crate) async fn get_data_buf_tcp(
+        &mut self,
+        socket: Socket,
+        buf: &mut [u8],
+    ) -> Result<usize, Error> {
+        let length = buf.len().min(MAX_NINA_RESPONSE_LENGTH) as u16;
+        let operation = Operation::new(NinaCommand::GetDataBufTcp)
+            .param(NinaLargeArrayParam::from_bytes(&[socket])?)
+            .param(NinaLargeArrayParam::from_bytes(&length.to_le_bytes())?);
+
+        self.execute(&operation).await?;
+
+        self.receive_data(&operation, buf).await
+    }
+
+    async fn execute<P: NinaParam>(&mut self, operation: &Operation<P>) -> Result<(), Error> {
+        self.control_pins.wait_for_esp_select().await;
+
+        let number_of_params = operation.params.len() as u8;
+        self.send_cmd(&operation.command, number_of_params).await;
+
+        // Only send params if they are present
+        if !operation.params.is_empty() {
+            let mut total_params_length: u16 = 0;
+            let mut total_params_length_size: u16 = 0;
+
+            for param in operation.params.iter() {
+                self.send_param(param).await;
+
+                total_params_length += param.length();
+                total_params_length_size += param.length_size() as u16;
+            }
+
+            self.send_end_cmd().await;
+
+            // 4 (start byte, command byte, number of params as byte, end byte)
+            // + the number of bytes to represent the param length (1 or 2)
+            // + the sum of all param lengths
+            let command_size: u16 = 4u16 + total_params_length_size + total_params_length;
+            self.pad_to_multiple_of_4(command_size).await;
+        }
+        self.control_pins.esp_deselect();
+
+        Ok(())
+    }
+
+    async fn receive<P: NinaParam>(
+        &mut self,
+        operation: &Operation<P>,
+        expected_num_params: u8,
+    ) -> Result<NinaResponseBuffer, Error> {
+        self.control_pins.wait_for_esp_select().await;
+
+        let result = match self
+            .check_response_ready(&operation.command, expected_num_params)
+            .await
+        {
+            Ok(()) => self.read_response().await,
+            Err(error) => Err(error),
+        };
+
+        self.control_pins.esp_deselect();
+
+        result
+    }
+
+    // Receives a single response param whose length is encoded in 2 bytes, as used by NINA
+    // data commands, and copies its bytes into `buf`. Returns the number of bytes received.
+    async fn receive_data<P: NinaParam>(
+        &mut self,
+        operation: &Operation<P>,
+        buf: &mut [u8],
+    ) -> Result<usize, Error> {
+        self.control_pins.wait_for_esp_select().await;
+
+        if let Err(error) = self.check_response_ready(&operation.command, 1).await {
+            self.control_pins.esp_deselect();
+            return Err(error);
+        }
+
+        let length_msb = self.get_byte().await as usize;
+        let length_lsb = self.get_byte().await as usize;
+        let response_length_in_bytes = (length_msb << 8) | length_lsb;
+
+        // Always consume the full response so that the next command stays aligned
+        for index in 0..response_length_in_bytes {
+            let byte = self.get_byte().await;
+            if let Some(slot) = buf.get_mut(index) {
+                *slot = byte;
+            }
+        }
+
+        self.get_byte().await; // End byte
+
+        self.control_pins.esp_deselect();
+
+        if response_length_in_bytes > buf.len() {
+            return Err(ProtocolError::PayloadTooLarge.into());
+        }
+
+        Ok(response_length_in_bytes)
+    }
+
+    // Receives a response made up of a variable number of params, each with a 1 byte length,
+    // and passes each param to `f` in order. Returns the number of params received.
+    async fn receive_each_param<P: NinaParam, F: FnMut(&[u8])>(
+        &mut self,
+        operation: &Operation<P>,
+        f: &mut F,
+    ) -> Result<u8, Error> {
+        self.control_pins.wait_for_esp_select().await;
+
+        if let Err(error) = self.check_reply_command(&operation.command).await {
+            self.control_pins.esp_deselect();
+            return Err(error);
+        }
+
+        let number_of_params = self.get_byte().await;
+        let mut param_buffer = [0u8; MAX_NINA_SMALL_ARRAY_PARAM_BUFFER_LENGTH];
+
+        for _ in 0..number_of_params {
+            let param_length = self.get_byte().await as usize;
+            for byte in param_buffer.iter_mut().take(param_length) {
+                *byte = self.get_byte().await;
+            }
+            f(&param_buffer[..param_length]);
+        }
+
+        self.get_byte().await; // End byte
+
+        self.control_pins.esp_deselect();
+
+        Ok(number_of_params)
+    }
+
+    async fn send_cmd(&mut self, cmd: &NinaCommand, num_params: u8) {
+        let buf: [u8; 3] = [
+            ControlByte::Start as u8,
+            (*cmd as u8) & !(ControlByte::Reply as u8),
+            num_params,
+        ];
+        self.bus.write(&buf).await.ok();
+
+        if num_params == 0 {
+            self.send_end_cmd().await;
+        }
+    }
+
+    async fn read_response(&mut self) -> Result<NinaResponseBuffer, Error> {
+        let response_length_in_bytes = self.get_byte().await as usize;
+
+        if response_length_in_bytes > MAX_NINA_PARAMS {
+            return Err(ProtocolError::TooManyParameters.into());
+        }
+
+        let mut response_param_buffer: NinaResponseBuffer = [0; MAX_NINA_RESPONSE_LENGTH];
+        for byte in response_param_buffer
+            .iter_mut()
+            .take(response_length_in_bytes)
+        {
+            *byte = self.get_byte().await;
+        }
+
+        self.get_byte().await; // End byte
+
+        Ok(response_param_buffer)
+    }
+
+    async fn check_response_ready(
+        &mut self,
+        cmd: &NinaCommand,
+        num_params: u8,
+    ) -> Result<(), Error> {
+        self.check_reply_command(cmd).await?;
+
+        // Ensure we see the number of params we expected to receive back
+        if self.get_byte().await != num_params {
+            return Err(ProtocolError::InvalidNumberOfParameters.into());
+        }
+        Ok(())
+    }
+
+    // Waits for the start of a reply and ensures it answers `cmd`.
+    async fn check_reply_command(&mut self, cmd: &NinaCommand) -> Result<(), Error> {
+        self.wait_for_byte(ControlByte::Start as u8).await?;
+
+        if self.get_byte().await != *cmd as u8 | ControlByte::Reply as u8 {
+            return Err(ProtocolError::InvalidCommand.into());
+        }
+        Ok(())
+    }
+
+    async fn send_end_cmd(&mut self) {
+        self.bus.write(&[ControlByte::End as u8]).await.ok();
+    }
+
+    async fn get_byte(&mut self) -> u8 {
+        let mut word = [ControlByte::Dummy as u8];
+        self.bus.transfer_in_place(&mut word).await.ok();
+        word[0]
+    }
+
+    async fn wait_for_byte(&mut self, wait_byte: u8) -> Result<(), Error> {
+        let retry_limit: u16 = 1000u16;
+
+        for _ in 0..retry_limit {
+            let byte_read = self.get_byte().await;
+            if byte_read == ControlByte::Error as u8 {
+                // consume remaining bytes after error: 0x00, 0xEE
+                self.get_byte().await;
+                self.get_byte().await;
+                return Err(ProtocolError::NinaProtocolVersionMismatch.into());
+            } else if byte_read == wait_byte {
+                return Ok(());
+            }
+        }
+        Err(ProtocolError::CommunicationTimeout.into())
+    }
+
+    async fn send_param<P: NinaParam>(&mut self, param: &P) {
+        let length = param.length_as_bytes();
+        self.bus
+            .write(&length[..param.length_size() as usize])
+            .await
+            .ok();
+        self.bus.write(param.data()).await.ok();
+    }
+
+    async fn pad_to_multiple_of_4(&mut self, mut command_size: u16) {
+        while !command_size.is_multiple_of(4) {
+            self.get_byte().await;
+            command_size += 1;
+        }
+    }
+}
@@ -0,0 +1,266 @@
+//! Locate UPnP devices such as internet gateways and media renderers on the local network via
+//! SSDP.
+//!
+//! An [`SsdpSearch`] multicasts an `M-SEARCH` request for a search target and collects the
+//! responses of all devices that answer it within a timeout. Each [`SsdpResponse`] carries the
+//! `LOCATION` URL of the device description, which can then be fetched over HTTP.
+//!
+//! ## Usage
+//!
+//! ```no_run
+//! use esp32_wroom_core::ssdp::{SsdpSearch, INTERNET_GATEWAY_DEVICE};
+//!
+//! let gateways = SsdpSearch::build(&mut wifi)
+//!     .search(INTERNET_GATEWAY_DEVICE, 3000)
+//!     .unwrap();
+//!
+//! for gateway in gateways {
+//!     defmt::info!("Found gateway at {=str}", gateway.location.as_str());
+//! }
+//! ```
+//!
+
+use core::fmt::Write;
+use core::net::{Ipv4Addr, SocketAddrV4};
+
+use embedded_hal::blocking::delay::DelayMs;
+
+use heapless::{String, Vec};
+
+use super::clock::Duration;
+use super::network::TransportMode;
+use super::protocol::{NinaProtocolHandler, ProtocolError, ProtocolInterface};
+use super::transport::Transport;
+use super::udp_client::send_datagram;
+use super::wifi::Wifi;
+use super::Error;
+
+/// Search target matching all devices and services.
+pub const SSDP_ALL: &str = "ssdp:all";
+
+/// Search target matching UPnP internet gateways, e.g. home routers.
+pub const INTERNET_GATEWAY_DEVICE: &str = "urn:schemas-upnp-org:device:InternetGatewayDevice:1";
+
+/// Search target matching UPnP media renderers, e.g. smart TVs and network speakers.
+pub const MEDIA_RENDERER: &str = "urn:schemas-upnp-org:device:MediaRenderer:1";
+
+/// The maximum number of responses returned by [`SsdpSearch::search`].
+pub const MAX_SSDP_RESPONSES: usize = 4;
+
+/// The maximum length of a header value of an [`SsdpResponse`] in bytes. Responses with longer
+/// values are skipped.
+pub const MAX_SSDP_HEADER_LENGTH: usize = 128;
+
+// The maximum length of an SSDP message in bytes
+const MAX_SSDP_MESSAGE_LENGTH: usize = 1024;
+
+const SSDP_GROUP: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(239, 255, 255, 250), 1900);
+
+// The longest random delay devices may wait before responding, as allowed by UPnP
+const MAX_MX_SECONDS: u16 = 5;
+
+// How often SsdpSearch::search() checks for received responses
+const SEARCH_POLL_INTERVAL_MS: u16 = 50;
+
+/// A response of a device to an `M-SEARCH` request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct SsdpResponse {
+    /// URL of the UPnP description of the device, from the `LOCATION` header.
+    pub location: String<MAX_SSDP_HEADER_LENGTH>,
+    /// The search target the device responded for, from the `ST` header.
+    pub search_target: String<MAX_SSDP_HEADER_LENGTH>,
+    /// Unique service name of the device or service, from the `USN` header.
+    pub usn: String<MAX_SSDP_HEADER_LENGTH>,
+}
+
+/// Searches the local network for UPnP devices and services via SSDP.
+pub struct SsdpSearch<'a, T, D> {
+    pub(crate) protocol_handler: &'a mut NinaProtocolHandler<T, D>,
+}
+
+impl<'a, T, D> SsdpSearch<'a, T, D>
+where
+    T: Transport,
+    D: DelayMs<u16>,
+{
+    /// Build a new instance of an [`SsdpSearch`] provided a [`Wifi`] instance.
+    pub fn build(wifi: &'a mut Wifi<T, D>) -> Self {
+        Self {
+            protocol_handler: wifi.protocol_handler.get_mut(),
+        }
+    }
+
+    /// Multicast an `M-SEARCH` request for `search_target` (e.g. [`INTERNET_GATEWAY_DEVICE`])
+    /// and collect the responses received within `timeout_ms`, up to [`MAX_SSDP_RESPONSES`] of
+    /// them with distinct `USN`s.
+    ///
+    /// Devices wait a random delay of up to the timeout before responding, capped at 5 seconds,
+    /// so a timeout of at least one second should be used.
+    pub fn search(
+        &mut self,
+        search_target: &str,
+        timeout_ms: u16,
+    ) -> Result<Vec<SsdpResponse, MAX_SSDP_RESPONSES>, Error> {
+        let mx_seconds = (timeout_ms / 1000).clamp(1, MAX_MX_SECONDS);
+        let request = encode_search(search_target, mx_seconds)?;
+
+        let socket = self.protocol_handler.get_socket()?;
+        self.protocol_handler
+            .start_server_multicast(socket, SSDP_GROUP)?;
+
+        let protocol_handler = &mut *self.protocol_handler;
+        let mut search = || -> Result<Vec<SsdpResponse, MAX_SSDP_RESPONSES>, Error> {
+            send_datagram(protocol_handler, socket, SSDP_GROUP, request.as_bytes())?;
+
+            let mut responses: Vec<SsdpResponse, MAX_SSDP_RESPONSES> = Vec::new();
+            let mut message = [0u8; MAX_SSDP_MESSAGE_LENGTH];
+            let mut deadline = protocol_handler.deadline(Some(Duration::millis(timeout_ms as u32)));
+
+            while !deadline.has_expired() {
+                let available = protocol_handler.avail_data_tcp(socket)?;
+                if available == 0 {
+                    protocol_handler.delay_ms(SEARCH_POLL_INTERVAL_MS);
+                    deadline.wait(Duration::millis(SEARCH_POLL_INTERVAL_MS as u32));
+                    continue;
+                }
+
+                let length = available.min(message.len());
+                let length = protocol_handler.get_data_buf_tcp(socket, &mut message[..length])?;
+
+                // Requests of other hosts, notifications and malformed responses are skipped
+                if let Some(response) = decode_response(&message[..length]) {
+                    if !responses.iter().any(|known| known.usn == response.usn) {
+                        responses.push(response).ok();
+                    }
+                }
+            }
+
+            Ok(responses)
+        };
+        let result = search();
+
+        self.protocol_handler
+            .stop_client_tcp(socket, &TransportMode::UdpMulticast)?;
+
+        result
+    }
+}
+
+// Encodes an M-SEARCH request for `search_target` that lets devices delay their response by
+// up to `mx_seconds`.
+fn encode_search(
+    search_target: &str,
+    mx_seconds: u16,
+) -> Result<String<MAX_SSDP_MESSAGE_LENGTH>, Error> {
+    let mut request = String::new();
+    write!(
+        request,
+        "M-SEARCH * HTTP/1.1\r\n\
+         HOST: 239.255.255.250:1900\r\n\
+         MAN: \"ssdp:discover\"\r\n\
+         MX: {}\r\n\
+         ST: {}\r\n\
+         \r\n",
+        mx_seconds, search_target
+    )
+    .map_err(|_| ProtocolError::PayloadTooLarge)?;
+
+    Ok(request)
+}
+
+// Decodes a successful response to an M-SEARCH request, returning `None` for any other
+// message and for responses without a `LOCATION` header.
+fn decode_response(message: &[u8]) -> Option<SsdpResponse> {
+    let message = core::str::from_utf8(message).ok()?;
+    let mut lines = message.lines();
+
+    let mut status = lines.next()?.split(' ');
+    if !status.next()?.starts_with("HTTP/") || status.next()? != "200" {
+        return None;
+    }
+
+    let mut has_location = false;
+    let mut response = SsdpResponse {
+        location: String::new(),
+        search_target: String::new(),
+        usn: String::new(),
+    };
+
+    for line in lines.take_while(|line| !line.is_empty()) {
+        let (name, value) = line.split_once(':')?;
+        let value = value.trim();
+
+        let header = if name.trim().eq_ignore_ascii_case("LOCATION") {
+            has_location = true;
+            &mut response.location
+        } else if name.trim().eq_ignore_ascii_case("ST") {
+            &mut response.search_target
+        } else if name.trim().eq_ignore_ascii_case("USN") {
+            &mut response.usn
+        } else {
+            continue;
+        };
+
+        header.clear();
+        header.push_str(value).ok()?;
+    }
+
+    has_location.then_some(response)
+}
+
+#[cfg(test)]
+mod ssdp_tests {
+    use super::*;
+
+    #[test]
+    fn encode_search_formats_an_m_search_request() {
+        let request = encode_search(SSDP_ALL, 2).unwrap();
+
+        assert_eq!(
+            request.as_str(),
+            "M-SEARCH * HTTP/1.1\r\nHOST: 239.255.255.250:1900\r\nMAN: \"ssdp:discover\"\r\n\
+             MX: 2\r\nST: ssdp:all\r\n\r\n"
+        );
+    }
+
+    #[test]
+    fn decode_response_reads_the_headers_of_a_search_response() {
+        let response = decode_response(
+            b"HTTP/1.1 200 OK\r\n\
+              CACHE-CONTROL: max-age=1800\r\n\
+              Location: http://192.168.1.1:5000/rootDesc.xml\r\n\
+              SERVER: Linux UPnP/1.1 MiniUPnPd/2.2\r\n\
+              st: urn:schemas-upnp-org:device:InternetGatewayDevice:1\r\n\
+              USN: uuid:1234::urn:schemas-upnp-org:device:InternetGatewayDevice:1\r\n\
+              \r\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            response.location.as_str(),
+            "http://192.168.1.1:5000/rootDesc.xml"
+        );
+        assert_eq!(response.search_target.as_str(), INTERNET_GATEWAY_DEVICE);
+        assert_eq!(
+            response.usn.as_str(),
+            "uuid:1234::urn:schemas-upnp-org:device:InternetGatewayDevice:1"
+        );
+    }
+
+    #[test]
+    fn decode_response_skips_requests_and_responses_without_location() {
+        assert_eq!(
+            decode_response(encode_search(SSDP_ALL, 1).unwrap().as_bytes()),
+            None
+        );
+        assert_eq!(
+            decode_response(b"NOTIFY * HTTP/1.1\r\nLOCATION: http://a/\r\n\r\n"),
+            None
+        );
+        assert_eq!(
+            decode_response(b"HTTP/1.1 200 OK\r\nUSN: uuid:1234\r\n\r\n"),
+            None
+        );
+    }
+}
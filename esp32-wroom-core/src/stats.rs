@@ -0,0 +1,58 @@
+//! Counters that track the health of the connection with the ESP32 target.
+//!
+//! All counters increase monotonically (wrapping around on overflow) for the lifetime of a
+//! [`Wifi`](crate::wifi::Wifi) instance, which makes them suitable for periodic upload to a fleet
+//! health dashboard where the difference between two samples is what matters.
+//!
+//! ## Usage
+//!
+//! ```no_run
+//! let stats = wifi.recovery_stats();
+//! defmt::info!("Recovery stats: {:?}", stats);
+//! ```
+//!
+
+#[cfg(feature = "defmt")]
+use defmt::{write, Format, Formatter};
+
+/// Counts the recovery actions taken while communicating with the NINA firmware.
+///
+/// The NINA firmware does not provide a soft reset command, so resets are always performed via
+/// the RESETn control pin and counted as hard resets.
+#[derive(Debug, Default, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "ufmt", derive(ufmt::derive::uDebug))]
+pub struct RecoveryStats {
+    /// Number of times the ESP32 target was reset via its RESETn pin, including the reset
+    /// performed by [`Wifi::init`](crate::wifi::Wifi::init).
+    pub hard_resets: u32,
+    /// Number of times the protocol stream had to be resynchronized after the NINA firmware
+    /// reported an error or sent an unexpected response.
+    pub protocol_resyncs: u32,
+    /// Number of times a WiFi network was joined again after a previous join.
+    pub rejoins: u32,
+}
+
+impl RecoveryStats {
+    pub(crate) fn record_hard_reset(&mut self) {
+        self.hard_resets = self.hard_resets.wrapping_add(1);
+    }
+
+    pub(crate) fn record_protocol_resync(&mut self) {
+        self.protocol_resyncs = self.protocol_resyncs.wrapping_add(1);
+    }
+
+    pub(crate) fn record_rejoin(&mut self) {
+        self.rejoins = self.rejoins.wrapping_add(1);
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl Format for RecoveryStats {
+    fn format(&self, fmt: Formatter) {
+        write!(
+            fmt,
+            "hard resets: {:?}, protocol resyncs: {:?}, rejoins: {:?}",
+            self.hard_resets, self.protocol_resyncs, self.rejoins
+        );
+    }
+}
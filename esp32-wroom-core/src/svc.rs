@@ -0,0 +1,225 @@
+//! An implementation of the [embedded-svc](https://github.com/esp-rs/embedded-svc) `Wifi` trait,
+//! enabled by the `embedded-svc` feature.
+//!
+//! This lets code written for esp-idf-svc style WiFi stacks manage the ESP32 target with few
+//! changes. The trait configures the WiFi network up front and applies it later on, while the
+//! NINA firmware applies everything right away, so [`SvcWifi`] keeps the configuration until
+//! [`Wifi::start`](embedded_svc::wifi::Wifi::start) and
+//! [`Wifi::connect`](embedded_svc::wifi::Wifi::connect) are called.
+//!
+//! The NINA firmware runs either as a client or as an access point but not both at once, so
+//! mixed configurations aren't supported. It also only hosts open or WPA2 access points and
+//! doesn't accept WPA2-Enterprise credentials without a username, for which
+//! [`Wifi::join_enterprise`] has to be used instead.
+//!
+//! ## Usage
+//!
+//! ```no_run
+//! use embedded_svc::wifi::{ClientConfiguration, Configuration, Wifi as _};
+//!
+//! let mut svc_wifi = SvcWifi::build(&mut wifi);
+//!
+//! svc_wifi
+//!     .set_configuration(&Configuration::Client(ClientConfiguration {
+//!         ssid: "Calebphone".try_into().unwrap(),
+//!         password: "secret".try_into().unwrap(),
+//!         ..Default::default()
+//!     }))
+//!     .unwrap();
+//! svc_wifi.start().unwrap();
+//! svc_wifi.connect().unwrap();
+//!
+//! while !svc_wifi.is_connected().unwrap() {
+//!     timer.delay_ms(500);
+//! }
+//! ```
+//!
+
+use embedded_hal::blocking::delay::DelayMs;
+
+use embedded_svc::wifi::{AccessPointInfo, AuthMethod, Capability, Configuration};
+
+use enumset::EnumSet;
+
+use super::network::NetworkError;
+use super::protocol::ProtocolInterface;
+use super::transport::Transport;
+use super::wifi::{AccessPointConfig, ConnectionStatus, EncryptionType, ScanResult, Wifi};
+use super::Error;
+
+/// Manages the WiFi connection of the ESP32 target through the embedded-svc
+/// [`Wifi`](embedded_svc::wifi::Wifi) trait.
+pub struct SvcWifi<'a, T, D> {
+    wifi: &'a mut Wifi<T, D>,
+    configuration: Configuration,
+    started: bool,
+}
+
+impl<'a, T, D> SvcWifi<'a, T, D>
+where
+    T: Transport,
+    D: DelayMs<u16>,
+{
+    /// Build a new instance of an [`SvcWifi`] provided a [`Wifi`] instance.
+    pub fn build(wifi: &'a mut Wifi<T, D>) -> Self {
+        Self {
+            wifi,
+            configuration: Configuration::None,
+            started: false,
+        }
+    }
+}
+
+impl<T, D> embedded_svc::wifi::Wifi for SvcWifi<'_, T, D>
+where
+    T: Transport,
+    D: DelayMs<u16>,
+{
+    type Error = Error;
+
+    fn get_capabilities(&self) -> Result<EnumSet<Capability>, Error> {
+        Ok(Capability::Client | Capability::AccessPoint)
+    }
+
+    fn get_configuration(&self) -> Result<Configuration, Error> {
+        Ok(self.configuration.clone())
+    }
+
+    /// Keep `configuration` to be applied by the next `start` or `connect`. Fails with
+    /// [`Error::UnsupportedOperation`] for mixed configurations and for authentication methods
+    /// the NINA firmware doesn't support.
+    fn set_configuration(&mut self, configuration: &Configuration) -> Result<(), Error> {
+        let supported = match configuration {
+            Configuration::None => true,
+            Configuration::Client(client) => client.auth_method != AuthMethod::WPA2Enterprise,
+            Configuration::AccessPoint(access_point) => matches!(
+                access_point.auth_method,
+                AuthMethod::None | AuthMethod::WPA2Personal
+            ),
+            Configuration::Mixed(_, _) => false,
+        };
+        if !supported {
+            return Err(Error::UnsupportedOperation);
+        }
+
+        self.configuration = configuration.clone();
+        Ok(())
+    }
+
+    /// Start hosting the access point of an access point configuration. Client configurations
+    /// are applied by `connect`.
+    fn start(&mut self) -> Result<(), Error> {
+        if let Configuration::AccessPoint(access_point) = &self.configuration {
+            let mut config =
+                AccessPointConfig::new(access_point.ssid.as_str()).channel(access_point.channel);
+            if access_point.auth_method == AuthMethod::WPA2Personal {
+                config = config.passphrase(access_point.password.as_str());
+            }
+
+            self.wifi.start_access_point(&config)?;
+        }
+
+        self.started = true;
+        Ok(())
+    }
+
+    fn stop(&mut self) -> Result<(), Error> {
+        if self.started {
+            self.wifi.leave()?;
+            self.started = false;
+        }
+
+        Ok(())
+    }
+
+    /// Join the WiFi network of the client configuration, failing with
+    /// [`NetworkError::ClientNotConfigured`] if there is none.
+    fn connect(&mut self) -> Result<(), Error> {
+        let client = match &self.configuration {
+            Configuration::Client(client) => client,
+            _ => return Err(NetworkError::ClientNotConfigured.into()),
+        };
+
+        if client.auth_method == AuthMethod::None {
+            self.wifi.join_open(client.ssid.as_str())
+        } else {
+            self.wifi
+                .join(client.ssid.as_str(), client.password.as_str())
+        }
+    }
+
+    fn disconnect(&mut self) -> Result<(), Error> {
+        self.wifi.leave()
+    }
+
+    fn is_started(&self) -> Result<bool, Error> {
+        Ok(self.started)
+    }
+
+    fn is_connected(&self) -> Result<bool, Error> {
+        let status = self.wifi.borrow_protocol_handler()?.get_conn_status()?;
+
+        Ok(status == ConnectionStatus::Connected)
+    }
+
+    /// Scan for nearby WiFi networks, returning up to `N` of them along with the total number
+    /// of networks found.
+    fn scan_n<const N: usize>(
+        &mut self,
+    ) -> Result<(heapless08::Vec<AccessPointInfo, N>, usize), Error> {
+        let mut access_points = heapless08::Vec::new();
+        let mut found = 0;
+
+        for result in self.wifi.scan_networks()? {
+            access_points.push(access_point_info(&result)).ok();
+            found += 1;
+        }
+
+        Ok((access_points, found))
+    }
+}
+
+// Converts a network found while scanning into its embedded-svc representation.
+fn access_point_info(result: &ScanResult) -> AccessPointInfo {
+    let mut info = AccessPointInfo {
+        bssid: result.bssid().octets(),
+        channel: result.channel(),
+        signal_strength: result.rssi().clamp(i8::MIN as i32, i8::MAX as i32) as i8,
+        auth_method: auth_method(result.encryption_type()),
+        ..Default::default()
+    };
+    info.ssid.push_str(result.ssid().unwrap_or_default()).ok();
+
+    info
+}
+
+// Maps the encryption type of a scanned network to an embedded-svc authentication method.
+fn auth_method(encryption_type: EncryptionType) -> Option<AuthMethod> {
+    match encryption_type {
+        EncryptionType::Tkip => Some(AuthMethod::WPA),
+        EncryptionType::Ccmp => Some(AuthMethod::WPA2Personal),
+        EncryptionType::Wep => Some(AuthMethod::WEP),
+        EncryptionType::None => Some(AuthMethod::None),
+        EncryptionType::Auto => Some(AuthMethod::WPAWPA2Personal),
+        EncryptionType::Unknown => None,
+    }
+}
+
+#[cfg(test)]
+mod svc_tests {
+    use super::*;
+
+    #[test]
+    fn auth_method_maps_encryption_types_of_scanned_networks() {
+        assert_eq!(
+            auth_method(EncryptionType::Ccmp),
+            Some(AuthMethod::WPA2Personal)
+        );
+        assert_eq!(
+            auth_method(EncryptionType::Auto),
+            Some(AuthMethod::WPAWPA2Personal)
+        );
+        assert_eq!(auth_method(EncryptionType::None), Some(AuthMethod::None));
+        assert_eq!(auth_method(EncryptionType::Unknown), None);
+    }
+}
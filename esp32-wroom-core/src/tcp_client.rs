@@ -0,0 +1,794 @@
+//! Send/receive data to/from a TCP server and associated types.
+//!
+//! ## Usage
+//!
+//! ```no_run
+//! let hostname = "github.com";
+//! // let ip_address = Ipv4Addr::new(140, 82, 114, 3); // github.com
+//!
+//! let port: Port = 80;
+//! let mode: TransportMode = TransportMode::Tcp;
+//! if let Err(e) = TcpClient::build(&mut wifi).connect(
+//!     hostname,
+//!     port,
+//!     mode,
+//!     &mut |tcp_client| {
+//!         defmt::info!(
+//!             "TCP connection to {:?}:{:?} successful",
+//!             hostname,
+//!             port
+//!         );
+//!         defmt::info!("Hostname: {:?}", tcp_client.server_hostname());
+//!         defmt::info!("Sending HTTP Document: {:?}", http_document.as_str());
+//!         match tcp_client.send_str(&http_document) {
+//!             Ok(response) => {
+//!                 defmt::info!("Response: {:?}", response)
+//!             }
+//!             Err(e) => {
+//!                 defmt::error!("Response error: {:?}", e)
+//!             }
+//!         }
+//!     },
+//! ) {
+//!     defmt::error!(
+//!         "TCP connection to {:?}:{:?} failed: {:?}",
+//!         hostname,
+//!         port,
+//!         e
+//!     );
+//! }
+//! ```
+//!
+
+use core::net::{Ipv4Addr, SocketAddrV4};
+
+#[cfg(feature = "defmt")]
+use defmt::{write, Format, Formatter};
+
+use embedded_hal::blocking::delay::DelayMs;
+
+use heapless::String;
+
+use super::clock::{Deadline, Duration};
+use super::keep_alive::KeepAlive;
+use super::network::{
+    ConnectionState, Hostname, IpAddr, NetworkError, Port, Socket, TransportMode,
+};
+use super::offline_queue::OfflineQueue;
+use super::protocol::{
+    NinaProtocolHandler, ProtocolInterface, MAX_NINA_LARGE_ARRAY_PARAM_BUFFER_LENGTH,
+};
+use super::transport::Transport;
+use super::wifi::Wifi;
+use super::Error;
+
+const MAX_HOSTNAME_LENGTH: usize = 255;
+
+// How long to wait in between two checks whether a connection has been established
+const CONNECT_POLL_INTERVAL_MS: u16 = 100;
+
+// How long to wait for a connection to be established before giving up
+const CONNECT_TIMEOUT: Duration = Duration::secs(1_000);
+
+/// Allows for a [`TcpClient`] instance to connect to a remote server by providing
+/// either a [`Hostname`] or an [`Ipv4Addr`]. This trait also makes it possible to
+/// implement and support IPv6 addresses.
+pub trait Connect<'a, S, T, D> {
+    /// Enable a client to connect to `server` on `port` using transport layer `mode`.
+    fn connect<F: FnMut(&mut TcpClient<'a, T, D>)>(
+        &mut self,
+        server: S,
+        port: Port,
+        mode: TransportMode,
+        f: &mut F,
+    ) -> Result<(), Error>;
+
+    /// Start connecting to `server` on `port` using transport layer `mode` without waiting for
+    /// the connection to be established. Call [`TcpClient::poll_connect`] afterwards to find
+    /// out when it is.
+    fn connect_nb(&mut self, server: S, port: Port, mode: TransportMode) -> Result<(), Error>;
+}
+
+/// The progress of a connection started with [`Connect::connect_nb`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "ufmt", derive(ufmt::derive::uDebug))]
+#[non_exhaustive]
+pub enum ConnectProgress {
+    /// The connection is still being established
+    Pending,
+    /// The connection is established and data can be sent and received
+    Established,
+    /// The connection couldn't be established and its socket has been closed
+    Failed,
+}
+
+#[cfg(feature = "defmt")]
+impl Format for ConnectProgress {
+    fn format(&self, fmt: Formatter) {
+        match self {
+            ConnectProgress::Pending => write!(fmt, "Pending"),
+            ConnectProgress::Established => write!(fmt, "Established"),
+            ConnectProgress::Failed => write!(fmt, "Failed"),
+        }
+    }
+}
+
+/// Configures how often a [`TcpClient`] checks for received data or room to send more while it
+/// waits for either.
+///
+/// The first check comes right away. Each one that comes up empty doubles the interval to the
+/// next, starting at `initial_interval_ms` and capped at `max_interval_ms`, so small
+/// request/response exchanges complete within a few milliseconds while long waits don't keep
+/// the bus busy.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "ufmt", derive(ufmt::derive::uDebug))]
+#[non_exhaustive]
+pub struct PollConfig {
+    /// The interval after the first empty check, 1 ms by default.
+    pub initial_interval_ms: u16,
+    /// The longest interval in between two checks, 50 ms by default.
+    pub max_interval_ms: u16,
+    /// How long a single [`TcpClient::receive_data`] or [`TcpClient::receive_stream`] call may
+    /// take in total before it fails with [`NetworkError::ReadTimeout`], even while data keeps
+    /// arriving. `None` (the default) leaves it to the read timeout.
+    pub deadline_ms: Option<u32>,
+}
+
+impl PollConfig {
+    /// Create a new [`PollConfig`] with the default intervals and no deadline.
+    pub fn new() -> Self {
+        Self {
+            initial_interval_ms: 1,
+            max_interval_ms: 50,
+            deadline_ms: None,
+        }
+    }
+
+    /// Wait `initial_interval_ms` after the first empty check.
+    pub fn initial_interval_ms(mut self, initial_interval_ms: u16) -> Self {
+        self.initial_interval_ms = initial_interval_ms;
+        self
+    }
+
+    /// Never wait longer than `max_interval_ms` in between two checks.
+    pub fn max_interval_ms(mut self, max_interval_ms: u16) -> Self {
+        self.max_interval_ms = max_interval_ms;
+        self
+    }
+
+    /// Give up receiving after `deadline_ms` in total, or never with `None`.
+    pub fn deadline_ms(mut self, deadline_ms: Option<u32>) -> Self {
+        self.deadline_ms = deadline_ms;
+        self
+    }
+}
+
+impl Default for PollConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl Format for PollConfig {
+    fn format(&self, fmt: Formatter) {
+        write!(
+            fmt,
+            "initial_interval_ms: {=u16}, max_interval_ms: {=u16}, deadline_ms: {=?}",
+            self.initial_interval_ms, self.max_interval_ms, self.deadline_ms
+        )
+    }
+}
+
+// The growing interval in between two checks of a PollConfig. Intervals are at least 1 ms, so
+// that waiting always makes progress towards a deadline.
+struct PollBackoff {
+    initial_interval_ms: u16,
+    max_interval_ms: u16,
+    interval_ms: u16,
+}
+
+impl PollBackoff {
+    fn new(config: &PollConfig) -> Self {
+        let max_interval_ms = config.max_interval_ms.max(1);
+        let initial_interval_ms = config.initial_interval_ms.clamp(1, max_interval_ms);
+        Self {
+            initial_interval_ms,
+            max_interval_ms,
+            interval_ms: initial_interval_ms,
+        }
+    }
+
+    // Starts over at the initial interval, e.g. once data has arrived.
+    fn restart(&mut self) {
+        self.interval_ms = self.initial_interval_ms;
+    }
+
+    // Returns the interval to wait now and doubles the following one.
+    fn next_interval_ms(&mut self) -> u16 {
+        let interval_ms = self.interval_ms;
+        self.interval_ms = interval_ms.saturating_mul(2).min(self.max_interval_ms);
+        interval_ms
+    }
+}
+
+/// A client type that connects to and performs send/receive operations with a remote
+/// server using the TCP protocol.
+pub struct TcpClient<'a, T, D> {
+    pub(crate) protocol_handler: &'a mut NinaProtocolHandler<T, D>,
+    pub(crate) socket: Option<Socket>,
+    pub(crate) server_ip_address: Option<Ipv4Addr>,
+    pub(crate) port: Port,
+    pub(crate) mode: TransportMode,
+    pub(crate) server_hostname: Option<String<MAX_HOSTNAME_LENGTH>>,
+    pub(crate) read_timeout_ms: Option<u32>,
+    pub(crate) write_timeout_ms: Option<u32>,
+    pub(crate) poll_config: PollConfig,
+    pub(crate) liveness_interval_ms: Option<u32>,
+    pub(crate) last_liveness_check_ms: Option<u32>,
+}
+
+impl<'a, T, D> Connect<'a, Ipv4Addr, T, D> for TcpClient<'a, T, D>
+where
+    T: Transport,
+    D: DelayMs<u16>,
+{
+    fn connect<F: FnMut(&mut TcpClient<'a, T, D>)>(
+        &mut self,
+        ip: Ipv4Addr,
+        port: Port,
+        mode: TransportMode,
+        f: &mut F,
+    ) -> Result<(), Error> {
+        self.prepare(Some(ip), "", port, mode)?;
+
+        self.connect_common(f)
+    }
+
+    fn connect_nb(&mut self, ip: Ipv4Addr, port: Port, mode: TransportMode) -> Result<(), Error> {
+        self.prepare(Some(ip), "", port, mode)?;
+
+        self.start_connection()
+    }
+}
+
+impl<'a, T, D> Connect<'a, IpAddr, T, D> for TcpClient<'a, T, D>
+where
+    T: Transport,
+    D: DelayMs<u16>,
+{
+    /// Connect to `ip`, which fails with [`NetworkError::Ipv6Unsupported`] for IPv6 addresses
+    /// before a socket is requested.
+    fn connect<F: FnMut(&mut TcpClient<'a, T, D>)>(
+        &mut self,
+        ip: IpAddr,
+        port: Port,
+        mode: TransportMode,
+        f: &mut F,
+    ) -> Result<(), Error> {
+        let ip = ip.to_ipv4()?;
+
+        Connect::<Ipv4Addr, T, D>::connect(self, ip, port, mode, f)
+    }
+
+    fn connect_nb(&mut self, ip: IpAddr, port: Port, mode: TransportMode) -> Result<(), Error> {
+        let ip = ip.to_ipv4()?;
+
+        Connect::<Ipv4Addr, T, D>::connect_nb(self, ip, port, mode)
+    }
+}
+
+impl<'a, T, D> Connect<'a, Hostname<'_>, T, D> for TcpClient<'a, T, D>
+where
+    T: Transport,
+    D: DelayMs<u16>,
+{
+    fn connect<F: FnMut(&mut TcpClient<'a, T, D>)>(
+        &mut self,
+        server_hostname: Hostname,
+        port: Port,
+        mode: TransportMode,
+        f: &mut F,
+    ) -> Result<(), Error> {
+        self.prepare(None, server_hostname, port, mode)?;
+
+        self.connect_common(f)
+    }
+
+    fn connect_nb(
+        &mut self,
+        server_hostname: Hostname,
+        port: Port,
+        mode: TransportMode,
+    ) -> Result<(), Error> {
+        self.prepare(None, server_hostname, port, mode)?;
+
+        self.start_connection()
+    }
+}
+
+impl<'a, T, D> TcpClient<'a, T, D>
+where
+    T: Transport,
+    D: DelayMs<u16>,
+{
+    /// Build a new instance of a [`TcpClient`] provided a [`Wifi`] instance.
+    pub fn build(wifi: &'a mut Wifi<T, D>) -> Self {
+        Self {
+            protocol_handler: wifi.protocol_handler.get_mut(),
+            socket: None,
+            server_ip_address: None,
+            port: 0,
+            mode: TransportMode::Tcp,
+            server_hostname: Some(String::new()),
+            read_timeout_ms: None,
+            write_timeout_ms: None,
+            poll_config: PollConfig::new(),
+            liveness_interval_ms: None,
+            last_liveness_check_ms: None,
+        }
+    }
+
+    /// Get an [`Ipv4Addr`] of the remote server to communicate with that is
+    /// set by calling [`TcpClient::connect`].
+    pub fn server_ip_address(&self) -> Option<Ipv4Addr> {
+        self.server_ip_address
+    }
+
+    /// Get a [`Hostname`] of the remote server to communicate with that is
+    /// set by calling [`TcpClient::connect`].
+    pub fn server_hostname(&self) -> &str {
+        self.server_hostname.as_ref().unwrap().as_str()
+    }
+
+    /// Get a [`Port`] of the remote server to communicate with that is
+    /// set by calling [`TcpClient::connect`].
+    pub fn port(&self) -> Port {
+        self.port
+    }
+
+    /// Get a [`TransportMode`] used in communication with the remote server that is
+    /// set by calling [`TcpClient::connect`].
+    pub fn mode(&self) -> TransportMode {
+        self.mode
+    }
+
+    /// Get the address and port of the connected server as seen by the ESP32 target, e.g. the
+    /// address a hostname passed to [`TcpClient::connect`] was resolved to.
+    pub fn remote_address(&mut self) -> Result<SocketAddrV4, Error> {
+        self.protocol_handler
+            .get_remote_data(self.socket.unwrap_or_default())
+    }
+
+    /// Bind the client to a fixed local (source) `port` for the following connection.
+    ///
+    /// The NINA firmware always picks the source port of a client connection itself and has no
+    /// way to specify one, so this currently always returns [`Error::UnsupportedOperation`].
+    pub fn set_local_port(&mut self, _port: Port) -> Result<(), Error> {
+        Err(Error::UnsupportedOperation)
+    }
+
+    /// Request current `Socket` handle.
+    pub fn get_socket(&mut self) -> Result<Socket, Error> {
+        self.protocol_handler.get_socket()
+    }
+
+    /// Send a slice of binary data, e.g. a protobuf or CBOR frame, to a connected server.
+    /// Returns the number of bytes the ESP32 target accepted, or fails with
+    /// [`NetworkError::SendFailed`] if it couldn't confirm they were sent.
+    pub fn send_data(&mut self, data: &[u8]) -> Result<usize, Error> {
+        let socket = self.socket.unwrap_or_default();
+        let written = self.protocol_handler.send_data(data, socket)?;
+
+        self.confirm_sent(socket)?;
+        Ok(written)
+    }
+
+    /// Send a string slice of data to a connected server.
+    pub fn send_str(&mut self, data: &str) -> Result<usize, Error> {
+        self.send_data(data.as_bytes())
+    }
+
+    /// Give up waiting for data in [`TcpClient::receive_data`] after `timeout_ms`
+    /// milliseconds, or wait forever with `None` (the default).
+    pub fn set_read_timeout(&mut self, timeout_ms: Option<u32>) {
+        self.read_timeout_ms = timeout_ms;
+    }
+
+    /// Give up waiting for the ESP32 target to accept more data in [`TcpClient::write_all`]
+    /// after `timeout_ms` milliseconds, or wait forever with `None` (the default).
+    pub fn set_write_timeout(&mut self, timeout_ms: Option<u32>) {
+        self.write_timeout_ms = timeout_ms;
+    }
+
+    /// Check for received data or room to send more as configured by `config` while waiting
+    /// for either, instead of the default [`PollConfig`].
+    pub fn set_poll_config(&mut self, config: PollConfig) {
+        self.poll_config = config;
+    }
+
+    /// Get the number of bytes received from the connected server that are ready to be read,
+    /// without waiting for any to arrive.
+    pub fn bytes_available(&mut self) -> Result<usize, Error> {
+        self.protocol_handler
+            .avail_data_tcp(self.socket.unwrap_or_default())
+    }
+
+    /// Wait for data from the connected server and read it into `buf`, returning the number
+    /// of bytes read. Fails with [`NetworkError::ReadTimeout`] if no data arrives within the
+    /// timeout set by [`TcpClient::set_read_timeout`] or the deadline of the [`PollConfig`].
+    pub fn receive_data(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let socket = self.socket.unwrap_or_default();
+        let timeout_ms = match (self.read_timeout_ms, self.poll_config.deadline_ms) {
+            (Some(timeout_ms), Some(deadline_ms)) => Some(timeout_ms.min(deadline_ms)),
+            (timeout_ms, deadline_ms) => timeout_ms.or(deadline_ms),
+        };
+        let mut deadline = self
+            .protocol_handler
+            .deadline(timeout_ms.map(Duration::millis));
+        let mut backoff = PollBackoff::new(&self.poll_config);
+
+        loop {
+            let available = self.protocol_handler.avail_data_tcp(socket)?;
+            if available > 0 {
+                let length = available.min(buf.len());
+                return self
+                    .protocol_handler
+                    .get_data_buf_tcp(socket, &mut buf[..length]);
+            }
+
+            if deadline.has_expired() {
+                return Err(NetworkError::ReadTimeout.into());
+            }
+            self.wait_to_poll(&mut backoff, &mut [&mut deadline]);
+        }
+    }
+
+    /// Read data from the connected server chunk by chunk into `buf` and pass each chunk to
+    /// `f`, e.g. to process an HTTP body larger than any buffer with constant memory. Stops
+    /// once `f` returns `false` or the server has closed the connection and all of its data
+    /// has been read, returning the total number of bytes received.
+    ///
+    /// Fails with [`NetworkError::ReadTimeout`] if no data arrives within the timeout set by
+    /// [`TcpClient::set_read_timeout`] in between two chunks, or once the deadline of the
+    /// [`PollConfig`] has passed.
+    pub fn receive_stream<F: FnMut(&[u8]) -> bool>(
+        &mut self,
+        buf: &mut [u8],
+        mut f: F,
+    ) -> Result<usize, Error> {
+        let socket = self.socket.unwrap_or_default();
+        let mut received = 0;
+        let mut deadline = self
+            .protocol_handler
+            .deadline(self.read_timeout_ms.map(Duration::millis));
+        let mut total_deadline = self
+            .protocol_handler
+            .deadline(self.poll_config.deadline_ms.map(Duration::millis));
+        let mut backoff = PollBackoff::new(&self.poll_config);
+
+        loop {
+            if total_deadline.has_expired() {
+                return Err(NetworkError::ReadTimeout.into());
+            }
+
+            let available = self.protocol_handler.avail_data_tcp(socket)?;
+            if available > 0 {
+                let length = available.min(buf.len());
+                let length = self
+                    .protocol_handler
+                    .get_data_buf_tcp(socket, &mut buf[..length])?;
+                received += length;
+                deadline.restart();
+                backoff.restart();
+
+                if !f(&buf[..length]) {
+                    return Ok(received);
+                }
+                continue;
+            }
+
+            if !self.is_connected()? {
+                return Ok(received);
+            }
+            if deadline.has_expired() {
+                return Err(NetworkError::ReadTimeout.into());
+            }
+            self.wait_to_poll(&mut backoff, &mut [&mut deadline, &mut total_deadline]);
+        }
+    }
+
+    /// Send all of `data` to the connected server, waiting while the ESP32 target has no room
+    /// to accept more. Fails with [`NetworkError::WriteTimeout`] if no more data could be sent
+    /// within the timeout set by [`TcpClient::set_write_timeout`], or with
+    /// [`NetworkError::SendFailed`] if the ESP32 target couldn't confirm it was sent.
+    pub fn write_all(&mut self, data: &[u8]) -> Result<(), Error> {
+        let socket = self.socket.unwrap_or_default();
+        let mut sent = 0;
+        let mut deadline = self
+            .protocol_handler
+            .deadline(self.write_timeout_ms.map(Duration::millis));
+        let mut backoff = PollBackoff::new(&self.poll_config);
+
+        while sent < data.len() {
+            let end = data
+                .len()
+                .min(sent + MAX_NINA_LARGE_ARRAY_PARAM_BUFFER_LENGTH);
+            let written = self.protocol_handler.send_data(&data[sent..end], socket)?;
+            if written > 0 {
+                sent += written;
+                deadline.restart();
+                backoff.restart();
+                continue;
+            }
+
+            if deadline.has_expired() {
+                return Err(NetworkError::WriteTimeout.into());
+            }
+            self.wait_to_poll(&mut backoff, &mut [&mut deadline]);
+        }
+
+        self.confirm_sent(socket)
+    }
+
+    /// Send `data` to the connected server if the connection is established, or append it to
+    /// `queue` otherwise. Data already waiting in `queue` is sent first, so that everything
+    /// arrives in the order it was written.
+    ///
+    /// If sending fails part way, or the ESP32 target only accepts part of the data, the unsent
+    /// data stays queued. Only a full `queue` is reported as an error.
+    pub fn send_data_or_queue<const N: usize>(
+        &mut self,
+        data: &[u8],
+        queue: &mut OfflineQueue<N>,
+    ) -> Result<(), Error> {
+        let socket = self.socket.unwrap_or_default();
+
+        if let Ok(ConnectionState::Established) = self.protocol_handler.get_client_state_tcp(socket)
+        {
+            let protocol_handler = &mut *self.protocol_handler;
+            let mut send = |chunk: &[u8]| protocol_handler.send_data(chunk, socket);
+
+            if queue.flush(&mut send).is_ok() {
+                if let Ok(sent) = send(data) {
+                    return queue.push(&data[sent.min(data.len())..]);
+                }
+            }
+        }
+
+        queue.push(data)
+    }
+
+    /// Send all data waiting in `queue` to the connected server, e.g. right after a connection
+    /// has been established again.
+    pub fn flush_queue<const N: usize>(
+        &mut self,
+        queue: &mut OfflineQueue<N>,
+    ) -> Result<(), Error> {
+        let socket = self.socket.unwrap_or_default();
+        let protocol_handler = &mut *self.protocol_handler;
+
+        queue.flush(&mut |chunk| protocol_handler.send_data(chunk, socket))
+    }
+
+    /// Register a [`KeepAlive`] whose ping is sent on the connection by [`TcpClient::poll`]
+    /// every time its interval has elapsed. The keep-alive is dropped when the connection is
+    /// closed.
+    pub fn set_keep_alive(&mut self, keep_alive: &'static dyn KeepAlive) -> Result<(), Error> {
+        self.protocol_handler
+            .set_keep_alive(self.socket.unwrap_or_default(), keep_alive)
+    }
+
+    /// Check that the connection is still established every `interval_ms` milliseconds from
+    /// [`TcpClient::poll`], or stop checking with `None` (the default).
+    ///
+    /// This notices a connection the ESP32 target has seen closed or reset (e.g. after a
+    /// failed keep-alive ping) without waiting for the next write to fail.
+    pub fn set_liveness_check(&mut self, interval_ms: Option<u32>) {
+        self.liveness_interval_ms = interval_ms;
+        self.last_liveness_check_ms = None;
+    }
+
+    /// Whether the ESP32 target still considers the connection to the server established.
+    pub fn is_connected(&mut self) -> Result<bool, Error> {
+        let state = self
+            .protocol_handler
+            .get_client_state_tcp(self.socket.unwrap_or_default())?;
+
+        Ok(state == ConnectionState::Established)
+    }
+
+    /// Send any keep-alive pings and run a liveness check if they are due by `now_ms`, the
+    /// current time in milliseconds from an arbitrary starting point. Call this regularly from
+    /// the application's main loop while connected.
+    ///
+    /// Fails with [`NetworkError::ConnectionLost`] once a liveness check finds the connection
+    /// is no longer established.
+    pub fn poll(&mut self, now_ms: u32) -> Result<(), Error> {
+        self.protocol_handler.poll_keep_alives(now_ms)?;
+
+        let due = match (self.liveness_interval_ms, self.last_liveness_check_ms) {
+            (None, _) => false,
+            (Some(interval_ms), Some(last_check_ms)) => {
+                now_ms.wrapping_sub(last_check_ms) >= interval_ms
+            }
+            (Some(_), None) => true,
+        };
+        if !due {
+            return Ok(());
+        }
+        self.last_liveness_check_ms = Some(now_ms);
+
+        if self.is_connected()? {
+            Ok(())
+        } else {
+            Err(NetworkError::ConnectionLost.into())
+        }
+    }
+
+    /// Resolve `hostname`, request a socket and connect to the server on `port` using
+    /// transport layer `mode`, returning once the connection is established. Data can then be
+    /// sent and received until the connection is closed with [`TcpClient::close`].
+    ///
+    /// TLS connections are made by hostname, so that the NINA firmware sends it for SNI and
+    /// verifies it against the server's certificate.
+    pub fn connect_to_host(
+        &mut self,
+        hostname: Hostname,
+        port: Port,
+        mode: TransportMode,
+    ) -> Result<(), Error> {
+        let ip = if mode.is_tls() {
+            None
+        } else {
+            Some(self.protocol_handler.resolve(hostname)?)
+        };
+        self.prepare(ip, hostname, port, mode)?;
+
+        self.start_connection()?;
+        self.wait_until_established()
+    }
+
+    /// Check on a connection started with [`Connect::connect_nb`] without blocking. Once this
+    /// returns [`ConnectProgress::Established`], data can be sent and received until the
+    /// connection is closed with [`TcpClient::close`]. On [`ConnectProgress::Failed`] the socket
+    /// has already been closed.
+    ///
+    /// How long to keep polling a [`ConnectProgress::Pending`] connection is up to the
+    /// application, which should [`TcpClient::close`] it when giving up.
+    pub fn poll_connect(&mut self) -> Result<ConnectProgress, Error> {
+        let socket = self.socket.unwrap_or_default();
+
+        match self.protocol_handler.get_client_state_tcp(socket)? {
+            ConnectionState::Established => Ok(ConnectProgress::Established),
+            ConnectionState::Listening
+            | ConnectionState::SynSent
+            | ConnectionState::SynReceived => Ok(ConnectProgress::Pending),
+            _ => {
+                self.protocol_handler.stop_client_tcp(socket, &self.mode)?;
+                Ok(ConnectProgress::Failed)
+            }
+        }
+    }
+
+    /// Close the connection to the server, e.g. one started with [`Connect::connect_nb`].
+    pub fn close(&mut self) -> Result<(), Error> {
+        self.protocol_handler
+            .stop_client_tcp(self.socket.unwrap_or_default(), &self.mode)
+    }
+
+    // Sleeps for the next backoff interval, charging it to every deadline.
+    fn wait_to_poll(&mut self, backoff: &mut PollBackoff, deadlines: &mut [&mut Deadline]) {
+        let interval_ms = backoff.next_interval_ms();
+        self.protocol_handler.delay_ms(interval_ms);
+        for deadline in deadlines.iter_mut() {
+            deadline.wait(Duration::millis(interval_ms as u32));
+        }
+    }
+
+    // Asks the ESP32 target whether the data written to `socket` was actually sent.
+    fn confirm_sent(&mut self, socket: Socket) -> Result<(), Error> {
+        if self.protocol_handler.check_data_sent(socket)? {
+            Ok(())
+        } else {
+            Err(NetworkError::SendFailed.into())
+        }
+    }
+
+    // Requests a socket and records the server to connect to, which is `ip` if given and
+    // `hostname` otherwise. TLS connections are always made by a given `hostname`.
+    fn prepare(
+        &mut self,
+        ip: Option<Ipv4Addr>,
+        hostname: &str,
+        port: Port,
+        mode: TransportMode,
+    ) -> Result<(), Error> {
+        let socket = self.get_socket()?;
+        self.socket = Some(socket);
+        self.server_ip_address = ip;
+        self.server_hostname = Some(hostname.into()); // into() makes a copy of the &str slice
+        self.port = port;
+        self.mode = mode;
+
+        Ok(())
+    }
+
+    // Asks the ESP32 target to start connecting to the server recorded by prepare().
+    fn start_connection(&mut self) -> Result<(), Error> {
+        let socket = self.socket.unwrap_or_default();
+        let mode = self.mode;
+        let mut ip = self.server_ip_address.unwrap_or(Ipv4Addr::UNSPECIFIED);
+        let hostname = self.server_hostname.as_ref().unwrap();
+        let port = self.port;
+
+        if !hostname.is_empty() && mode.is_tls() {
+            self.protocol_handler.start_client_tcp_by_hostname(
+                socket,
+                hostname.as_str(),
+                port,
+                &mode,
+            )
+        } else {
+            if !hostname.is_empty() && self.server_ip_address.is_none() {
+                ip = self
+                    .protocol_handler
+                    .resolve(hostname.as_str())
+                    .unwrap_or(Ipv4Addr::UNSPECIFIED);
+            }
+
+            self.protocol_handler
+                .start_client_tcp(socket, SocketAddrV4::new(ip, port), &mode)
+        }
+    }
+
+    // Provides the in-common connect() functionality used by the public interface's
+    // connect(ip_address) or connect(hostname) instances.
+    fn connect_common<F: FnMut(&mut TcpClient<'a, T, D>)>(
+        &mut self,
+        mut f: F,
+    ) -> Result<(), Error> {
+        self.start_connection()?;
+        self.wait_until_established()?;
+
+        f(self);
+
+        self.close()
+    }
+
+    // Waits for a connection started by start_connection() to be established, closing it
+    // again if that fails.
+    fn wait_until_established(&mut self) -> Result<(), Error> {
+        let socket = self.socket.unwrap_or_default();
+        let mode = self.mode;
+
+        // FIXME: without this delay, we'll frequently see timing issues and receive
+        // a CmdResponseErr. We may not be handling busy/ack flag handling properly
+        // and needs further investigation. I suspect that the ESP32 isn't ready to
+        // receive another command yet. (copied this from POC)
+        self.protocol_handler.delay_ms(250);
+
+        let mut deadline = self.protocol_handler.deadline(Some(CONNECT_TIMEOUT));
+
+        while !deadline.has_expired() {
+            match self.protocol_handler.get_client_state_tcp(socket) {
+                Ok(ConnectionState::Established) => return Ok(()),
+                Ok(_status) => {
+                    self.protocol_handler.delay_ms(CONNECT_POLL_INTERVAL_MS);
+                    deadline.wait(Duration::millis(CONNECT_POLL_INTERVAL_MS as u32));
+                }
+                Err(error) => {
+                    // At this point any error will likely be a protocol level error.
+                    // We do not currently consider any ConnectionState variants as errors.
+                    self.protocol_handler.stop_client_tcp(socket, &mode)?;
+
+                    return Err(error);
+                }
+            }
+        }
+
+        self.protocol_handler.stop_client_tcp(socket, &mode)?;
+
+        Err(NetworkError::ConnectionTimeout.into())
+    }
+}
@@ -0,0 +1,133 @@
+//! Accept connections from remote TCP clients and associated types.
+//!
+//! ## Usage
+//!
+//! ```no_run
+//! let port: Port = 8080;
+//!
+//! let mut tcp_server = TcpServer::build(&mut wifi);
+//! tcp_server.bind(port).unwrap();
+//!
+//! loop {
+//!     if let Ok(Some(socket)) = tcp_server.accept() {
+//!         defmt::info!("Accepted client on socket: {:?}", socket);
+//!
+//!         let mut buf = [0u8; 64];
+//!         let received = tcp_server.receive_data(socket, &mut buf).unwrap_or_default();
+//!         defmt::info!("Received: {:?}", buf[..received]);
+//!
+//!         tcp_server.close(socket).ok();
+//!     }
+//!
+//!     timer.delay_ms(100);
+//! }
+//! ```
+//!
+
+use core::net::SocketAddrV4;
+
+use embedded_hal::blocking::delay::DelayMs;
+
+use super::network::{ConnectionState, NetworkError, Port, Socket, TransportMode};
+use super::protocol::{NinaProtocolHandler, ProtocolInterface};
+use super::transport::Transport;
+use super::wifi::Wifi;
+use super::Error;
+
+/// A server type that listens on a local port and accepts connections from remote clients
+/// using the TCP protocol.
+///
+/// Every accepted client is handed out as its own `Socket` handle, which is then passed to the
+/// send/receive methods of the server.
+pub struct TcpServer<'a, T, D> {
+    pub(crate) protocol_handler: &'a mut NinaProtocolHandler<T, D>,
+    pub(crate) socket: Option<Socket>,
+    pub(crate) port: Port,
+}
+
+impl<'a, T, D> TcpServer<'a, T, D>
+where
+    T: Transport,
+    D: DelayMs<u16>,
+{
+    /// Build a new instance of a [`TcpServer`] provided a [`Wifi`] instance.
+    pub fn build(wifi: &'a mut Wifi<T, D>) -> Self {
+        Self {
+            protocol_handler: wifi.protocol_handler.get_mut(),
+            socket: None,
+            port: 0,
+        }
+    }
+
+    /// Start listening for incoming client connections on `port`.
+    pub fn bind(&mut self, port: Port) -> Result<(), Error> {
+        let socket = self.protocol_handler.get_socket()?;
+
+        self.protocol_handler
+            .start_server_tcp(socket, port, &TransportMode::Tcp)?;
+
+        self.socket = Some(socket);
+        self.port = port;
+
+        Ok(())
+    }
+
+    /// Get the `Socket` handle the server is listening on that is set by calling
+    /// [`TcpServer::bind`].
+    pub fn socket(&self) -> Option<Socket> {
+        self.socket
+    }
+
+    /// Get the [`Port`] the server is listening on that is set by calling [`TcpServer::bind`].
+    pub fn port(&self) -> Port {
+        self.port
+    }
+
+    /// Poll once for a newly connected client, returning its `Socket` handle if there is one.
+    pub fn accept(&mut self) -> Result<Option<Socket>, Error> {
+        let socket = self.socket.ok_or(NetworkError::ServerNotBound)?;
+
+        self.protocol_handler.avail_server_tcp(socket)
+    }
+
+    /// Get the number of bytes received from the client on `socket` that are ready to be read.
+    pub fn available_data(&mut self, socket: Socket) -> Result<usize, Error> {
+        self.protocol_handler.avail_data_tcp(socket)
+    }
+
+    /// Read data received from the client on `socket` into `buf`, returning the number of
+    /// bytes read.
+    pub fn receive_data(&mut self, socket: Socket, buf: &mut [u8]) -> Result<usize, Error> {
+        self.protocol_handler.get_data_buf_tcp(socket, buf)
+    }
+
+    /// Send a slice of data to the client on `socket`, returning the number of bytes the
+    /// ESP32 target accepted.
+    pub fn send_data(&mut self, socket: Socket, data: &[u8]) -> Result<usize, Error> {
+        self.protocol_handler.send_data(data, socket)
+    }
+
+    /// Get the [`ConnectionState`] of the client on `socket`.
+    pub fn connection_state(&mut self, socket: Socket) -> Result<ConnectionState, Error> {
+        self.protocol_handler.get_client_state_tcp(socket)
+    }
+
+    /// Get the address and port of the client on `socket`, e.g. for logging.
+    pub fn remote_address(&mut self, socket: Socket) -> Result<SocketAddrV4, Error> {
+        self.protocol_handler.get_remote_data(socket)
+    }
+
+    /// Close the connection to the client on `socket`.
+    pub fn close(&mut self, socket: Socket) -> Result<(), Error> {
+        self.protocol_handler
+            .stop_client_tcp(socket, &TransportMode::Tcp)
+    }
+
+    /// Stop listening for incoming client connections.
+    pub fn stop(&mut self) -> Result<(), Error> {
+        match self.socket.take() {
+            Some(socket) => self.close(socket),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,120 @@
+//! Send/receive data to/from a server over a TLS encrypted connection.
+//!
+//! The TLS session is terminated by the NINA firmware on the ESP32 target, which validates the
+//! server's certificate against the root CA certificates it has been provisioned with. Data is
+//! therefore sent and received in the clear over the SPI bus. The firmware has no way to skip
+//! that verification or to pin a server certificate by its fingerprint instead.
+//!
+//! Endpoints that require mutual TLS (e.g. AWS IoT or private MQTT brokers) additionally need
+//! a client certificate and private key installed on the ESP32 target with
+//! [`Wifi::set_client_certificate`] and [`Wifi::set_private_key`]. Once both are installed,
+//! the NINA firmware presents them for every following TLS connection.
+//!
+//! ## Usage
+//!
+//! ```no_run
+//! let hostname = "github.com";
+//! let port: Port = 443;
+//!
+//! if let Err(e) = TlsClient::build(&mut wifi).connect(
+//!     hostname,
+//!     port,
+//!     &mut |tcp_client| {
+//!         defmt::info!("TLS connection to {:?}:{:?} successful", hostname, port);
+//!         tcp_client.send_str(&http_document).ok();
+//!     },
+//! ) {
+//!     defmt::error!("TLS connection to {:?}:{:?} failed: {:?}", hostname, port, e);
+//! }
+//! ```
+//!
+
+use embedded_hal::blocking::delay::DelayMs;
+
+use super::credentials::{clear_credential, CredentialProvider};
+use super::network::{Hostname, NetworkError, Port, TransportMode};
+use super::protocol::ProtocolInterface;
+use super::tcp_client::{Connect, TcpClient};
+use super::transport::Transport;
+use super::wifi::Wifi;
+use super::Error;
+
+/// The maximum length in bytes of a PEM encoded client certificate the NINA firmware accepts.
+pub const MAX_CLIENT_CERTIFICATE_LENGTH: usize = 1300;
+
+/// The maximum length in bytes of a PEM encoded private key the NINA firmware accepts.
+pub const MAX_PRIVATE_KEY_LENGTH: usize = 1700;
+
+/// A client type that connects to a remote server using TLS on top of the TCP protocol.
+///
+/// A hostname is always required since the NINA firmware uses it for SNI and to verify the
+/// server's certificate.
+pub struct TlsClient<'a, T, D> {
+    pub(crate) tcp_client: TcpClient<'a, T, D>,
+}
+
+impl<'a, T, D> TlsClient<'a, T, D>
+where
+    T: Transport,
+    D: DelayMs<u16>,
+{
+    /// Build a new instance of a [`TlsClient`] provided a [`Wifi`] instance.
+    pub fn build(wifi: &'a mut Wifi<T, D>) -> Self {
+        Self {
+            tcp_client: TcpClient::build(wifi),
+        }
+    }
+
+    /// Connect to `server_hostname` on `port` using TLS, invoking `f` with the connected
+    /// [`TcpClient`] to send/receive data before the connection is closed again.
+    pub fn connect<F: FnMut(&mut TcpClient<'a, T, D>)>(
+        &mut self,
+        server_hostname: Hostname,
+        port: Port,
+        f: &mut F,
+    ) -> Result<(), Error> {
+        self.tcp_client
+            .connect(server_hostname, port, TransportMode::Tls, f)
+    }
+
+    /// Connect to `server_hostname` on `port` using TLS like [`TlsClient::connect`], presenting
+    /// the client certificate `provider` supplies.
+    ///
+    /// The certificate is fetched right before connecting and cleared from memory once it has
+    /// been installed on the ESP32 target. Its private key has to be installed with
+    /// [`Wifi::set_private_key`] beforehand. A provider reporting more bytes than fit into the
+    /// buffer it was given fails with [`NetworkError::CredentialsUnavailable`].
+    pub fn connect_with_credentials<P, F>(
+        &mut self,
+        server_hostname: Hostname,
+        port: Port,
+        provider: &mut P,
+        f: &mut F,
+    ) -> Result<(), Error>
+    where
+        P: CredentialProvider,
+        F: FnMut(&mut TcpClient<'a, T, D>),
+    {
+        let mut certificate = [0u8; MAX_CLIENT_CERTIFICATE_LENGTH];
+
+        let result =
+            provider
+                .client_certificate(&mut certificate)
+                .and_then(|length| match length {
+                    Some(length) => {
+                        let certificate = certificate
+                            .get(..length)
+                            .ok_or(Error::Network(NetworkError::CredentialsUnavailable))?;
+                        self.tcp_client
+                            .protocol_handler
+                            .set_client_cert(certificate)
+                    }
+                    None => Ok(()),
+                });
+
+        clear_credential(&mut certificate);
+        result?;
+
+        self.connect(server_hostname, port, f)
+    }
+}
@@ -0,0 +1,452 @@
+//! The byte transport that carries NINA protocol commands to and replies from the ESP32 target.
+//!
+//! The protocol itself, i.e. which bytes make up a command and how its reply is parsed, doesn't
+//! depend on how those bytes travel. Everything that does, the data bus and the control lines
+//! that pace it, sits behind the [`Transport`] trait, so another kind of link to the ESP32
+//! target (e.g. UART or SDIO) only needs a new implementation of it.
+//!
+//! [`SpiTransport`] is the transport of all NINA firmware-based boards supported so far: an SPI
+//! bus and the [`EspControlInterface`] pins that go along with it. [`Wifi::init`] builds one,
+//! while [`Wifi::init_with_transport`] accepts any [`Transport`].
+//!
+//! ## Usage
+//!
+//! ```no_run
+//! use esp32_wroom_core::transport::SpiTransport;
+//! use esp32_wroom_core::wifi::Wifi;
+//!
+//! // The same as Wifi::init(spi, esp_pins, delay)
+//! let transport = SpiTransport::new(spi, esp_pins);
+//! let mut wifi = Wifi::init_with_transport(transport, delay).unwrap();
+//! ```
+//!
+//! ## SPI clock
+//!
+//! The NINA firmware receives over the ESP32's SPI slave peripheral, which keeps up with a bus
+//! clocked at up to [`MAX_SPI_FREQUENCY_HZ`] (10 MHz). Arduino and Adafruit clock it at 8 MHz,
+//! the [`DEFAULT_SPI_FREQUENCY_HZ`], which leaves headroom for the jumper wires and long traces
+//! of breakout boards. Any lower frequency works too, just more slowly.
+//!
+//! The driver doesn't clock the bus itself, the HAL does when its SPI peripheral is set up. To
+//! have the driver check that the frequency it was set up with actually works, pass it in a
+//! [`SpiConfig`] to [`Wifi::init_with_spi_config`]. A bus clocked too fast garbles the replies
+//! of the NINA firmware, which is then reported as [`SpiClockError::Unreliable`] instead of
+//! surfacing later as seemingly random [`CommunicationTimeout`]s.
+//! [`Wifi::init_negotiating_spi_clock`] goes one step further and lowers the frequency until
+//! the check passes.
+//!
+//! ```no_run
+//! use esp32_wroom_core::transport::SpiConfig;
+//! use esp32_wroom_core::wifi::Wifi;
+//!
+//! let config = SpiConfig::new().frequency_hz(10_000_000);
+//! let spi = hal::Spi::<_, _, 8>::new(pac.SPI0).init(
+//!     &mut pac.RESETS,
+//!     clocks.peripheral_clock.freq(),
+//!     config.frequency_hz.Hz(),
+//!     &MODE_0,
+//! );
+//!
+//! let mut wifi = Wifi::init_with_spi_config(spi, esp_pins, delay, config).unwrap();
+//! ```
+//!
+//! [`Wifi::init`]: crate::wifi::Wifi::init
+//! [`Wifi::init_with_transport`]: crate::wifi::Wifi::init_with_transport
+//! [`Wifi::init_with_spi_config`]: crate::wifi::Wifi::init_with_spi_config
+//! [`Wifi::init_negotiating_spi_clock`]: crate::wifi::Wifi::init_negotiating_spi_clock
+//! [`CommunicationTimeout`]: crate::protocol::ProtocolError::CommunicationTimeout
+
+use core::hint;
+
+#[cfg(feature = "defmt")]
+use defmt::{write, Format, Formatter};
+
+use embedded_hal::blocking::delay::DelayMs;
+use embedded_hal::blocking::spi::Transfer;
+
+use super::gpio::EspControlInterface;
+use super::Error;
+
+/// The SPI clock frequency the NINA firmware is driven with by default, in Hz.
+pub const DEFAULT_SPI_FREQUENCY_HZ: u32 = 8_000_000;
+
+/// The fastest SPI clock frequency the NINA firmware keeps up with, in Hz.
+pub const MAX_SPI_FREQUENCY_HZ: u32 = 10_000_000;
+
+/// The lowest SPI clock frequency [`Wifi::init_negotiating_spi_clock`] steps down to by default,
+/// in Hz.
+///
+/// [`Wifi::init_negotiating_spi_clock`]: crate::wifi::Wifi::init_negotiating_spi_clock
+pub const DEFAULT_MIN_SPI_FREQUENCY_HZ: u32 = 1_000_000;
+
+// Sent while reading, since every byte read over SPI means writing one
+const DUMMY_BYTE: u8 = 0xFF;
+
+// The most bytes SpiTransport moves in a single transfer. Longer writes and reads are split
+// into transfers of this length, which keeps the buffer they're staged in small enough for the
+// stack while making the per-transfer overhead of the HAL negligible.
+pub(crate) const MAX_TRANSFER_LENGTH: usize = 64;
+
+/// Moves the bytes of NINA protocol commands and replies between the driver and the ESP32
+/// target, and paces them the way the link to the target requires.
+///
+/// A command is exchanged by waiting for [`is_ready`](Transport::is_ready), calling
+/// [`select`](Transport::select), waiting for [`is_acknowledged`](Transport::is_acknowledged),
+/// writing the command with [`write`](Transport::write), finishing it with
+/// [`end_command`](Transport::end_command) and calling [`deselect`](Transport::deselect). The
+/// reply is read in between another select and deselect.
+pub trait Transport {
+    /// Puts the link into its idle state, ready for the first command.
+    fn init(&mut self);
+
+    /// Resets the ESP32 target so that the NINA firmware starts over, using `delay` to time it.
+    fn reset<D: DelayMs<u16>>(&mut self, delay: &mut D);
+
+    /// Holds the ESP32 target in reset until the next [`reset`](Transport::reset).
+    fn hold_in_reset(&mut self);
+
+    /// Is the NINA firmware ready to exchange a command or reply?
+    fn is_ready(&self) -> bool;
+
+    /// Tells the NINA firmware a command is about to be sent or a reply read.
+    fn select(&mut self);
+
+    /// Has the NINA firmware acknowledged being selected?
+    fn is_acknowledged(&self) -> bool;
+
+    /// Tells the NINA firmware the command has been sent or the reply read.
+    fn deselect(&mut self);
+
+    /// Sends `bytes` to the NINA firmware.
+    fn write(&mut self, bytes: &[u8]);
+
+    /// Receives as many bytes from the NINA firmware as fit into `buffer`.
+    fn read(&mut self, buffer: &mut [u8]);
+
+    /// Completes a command of `command_length` bytes whose last byte has been written, e.g. by
+    /// padding it to the frame size the link requires.
+    fn end_command(&mut self, _command_length: u16) {}
+
+    /// Receives a single byte from the NINA firmware.
+    fn read_byte(&mut self) -> u8 {
+        let mut byte = [0u8];
+        self.read(&mut byte);
+        byte[0]
+    }
+
+    /// Blocking waits for the NINA firmware to be ready, selects it and blocking waits for it to
+    /// acknowledge that.
+    fn wait_for_select(&mut self) {
+        while !self.is_ready() {
+            hint::spin_loop(); // Make sure rustc doesn't optimize this loop out
+        }
+        self.select();
+        while !self.is_acknowledged() {
+            hint::spin_loop();
+        }
+    }
+}
+
+/// Errors of an SPI bus clocked at a frequency the NINA firmware can't keep up with.
+#[derive(Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "ufmt", derive(ufmt::derive::uDebug))]
+#[non_exhaustive]
+pub enum SpiClockError {
+    /// The configured frequency is above [`MAX_SPI_FREQUENCY_HZ`]
+    AboveMaximum,
+    /// The replies of the NINA firmware were garbled or missing at every frequency tried, as
+    /// they are when the bus is clocked too fast for its wiring
+    Unreliable,
+}
+
+#[cfg(feature = "defmt")]
+impl Format for SpiClockError {
+    fn format(&self, fmt: Formatter) {
+        match self {
+            SpiClockError::AboveMaximum => write!(
+                fmt,
+                "The SPI clock frequency is above the maximum the NINA firmware supports."
+            ),
+            SpiClockError::Unreliable => write!(
+                fmt,
+                "The NINA firmware's replies were garbled, the SPI bus is likely clocked too fast."
+            ),
+        }
+    }
+}
+
+impl From<SpiClockError> for Error {
+    fn from(err: SpiClockError) -> Self {
+        Error::SpiClock(err)
+    }
+}
+
+/// The clock of the SPI bus the ESP32 target is connected to, checked by
+/// [`Wifi::init_with_spi_config`] and [`Wifi::init_negotiating_spi_clock`].
+///
+/// [`Wifi::init_with_spi_config`]: crate::wifi::Wifi::init_with_spi_config
+/// [`Wifi::init_negotiating_spi_clock`]: crate::wifi::Wifi::init_negotiating_spi_clock
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "ufmt", derive(ufmt::derive::uDebug))]
+#[non_exhaustive]
+pub struct SpiConfig {
+    /// The frequency the bus is clocked at, in Hz, [`DEFAULT_SPI_FREQUENCY_HZ`] by default.
+    pub frequency_hz: u32,
+    /// The lowest frequency negotiating the clock steps down to, in Hz,
+    /// [`DEFAULT_MIN_SPI_FREQUENCY_HZ`] by default.
+    pub min_frequency_hz: u32,
+}
+
+impl SpiConfig {
+    /// Create a new [`SpiConfig`] with the default frequencies.
+    pub fn new() -> Self {
+        Self {
+            frequency_hz: DEFAULT_SPI_FREQUENCY_HZ,
+            min_frequency_hz: DEFAULT_MIN_SPI_FREQUENCY_HZ,
+        }
+    }
+
+    /// Clock the bus at `frequency_hz`.
+    pub fn frequency_hz(mut self, frequency_hz: u32) -> Self {
+        self.frequency_hz = frequency_hz;
+        self
+    }
+
+    /// Don't step the clock down below `min_frequency_hz` while negotiating it.
+    pub fn min_frequency_hz(mut self, min_frequency_hz: u32) -> Self {
+        self.min_frequency_hz = min_frequency_hz;
+        self
+    }
+
+    // Fails with SpiClockError::AboveMaximum if the bus is configured to be clocked faster than
+    // the NINA firmware keeps up with.
+    pub(crate) fn validate(&self) -> Result<(), Error> {
+        if self.frequency_hz > MAX_SPI_FREQUENCY_HZ {
+            return Err(SpiClockError::AboveMaximum.into());
+        }
+        Ok(())
+    }
+
+    // The frequency to try after the check failed at `frequency_hz`: half of it, but no lower
+    // than the minimum. None once the minimum has been tried.
+    pub(crate) fn next_frequency_hz(&self, frequency_hz: u32) -> Option<u32> {
+        if frequency_hz <= self.min_frequency_hz {
+            None
+        } else {
+            Some((frequency_hz / 2).max(self.min_frequency_hz))
+        }
+    }
+}
+
+impl Default for SpiConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl Format for SpiConfig {
+    fn format(&self, fmt: Formatter) {
+        write!(
+            fmt,
+            "frequency_hz: {=u32}, min_frequency_hz: {=u32}",
+            self.frequency_hz, self.min_frequency_hz
+        )
+    }
+}
+
+/// The [`Transport`] of NINA firmware spoken over an SPI bus, with chip select and ACK lines
+/// pacing it.
+///
+/// Commands sent over SPI are padded to a multiple of 4 bytes, as the NINA firmware expects.
+/// Writes and reads are performed in as few `transfer()` calls as possible, each moving up to
+/// 64 bytes.
+#[derive(Debug)]
+pub struct SpiTransport<S, C> {
+    pub(crate) bus: S,
+    pub(crate) control_pins: C,
+}
+
+impl<S, C> SpiTransport<S, C>
+where
+    S: Transfer<u8>,
+    C: EspControlInterface,
+{
+    /// Combines the SPI `bus` and the `control_pins` the ESP32 target is wired to.
+    pub fn new(bus: S, control_pins: C) -> Self {
+        Self { bus, control_pins }
+    }
+
+    /// Resets the ESP32 target into its serial bootloader and hands the bus and control pins
+    /// back.
+    pub fn into_bootloader<D: DelayMs<u16>>(mut self, delay: &mut D) -> (S, C) {
+        self.control_pins.enter_bootloader(delay);
+        (self.bus, self.control_pins)
+    }
+
+    /// Hands the bus and control pins back.
+    pub fn release(self) -> (S, C) {
+        (self.bus, self.control_pins)
+    }
+}
+
+impl<S, C> Transport for SpiTransport<S, C>
+where
+    S: Transfer<u8>,
+    C: EspControlInterface,
+{
+    fn init(&mut self) {
+        // Chip select is active-low, so we'll initialize it to a driven-high state
+        self.control_pins.init();
+    }
+
+    fn reset<D: DelayMs<u16>>(&mut self, delay: &mut D) {
+        self.control_pins.reset(delay);
+    }
+
+    fn hold_in_reset(&mut self) {
+        self.control_pins.hold_in_reset();
+    }
+
+    fn is_ready(&self) -> bool {
+        self.control_pins.get_esp_ready()
+    }
+
+    fn select(&mut self) {
+        self.control_pins.esp_select();
+    }
+
+    fn is_acknowledged(&self) -> bool {
+        self.control_pins.get_esp_ack()
+    }
+
+    fn deselect(&mut self) {
+        self.control_pins.esp_deselect();
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        let mut words = [0u8; MAX_TRANSFER_LENGTH];
+        for chunk in bytes.chunks(MAX_TRANSFER_LENGTH) {
+            let words = &mut words[..chunk.len()];
+            words.copy_from_slice(chunk);
+            self.bus.transfer(words).ok();
+        }
+    }
+
+    fn read(&mut self, buffer: &mut [u8]) {
+        let mut words = [0u8; MAX_TRANSFER_LENGTH];
+        for chunk in buffer.chunks_mut(MAX_TRANSFER_LENGTH) {
+            let words = &mut words[..chunk.len()];
+            words.fill(DUMMY_BYTE);
+            if let Ok(received) = self.bus.transfer(words) {
+                let length = received.len().min(chunk.len());
+                chunk[..length].copy_from_slice(&received[..length]);
+            }
+        }
+    }
+
+    fn end_command(&mut self, command_length: u16) {
+        let padding_length = (4 - command_length % 4) % 4;
+        let mut padding = [0u8; 3];
+        self.read(&mut padding[..padding_length as usize]);
+    }
+
+    fn wait_for_select(&mut self) {
+        self.control_pins.wait_for_esp_select();
+    }
+}
+
+#[cfg(test)]
+mod transport_tests {
+    use super::{
+        SpiClockError, SpiConfig, SpiTransport, Transport, MAX_SPI_FREQUENCY_HZ,
+        MAX_TRANSFER_LENGTH,
+    };
+    use crate::gpio::EspControlPins;
+    use crate::Error;
+    use embedded_hal_mock::pin::Mock as PinMock;
+    use embedded_hal_mock::spi::{Mock as SpiMock, Transaction as SpiTransaction};
+
+    fn transport(
+        expectations: &[SpiTransaction],
+    ) -> SpiTransport<SpiMock, EspControlPins<PinMock, PinMock, PinMock, PinMock>> {
+        SpiTransport::new(
+            SpiMock::new(expectations),
+            EspControlPins {
+                cs: PinMock::new(&[]),
+                gpio0: PinMock::new(&[]),
+                resetn: PinMock::new(&[]),
+                ack: PinMock::new(&[]),
+            },
+        )
+    }
+
+    #[test]
+    fn spi_transport_end_command_pads_to_a_multiple_of_4_in_a_single_transfer() {
+        let expectations = [SpiTransaction::transfer(
+            vec![0xFF, 0xFF, 0xFF],
+            vec![0x00, 0x00, 0x00],
+        )];
+        let mut transport = transport(&expectations);
+
+        transport.end_command(5);
+        transport.end_command(8);
+
+        transport.bus.done();
+    }
+
+    #[test]
+    fn spi_transport_read_clocks_out_dummy_bytes_in_a_single_transfer() {
+        let expectations = [SpiTransaction::transfer(vec![0xFF, 0xFF], vec![0x12, 0x34])];
+        let mut transport = transport(&expectations);
+
+        let mut buffer = [0u8; 2];
+        transport.read(&mut buffer);
+
+        assert_eq!(buffer, [0x12, 0x34]);
+        transport.bus.done();
+    }
+
+    #[test]
+    fn spi_transport_splits_long_writes_into_transfers_of_the_maximum_length() {
+        let bytes: Vec<u8> = (0..MAX_TRANSFER_LENGTH as u8 + 6).collect();
+        let expectations = [
+            SpiTransaction::transfer(
+                bytes[..MAX_TRANSFER_LENGTH].to_vec(),
+                vec![0x00; MAX_TRANSFER_LENGTH],
+            ),
+            SpiTransaction::transfer(bytes[MAX_TRANSFER_LENGTH..].to_vec(), vec![0x00; 6]),
+        ];
+        let mut transport = transport(&expectations);
+
+        transport.write(&bytes);
+
+        transport.bus.done();
+    }
+
+    #[test]
+    fn spi_config_rejects_frequencies_above_the_maximum() {
+        assert_eq!(SpiConfig::new().validate(), Ok(()));
+        assert_eq!(
+            SpiConfig::new()
+                .frequency_hz(MAX_SPI_FREQUENCY_HZ)
+                .validate(),
+            Ok(())
+        );
+        assert_eq!(
+            SpiConfig::new()
+                .frequency_hz(MAX_SPI_FREQUENCY_HZ + 1)
+                .validate(),
+            Err(Error::SpiClock(SpiClockError::AboveMaximum))
+        );
+    }
+
+    #[test]
+    fn spi_config_halves_the_frequency_down_to_the_minimum() {
+        let config = SpiConfig::new().min_frequency_hz(3_000_000);
+
+        assert_eq!(config.next_frequency_hz(8_000_000), Some(4_000_000));
+        assert_eq!(config.next_frequency_hz(4_000_000), Some(3_000_000));
+        assert_eq!(config.next_frequency_hz(3_000_000), None);
+    }
+}
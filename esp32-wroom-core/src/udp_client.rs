@@ -0,0 +1,163 @@
+//! Send datagrams to a remote UDP server and associated types.
+//!
+//! ## Usage
+//!
+//! ```no_run
+//! let syslog_server = SocketAddrV4::new(Ipv4Addr::new(192, 168, 1, 10), 514);
+//!
+//! let mut udp_client = UdpClient::build(&mut wifi);
+//!
+//! if let Err(e) = udp_client.send_to(syslog_server, b"<14>rp2040: hello") {
+//!     defmt::error!("Sending UDP datagram failed: {:?}", e);
+//! }
+//!
+//! udp_client.close().ok();
+//! ```
+//!
+
+use core::net::SocketAddrV4;
+
+use embedded_hal::blocking::delay::DelayMs;
+
+use super::clock::Duration;
+use super::network::{Port, Socket, TransportMode, BROADCAST_ADDRESS};
+use super::protocol::{NinaProtocolHandler, ProtocolInterface};
+use super::transport::Transport;
+use super::wifi::Wifi;
+use super::Error;
+
+// Sockets that aren't bound to a port by the application receive on this port plus their
+// socket number
+#[cfg(any(feature = "embedded-nal", feature = "async"))]
+pub(crate) const UDP_EPHEMERAL_PORT_BASE: Port = 49200;
+
+/// A client type that sends datagrams to remote servers using the UDP protocol.
+///
+/// A socket is requested from the ESP32 target on the first call to [`UdpClient::send_to`] and
+/// reused for every datagram after that until [`UdpClient::close`] is called.
+pub struct UdpClient<'a, T, D> {
+    pub(crate) protocol_handler: &'a mut NinaProtocolHandler<T, D>,
+    pub(crate) socket: Option<Socket>,
+}
+
+impl<'a, T, D> UdpClient<'a, T, D>
+where
+    T: Transport,
+    D: DelayMs<u16>,
+{
+    /// Build a new instance of a [`UdpClient`] provided a [`Wifi`] instance.
+    pub fn build(wifi: &'a mut Wifi<T, D>) -> Self {
+        Self {
+            protocol_handler: wifi.protocol_handler.get_mut(),
+            socket: None,
+        }
+    }
+
+    /// Get the `Socket` handle used for sending datagrams, if one has been requested yet.
+    pub fn socket(&self) -> Option<Socket> {
+        self.socket
+    }
+
+    /// Send `data` as a single datagram to the remote server at `remote`.
+    pub fn send_to(&mut self, remote: SocketAddrV4, data: &[u8]) -> Result<(), Error> {
+        let socket = match self.socket {
+            Some(socket) => socket,
+            None => {
+                let socket = self.protocol_handler.get_socket()?;
+                self.socket = Some(socket);
+                socket
+            }
+        };
+
+        send_datagram(self.protocol_handler, socket, remote, data)
+    }
+
+    /// Send `data` as a single broadcast datagram to `port` on every host of the local network,
+    /// e.g. as a device discovery beacon.
+    ///
+    /// This uses the limited broadcast address [`BROADCAST_ADDRESS`]. To broadcast to the
+    /// local subnet only, pass the address from [`IpConfig::broadcast_address`] of
+    /// [`Wifi::get_ip_config`] to [`UdpClient::send_to`] instead. Either way no additional mode
+    /// needs to be set on the ESP32 target.
+    ///
+    /// [`IpConfig::broadcast_address`]: crate::network::IpConfig::broadcast_address
+    /// [`Wifi::get_ip_config`]: crate::wifi::Wifi::get_ip_config
+    pub fn broadcast(&mut self, port: Port, data: &[u8]) -> Result<(), Error> {
+        self.send_to(SocketAddrV4::new(BROADCAST_ADDRESS, port), data)
+    }
+
+    /// Release the socket used for sending datagrams back to the ESP32 target.
+    pub fn close(&mut self) -> Result<(), Error> {
+        match self.socket.take() {
+            Some(socket) => self
+                .protocol_handler
+                .stop_client_tcp(socket, &TransportMode::Udp),
+            None => Ok(()),
+        }
+    }
+}
+
+// Sends `data` as a single datagram from `socket` to `remote`. The socket may also be bound
+// to a local port by a UdpServer.
+pub(crate) fn send_datagram<T, D>(
+    protocol_handler: &mut NinaProtocolHandler<T, D>,
+    socket: Socket,
+    remote: SocketAddrV4,
+    data: &[u8],
+) -> Result<(), Error>
+where
+    T: Transport,
+    D: DelayMs<u16>,
+{
+    protocol_handler.start_client_tcp(socket, remote, &TransportMode::Udp)?;
+    protocol_handler.insert_data_buf(data, socket)?;
+    protocol_handler.send_udp_data(socket)
+}
+
+// How often exchange_datagram() checks whether the response has arrived
+const EXCHANGE_POLL_INTERVAL_MS: u16 = 50;
+
+// Sends `request` to `server` from a temporary UDP socket bound to
+// `local_port` and copies the first datagram received in return within `timeout_ms` into
+// `response`, returning its length or `None` if nothing arrived in time. The socket is always
+// released again before returning.
+pub(crate) fn exchange_datagram<T, D>(
+    protocol_handler: &mut NinaProtocolHandler<T, D>,
+    local_port: Port,
+    server: SocketAddrV4,
+    request: &[u8],
+    response: &mut [u8],
+    timeout_ms: u16,
+) -> Result<Option<usize>, Error>
+where
+    T: Transport,
+    D: DelayMs<u16>,
+{
+    let socket = protocol_handler.get_socket()?;
+    protocol_handler.start_server_tcp(socket, local_port, &TransportMode::Udp)?;
+
+    let mut receive = || -> Result<Option<usize>, Error> {
+        send_datagram(protocol_handler, socket, server, request)?;
+
+        let mut deadline = protocol_handler.deadline(Some(Duration::millis(timeout_ms as u32)));
+
+        while !deadline.has_expired() {
+            let available = protocol_handler.avail_data_tcp(socket)?;
+            if available > 0 {
+                let length = available.min(response.len());
+                return protocol_handler
+                    .get_data_buf_tcp(socket, &mut response[..length])
+                    .map(Some);
+            }
+            protocol_handler.delay_ms(EXCHANGE_POLL_INTERVAL_MS);
+            deadline.wait(Duration::millis(EXCHANGE_POLL_INTERVAL_MS as u32));
+        }
+
+        Ok(None)
+    };
+    let result = receive();
+
+    protocol_handler.stop_client_tcp(socket, &TransportMode::Udp)?;
+
+    result
+}
@@ -0,0 +1,153 @@
+//! Receive datagrams on a local UDP port and associated types.
+//!
+//! ## Usage
+//!
+//! ```no_run
+//! let port: Port = 5353;
+//!
+//! let mut udp_server = UdpServer::build(&mut wifi);
+//! udp_server.bind(port).unwrap();
+//!
+//! let mut buf = [0u8; 512];
+//!
+//! loop {
+//!     if let Ok(Some(datagram)) = udp_server.recv_from(&mut buf) {
+//!         defmt::info!("Received {:?} from {}", buf[..datagram.length], datagram.sender);
+//!
+//!         // Reply straight back to the sender
+//!         udp_server.send_to(datagram.sender, b"pong").ok();
+//!     }
+//!
+//!     timer.delay_ms(100);
+//! }
+//! ```
+//!
+
+use core::net::SocketAddrV4;
+
+#[cfg(feature = "defmt")]
+use defmt::{write, Format, Formatter};
+
+use embedded_hal::blocking::delay::DelayMs;
+
+use super::network::{NetworkError, Port, Socket, TransportMode};
+use super::protocol::{NinaProtocolHandler, ProtocolInterface};
+use super::transport::Transport;
+use super::udp_client::send_datagram;
+use super::wifi::Wifi;
+use super::Error;
+
+/// Describes a datagram received by [`UdpServer::recv_from`].
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub struct Datagram {
+    /// Number of payload bytes that were copied into the receive buffer.
+    pub length: usize,
+    /// Address and port the sender sent the datagram from.
+    pub sender: SocketAddrV4,
+}
+
+#[cfg(feature = "defmt")]
+impl Format for Datagram {
+    fn format(&self, fmt: Formatter) {
+        write!(fmt, "length: {:?}, sender: {}", self.length, self.sender);
+    }
+}
+
+#[cfg(feature = "ufmt")]
+impl ufmt::uDebug for Datagram {
+    fn fmt<W: ufmt::uWrite + ?Sized>(
+        &self,
+        f: &mut ufmt::Formatter<'_, W>,
+    ) -> Result<(), W::Error> {
+        ufmt::uwrite!(f, "Datagram {{ length: {}, sender: ", self.length)?;
+        super::network::uwrite_ipv4(f, *self.sender.ip())?;
+        ufmt::uwrite!(f, ":{} }}", self.sender.port())
+    }
+}
+
+/// A server type that is bound to a local port and receives datagrams from remote peers
+/// using the UDP protocol.
+pub struct UdpServer<'a, T, D> {
+    pub(crate) protocol_handler: &'a mut NinaProtocolHandler<T, D>,
+    pub(crate) socket: Option<Socket>,
+    pub(crate) port: Port,
+}
+
+impl<'a, T, D> UdpServer<'a, T, D>
+where
+    T: Transport,
+    D: DelayMs<u16>,
+{
+    /// Build a new instance of a [`UdpServer`] provided a [`Wifi`] instance.
+    pub fn build(wifi: &'a mut Wifi<T, D>) -> Self {
+        Self {
+            protocol_handler: wifi.protocol_handler.get_mut(),
+            socket: None,
+            port: 0,
+        }
+    }
+
+    /// Start receiving datagrams sent to the local `port`.
+    pub fn bind(&mut self, port: Port) -> Result<(), Error> {
+        let socket = self.protocol_handler.get_socket()?;
+
+        self.protocol_handler
+            .start_server_tcp(socket, port, &TransportMode::Udp)?;
+
+        self.socket = Some(socket);
+        self.port = port;
+
+        Ok(())
+    }
+
+    /// Get the `Socket` handle the server is bound to that is set by calling
+    /// [`UdpServer::bind`].
+    pub fn socket(&self) -> Option<Socket> {
+        self.socket
+    }
+
+    /// Get the local [`Port`] the server is bound to that is set by calling [`UdpServer::bind`].
+    pub fn port(&self) -> Port {
+        self.port
+    }
+
+    /// Poll once for a received datagram, copying its payload into `buf` and returning the
+    /// [`Datagram`] details if there is one.
+    ///
+    /// `buf` should be large enough to hold a whole datagram. Any bytes of a datagram that don't
+    /// fit are returned by the next call.
+    pub fn recv_from(&mut self, buf: &mut [u8]) -> Result<Option<Datagram>, Error> {
+        let socket = self.socket.ok_or(NetworkError::ServerNotBound)?;
+
+        let available = self.protocol_handler.avail_data_tcp(socket)?;
+        if available == 0 {
+            return Ok(None);
+        }
+
+        let length = available.min(buf.len());
+        let length = self
+            .protocol_handler
+            .get_data_buf_tcp(socket, &mut buf[..length])?;
+        let sender = self.protocol_handler.get_remote_data(socket)?;
+
+        Ok(Some(Datagram { length, sender }))
+    }
+
+    /// Send `data` as a single datagram from the bound local port to the remote peer at
+    /// `remote`, such as to the sender of a received [`Datagram`].
+    pub fn send_to(&mut self, remote: SocketAddrV4, data: &[u8]) -> Result<(), Error> {
+        let socket = self.socket.ok_or(NetworkError::ServerNotBound)?;
+
+        send_datagram(self.protocol_handler, socket, remote, data)
+    }
+
+    /// Stop receiving datagrams and release the bound socket back to the ESP32 target.
+    pub fn stop(&mut self) -> Result<(), Error> {
+        match self.socket.take() {
+            Some(socket) => self
+                .protocol_handler
+                .stop_client_tcp(socket, &TransportMode::Udp),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,1220 @@
+//! Perform common core WiFi functions such as join a network, resolve a DNS hostname, etc.
+//!
+//! ## Usage
+//!
+//! ```no_run
+//! let mut wifi = Wifi::init(spi, esp_pins, delay).unwrap();
+//!
+//! let result = wifi.join(ssid, passphrase);
+//! defmt::info!("Join Result: {:?}", result);
+//!
+//! defmt::info!("Entering main loop");
+//!
+//! let mut sleep: u32 = 1500;
+//! loop {
+//!     match wifi.get_connection_status() {
+//!         Ok(status) => {
+//!             defmt::info!("Connection status: {:?}", status);
+//!             timer.delay_ms(sleep);
+//!
+//!             if status == ConnectionStatus::Connected {
+//!                 defmt::info!("Connected to network: {:?}", SSID);
+//!
+//!                 // The IPAddresses of two DNS servers to resolve hostnames with.
+//!                 // Note that failover from ip1 to ip2 is fully functional.
+//!                 let ip1 = Ipv4Addr::new(9, 9, 9, 9);
+//!                 let ip2 = Ipv4Addr::new(8, 8, 8, 8);
+//!                 let dns_result = wifi.set_dns(ip1, Some(ip2));
+//!
+//!                 defmt::info!("set_dns result: {:?}", dns_result);
+//!
+//!                 match wifi.resolve(hostname) {
+//!                     Ok(ip) => {
+//!                         defmt::info!("Server IP: {:?}", ip);
+//!                     }
+//!                     Err(e) => {
+//!                         defmt::error!("Failed to resolve hostname {}", hostname);
+//!                         defmt::error!("Err: {}", e);
+//!                     }
+//!                 }
+//!
+//!                 defmt::info!("Leaving network: {:?}", ssid);
+//!                 wifi.leave().ok();
+//!             } else if status == ConnectionStatus::Disconnected {
+//!                 sleep = 20000; // No need to loop as often after disconnecting
+//!             }
+//!         }
+//!         Err(e) => {
+//!             defmt::error!("Failed to get connection result: {:?}", e);
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! ## Multiple ESP32 targets
+//!
+//! Each [`Wifi`] instance exclusively owns its bus, control pins and delay and keeps all of its state
+//! (open sockets, recovery stats, etc.) to itself, so a host with two ESP32 coprocessors can
+//! drive them independently, e.g. one as a station uplink and one as a dedicated access point.
+//! When lending working buffers with [`Wifi::init_with_buffer`], give each instance its own.
+//!
+//! ```no_run
+//! let mut uplink = Wifi::init(spi0, uplink_pins, delay0).unwrap();
+//! let mut access_point = Wifi::init(spi1, access_point_pins, delay1).unwrap();
+//!
+//! uplink.join(ssid, passphrase).ok();
+//! defmt::info!("Access point status: {:?}", access_point.get_connection_status());
+//! ```
+//!
+
+use core::cell::{RefCell, RefMut};
+use core::net::Ipv4Addr;
+
+#[cfg(feature = "defmt")]
+use defmt::{write, Format, Formatter};
+
+use embedded_hal::blocking::{delay::DelayMs, spi::Transfer};
+
+use heapless::{String, Vec};
+
+use super::activity::{ActivityHook, ActivityIndicator};
+use super::clock::{Clock, ClockHook, Duration};
+use super::credentials::{
+    clear_credential, provided_str, truncate_utf8, validate_passphrase, validate_ssid,
+    CredentialProvider, EnterpriseConfig, MAX_PASSPHRASE_LENGTH, MAX_SSID_LENGTH,
+};
+use super::dns::{
+    self, DnsCache, DnsCacheConfig, MAX_DNS_MESSAGE_LENGTH, MAX_DNS_NAME_LENGTH,
+    MAX_RESOLVED_ADDRESSES,
+};
+use super::events::{transition_event, EventHook, WifiEvent, WifiEventHandler};
+use super::gpio::EspControlInterface;
+use super::network::{
+    validate_hostname, IpAddr, IpConfig, MacAddress, NetworkError, TransportMode,
+};
+use super::protocol::{NinaProtocolHandler, ProtocolError, ProtocolInterface};
+use super::scheduler::{Yield, YieldHook};
+use super::stats::RecoveryStats;
+use super::transport::{SpiClockError, SpiConfig, SpiTransport, Transport};
+use super::{Error, FirmwareVersion};
+
+/// An enumerated type that represents the current WiFi network connection status.
+#[repr(u8)]
+#[derive(Eq, PartialEq, PartialOrd, Debug, Clone, Copy)]
+#[cfg_attr(feature = "ufmt", derive(ufmt::derive::uDebug))]
+#[non_exhaustive]
+pub enum ConnectionStatus {
+    /// No device is connected to hardware
+    NoEsp32 = 255,
+    /// No SSID is available
+    NoActiveSsid = 1,
+    /// WiFi network scan has finished
+    ScanCompleted,
+    /// Device is connected to WiFi network
+    Connected,
+    /// Device failed to connect to WiFi network
+    Failed,
+    /// Device lost connection to WiFi network
+    Lost,
+    /// Device disconnected from WiFi network
+    Disconnected,
+    /// Device is listening for connections in Access Point mode
+    ApListening,
+    /// Device is connected in Access Point mode
+    ApConnected,
+    /// Device failed to make connection in Access Point mode
+    ApFailed,
+    /// Unexpected value returned from device, reset may be required
+    Invalid,
+}
+
+impl From<u8> for ConnectionStatus {
+    fn from(status: u8) -> ConnectionStatus {
+        match status {
+            1 => ConnectionStatus::NoActiveSsid,
+            2 => ConnectionStatus::ScanCompleted,
+            3 => ConnectionStatus::Connected,
+            4 => ConnectionStatus::Failed,
+            5 => ConnectionStatus::Lost,
+            6 => ConnectionStatus::Disconnected,
+            7 => ConnectionStatus::ApListening,
+            8 => ConnectionStatus::ApConnected,
+            9 => ConnectionStatus::ApFailed,
+            255 => ConnectionStatus::NoEsp32,
+            _ => ConnectionStatus::Invalid,
+        }
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl Format for ConnectionStatus {
+    fn format(&self, fmt: Formatter) {
+        match self {
+            ConnectionStatus::NoEsp32 => write!(fmt, "No device is connected to hardware"),
+            ConnectionStatus::NoActiveSsid => write!(fmt, "No SSID is available"),
+            ConnectionStatus::ScanCompleted => write!(fmt, "WiFi network scan has finished"),
+            ConnectionStatus::Connected => write!(fmt, "Device is connected to WiFi network"),
+            ConnectionStatus::Failed => write!(fmt, "Device failed to connect to WiFi network"),
+            ConnectionStatus::Lost => write!(fmt, "Device lost connection to WiFi network"),
+            ConnectionStatus::Disconnected => write!(fmt, "Device disconnected from WiFi network"),
+            ConnectionStatus::ApListening => write!(
+                fmt,
+                "Device is listening for connections in Access Point mode"
+            ),
+            ConnectionStatus::ApConnected => {
+                write!(fmt, "Device is connected in Access Point mode")
+            }
+            ConnectionStatus::ApFailed => {
+                write!(fmt, "Device failed to make connection in Access Point mode")
+            }
+            ConnectionStatus::Invalid => write!(
+                fmt,
+                "Unexpected value returned from device, reset may be required"
+            ),
+        }
+    }
+}
+
+/// The stages of initializing an ESP32-WROOM device that are reported by [`Wifi::init_with_progress`].
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "ufmt", derive(ufmt::derive::uDebug))]
+#[non_exhaustive]
+pub enum InitStage {
+    /// The device has been reset via its control pins
+    Reset,
+    /// The device signaled that it's ready to receive protocol commands
+    Handshake,
+    /// The NINA firmware version was successfully read from the device
+    FirmwareVersion,
+}
+
+#[cfg(feature = "defmt")]
+impl Format for InitStage {
+    fn format(&self, fmt: Formatter) {
+        match self {
+            InitStage::Reset => write!(fmt, "Reset"),
+            InitStage::Handshake => write!(fmt, "Handshake"),
+            InitStage::FirmwareVersion => write!(fmt, "Firmware version"),
+        }
+    }
+}
+
+// How long to wait for the NINA firmware to finish a scan before collecting results
+const SCAN_WAIT_MS: u16 = 2000;
+
+// How many times to collect results while a scan hasn't found any networks yet
+const SCAN_ATTEMPTS: u8 = 10;
+
+/// The maximum number of networks the NINA firmware reports per scan.
+pub const MAX_SCAN_RESULTS: usize = 10;
+
+// How long to wait for the NINA firmware to be ready before checking the SPI clock
+const SPI_CLOCK_CHECK_READY_TIMEOUT_MS: u16 = 1_000;
+
+// How many times the firmware version is read to check the SPI clock
+const SPI_CLOCK_CHECK_READS: u8 = 2;
+
+/// The encryption type of a WiFi network found while scanning, as reported by the NINA firmware.
+#[repr(u8)]
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "ufmt", derive(ufmt::derive::uDebug))]
+#[non_exhaustive]
+pub enum EncryptionType {
+    /// WPA with TKIP (WPA-PSK)
+    Tkip = 2,
+    /// WPA2 with CCMP (WPA2-PSK)
+    Ccmp = 4,
+    /// WEP
+    Wep = 5,
+    /// Open network without encryption
+    None = 7,
+    /// WPA or WPA2, whichever the access point negotiates (WPA/WPA2-PSK)
+    Auto = 8,
+    /// Unexpected or unsupported encryption type returned from device
+    #[default]
+    Unknown = 255,
+}
+
+impl From<u8> for EncryptionType {
+    fn from(encryption_type: u8) -> EncryptionType {
+        match encryption_type {
+            2 => EncryptionType::Tkip,
+            4 => EncryptionType::Ccmp,
+            5 => EncryptionType::Wep,
+            7 => EncryptionType::None,
+            8 => EncryptionType::Auto,
+            _ => EncryptionType::Unknown,
+        }
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl Format for EncryptionType {
+    fn format(&self, fmt: Formatter) {
+        match self {
+            EncryptionType::Tkip => write!(fmt, "WPA (TKIP)"),
+            EncryptionType::Ccmp => write!(fmt, "WPA2 (CCMP)"),
+            EncryptionType::Wep => write!(fmt, "WEP"),
+            EncryptionType::None => write!(fmt, "Open"),
+            EncryptionType::Auto => write!(fmt, "WPA/WPA2"),
+            EncryptionType::Unknown => write!(fmt, "Unknown"),
+        }
+    }
+}
+
+/// A nearby WiFi network found while scanning with [`Wifi::scan_networks`].
+#[derive(Eq, PartialEq, Debug, Clone, Default)]
+pub struct ScanResult {
+    ssid: Vec<u8, MAX_SSID_LENGTH>,
+    rssi: i32,
+    channel: u8,
+    encryption_type: EncryptionType,
+    bssid: MacAddress,
+}
+
+impl ScanResult {
+    /// The SSID of the scanned network as raw bytes.
+    pub fn ssid_bytes(&self) -> &[u8] {
+        self.ssid.as_slice()
+    }
+
+    /// The SSID of the scanned network if it's valid UTF-8.
+    pub fn ssid(&self) -> Option<&str> {
+        core::str::from_utf8(self.ssid.as_slice()).ok()
+    }
+
+    /// The received signal strength of the scanned network in dBm.
+    pub fn rssi(&self) -> i32 {
+        self.rssi
+    }
+
+    /// The WiFi channel the scanned network is on.
+    pub fn channel(&self) -> u8 {
+        self.channel
+    }
+
+    /// The [`EncryptionType`] the scanned network uses.
+    pub fn encryption_type(&self) -> EncryptionType {
+        self.encryption_type
+    }
+
+    /// The BSSID (MAC address of the access point) of the scanned network.
+    pub fn bssid(&self) -> MacAddress {
+        self.bssid
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl Format for ScanResult {
+    fn format(&self, fmt: Formatter) {
+        match self.ssid() {
+            Some(ssid) => write!(fmt, "SSID: {}", ssid),
+            None => write!(fmt, "SSID: {=[u8]:x}", self.ssid_bytes()),
+        }
+        write!(
+            fmt,
+            ", RSSI: {} dBm, channel: {}, encryption: {}, BSSID: {}",
+            self.rssi, self.channel, self.encryption_type, self.bssid
+        );
+    }
+}
+
+#[cfg(feature = "ufmt")]
+impl ufmt::uDebug for ScanResult {
+    fn fmt<W: ufmt::uWrite + ?Sized>(
+        &self,
+        f: &mut ufmt::Formatter<'_, W>,
+    ) -> Result<(), W::Error> {
+        match self.ssid() {
+            Some(ssid) => ufmt::uwrite!(f, "SSID: {}", ssid)?,
+            None => ufmt::uwrite!(f, "SSID: {:?}", self.ssid_bytes())?,
+        }
+        ufmt::uwrite!(
+            f,
+            ", RSSI: {} dBm, channel: {}, encryption: {:?}, BSSID: {}",
+            self.rssi,
+            self.channel,
+            self.encryption_type,
+            self.bssid
+        )
+    }
+}
+
+/// Details about the WiFi network the ESP32 target is currently joined to, see
+/// [`Wifi::connection_info`].
+///
+/// The NINA firmware has no command to query the channel of the current network. It can be
+/// found by matching the BSSID against the results of [`Wifi::scan_networks`].
+#[derive(Eq, PartialEq, Debug, Clone, Default)]
+pub struct ConnectionInfo {
+    ssid: Vec<u8, MAX_SSID_LENGTH>,
+    bssid: MacAddress,
+    rssi: i32,
+    encryption_type: EncryptionType,
+}
+
+impl ConnectionInfo {
+    /// The SSID of the current network as raw bytes.
+    pub fn ssid_bytes(&self) -> &[u8] {
+        self.ssid.as_slice()
+    }
+
+    /// The SSID of the current network if it's valid UTF-8.
+    pub fn ssid(&self) -> Option<&str> {
+        core::str::from_utf8(self.ssid.as_slice()).ok()
+    }
+
+    /// The BSSID (MAC address of the access point) the ESP32 target is attached to.
+    pub fn bssid(&self) -> MacAddress {
+        self.bssid
+    }
+
+    /// The received signal strength of the current network in dBm.
+    pub fn rssi(&self) -> i32 {
+        self.rssi
+    }
+
+    /// The [`EncryptionType`] the current network uses.
+    pub fn encryption_type(&self) -> EncryptionType {
+        self.encryption_type
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl Format for ConnectionInfo {
+    fn format(&self, fmt: Formatter) {
+        match self.ssid() {
+            Some(ssid) => write!(fmt, "SSID: {}", ssid),
+            None => write!(fmt, "SSID: {=[u8]:x}", self.ssid_bytes()),
+        }
+        write!(
+            fmt,
+            ", BSSID: {}, RSSI: {} dBm, encryption: {}",
+            self.bssid, self.rssi, self.encryption_type
+        );
+    }
+}
+
+#[cfg(feature = "ufmt")]
+impl ufmt::uDebug for ConnectionInfo {
+    fn fmt<W: ufmt::uWrite + ?Sized>(
+        &self,
+        f: &mut ufmt::Formatter<'_, W>,
+    ) -> Result<(), W::Error> {
+        match self.ssid() {
+            Some(ssid) => ufmt::uwrite!(f, "SSID: {}", ssid)?,
+            None => ufmt::uwrite!(f, "SSID: {:?}", self.ssid_bytes())?,
+        }
+        ufmt::uwrite!(
+            f,
+            ", BSSID: {}, RSSI: {} dBm, encryption: {:?}",
+            self.bssid,
+            self.rssi,
+            self.encryption_type
+        )
+    }
+}
+
+/// The WiFi channel an access point started with [`Wifi::start_access_point`] uses unless
+/// another one is set.
+pub const DEFAULT_ACCESS_POINT_CHANNEL: u8 = 1;
+
+/// Describes the WiFi network hosted by the ESP32 target in Access Point mode, see
+/// [`Wifi::start_access_point`].
+///
+/// Only the SSID is required. The network is open unless a passphrase is set and uses
+/// [`DEFAULT_ACCESS_POINT_CHANNEL`] unless another channel is set.
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub struct AccessPointConfig<'a> {
+    /// SSID of the hosted network.
+    pub ssid: &'a str,
+    /// WPA2 passphrase of the hosted network, or `None` for an open network.
+    pub passphrase: Option<&'a str>,
+    /// WiFi channel of the hosted network.
+    pub channel: u8,
+}
+
+impl<'a> AccessPointConfig<'a> {
+    /// Create a new [`AccessPointConfig`] for an open network named `ssid`.
+    pub fn new(ssid: &'a str) -> Self {
+        Self {
+            ssid,
+            passphrase: None,
+            channel: DEFAULT_ACCESS_POINT_CHANNEL,
+        }
+    }
+
+    /// Secure the hosted network with a WPA2 `passphrase`.
+    pub fn passphrase(mut self, passphrase: &'a str) -> Self {
+        self.passphrase = Some(passphrase);
+        self
+    }
+
+    /// Host the network on WiFi `channel`.
+    pub fn channel(mut self, channel: u8) -> Self {
+        self.channel = channel;
+        self
+    }
+}
+
+/// Base type for controlling an ESP32-WROOM NINA firmware-based WiFi board.
+///
+/// `T` is the [`Transport`] commands travel over, a [`SpiTransport`] for all boards supported so
+/// far, and `D` the delay source the driver keeps for its timing.
+#[derive(Debug)]
+pub struct Wifi<T, D> {
+    pub(crate) protocol_handler: RefCell<NinaProtocolHandler<T, D>>,
+}
+
+impl<S, C, D> Wifi<SpiTransport<S, C>, D>
+where
+    S: Transfer<u8>,
+    C: EspControlInterface,
+    D: DelayMs<u16>,
+{
+    /// Initialize the ESP32-WROOM WiFi device.
+    /// Call this function to put the connected ESP32-WROOM device in a known good state to accept commands.
+    ///
+    /// The driver keeps `delay` for all of its timing, e.g. resetting the device or waiting for
+    /// data to arrive, so none of the other methods take a delay of their own.
+    pub fn init(spi: S, esp32_control_pins: C, delay: D) -> Result<Self, Error> {
+        Self::init_with_transport(SpiTransport::new(spi, esp32_control_pins), delay)
+    }
+
+    /// Initialize the ESP32-WROOM WiFi device while lending it a working `buffer`.
+    ///
+    /// The driver uses `buffer` instead of a temporary buffer on the stack to hold data it
+    /// receives on behalf of the application, i.e. the data echoed by a
+    /// [`diagnostics::EchoServer`](crate::diagnostics::EchoServer), which is processed in chunks
+    /// of at most the length of `buffer`, and the excess of a reply that didn't fit the buffer
+    /// it was read into. This puts the placement of that memory, such as in a specific RAM
+    /// bank, under the application's control.
+    ///
+    /// Replies to other commands are still parsed in a fixed size buffer on the stack, as are
+    /// the bytes shifted over the SPI bus.
+    pub fn init_with_buffer(
+        spi: S,
+        esp32_control_pins: C,
+        delay: D,
+        buffer: &'static mut [u8],
+    ) -> Result<Self, Error> {
+        Self::init_with_transport_and_buffer(
+            SpiTransport::new(spi, esp32_control_pins),
+            delay,
+            buffer,
+        )
+    }
+
+    /// Initialize the ESP32-WROOM WiFi device while reporting progress.
+    ///
+    /// In addition to what [`Wifi::init`] does, this waits for the device to signal it's ready
+    /// to receive commands and reads its firmware version. `f` is invoked with each [`InitStage`]
+    /// as it completes so that a boot screen can show meaningful status. Waiting for each stage
+    /// is bounded by `stage_timeout_ms` and a hang is reported as an [`Error::InitTimeout`] for the
+    /// stage that did not complete.
+    pub fn init_with_progress<F: FnMut(InitStage)>(
+        spi: S,
+        esp32_control_pins: C,
+        delay: D,
+        stage_timeout_ms: u16,
+        f: &mut F,
+    ) -> Result<Self, Error> {
+        let wifi = Self::init(spi, esp32_control_pins, delay)?;
+        f(InitStage::Reset);
+
+        wifi.complete_init(stage_timeout_ms, f)
+    }
+
+    /// Initialize the ESP32-WROOM WiFi device on an SPI bus clocked at `config.frequency_hz`,
+    /// checking that the NINA firmware's replies come through intact at that frequency.
+    ///
+    /// `spi` must already be clocked at `config.frequency_hz` by the HAL. The check reads the
+    /// firmware version a couple of times and fails with [`SpiClockError::Unreliable`] if the
+    /// replies are garbled or missing, which is what a bus clocked too fast for its wiring
+    /// looks like. A frequency above [`MAX_SPI_FREQUENCY_HZ`] is rejected with
+    /// [`SpiClockError::AboveMaximum`] before anything is sent.
+    ///
+    /// [`MAX_SPI_FREQUENCY_HZ`]: crate::transport::MAX_SPI_FREQUENCY_HZ
+    pub fn init_with_spi_config(
+        spi: S,
+        esp32_control_pins: C,
+        delay: D,
+        config: SpiConfig,
+    ) -> Result<Self, Error> {
+        config.validate()?;
+
+        let wifi = Self::init(spi, esp32_control_pins, delay)?;
+        if wifi.check_spi_clock()? {
+            Ok(wifi)
+        } else {
+            Err(SpiClockError::Unreliable.into())
+        }
+    }
+
+    /// Initialize the ESP32-WROOM WiFi device like [`Wifi::init_with_spi_config`], but lower
+    /// the SPI clock while the check fails instead of failing right away.
+    ///
+    /// `set_frequency` is called with the bus and the frequency in Hz to clock it at before each
+    /// check, starting with `config.frequency_hz` and halving it down to no lower than
+    /// `config.min_frequency_hz`. The ESP32 target is reset in between checks. Returns the
+    /// frequency the check passed at along with the [`Wifi`], or
+    /// [`SpiClockError::Unreliable`] if it failed at the minimum frequency too.
+    pub fn init_negotiating_spi_clock<F: FnMut(&mut S, u32)>(
+        mut spi: S,
+        esp32_control_pins: C,
+        delay: D,
+        config: SpiConfig,
+        set_frequency: &mut F,
+    ) -> Result<(Self, u32), Error> {
+        config.validate()?;
+
+        let mut frequency_hz = config.frequency_hz;
+        set_frequency(&mut spi, frequency_hz);
+
+        let mut wifi = Self::init(spi, esp32_control_pins, delay)?;
+        loop {
+            if wifi.check_spi_clock()? {
+                return Ok((wifi, frequency_hz));
+            }
+
+            frequency_hz = config
+                .next_frequency_hz(frequency_hz)
+                .ok_or(SpiClockError::Unreliable)?;
+
+            let protocol_handler = wifi.protocol_handler.get_mut();
+            set_frequency(&mut protocol_handler.transport.bus, frequency_hz);
+            protocol_handler.reset();
+        }
+    }
+
+    /// Reset the ESP32 target into its serial bootloader and hand the bus, control pins and
+    /// delay back, e.g. to update the NINA firmware through a UART bridge running on the RP2040.
+    ///
+    /// No more commands are sent over the bus after this. Pass the bus, control pins and delay
+    /// to [`Wifi::init`] again once flashing is done to restart the NINA firmware.
+    pub fn into_passthrough(self) -> (S, C, D) {
+        let mut protocol_handler = self.protocol_handler.into_inner();
+        let (bus, control_pins) = protocol_handler
+            .transport
+            .into_bootloader(&mut protocol_handler.delay);
+
+        (bus, control_pins, protocol_handler.delay)
+    }
+
+    /// Put the ESP32-WROOM device into a defined state and hand the bus, control pins and delay
+    /// back, consuming the [`Wifi`] instance so that no more commands can be sent to it.
+    ///
+    /// All sockets that are still open are stopped and, if `leave_network` is `true`, the
+    /// currently joined WiFi network is left. Finally the device is held in reset so that the
+    /// next call to [`Wifi::init`] starts from a clean slate. The device is held in reset even
+    /// if stopping a socket or leaving the network fails, in which case the first error
+    /// encountered is returned along with the bus, control pins and delay.
+    #[allow(clippy::type_complexity)]
+    pub fn shutdown(self, leave_network: bool) -> Result<(S, C, D), (Error, (S, C, D))> {
+        let mut protocol_handler = self.protocol_handler.into_inner();
+        let mut result = Ok(());
+
+        while let Some(socket) = protocol_handler.first_open_socket() {
+            if let Err(e) = protocol_handler.stop_client_tcp(socket, &TransportMode::Tcp) {
+                // Don't retry a socket that failed to stop
+                protocol_handler.mark_socket_closed(socket);
+                result = result.and(Err(e));
+            }
+        }
+
+        if leave_network {
+            result = result.and(protocol_handler.disconnect());
+        }
+
+        protocol_handler.transport.hold_in_reset();
+
+        let (bus, control_pins) = protocol_handler.transport.release();
+        let parts = (bus, control_pins, protocol_handler.delay);
+
+        match result {
+            Ok(()) => Ok(parts),
+            Err(e) => Err((e, parts)),
+        }
+    }
+
+    /// Return a reference to the `Spi` bus instance typically used when cleaning up
+    /// an instance of [`Wifi`].
+    pub fn destroy(self) -> S {
+        let (bus, _) = self.protocol_handler.into_inner().transport.release();
+        bus
+    }
+}
+
+impl<T, D> Wifi<T, D> {
+    // Borrows the protocol handler, failing with Error::Busy instead of panicking if it's
+    // already borrowed, e.g. by a command that is still in progress on another socket.
+    pub(crate) fn borrow_protocol_handler(
+        &self,
+    ) -> Result<RefMut<'_, NinaProtocolHandler<T, D>>, Error> {
+        self.protocol_handler
+            .try_borrow_mut()
+            .map_err(|_| Error::Busy)
+    }
+}
+
+impl<T, D> Wifi<T, D>
+where
+    T: Transport,
+    D: DelayMs<u16>,
+{
+    /// Initialize the ESP32-WROOM WiFi device connected through `transport`.
+    ///
+    /// This is [`Wifi::init`] for any [`Transport`], not just SPI.
+    pub fn init_with_transport(transport: T, delay: D) -> Result<Self, Error> {
+        let wifi = Wifi {
+            protocol_handler: RefCell::new(NinaProtocolHandler::new(transport, delay)),
+        };
+
+        wifi.protocol_handler.borrow_mut().init();
+        wifi.protocol_handler.borrow_mut().reset();
+        Ok(wifi)
+    }
+
+    /// Initialize the ESP32-WROOM WiFi device connected through `transport` while lending it a
+    /// working `buffer`.
+    ///
+    /// This is [`Wifi::init_with_buffer`] for any [`Transport`], not just SPI.
+    pub fn init_with_transport_and_buffer(
+        transport: T,
+        delay: D,
+        buffer: &'static mut [u8],
+    ) -> Result<Self, Error> {
+        let wifi = Self::init_with_transport(transport, delay)?;
+        wifi.protocol_handler.borrow_mut().working_buffer = Some(buffer);
+        Ok(wifi)
+    }
+
+    /// Hand the transport back, typically used when cleaning up an instance of [`Wifi`]
+    /// initialized with [`Wifi::init_with_transport`].
+    pub fn into_transport(self) -> T {
+        self.protocol_handler.into_inner().transport
+    }
+
+    // Reads the firmware version of a freshly reset device SPI_CLOCK_CHECK_READS times. Returns
+    // whether all of them came through intact and alike, failing only if the device never
+    // signals it's ready, which doesn't depend on the bus clock.
+    fn check_spi_clock(&self) -> Result<bool, Error> {
+        let mut protocol_handler = self.borrow_protocol_handler()?;
+        if !protocol_handler.wait_for_esp_ready(SPI_CLOCK_CHECK_READY_TIMEOUT_MS) {
+            return Err(Error::InitTimeout(InitStage::Handshake));
+        }
+
+        let mut first_version = None;
+        for _ in 0..SPI_CLOCK_CHECK_READS {
+            let version = match protocol_handler.get_fw_version() {
+                // Garbled digits parse as 0.0.0, which no NINA firmware release is
+                Ok(version) if (version.major(), version.minor(), version.patch()) != (0, 0, 0) => {
+                    version
+                }
+                _ => return Ok(false),
+            };
+
+            match &first_version {
+                Some(first_version) if *first_version != version => return Ok(false),
+                Some(_) => {}
+                None => first_version = Some(version),
+            }
+        }
+
+        Ok(true)
+    }
+
+    // Waits for a freshly reset device to signal it's ready and reads its firmware version,
+    // reporting each stage that completes to `f`.
+    fn complete_init<F: FnMut(InitStage)>(
+        self,
+        stage_timeout_ms: u16,
+        f: &mut F,
+    ) -> Result<Self, Error> {
+        if !self
+            .borrow_protocol_handler()?
+            .wait_for_esp_ready(stage_timeout_ms)
+        {
+            return Err(Error::InitTimeout(InitStage::Handshake));
+        }
+        f(InitStage::Handshake);
+
+        let mut protocol_handler = self.borrow_protocol_handler()?;
+        protocol_handler.select_deadline =
+            Some(protocol_handler.deadline(Some(Duration::millis(stage_timeout_ms as u32))));
+        let result = protocol_handler.get_fw_version();
+        protocol_handler.select_deadline = None;
+        drop(protocol_handler);
+
+        match result {
+            Ok(_) => f(InitStage::FirmwareVersion),
+            Err(Error::Protocol(ProtocolError::CommunicationTimeout)) => {
+                return Err(Error::InitTimeout(InitStage::FirmwareVersion))
+            }
+            Err(e) => return Err(e),
+        }
+
+        Ok(self)
+    }
+
+    /// Retrieve the NINA firmware version contained on the connected ESP32-WROOM device (e.g. 1.7.4).
+    pub fn firmware_version(&mut self) -> Result<FirmwareVersion, Error> {
+        self.borrow_protocol_handler()?.get_fw_version()
+    }
+
+    /// Join a WiFi network given an SSID and a Passphrase.
+    ///
+    /// Both may contain any UTF-8 characters, as long as their encoding fits into
+    /// [`MAX_SSID_LENGTH`] and [`MAX_PASSPHRASE_LENGTH`] bytes respectively.
+    pub fn join(&mut self, ssid: &str, passphrase: &str) -> Result<(), Error> {
+        validate_ssid(ssid)?;
+        validate_passphrase(passphrase)?;
+
+        self.record_join();
+        self.borrow_protocol_handler()?
+            .set_passphrase(ssid.as_bytes(), passphrase.as_bytes())
+    }
+
+    /// Join an open (unsecured) WiFi network given its SSID, such as a guest network or a
+    /// captive portal access point.
+    pub fn join_open(&mut self, ssid: &str) -> Result<(), Error> {
+        validate_ssid(ssid)?;
+
+        self.record_join();
+        self.borrow_protocol_handler()?.set_network(ssid.as_bytes())
+    }
+
+    /// Host a WiFi network described by `config` in Access Point mode, e.g. for initial setup
+    /// when no infrastructure network is available yet.
+    ///
+    /// Use [`Wifi::get_connection_status`] to check for [`ConnectionStatus::ApListening`] and
+    /// [`ConnectionStatus::ApConnected`]. The latter reports that at least one station has
+    /// joined. Note that the NINA firmware has no command to list the connected stations or
+    /// their MAC addresses and RSSI.
+    pub fn start_access_point(&mut self, config: &AccessPointConfig) -> Result<(), Error> {
+        validate_ssid(config.ssid)?;
+        if let Some(passphrase) = config.passphrase {
+            validate_passphrase(passphrase)?;
+        }
+
+        let mut protocol_handler = self.borrow_protocol_handler()?;
+
+        match config.passphrase {
+            Some(passphrase) => protocol_handler.set_ap_passphrase(
+                config.ssid.as_bytes(),
+                passphrase.as_bytes(),
+                config.channel,
+            ),
+            None => protocol_handler.set_ap_net(config.ssid.as_bytes(), config.channel),
+        }
+    }
+
+    /// Join a WPA2-Enterprise (802.1X) WiFi network given its SSID and an [`EnterpriseConfig`].
+    ///
+    /// The NINA firmware doesn't implement installing a CA certificate for enterprise networks,
+    /// so [`Error::UnsupportedOperation`] is returned if `config` contains one, before anything
+    /// is sent to the ESP32 target.
+    pub fn join_enterprise(&mut self, ssid: &str, config: &EnterpriseConfig) -> Result<(), Error> {
+        if config.ca_certificate.is_some() {
+            return Err(Error::UnsupportedOperation);
+        }
+        validate_ssid(ssid)?;
+
+        self.record_join();
+
+        let mut protocol_handler = self.borrow_protocol_handler()?;
+
+        protocol_handler.set_ent_identity(config.outer_identity().as_bytes())?;
+        protocol_handler.set_ent_username(config.username.as_bytes())?;
+        protocol_handler.set_ent_password(config.password.as_bytes())?;
+        protocol_handler.set_ent_enable()?;
+
+        protocol_handler.set_network(ssid.as_bytes())
+    }
+
+    /// Join a WiFi network using the SSID and passphrase supplied by a [`CredentialProvider`].
+    ///
+    /// The credentials are requested from `provider` right before they are sent to the ESP32
+    /// target and are cleared from memory as soon as the command has been sent. They're
+    /// checked like the ones passed to [`Wifi::join`], and a provider reporting more bytes than
+    /// fit into the buffer it was given or credentials that aren't valid UTF-8 fail with
+    /// [`NetworkError::CredentialsUnavailable`](crate::network::NetworkError::CredentialsUnavailable).
+    pub fn join_with<P: CredentialProvider>(&mut self, provider: &mut P) -> Result<(), Error> {
+        let mut ssid = [0u8; MAX_SSID_LENGTH];
+        let mut passphrase = [0u8; MAX_PASSPHRASE_LENGTH];
+
+        let result = provider.ssid(&mut ssid).and_then(|ssid_length| {
+            let ssid = provided_str(&ssid, ssid_length)?;
+            validate_ssid(ssid)?;
+
+            let passphrase_length = provider.passphrase(&mut passphrase)?;
+            let passphrase = provided_str(&passphrase, passphrase_length)?;
+            validate_passphrase(passphrase)?;
+
+            self.record_join();
+            self.borrow_protocol_handler()?
+                .set_passphrase(ssid.as_bytes(), passphrase.as_bytes())
+        });
+
+        clear_credential(&mut ssid);
+        clear_credential(&mut passphrase);
+
+        result
+    }
+
+    /// Disconnect from a previously joined WiFi network.
+    pub fn leave(&mut self) -> Result<(), Error> {
+        self.borrow_protocol_handler()?.disconnect()
+    }
+
+    /// Retrieve the SSID, BSSID, signal strength and encryption type of the WiFi network the
+    /// ESP32 target is currently joined to, e.g. to log link quality.
+    pub fn connection_info(&mut self) -> Result<ConnectionInfo, Error> {
+        let mut protocol_handler = self.borrow_protocol_handler()?;
+        let mut connection_info = ConnectionInfo::default();
+
+        protocol_handler.get_curr_ssid(&mut |ssid| {
+            connection_info
+                .ssid
+                .extend_from_slice(truncate_utf8(ssid, MAX_SSID_LENGTH))
+                .ok();
+        })?;
+        connection_info.bssid = protocol_handler.get_curr_bssid()?;
+        connection_info.rssi = protocol_handler.get_curr_rssi()?;
+        connection_info.encryption_type = protocol_handler.get_curr_enct()?;
+
+        Ok(connection_info)
+    }
+
+    /// Retrieve the current WiFi network [`ConnectionStatus`].
+    ///
+    /// A [`WifiEventHandler`] set with [`Wifi::set_event_handler`] is notified if the status
+    /// changed since it was read before.
+    pub fn get_connection_status(&mut self) -> Result<ConnectionStatus, Error> {
+        let mut protocol_handler = self.borrow_protocol_handler()?;
+        let status = protocol_handler.get_conn_status()?;
+
+        let previous = protocol_handler.last_connection_status.replace(status);
+        if let Some(event_hook) = protocol_handler.event_hook {
+            if let Some(event) = transition_event(previous, status) {
+                event_hook.on_event(event);
+
+                if event == WifiEvent::Connected {
+                    if let Ok(ip_config) = protocol_handler.get_ip_config() {
+                        event_hook.on_event(WifiEvent::GotIp(ip_config.ip));
+                    }
+                }
+            }
+        }
+
+        Ok(status)
+    }
+
+    /// Assign the static IP address, gateway and subnet mask in `config` to the ESP32 target
+    /// instead of obtaining them via DHCP.
+    ///
+    /// Call this before [`Wifi::join`] so the static configuration is used for the connection.
+    /// DNS servers aren't part of the static configuration, set them with [`Wifi::set_dns`].
+    pub fn set_ip_config(&mut self, config: &IpConfig) -> Result<(), Error> {
+        self.borrow_protocol_handler()?
+            .set_ip_config(config.ip, config.gateway, config.subnet_mask)
+    }
+
+    /// Get the IP address, gateway and subnet mask currently used by the ESP32 target, e.g.
+    /// the ones assigned via DHCP after joining a network.
+    pub fn get_ip_config(&mut self) -> Result<IpConfig, Error> {
+        self.borrow_protocol_handler()?.get_ip_config()
+    }
+
+    /// Get the [`MacAddress`] of the ESP32 target's WiFi station interface, e.g. for DHCP
+    /// reservations or device registration.
+    pub fn get_mac_address(&mut self) -> Result<MacAddress, Error> {
+        self.borrow_protocol_handler()?.get_mac_address()
+    }
+
+    /// Set the `hostname` the ESP32 target announces via DHCP, e.g. in a router's list of
+    /// clients. Call this before [`Wifi::join`] for the hostname to be used for the connection.
+    ///
+    /// The hostname must be 1 to
+    /// [`MAX_DHCP_HOSTNAME_LENGTH`](crate::network::MAX_DHCP_HOSTNAME_LENGTH) ASCII letters,
+    /// digits and hyphens and can't start or end with a hyphen.
+    pub fn set_hostname(&mut self, hostname: &str) -> Result<(), Error> {
+        validate_hostname(hostname)?;
+
+        self.borrow_protocol_handler()?.set_hostname(hostname)
+    }
+
+    /// Get the current time as the number of seconds since the Unix epoch, as kept by the
+    /// NINA firmware via SNTP once a network with internet access has been joined.
+    ///
+    /// Fails with [`NetworkError::TimeUnavailable`](crate::network::NetworkError::TimeUnavailable)
+    /// until the time has been synchronized, which usually takes a few seconds after joining.
+    pub fn get_time(&mut self) -> Result<u32, Error> {
+        self.borrow_protocol_handler()?.get_time()
+    }
+
+    /// Read the die temperature of the ESP32 target in hundredths of a degree Celsius, e.g.
+    /// `4250` for 42.5 °C.
+    ///
+    /// The sensor isn't calibrated, so it's best used to watch for changes in temperature
+    /// rather than as an absolute reading.
+    pub fn get_temperature(&mut self) -> Result<i32, Error> {
+        let celsius = self.borrow_protocol_handler()?.get_temperature()?;
+        Ok((celsius * 100.0) as i32)
+    }
+
+    /// Enable or disable the WiFi modem sleep of the ESP32 target, which lowers its power draw
+    /// in between beacons from the access point at the cost of added latency.
+    ///
+    /// Note that the NINA firmware has no command to set the transmit power itself, so this is
+    /// the only way to reduce the radio's power draw from the driver.
+    pub fn set_low_power_mode(&mut self, enabled: bool) -> Result<(), Error> {
+        self.borrow_protocol_handler()?.set_power_mode(enabled)
+    }
+
+    /// Enable or disable the debug output of the NINA firmware on the ESP32 target's serial
+    /// port (115200 baud), which helps to diagnose protocol issues without reflashing it.
+    pub fn set_firmware_debug(&mut self, enabled: bool) -> Result<(), Error> {
+        self.borrow_protocol_handler()?.set_debug(enabled)
+    }
+
+    /// Set 1 or 2 DNS servers that are used for network hostname resolution.
+    pub fn set_dns(&mut self, dns1: Ipv4Addr, dns2: Option<Ipv4Addr>) -> Result<(), Error> {
+        self.borrow_protocol_handler()?.set_dns_config(dns1, dns2)
+    }
+
+    /// Install a PEM encoded client `certificate` on the ESP32 target for mutual TLS.
+    ///
+    /// The NINA firmware keeps the certificate in RAM only and presents it for every TLS
+    /// connection made after a private key has also been set with [`Wifi::set_private_key`].
+    /// It holds at most
+    /// [`MAX_CLIENT_CERTIFICATE_LENGTH`](crate::tls_client::MAX_CLIENT_CERTIFICATE_LENGTH) bytes.
+    pub fn set_client_certificate(&mut self, certificate: &[u8]) -> Result<(), Error> {
+        self.borrow_protocol_handler()?.set_client_cert(certificate)
+    }
+
+    /// Install the PEM encoded `private_key` that belongs to the client certificate set with
+    /// [`Wifi::set_client_certificate`].
+    ///
+    /// It holds at most [`MAX_PRIVATE_KEY_LENGTH`](crate::tls_client::MAX_PRIVATE_KEY_LENGTH) bytes.
+    pub fn set_private_key(&mut self, private_key: &[u8]) -> Result<(), Error> {
+        self.borrow_protocol_handler()?.set_private_key(private_key)
+    }
+
+    /// Scan for nearby WiFi networks and return an iterator over the [`ScanResult`] of every
+    /// network found.
+    ///
+    /// Note that the NINA firmware itself reports at most [`MAX_SCAN_RESULTS`] networks per scan.
+    /// Should it ever report more, this fails with [`NetworkError::ScanResultsTruncated`]
+    /// rather than silently dropping any. [`Wifi::scan_networks_with`] still passes on the
+    /// results that fit in that case.
+    pub fn scan_networks(&mut self) -> Result<impl Iterator<Item = ScanResult>, Error> {
+        let (results, truncated) = self.collect_scan_results()?;
+        if truncated {
+            return Err(NetworkError::ScanResultsTruncated.into());
+        }
+
+        Ok(results.into_iter())
+    }
+
+    /// Scan for nearby WiFi networks and invoke `f` once for every network found.
+    ///
+    /// This is a convenience on top of [`Wifi::scan_networks`]. Returns the number of networks
+    /// found. If the NINA firmware reports more than [`MAX_SCAN_RESULTS`] networks, `f` is
+    /// invoked for the first [`MAX_SCAN_RESULTS`] of them before failing with
+    /// [`NetworkError::ScanResultsTruncated`].
+    pub fn scan_networks_with<F: FnMut(&ScanResult)>(&mut self, f: &mut F) -> Result<u8, Error> {
+        let (results, truncated) = self.collect_scan_results()?;
+
+        let mut number_of_networks = 0;
+        for result in results.iter() {
+            f(result);
+            number_of_networks += 1;
+        }
+
+        if truncated {
+            return Err(NetworkError::ScanResultsTruncated.into());
+        }
+
+        Ok(number_of_networks)
+    }
+
+    // Scans for nearby WiFi networks and looks up the details of each one found. Also returns
+    // whether the NINA firmware reported more networks than fit into the results.
+    fn collect_scan_results(&mut self) -> Result<(Vec<ScanResult, MAX_SCAN_RESULTS>, bool), Error> {
+        let mut protocol_handler = self.borrow_protocol_handler()?;
+
+        protocol_handler.start_scan_networks()?;
+
+        let mut results: Vec<ScanResult, MAX_SCAN_RESULTS> = Vec::new();
+        let mut truncated = false;
+
+        for _ in 0..SCAN_ATTEMPTS {
+            protocol_handler.delay_ms(SCAN_WAIT_MS);
+
+            protocol_handler.scan_networks(&mut |ssid| {
+                let mut result = ScanResult::default();
+                result
+                    .ssid
+                    .extend_from_slice(truncate_utf8(ssid, MAX_SSID_LENGTH))
+                    .ok();
+
+                truncated |= results.push(result).is_err();
+            })?;
+
+            if !results.is_empty() {
+                break;
+            }
+        }
+
+        // The remaining details of each network are looked up by its index in the scan results
+        for (index, result) in results.iter_mut().enumerate() {
+            let index = index as u8;
+
+            result.rssi = protocol_handler.get_idx_rssi(index)?;
+            result.encryption_type = protocol_handler.get_idx_enct(index)?;
+            result.bssid = protocol_handler.get_idx_bssid(index)?;
+            result.channel = protocol_handler.get_idx_channel(index)?;
+        }
+
+        Ok((results, truncated))
+    }
+
+    /// Query the DNS server(s) provided via `set_dns` for the associated IP address to the provided hostname.
+    pub fn resolve(&mut self, hostname: &str) -> Result<Ipv4Addr, Error> {
+        self.borrow_protocol_handler()?.resolve(hostname)
+    }
+
+    /// Cache the addresses of resolved hostnames for the TTL set in `config`, as measured by
+    /// `clock`. While enabled, [`Wifi::resolve`] and connecting a [`TcpClient`] by hostname only
+    /// query the DNS server for hostnames that aren't cached yet or whose TTL has passed.
+    ///
+    /// Enabling the cache again replaces the previous one, which starts out empty.
+    ///
+    /// [`TcpClient`]: crate::tcp_client::TcpClient
+    pub fn enable_dns_cache(&mut self, clock: &'static dyn Clock, config: DnsCacheConfig) {
+        self.protocol_handler.get_mut().dns_cache = Some(DnsCache::new(ClockHook(clock), config));
+    }
+
+    /// Stop caching resolved hostnames and forget the ones cached so far.
+    pub fn disable_dns_cache(&mut self) {
+        self.protocol_handler.get_mut().dns_cache = None;
+    }
+
+    /// Resolve `hostname` to all of its IP addresses (at most [`MAX_RESOLVED_ADDRESSES`]) by
+    /// querying `dns_server`, so that a connection can fail over to the next address when
+    /// connecting to the first one fails.
+    ///
+    /// The NINA firmware only passes on the first address it resolves, so the query is sent to
+    /// `dns_server` over a temporary UDP socket and waits up to 2 seconds for its response.
+    /// Addresses are returned in the order the DNS server listed them in. As the NINA firmware
+    /// only supports IPv4, only A records are queried so far.
+    pub fn resolve_all(
+        &mut self,
+        hostname: &str,
+        dns_server: Ipv4Addr,
+    ) -> Result<Vec<IpAddr, MAX_RESOLVED_ADDRESSES>, Error> {
+        let protocol_handler = self.protocol_handler.get_mut();
+
+        let id = protocol_handler.next_dns_id;
+        protocol_handler.next_dns_id = id.wrapping_add(1);
+
+        let mut query = [0u8; MAX_DNS_MESSAGE_LENGTH];
+        let query_length = dns::encode_a_query(id, hostname, &mut query)?;
+
+        let mut response = [0u8; MAX_DNS_MESSAGE_LENGTH];
+        let response_length = dns::exchange(
+            protocol_handler,
+            dns_server,
+            &query[..query_length],
+            &mut response,
+        )?;
+
+        dns::decode_a_response(id, &response[..response_length])
+    }
+
+    /// Look up the hostname of `ip` (its reverse DNS / PTR record) by querying `dns_server`.
+    ///
+    /// The NINA firmware can only resolve hostnames to IP addresses, so the query is sent to
+    /// `dns_server` over a temporary UDP socket and waits up to 2 seconds for its response.
+    pub fn get_host_by_addr(
+        &mut self,
+        ip: Ipv4Addr,
+        dns_server: Ipv4Addr,
+    ) -> Result<String<MAX_DNS_NAME_LENGTH>, Error> {
+        let protocol_handler = self.protocol_handler.get_mut();
+
+        let id = protocol_handler.next_dns_id;
+        protocol_handler.next_dns_id = id.wrapping_add(1);
+
+        let mut query = [0u8; MAX_DNS_MESSAGE_LENGTH];
+        let query_length = dns::encode_ptr_query(id, ip, &mut query)?;
+
+        let mut response = [0u8; MAX_DNS_MESSAGE_LENGTH];
+        let response_length = dns::exchange(
+            protocol_handler,
+            dns_server,
+            &query[..query_length],
+            &mut response,
+        )?;
+
+        dns::decode_ptr_response(id, &response[..response_length])
+    }
+
+    /// Set a [`Yield`] implementation that is invoked in between commands sent to the ESP32
+    /// target, letting a cooperative scheduler run other tasks during long operations.
+    pub fn set_yield(&mut self, yield_hook: &'static dyn Yield) {
+        self.protocol_handler.get_mut().yield_hook = Some(YieldHook(yield_hook));
+    }
+
+    /// Set the [`Clock`] that timeouts and retries are measured with, instead of estimating them
+    /// from the time spent waiting in between polls of the ESP32 target.
+    pub fn set_clock(&mut self, clock: &'static dyn Clock) {
+        self.protocol_handler.get_mut().clock = Some(ClockHook(clock));
+    }
+
+    /// Set an [`ActivityIndicator`] that is notified every time socket data is sent to or
+    /// received from the ESP32 target, e.g. to blink a network status LED.
+    pub fn set_activity_indicator(&mut self, activity_indicator: &'static dyn ActivityIndicator) {
+        self.protocol_handler.get_mut().activity_hook = Some(ActivityHook(activity_indicator));
+    }
+
+    /// Set a [`WifiEventHandler`] that is notified about changes of the WiFi connection
+    /// detected while reading the connection status.
+    pub fn set_event_handler(&mut self, event_handler: &'static dyn WifiEventHandler) {
+        self.protocol_handler.get_mut().event_hook = Some(EventHook(event_handler));
+    }
+
+    /// Retrieve the counters of recovery actions taken since this [`Wifi`] instance was initialized.
+    pub fn recovery_stats(&self) -> RecoveryStats {
+        self.protocol_handler.borrow().recovery_stats
+    }
+
+    // Every join after the first one is counted as a rejoin.
+    fn record_join(&mut self) {
+        let protocol_handler = self.protocol_handler.get_mut();
+        if protocol_handler.joined {
+            protocol_handler.recovery_stats.record_rejoin();
+        }
+        protocol_handler.joined = true;
+    }
+}
+
+#[cfg(test)]
+mod wifi_tests {
+    use super::*;
+
+    #[test]
+    fn borrow_protocol_handler_returns_busy_error_while_already_borrowed() {
+        let wifi = Wifi {
+            protocol_handler: RefCell::new(NinaProtocolHandler::new((), ())),
+        };
+
+        let protocol_handler = wifi.borrow_protocol_handler().unwrap();
+        assert_eq!(wifi.borrow_protocol_handler().err(), Some(Error::Busy));
+
+        drop(protocol_handler);
+        assert!(wifi.borrow_protocol_handler().is_ok());
+    }
+}
@@ -0,0 +1,194 @@
+//! An asynchronous driver for the ESP32 target built on [embedded-hal-async](https://github.com/rust-embedded/embedded-hal/tree/master/embedded-hal-async),
+//! enabled by the `async-hal` feature.
+//!
+//! [`Wifi`](crate::wifi::Wifi) spins while it waits for the NINA firmware to answer a command,
+//! which blocks an async executor (e.g. [embassy](https://embassy.dev)) for hundreds of
+//! milliseconds while joining a network or resolving a hostname. [`AsyncWifi`] awaits the ACK
+//! pin of [`AsyncEspControlPins`](crate::gpio::AsyncEspControlPins) and the SPI bus instead,
+//! so other tasks keep running in the meantime.
+//!
+//! [`AsyncWifi`] currently covers joining a WiFi network, DNS and TCP client sockets.
+//!
+//! ## Usage
+//!
+//! ```no_run
+//! use esp32_wroom_core::gpio::AsyncEspControlPins;
+//! use esp32_wroom_core::wifi_async::AsyncWifi;
+//!
+//! let esp_pins = AsyncEspControlPins {
+//!     cs: Output::new(p.PIN_7, Level::High),
+//!     gpio0: Output::new(p.PIN_2, Level::High),
+//!     resetn: Output::new(p.PIN_11, Level::High),
+//!     ack: Input::new(p.PIN_10, Pull::None),
+//! };
+//!
+//! let mut wifi = AsyncWifi::init(spi, esp_pins, &mut Delay).await.unwrap();
+//! wifi.join(SSID, PASSPHRASE).await.unwrap();
+//!
+//! while wifi.get_connection_status().await.unwrap() != ConnectionStatus::Connected {
+//!     Timer::after_millis(500).await;
+//! }
+//!
+//! let server = SocketAddrV4::new(wifi.resolve("example.com").await.unwrap(), 80);
+//! let socket = wifi.connect(server, &mut Delay).await.unwrap();
+//! wifi.send(socket, b"GET / HTTP/1.1\r\nHost: example.com\r\n\r\n").await.unwrap();
+//! ```
+//!
+
+use core::net::{Ipv4Addr, SocketAddrV4};
+
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_async::spi::SpiBus;
+
+use super::credentials::{validate_passphrase, validate_ssid};
+use super::gpio::AsyncEspControlInterface;
+use super::network::{ConnectionState, NetworkError, Socket, TransportMode};
+use super::spi_async::AsyncNinaProtocolHandler;
+use super::wifi::ConnectionStatus;
+use super::{Error, FirmwareVersion};
+
+/// An asynchronous counterpart of [`Wifi`](crate::wifi::Wifi) for async executors.
+pub struct AsyncWifi<B, C> {
+    protocol_handler: AsyncNinaProtocolHandler<B, C>,
+}
+
+impl<B, C> AsyncWifi<B, C>
+where
+    B: SpiBus<u8>,
+    C: AsyncEspControlInterface,
+{
+    /// Initialize the ESP32-WROOM WiFi device. Call this function to put the connected device
+    /// into a known good state to accept commands.
+    pub async fn init<D: DelayNs>(
+        spi: B,
+        esp32_control_pins: C,
+        delay: &mut D,
+    ) -> Result<AsyncWifi<B, C>, Error> {
+        let mut wifi = AsyncWifi {
+            protocol_handler: AsyncNinaProtocolHandler::new(spi, esp32_control_pins),
+        };
+
+        wifi.protocol_handler.reset(delay).await;
+        Ok(wifi)
+    }
+
+    /// Retrieve the NINA firmware version contained on the connected ESP32-WROOM device.
+    pub async fn firmware_version(&mut self) -> Result<FirmwareVersion, Error> {
+        self.protocol_handler.get_fw_version().await
+    }
+
+    /// Join a WiFi network given an SSID and a Passphrase.
+    pub async fn join(&mut self, ssid: &str, passphrase: &str) -> Result<(), Error> {
+        validate_ssid(ssid)?;
+        validate_passphrase(passphrase)?;
+
+        self.protocol_handler
+            .set_passphrase(ssid.as_bytes(), passphrase.as_bytes())
+            .await
+    }
+
+    /// Join an open (unsecured) WiFi network given its SSID.
+    pub async fn join_open(&mut self, ssid: &str) -> Result<(), Error> {
+        validate_ssid(ssid)?;
+
+        self.protocol_handler.set_network(ssid.as_bytes()).await
+    }
+
+    /// Disconnect from a previously joined WiFi network.
+    pub async fn leave(&mut self) -> Result<(), Error> {
+        self.protocol_handler.disconnect().await
+    }
+
+    /// Retrieve the current WiFi network [`ConnectionStatus`].
+    pub async fn get_connection_status(&mut self) -> Result<ConnectionStatus, Error> {
+        self.protocol_handler.get_conn_status().await
+    }
+
+    /// Set 1 or 2 DNS servers that are used for network hostname resolution.
+    pub async fn set_dns(&mut self, dns1: Ipv4Addr, dns2: Option<Ipv4Addr>) -> Result<(), Error> {
+        self.protocol_handler.set_dns_config(dns1, dns2).await
+    }
+
+    /// Query the DNS server(s) provided via [`AsyncWifi::set_dns`] for the IP address of
+    /// `hostname`.
+    pub async fn resolve(&mut self, hostname: &str) -> Result<Ipv4Addr, Error> {
+        self.protocol_handler.resolve(hostname).await
+    }
+
+    /// Connect to the TCP server at `remote`, returning the [`Socket`] of the connection once
+    /// it has been established. `delay` paces checking on the connection, which fails with
+    /// [`NetworkError::ConnectionTimeout`] if it isn't established in time.
+    pub async fn connect<D: DelayNs>(
+        &mut self,
+        remote: SocketAddrV4,
+        delay: &mut D,
+    ) -> Result<Socket, Error> {
+        let socket = self.protocol_handler.get_socket().await?;
+        self.protocol_handler
+            .start_client_tcp(socket, remote, &TransportMode::Tcp)
+            .await?;
+
+        // Give the NINA firmware a moment before asking for the connection state, as
+        // TcpClient does
+        delay.delay_ms(250).await;
+
+        for _ in 0..10_000 {
+            match self.protocol_handler.get_client_state_tcp(socket).await {
+                Ok(ConnectionState::Established) => return Ok(socket),
+                Ok(_state) => delay.delay_ms(100).await,
+                Err(error) => {
+                    self.protocol_handler.stop_client_tcp(socket).await?;
+                    return Err(error);
+                }
+            }
+        }
+
+        self.protocol_handler.stop_client_tcp(socket).await?;
+
+        Err(NetworkError::ConnectionTimeout.into())
+    }
+
+    /// Send `data` to the server connected on `socket`, returning the number of bytes the
+    /// ESP32 target accepted. Fails with [`NetworkError::SendFailed`] if it couldn't confirm
+    /// the data was sent.
+    pub async fn send(&mut self, socket: Socket, data: &[u8]) -> Result<usize, Error> {
+        let written = self.protocol_handler.send_data(data, socket).await?;
+
+        if written > 0 && !self.protocol_handler.check_data_sent(socket).await? {
+            return Err(NetworkError::SendFailed.into());
+        }
+
+        Ok(written)
+    }
+
+    /// Copy data received from the server connected on `socket` into `buf`, returning the
+    /// number of bytes copied. Returns 0 right away if no data is available.
+    pub async fn receive(&mut self, socket: Socket, buf: &mut [u8]) -> Result<usize, Error> {
+        let available = self.protocol_handler.avail_data_tcp(socket).await?;
+        if available == 0 || buf.is_empty() {
+            return Ok(0);
+        }
+
+        let length = available.min(buf.len());
+        self.protocol_handler
+            .get_data_buf_tcp(socket, &mut buf[..length])
+            .await
+    }
+
+    /// Whether the connection on `socket` is still established.
+    pub async fn is_connected(&mut self, socket: Socket) -> Result<bool, Error> {
+        let state = self.protocol_handler.get_client_state_tcp(socket).await?;
+
+        Ok(state == ConnectionState::Established)
+    }
+
+    /// Close the connection on `socket`.
+    pub async fn close(&mut self, socket: Socket) -> Result<(), Error> {
+        self.protocol_handler.stop_client_tcp(socket).await
+    }
+
+    /// Consumes the [`AsyncWifi`] instance and returns the SPI bus it used.
+    pub fn destroy(self) -> B {
+        self.protocol_handler.bus
+    }
+}
@@ -0,0 +1,9 @@
+//! Presets for RP2040 based boards and add-ons that connect an ESP32 or NINA-W10x WiFi target
+//! to the RP2040.
+//!
+//! Each board module maps the board's wiring onto [`EspControlPins`](crate::gpio::EspControlPins)
+//! and provides a constructor for a [`Wifi`](crate::wifi::Wifi) instance on it.
+
+pub mod airlift;
+pub mod nano_rp2040_connect;
+pub mod pico_wireless_pack;
@@ -0,0 +1,155 @@
+//! Adafruit's AirLift [breakout](https://www.adafruit.com/product/4201) and
+//! [FeatherWing](https://www.adafruit.com/product/4264), which carry an ESP32-WROOM-32 running
+//! Adafruit's NINA firmware.
+//!
+//! Adafruit labels the control lines differently than the ESP32's pin names:
+//!
+//! | AirLift | Role |
+//! | ------- | ---- |
+//! | CS      | CS |
+//! | BUSY    | ACK |
+//! | RST     | RESETn |
+//! | GP0     | GPIO0 |
+//!
+//! Stacked on a Feather RP2040, the FeatherWing connects CS to D13 (GPIO13), BUSY to D11
+//! (GPIO11), RST to D12 (GPIO12) and the SPI bus to SPI0 on SCK (GPIO18), MO (GPIO19) and MI
+//! (GPIO20). Its GP0 pad isn't connected to the Feather, so the FeatherWing presets leave GPIO0
+//! [unconnected](crate::gpio::UnconnectedPin).
+//!
+//! The breakout is wired up freely. Adafruit's guides use the same Feather pins as the
+//! FeatherWing, which [`breakout_esp_control_pins`] accepts along with any other wiring.
+//!
+//! The AirLift's reset timing, 10 ms in reset followed by 750 ms for its firmware to boot,
+//! matches the one [`Wifi::init`] uses for all ESP32 targets, so it needs no special handling.
+//!
+//! ## Usage
+//!
+//! ```no_run
+//! use esp32_wroom_rp::boards::airlift;
+//!
+//! let (_mi, _mo, _sck) = airlift::featherwing_spi_pins(pins.gpio20, pins.gpio19, pins.gpio18);
+//! let spi = hal::Spi::<_, _, 8>::new(pac.SPI0).init(
+//!     &mut pac.RESETS,
+//!     clocks.peripheral_clock.freq(),
+//!     8.MHz(),
+//!     &MODE_0,
+//! );
+//!
+//! let esp_pins = airlift::featherwing_esp_control_pins(pins.gpio13, pins.gpio11, pins.gpio12);
+//! let mut wifi = airlift::init(spi, esp_pins, delay).unwrap();
+//! ```
+
+use embedded_hal::blocking::delay::DelayMs;
+use embedded_hal::blocking::spi::Transfer;
+use embedded_hal::digital::v2::OutputPin;
+
+use rp2040_hal::gpio::bank0::{Gpio11, Gpio12, Gpio13, Gpio18, Gpio19, Gpio20};
+use rp2040_hal::gpio::{
+    FloatingInput, FunctionSpi, Pin, PinId, PinMode, PushPullOutput, ValidPinMode,
+};
+
+use crate::gpio::{EspControlPins, UnconnectedPin};
+use crate::transport::SpiTransport;
+use crate::wifi::Wifi;
+use crate::Error;
+
+/// The [`EspControlPins`] of an AirLift with its CS, BUSY and RST lines on RP2040 pins and its
+/// GP0 line on `GPIO0`.
+pub type AirLiftEspControlPins<CS, BUSY, RST, GPIO0 = UnconnectedPin> = EspControlPins<
+    Pin<CS, PushPullOutput>,
+    GPIO0,
+    Pin<RST, PushPullOutput>,
+    Pin<BUSY, FloatingInput>,
+>;
+
+/// The [`Wifi`] of an AirLift wired up with [`AirLiftEspControlPins`].
+pub type AirLiftWifi<S, CS, BUSY, RST, GPIO0, D> =
+    Wifi<SpiTransport<S, AirLiftEspControlPins<CS, BUSY, RST, GPIO0>>, D>;
+
+/// The [`EspControlPins`] of an AirLift FeatherWing stacked on a Feather RP2040.
+pub type FeatherWingEspControlPins = AirLiftEspControlPins<Gpio13, Gpio11, Gpio12>;
+
+/// The SPI0 pins of a Feather RP2040 connected to an AirLift FeatherWing: MI, MO and SCK.
+pub type FeatherWingSpiPins = (
+    Pin<Gpio20, FunctionSpi>,
+    Pin<Gpio19, FunctionSpi>,
+    Pin<Gpio18, FunctionSpi>,
+);
+
+/// Configures the Feather RP2040 pins the AirLift FeatherWing's CS (D13), BUSY (D11) and RST
+/// (D12) lines are connected to for their roles.
+pub fn featherwing_esp_control_pins<M1, M2, M3>(
+    cs: Pin<Gpio13, M1>,
+    busy: Pin<Gpio11, M2>,
+    rst: Pin<Gpio12, M3>,
+) -> FeatherWingEspControlPins
+where
+    M1: PinMode + ValidPinMode<Gpio13>,
+    M2: PinMode + ValidPinMode<Gpio11>,
+    M3: PinMode + ValidPinMode<Gpio12>,
+{
+    breakout_esp_control_pins(cs, busy, rst, UnconnectedPin)
+}
+
+/// Hands the Feather RP2040's SPI0 pins connected to the AirLift FeatherWing over to the SPI
+/// peripheral. They only need to be kept around, the SPI driver uses them implicitly.
+pub fn featherwing_spi_pins<M1, M2, M3>(
+    mi: Pin<Gpio20, M1>,
+    mo: Pin<Gpio19, M2>,
+    sck: Pin<Gpio18, M3>,
+) -> FeatherWingSpiPins
+where
+    M1: PinMode + ValidPinMode<Gpio20>,
+    M2: PinMode + ValidPinMode<Gpio19>,
+    M3: PinMode + ValidPinMode<Gpio18>,
+{
+    (mi.into_mode(), mo.into_mode(), sck.into_mode())
+}
+
+/// Configures the RP2040 pins an AirLift breakout's CS, BUSY and RST lines are wired to for
+/// their roles.
+///
+/// `gpio0` is the output pin wired to GP0, already configured, or [`UnconnectedPin`] if GP0 isn't
+/// wired up.
+pub fn breakout_esp_control_pins<CS, BUSY, RST, GPIO0, M1, M2, M3>(
+    cs: Pin<CS, M1>,
+    busy: Pin<BUSY, M2>,
+    rst: Pin<RST, M3>,
+    gpio0: GPIO0,
+) -> AirLiftEspControlPins<CS, BUSY, RST, GPIO0>
+where
+    CS: PinId,
+    BUSY: PinId,
+    RST: PinId,
+    GPIO0: OutputPin,
+    M1: PinMode + ValidPinMode<CS>,
+    M2: PinMode + ValidPinMode<BUSY>,
+    M3: PinMode + ValidPinMode<RST>,
+{
+    EspControlPins {
+        cs: cs.into_mode(),
+        gpio0,
+        resetn: rst.into_mode(),
+        ack: busy.into_mode(),
+    }
+}
+
+/// Initialize an AirLift, given its SPI bus `spi` and its control pins from
+/// [`featherwing_esp_control_pins`] or [`breakout_esp_control_pins`].
+///
+/// This is [`Wifi::init`] for the wiring of an AirLift.
+pub fn init<S, CS, BUSY, RST, GPIO0, D>(
+    spi: S,
+    esp_pins: AirLiftEspControlPins<CS, BUSY, RST, GPIO0>,
+    delay: D,
+) -> Result<AirLiftWifi<S, CS, BUSY, RST, GPIO0, D>, Error>
+where
+    S: Transfer<u8>,
+    CS: PinId,
+    BUSY: PinId,
+    RST: PinId,
+    GPIO0: OutputPin,
+    D: DelayMs<u16>,
+{
+    Wifi::init(spi, esp_pins, delay)
+}
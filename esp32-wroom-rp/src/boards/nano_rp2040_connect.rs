@@ -0,0 +1,137 @@
+//! The [Arduino Nano RP2040 Connect](https://docs.arduino.cc/hardware/nano-rp2040-connect),
+//! which pairs the RP2040 with a u-blox NINA-W102 module running Arduino's NINA firmware.
+//!
+//! The NINA-W102 is built around an ESP32 and Arduino's firmware speaks the same protocol as the
+//! ESP32-WROOM's, so the whole driver works unchanged on it. Only the wiring differs from the
+//! other boards and is captured by the presets in this module.
+//!
+//! The NINA-W102 is wired to the RP2040 as follows:
+//!
+//! | RP2040 | NINA-W102 |
+//! | ------ | --------- |
+//! | GPIO8  | CIPO (SPI1 RX) |
+//! | GPIO9  | CS |
+//! | GPIO10 | ACK |
+//! | GPIO11 | COPI (SPI1 TX) |
+//! | GPIO14 | SCK (SPI1 SCK) |
+//! | GPIO2  | GPIO0 |
+//! | GPIO3  | RESETn |
+//!
+//! Unlike the other boards, some of the Nano's headers are connected to the NINA-W102 rather
+//! than the RP2040. The RGB LED is driven through
+//! [`RgbLedPins::nano_rp2040_connect`](crate::esp_gpio::RgbLedPins::nano_rp2040_connect) and
+//! the A6 and A7 analog inputs are read through [`EspGpio`](crate::esp_gpio::EspGpio) with
+//! the pins in [`AnalogInput`].
+//!
+//! ## Usage
+//!
+//! ```no_run
+//! use esp32_wroom_rp::boards::nano_rp2040_connect::{self, AnalogInput};
+//! use esp32_wroom_rp::esp_gpio::{AdcAttenuation, EspGpio};
+//!
+//! let (_cipo, _copi, _sck) = nano_rp2040_connect::spi_pins(pins.gpio8, pins.gpio11, pins.gpio14);
+//! let spi = hal::Spi::<_, _, 8>::new(pac.SPI1).init(
+//!     &mut pac.RESETS,
+//!     clocks.peripheral_clock.freq(),
+//!     8.MHz(),
+//!     &MODE_0,
+//! );
+//!
+//! let esp_pins =
+//!     nano_rp2040_connect::esp_control_pins(pins.gpio9, pins.gpio2, pins.gpio3, pins.gpio10);
+//! let mut wifi = nano_rp2040_connect::init(spi, esp_pins, delay).unwrap();
+//!
+//! let reading = EspGpio::build(&mut wifi)
+//!     .analog_read(AnalogInput::A6.esp_pin(), AdcAttenuation::Db11)
+//!     .unwrap();
+//! ```
+
+use embedded_hal::blocking::delay::DelayMs;
+use embedded_hal::blocking::spi::Transfer;
+
+use rp2040_hal::gpio::bank0::{Gpio10, Gpio11, Gpio14, Gpio2, Gpio3, Gpio8, Gpio9};
+use rp2040_hal::gpio::{FunctionSpi, Pin, PinMode, ValidPinMode};
+
+use crate::esp_gpio::AnalogPin;
+use crate::pins::{esp_control_pins as rp_esp_control_pins, RpEspControlPins};
+use crate::transport::SpiTransport;
+use crate::wifi::Wifi;
+use crate::Error;
+
+/// The [`EspControlPins`](crate::gpio::EspControlPins) of the NINA-W102.
+pub type EspPins = RpEspControlPins<Gpio9, Gpio2, Gpio3, Gpio10>;
+
+/// The SPI1 pins connected to the NINA-W102: CIPO, COPI and SCK.
+pub type SpiPins = (
+    Pin<Gpio8, FunctionSpi>,
+    Pin<Gpio11, FunctionSpi>,
+    Pin<Gpio14, FunctionSpi>,
+);
+
+/// The analog inputs of the Nano's headers that are connected to the NINA-W102 instead of the
+/// RP2040.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AnalogInput {
+    /// The A6 header pin, on the NINA-W102's GPIO36
+    A6,
+    /// The A7 header pin, on the NINA-W102's GPIO35
+    A7,
+}
+
+impl AnalogInput {
+    /// The NINA-W102 pin to read this input with.
+    pub fn esp_pin(self) -> AnalogPin {
+        match self {
+            AnalogInput::A6 => AnalogPin::Gpio36,
+            AnalogInput::A7 => AnalogPin::Gpio35,
+        }
+    }
+}
+
+/// Configures the control pins of the NINA-W102 (CS on GPIO9, GPIO0 on GPIO2, RESETn on GPIO3
+/// and ACK on GPIO10) for their roles.
+pub fn esp_control_pins<M1, M2, M3, M4>(
+    cs: Pin<Gpio9, M1>,
+    gpio0: Pin<Gpio2, M2>,
+    resetn: Pin<Gpio3, M3>,
+    ack: Pin<Gpio10, M4>,
+) -> EspPins
+where
+    M1: PinMode + ValidPinMode<Gpio9>,
+    M2: PinMode + ValidPinMode<Gpio2>,
+    M3: PinMode + ValidPinMode<Gpio3>,
+    M4: PinMode + ValidPinMode<Gpio10>,
+{
+    rp_esp_control_pins(cs, gpio0, resetn, ack)
+}
+
+/// Hands the SPI1 pins connected to the NINA-W102 over to the SPI peripheral. They only need to
+/// be kept around, the SPI driver uses them implicitly.
+pub fn spi_pins<M1, M2, M3>(
+    cipo: Pin<Gpio8, M1>,
+    copi: Pin<Gpio11, M2>,
+    sck: Pin<Gpio14, M3>,
+) -> SpiPins
+where
+    M1: PinMode + ValidPinMode<Gpio8>,
+    M2: PinMode + ValidPinMode<Gpio11>,
+    M3: PinMode + ValidPinMode<Gpio14>,
+{
+    (cipo.into_mode(), copi.into_mode(), sck.into_mode())
+}
+
+/// Initialize the NINA-W102 of the Nano RP2040 Connect, given its SPI1 bus `spi` and its
+/// control pins from [`esp_control_pins`].
+///
+/// This is [`Wifi::init`] with the wiring of the board fixed in its type.
+pub fn init<S, D>(
+    spi: S,
+    esp_pins: EspPins,
+    delay: D,
+) -> Result<Wifi<SpiTransport<S, EspPins>, D>, Error>
+where
+    S: Transfer<u8>,
+    D: DelayMs<u16>,
+{
+    Wifi::init(spi, esp_pins, delay)
+}
@@ -0,0 +1,154 @@
+//! The [Pimoroni Pico Wireless Pack](https://shop.pimoroni.com/products/pico-wireless-pack),
+//! which plugs an ESP32-WROOM-32E running Pimoroni's build of the NINA firmware, a micro SD card
+//! slot, a button and an RGB LED onto the pins of a Pico.
+//!
+//! The pack is wired to the Pico as follows:
+//!
+//! | Pico | Pico Wireless Pack |
+//! | ---- | ------------------ |
+//! | GP16 | MISO (SPI0 RX), shared by the ESP32 and the SD card |
+//! | GP18 | SCLK (SPI0 SCK), shared by the ESP32 and the SD card |
+//! | GP19 | MOSI (SPI0 TX), shared by the ESP32 and the SD card |
+//! | GP7  | ESP32 CS |
+//! | GP2  | ESP32 GPIO0 |
+//! | GP10 | ESP32 ACK |
+//! | GP11 | ESP32 RESETn |
+//! | GP22 | SD card CS |
+//! | GP12 | Button A, active low |
+//!
+//! This is the wiring of the examples under `cross/`, so the pack's control pins are the
+//! [`DefaultEspControlPins`]. The RGB LED is connected to the ESP32 and is driven through
+//! [`RgbLedPins::pico_wireless_pack`](crate::esp_gpio::RgbLedPins::pico_wireless_pack).
+//!
+//! ## Sharing the bus with the SD card
+//!
+//! The ESP32 and the SD card both sit on SPI0, each with its own chip select. [`sd_card_cs`]
+//! drives the SD card's chip select high so it stays off the bus while only WiFi is in use. To
+//! use both, pass [`init`] one proxy of a bus manager (e.g. the `shared-bus` crate) and the SD
+//! card driver another, together with the pin from [`sd_card_cs`]. Only one of them may have
+//! its chip select asserted at a time, which the drivers guarantee as long as they're used from
+//! the same thread of execution.
+//!
+//! ## Usage
+//!
+//! ```no_run
+//! use esp32_wroom_rp::boards::pico_wireless_pack;
+//! use esp32_wroom_rp::esp_gpio::{RgbLed, RgbLedPins};
+//!
+//! let (_miso, _mosi, _sclk) =
+//!     pico_wireless_pack::spi_pins(pins.gpio16, pins.gpio19, pins.gpio18);
+//! let _sd_card_cs = pico_wireless_pack::sd_card_cs(pins.gpio22);
+//! let button = pico_wireless_pack::button(pins.gpio12);
+//!
+//! let spi = hal::Spi::<_, _, 8>::new(pac.SPI0).init(
+//!     &mut pac.RESETS,
+//!     clocks.peripheral_clock.freq(),
+//!     8.MHz(),
+//!     &MODE_0,
+//! );
+//!
+//! let esp_pins =
+//!     pico_wireless_pack::esp_control_pins(pins.gpio7, pins.gpio2, pins.gpio11, pins.gpio10);
+//! let mut wifi = pico_wireless_pack::init(spi, esp_pins, delay).unwrap();
+//!
+//! let mut rgb_led = RgbLed::new(&mut wifi, RgbLedPins::pico_wireless_pack()).unwrap();
+//! if button.is_low().unwrap() {
+//!     rgb_led.set_color(0, 0, 255).unwrap();
+//! }
+//! ```
+//!
+//! See `cross/pico_wireless_pack` for the complete example.
+
+use embedded_hal::blocking::delay::DelayMs;
+use embedded_hal::blocking::spi::Transfer;
+use embedded_hal::digital::v2::OutputPin;
+
+use rp2040_hal::gpio::bank0::{
+    Gpio10, Gpio11, Gpio12, Gpio16, Gpio18, Gpio19, Gpio2, Gpio22, Gpio7,
+};
+use rp2040_hal::gpio::{FunctionSpi, Pin, PinMode, PullUpInput, PushPullOutput, ValidPinMode};
+
+use crate::pins::{default_esp_control_pins, DefaultEspControlPins};
+use crate::transport::SpiTransport;
+use crate::wifi::Wifi;
+use crate::Error;
+
+/// The SPI0 pins shared by the ESP32 and the SD card: MISO, MOSI and SCLK.
+pub type SpiPins = (
+    Pin<Gpio16, FunctionSpi>,
+    Pin<Gpio19, FunctionSpi>,
+    Pin<Gpio18, FunctionSpi>,
+);
+
+/// The chip select pin of the SD card.
+pub type SdCardCs = Pin<Gpio22, PushPullOutput>;
+
+/// The pin of button A, which reads low while the button is pressed.
+pub type Button = Pin<Gpio12, PullUpInput>;
+
+/// Configures the control pins of the pack's ESP32 (CS on GP7, GPIO0 on GP2, RESETn on GP11 and
+/// ACK on GP10) for their roles.
+pub fn esp_control_pins<M1, M2, M3, M4>(
+    cs: Pin<Gpio7, M1>,
+    gpio0: Pin<Gpio2, M2>,
+    resetn: Pin<Gpio11, M3>,
+    ack: Pin<Gpio10, M4>,
+) -> DefaultEspControlPins
+where
+    M1: PinMode + ValidPinMode<Gpio7>,
+    M2: PinMode + ValidPinMode<Gpio2>,
+    M3: PinMode + ValidPinMode<Gpio11>,
+    M4: PinMode + ValidPinMode<Gpio10>,
+{
+    default_esp_control_pins(cs, gpio0, resetn, ack)
+}
+
+/// Hands the SPI0 pins over to the SPI peripheral. They only need to be kept around, the SPI
+/// driver uses them implicitly.
+pub fn spi_pins<M1, M2, M3>(
+    miso: Pin<Gpio16, M1>,
+    mosi: Pin<Gpio19, M2>,
+    sclk: Pin<Gpio18, M3>,
+) -> SpiPins
+where
+    M1: PinMode + ValidPinMode<Gpio16>,
+    M2: PinMode + ValidPinMode<Gpio19>,
+    M3: PinMode + ValidPinMode<Gpio18>,
+{
+    (miso.into_mode(), mosi.into_mode(), sclk.into_mode())
+}
+
+/// Configures the chip select pin of the SD card as an output and deselects the SD card, so it
+/// doesn't interfere with the ESP32 on the shared bus.
+pub fn sd_card_cs<M>(cs: Pin<Gpio22, M>) -> SdCardCs
+where
+    M: PinMode + ValidPinMode<Gpio22>,
+{
+    let mut cs: SdCardCs = cs.into_mode();
+    cs.set_high().ok();
+    cs
+}
+
+/// Configures the pin of button A as an input with its pull-up resistor enabled.
+pub fn button<M>(button: Pin<Gpio12, M>) -> Button
+where
+    M: PinMode + ValidPinMode<Gpio12>,
+{
+    button.into_mode()
+}
+
+/// Initialize the ESP32 of the Pico Wireless Pack, given the SPI0 bus `spi`, or a proxy of it
+/// when sharing it with the SD card, and its control pins from [`esp_control_pins`].
+///
+/// The SD card has to be deselected with [`sd_card_cs`] beforehand.
+pub fn init<S, D>(
+    spi: S,
+    esp_pins: DefaultEspControlPins,
+    delay: D,
+) -> Result<Wifi<SpiTransport<S, DefaultEspControlPins>, D>, Error>
+where
+    S: Transfer<u8>,
+    D: DelayMs<u16>,
+{
+    Wifi::init(spi, esp_pins, delay)
+}
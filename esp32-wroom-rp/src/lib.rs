@@ -8,27 +8,45 @@
 //! It's intended to communicate with recent versions of most [Arduino-derived WiFiNINA firmwares](https://www.arduino.cc/reference/en/libraries/wifinina/)
 //! that run on an ESP32-WROOM-XX WiFi module. For example, Adafruit makes such WiFi hardware, referred to as the [Airlift](https://www.adafruit.com/product/4201), and maintains its [firmware](https://github.com/adafruit/nina-fw).
 //!
-//! This driver is implemented on top of [embedded-hal](https://github.com/rust-embedded/embedded-hal/), which makes it platform-independent, but is currently only intended
-//! to be used with [rp2040-hal](https://github.com/rp-rs/rp-hal/tree/main/rp2040-hal) for your application.
+//! The driver itself lives in the MCU-agnostic [esp32-wroom-core](https://docs.rs/esp32-wroom-core) crate, which is implemented on top of
+//! [embedded-hal](https://github.com/rust-embedded/embedded-hal/). This crate re-exports all of it and adds pin presets for
+//! [rp2040-hal](https://github.com/rp-rs/rp-hal/tree/main/rp2040-hal) in [`pins`], as well as the examples under `cross/`.
 //!
 //! Please see the README.md for details on where to obtain and how to connect your RP2040-based device (e.g. Pico) to your ESP32-WROOM-XX WiFi board.
 //!
 //! Once connected, note that all communication with the WiFi board occurs via a SPI bus. As the example below (and all examples under the directory `cross/`)
-//! show, you first need to create an `embedded_hal::spi::Spi` instance. See the [rp2040-hal documentation](https://docs.rs/rp2040-hal/0.6.0/rp2040_hal/spi/index.html) along
+//! show, you first need to create an `embedded_hal::spi::Spi` instance. See the [rp2040-hal documentation](https://docs.rs/rp2040-hal/0.8.2/rp2040_hal/spi/index.html) along
 //! with the datasheet for your device on what specific SPI ports are available to you.
 //!
-//! You'll also need to reserve 4 important [GPIO pins](https://docs.rs/rp2040-hal/0.6.0/rp2040_hal/gpio/index.html) (3 output, 1 input) that are used to mediate communication between the two boards. The examples
-//! also demonstrate how to do this through instantiating an instance of `esp32_wroom_rp::gpio::EspControlPins`.
+//! You'll also need to reserve 4 important [GPIO pins](https://docs.rs/rp2040-hal/0.8.2/rp2040_hal/gpio/index.html) (3 output, 1 input) that are used to mediate communication between the two boards. The examples
+//! also demonstrate how to do this through the presets in `esp32_wroom_rp::pins`.
 //!
 //! **NOTE:** This crate is still under active development. This API will remain volatile until 1.0.0.
 //!
+//! ## Crate layout
+//!
+//! Everything but the pin presets is re-exported from esp32-wroom-core, so `esp32_wroom_rp::wifi::Wifi`
+//! and `esp32_wroom_core::wifi::Wifi` are the same type. Applications on other microcontrollers (e.g. STM32
+//! or nRF52) can depend on esp32-wroom-core directly and construct a [`gpio::EspControlPins`] from their own
+//! HAL's pin types.
+//!
+//! The runtime crates an application needs (e.g. `cortex-m-rt`, `defmt-rtt`, `panic-probe`
+//! and `rp2040-hal`) are left for the application itself to depend on, as the examples
+//! under `cross/` do.
+//!
+//! ## Logging
+//!
+//! The driver doesn't log anything by default. Enable the `defmt` feature to log through
+//! [defmt](https://defmt.ferrous-systems.com) and implement `defmt::Format` for the crate's
+//! public types, or the `log` feature to log through the [log](https://docs.rs/log) crate.
+//!
 //! ## Usage
 //!
 //! First add this to your Cargo.toml
 //!
 //! ```toml
 //! [dependencies]
-//! esp32_wroom_rp = 0.3.0
+//! esp32_wroom_rp = { version = "0.3.0", features = ["defmt"] }
 //! ...
 //! ```
 //!
@@ -51,7 +69,6 @@
 //! use hal::clocks::Clock;
 //! use hal::pac;
 //!
-//! use esp32_wroom_rp::gpio::EspControlPins;
 //! use esp32_wroom_rp::wifi::Wifi;
 //!
 //! // The linker will place this boot block at the start of our program image. We
@@ -90,7 +107,7 @@
 //!     .ok()
 //!     .unwrap();
 //!
-//!     let mut delay = cortex_m::delay::Delay::new(core.SYST, clocks.system_clock.freq().to_Hz());
+//!     let delay = cortex_m::delay::Delay::new(core.SYST, clocks.system_clock.freq().to_Hz());
 //!
 //!     // The single-cycle I/O block controls our GPIO pins
 //!     let sio = hal::Sio::new(pac.SIO);
@@ -112,7 +129,8 @@
 //!
 //!     let spi = hal::Spi::<_, _, 8>::new(pac.SPI0);
 //!
-//!     // Exchange the uninitialized SPI driver for an initialized one
+//!     // Exchange the uninitialized SPI driver for an initialized one, clocked at the NINA
+//!     // firmware's default of 8 MHz (see `transport` for the safe range and checking it)
 //!     let spi = spi.init(
 //!         &mut pac.RESETS,
 //!         clocks.peripheral_clock.freq(),
@@ -120,17 +138,14 @@
 //!         &MODE_0,
 //!     );
 //!
-//!     let esp_pins = EspControlPins {
-//!         // CS on pin x (GPIO7)
-//!         cs: pins.gpio7.into_mode::<hal::gpio::PushPullOutput>(),
-//!         // GPIO0 on pin x (GPIO2)
-//!         gpio0: pins.gpio2.into_mode::<hal::gpio::PushPullOutput>(),
-//!         // RESETn on pin x (GPIO11)
-//!         resetn: pins.gpio11.into_mode::<hal::gpio::PushPullOutput>(),
-//!         // ACK on pin x (GPIO10)
-//!         ack: pins.gpio10.into_mode::<hal::gpio::FloatingInput>(),
-//!     };
-//!     let mut wifi = Wifi::init(spi, esp_pins, &mut delay).unwrap();
+//!     // CS on GPIO7, GPIO0 on GPIO2, RESETn on GPIO11 and ACK on GPIO10
+//!     let esp_pins = esp32_wroom_rp::pins::default_esp_control_pins(
+//!         pins.gpio7,
+//!         pins.gpio2,
+//!         pins.gpio11,
+//!         pins.gpio10,
+//!     );
+//!     let mut wifi = Wifi::init(spi, esp_pins, delay).unwrap();
 //!     let firmware_version = wifi.firmware_version();
 //!     defmt::info!("NINA firmware version: {:?}", firmware_version);
 //!
@@ -147,112 +162,9 @@
 #![doc(html_root_url = "https://docs.rs/esp32-wroom-rp")]
 #![doc(issue_tracker_base_url = "https://github.com/Jim-Hodapp-Coaching/esp32-wroom-rp/issues")]
 #![warn(missing_docs)]
-#![cfg_attr(not(test), no_std)]
-
-pub mod gpio;
-pub mod network;
-pub mod protocol;
-pub mod tcp_client;
-pub mod wifi;
-
-mod spi;
-
-use defmt::{write, Format, Formatter};
-
-use network::NetworkError;
-
-use protocol::ProtocolError;
-
-/// Highest level error types for this crate.
-#[derive(Debug, Eq, PartialEq)]
-pub enum Error {
-    /// SPI/I2C related communications error with the ESP32 WiFi target
-    Bus,
-    /// Protocol error in communicating with the ESP32 WiFi target
-    Protocol(ProtocolError),
-
-    /// Network related error
-    Network(NetworkError),
-}
-
-impl Format for Error {
-    fn format(&self, fmt: Formatter) {
-        match self {
-            Error::Bus => write!(fmt, "Bus error"),
-            Error::Protocol(e) => write!(
-                fmt,
-                "Communication protocol error with ESP32 WiFi target: {}",
-                e
-            ),
-            Error::Network(e) => write!(fmt, "Network error: {}", e),
-        }
-    }
-}
-
-impl From<protocol::ProtocolError> for Error {
-    fn from(err: protocol::ProtocolError) -> Self {
-        Error::Protocol(err)
-    }
-}
-
-impl From<network::NetworkError> for Error {
-    fn from(err: network::NetworkError) -> Self {
-        Error::Network(err)
-    }
-}
-
-/// A structured representation of a connected NINA firmware device's version number (e.g. 1.7.4).
-#[derive(Debug, Default, Eq, PartialEq)]
-pub struct FirmwareVersion {
-    major: u8,
-    minor: u8,
-    patch: u8,
-}
-
-impl FirmwareVersion {
-    fn new(version: &[u8]) -> FirmwareVersion {
-        Self::parse(version)
-    }
-
-    // Takes in 8 bytes (e.g. 1.7.4) and returns a FirmwareVersion instance
-    fn parse(version: &[u8]) -> FirmwareVersion {
-        let major_version: u8 = version[0];
-        let minor_version: u8 = version[2];
-        let patch_version: u8 = version[4];
-
-        FirmwareVersion {
-            major: major_version,
-            minor: minor_version,
-            patch: patch_version,
-        }
-    }
-}
-
-impl Format for FirmwareVersion {
-    fn format(&self, fmt: Formatter) {
-        write!(
-            fmt,
-            "Major: {:?}, Minor: {:?}, Patch: {:?}",
-            self.major as char, self.minor as char, self.patch as char
-        );
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
+#![no_std]
 
-    #[test]
-    fn firmware_new_returns_a_populated_firmware_struct() {
-        let firmware_version: FirmwareVersion = FirmwareVersion::new(&[0x1, 0x2e, 0x7, 0x2e, 0x4]);
+pub mod boards;
+pub mod pins;
 
-        assert_eq!(
-            firmware_version,
-            FirmwareVersion {
-                major: 1,
-                minor: 7,
-                patch: 4
-            }
-        )
-    }
-}
+pub use esp32_wroom_core::*;
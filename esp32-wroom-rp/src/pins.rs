@@ -0,0 +1,94 @@
+//! Presets for the RP2040 GPIO pins that control a connected ESP32-WROOM target WiFi board.
+//!
+//! ## Usage
+//!
+//! ```no_run
+//! let mut pac = pac::Peripherals::take().unwrap();
+//!
+//! // The single-cycle I/O block controls our GPIO pins
+//! let sio = hal::Sio::new(pac.SIO);
+//!
+//! // Set the pins to their default state
+//! let pins = hal::gpio::Pins::new(
+//!     pac.IO_BANK0,
+//!     pac.PADS_BANK0,
+//!     sio.gpio_bank0,
+//!     &mut pac.RESETS,
+//! );
+//!
+//! // CS on GPIO7, GPIO0 on GPIO2, RESETn on GPIO11 and ACK on GPIO10
+//! let esp_pins = esp32_wroom_rp::pins::default_esp_control_pins(
+//!     pins.gpio7,
+//!     pins.gpio2,
+//!     pins.gpio11,
+//!     pins.gpio10,
+//! );
+//!
+//! // Or any other wiring
+//! let esp_pins = esp32_wroom_rp::pins::esp_control_pins(
+//!     pins.gpio17,
+//!     pins.gpio3,
+//!     pins.gpio20,
+//!     pins.gpio21,
+//! );
+//! ```
+
+use rp2040_hal::gpio::bank0::{Gpio10, Gpio11, Gpio2, Gpio7};
+use rp2040_hal::gpio::{FloatingInput, Pin, PinId, PinMode, PushPullOutput, ValidPinMode};
+
+use crate::gpio::EspControlPins;
+
+/// [`EspControlPins`] made of RP2040 pins that are configured for their roles: push-pull
+/// outputs for CS, GPIO0 and RESETn and a floating input for ACK.
+pub type RpEspControlPins<CS, GPIO0, RESETN, ACK> = EspControlPins<
+    Pin<CS, PushPullOutput>,
+    Pin<GPIO0, PushPullOutput>,
+    Pin<RESETN, PushPullOutput>,
+    Pin<ACK, FloatingInput>,
+>;
+
+/// The [`EspControlPins`] wired as in the README and used by all examples under `cross/`.
+pub type DefaultEspControlPins = RpEspControlPins<Gpio7, Gpio2, Gpio11, Gpio10>;
+
+/// Configures the given RP2040 pins, in any mode, for their roles in controlling the ESP32
+/// target and returns them as [`EspControlPins`] ready to be passed into `Wifi::init()`.
+pub fn esp_control_pins<CS, GPIO0, RESETN, ACK, M1, M2, M3, M4>(
+    cs: Pin<CS, M1>,
+    gpio0: Pin<GPIO0, M2>,
+    resetn: Pin<RESETN, M3>,
+    ack: Pin<ACK, M4>,
+) -> RpEspControlPins<CS, GPIO0, RESETN, ACK>
+where
+    CS: PinId,
+    GPIO0: PinId,
+    RESETN: PinId,
+    ACK: PinId,
+    M1: PinMode + ValidPinMode<CS>,
+    M2: PinMode + ValidPinMode<GPIO0>,
+    M3: PinMode + ValidPinMode<RESETN>,
+    M4: PinMode + ValidPinMode<ACK>,
+{
+    EspControlPins {
+        cs: cs.into_mode(),
+        gpio0: gpio0.into_mode(),
+        resetn: resetn.into_mode(),
+        ack: ack.into_mode(),
+    }
+}
+
+/// Configures the pins of the default wiring (CS on GPIO7, GPIO0 on GPIO2, RESETn on GPIO11
+/// and ACK on GPIO10) for their roles in controlling the ESP32 target.
+pub fn default_esp_control_pins<M1, M2, M3, M4>(
+    cs: Pin<Gpio7, M1>,
+    gpio0: Pin<Gpio2, M2>,
+    resetn: Pin<Gpio11, M3>,
+    ack: Pin<Gpio10, M4>,
+) -> DefaultEspControlPins
+where
+    M1: PinMode + ValidPinMode<Gpio7>,
+    M2: PinMode + ValidPinMode<Gpio2>,
+    M3: PinMode + ValidPinMode<Gpio11>,
+    M4: PinMode + ValidPinMode<Gpio10>,
+{
+    esp_control_pins(cs, gpio0, resetn, ack)
+}
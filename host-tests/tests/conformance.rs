@@ -0,0 +1,636 @@
+// NINA protocol conformance test vectors.
+//
+// Every vector lists the exact bytes of one or more command/reply exchanges as laid
+// out by the nina-fw CommandHandler (https://github.com/arduino/nina-fw/blob/master/main/CommandHandler.cpp):
+//
+//   request: START_CMD (0xe0), command, number of params, [param length, param bytes]...,
+//            END_CMD (0xee), then 0xff padding up to a multiple of 4 bytes
+//   reply:   START_CMD (0xe0), command | REPLY_FLAG (0x80), number of params,
+//            [param length, param bytes]..., END_CMD (0xee)
+//
+// Param lengths are 1 byte, except for data commands (0x40 to 0x46) whose request params
+// and data replies use 2 byte big-endian lengths.
+
+use core::net::{Ipv4Addr, SocketAddrV4};
+
+use embedded_hal_mock::delay::MockNoop;
+use embedded_hal_mock::spi;
+
+use esp32_wroom_core::clock::Clock;
+use esp32_wroom_core::credentials::EnterpriseConfig;
+use esp32_wroom_core::dns::DnsCacheConfig;
+use esp32_wroom_core::esp_gpio::{AdcAttenuation, AnalogPin, EspGpio, PinMode, RgbLed, RgbLedPins};
+use esp32_wroom_core::network::IpConfig;
+use esp32_wroom_core::tcp_server::TcpServer;
+use esp32_wroom_core::transport::SpiTransport;
+use esp32_wroom_core::udp_client::UdpClient;
+use esp32_wroom_core::wifi::{AccessPointConfig, ConnectionStatus, EncryptionType, Wifi};
+
+pub mod support;
+
+use support::*;
+
+type MockWifi = Wifi<SpiTransport<BytewiseSpiMock, EspControlMock>, MockNoop>;
+
+struct FixedClock;
+
+impl Clock for FixedClock {
+    fn now_ms(&self) -> u32 {
+        0
+    }
+}
+
+struct Exchange {
+    request: &'static [u8],
+    reply: &'static [u8],
+}
+
+struct TestVector {
+    name: &'static str,
+    exchanges: &'static [Exchange],
+    run: fn(&mut MockWifi),
+}
+
+const TEST_VECTORS: &[TestVector] = &[
+    TestVector {
+        name: "GET_FW_VERSION",
+        exchanges: &[Exchange {
+            request: &[0xe0, 0x37, 0x00, 0xee],
+            reply: &[
+                0xe0, 0xb7, 0x01, 0x06, b'1', b'.', b'7', b'.', b'4', 0x00, 0xee,
+            ],
+        }],
+        run: |wifi| {
+            assert_eq!(
+                wifi.firmware_version().unwrap().to_string(),
+                "1.7.4".to_string()
+            );
+        },
+    },
+    TestVector {
+        name: "GET_CONN_STATUS",
+        exchanges: &[Exchange {
+            request: &[0xe0, 0x20, 0x00, 0xee],
+            reply: &[0xe0, 0xa0, 0x01, 0x01, 0x03, 0xee],
+        }],
+        run: |wifi| {
+            assert_eq!(
+                wifi.get_connection_status().unwrap(),
+                ConnectionStatus::Connected
+            );
+        },
+    },
+    TestVector {
+        name: "SET_NET",
+        exchanges: &[Exchange {
+            request: &[0xe0, 0x10, 0x01, 0x02, b'a', b'b', 0xee, 0xff],
+            reply: &[0xe0, 0x90, 0x01, 0x01, 0x01, 0xee],
+        }],
+        run: |wifi| {
+            wifi.join_open("ab").unwrap();
+        },
+    },
+    TestVector {
+        name: "SET_ENT_IDENT + SET_ENT_UNAME + SET_ENT_PASSWD + SET_ENT_ENABLE + SET_NET",
+        exchanges: &[
+            Exchange {
+                request: &[0xe0, 0x4a, 0x01, 0x02, b'i', b'd', 0xee, 0xff],
+                reply: &[0xe0, 0xca, 0x01, 0x01, 0x01, 0xee],
+            },
+            Exchange {
+                request: &[0xe0, 0x4b, 0x01, 0x02, b'u', b'n', 0xee, 0xff],
+                reply: &[0xe0, 0xcb, 0x01, 0x01, 0x01, 0xee],
+            },
+            Exchange {
+                request: &[0xe0, 0x4c, 0x01, 0x02, b'p', b'w', 0xee, 0xff],
+                reply: &[0xe0, 0xcc, 0x01, 0x01, 0x01, 0xee],
+            },
+            Exchange {
+                request: &[0xe0, 0x4f, 0x00, 0xee],
+                reply: &[0xe0, 0xcf, 0x01, 0x01, 0x01, 0xee],
+            },
+            Exchange {
+                request: &[0xe0, 0x10, 0x01, 0x02, b'a', b'b', 0xee, 0xff],
+                reply: &[0xe0, 0x90, 0x01, 0x01, 0x01, 0xee],
+            },
+        ],
+        run: |wifi| {
+            let config = EnterpriseConfig::new("un", "pw").identity("id");
+            wifi.join_enterprise("ab", &config).unwrap();
+        },
+    },
+    TestVector {
+        name: "SET_PASSPHRASE",
+        exchanges: &[Exchange {
+            request: &[
+                0xe0, 0x11, 0x02, 0x02, b'a', b'b', 0x02, b'c', b'd', 0xee, 0xff, 0xff,
+            ],
+            reply: &[0xe0, 0x91, 0x01, 0x01, 0x01, 0xee],
+        }],
+        run: |wifi| {
+            wifi.join("ab", "cd").unwrap();
+        },
+    },
+    TestVector {
+        name: "SET_PASSPHRASE with UTF-8 SSID and passphrase",
+        exchanges: &[Exchange {
+            // "☕" is 3 bytes long and "é" is 2 bytes long
+            request: &[
+                0xe0, 0x11, 0x02, 0x03, 0xe2, 0x98, 0x95, 0x02, 0xc3, 0xa9, 0xee, 0xff,
+            ],
+            reply: &[0xe0, 0x91, 0x01, 0x01, 0x01, 0xee],
+        }],
+        run: |wifi| {
+            wifi.join("☕", "é").unwrap();
+        },
+    },
+    TestVector {
+        name: "SET_AP_NET",
+        exchanges: &[Exchange {
+            request: &[
+                0xe0, 0x18, 0x02, 0x02, b'a', b'b', 0x01, 0x06, 0xee, 0xff, 0xff, 0xff,
+            ],
+            reply: &[0xe0, 0x98, 0x01, 0x01, 0x01, 0xee],
+        }],
+        run: |wifi| {
+            wifi.start_access_point(&AccessPointConfig::new("ab").channel(6))
+                .unwrap();
+        },
+    },
+    TestVector {
+        name: "SET_AP_PASSPHRASE",
+        exchanges: &[Exchange {
+            request: &[
+                0xe0, 0x19, 0x03, 0x02, b'a', b'b', 0x02, b'c', b'd', 0x01, 0x01, 0xee,
+            ],
+            reply: &[0xe0, 0x99, 0x01, 0x01, 0x01, 0xee],
+        }],
+        run: |wifi| {
+            wifi.start_access_point(&AccessPointConfig::new("ab").passphrase("cd"))
+                .unwrap();
+        },
+    },
+    TestVector {
+        name: "DISCONNECT",
+        exchanges: &[Exchange {
+            request: &[0xe0, 0x30, 0x01, 0x01, 0xff, 0xee, 0xff, 0xff],
+            reply: &[0xe0, 0xb0, 0x01, 0x01, 0x01, 0xee],
+        }],
+        run: |wifi| {
+            wifi.leave().unwrap();
+        },
+    },
+    TestVector {
+        name: "SET_IP_CONFIG",
+        exchanges: &[Exchange {
+            request: &[
+                0xe0, 0x14, 0x04, 0x01, 0x03, 0x04, 0x0a, 0x00, 0x00, 0x14, 0x04, 0x0a, 0x00, 0x00,
+                0xfe, 0x04, 0xff, 0xff, 0x00, 0x00, 0xee, 0xff, 0xff, 0xff,
+            ],
+            reply: &[0xe0, 0x94, 0x01, 0x01, 0x01, 0xee],
+        }],
+        run: |wifi| {
+            let ip_config = IpConfig::new(Ipv4Addr::new(10, 0, 0, 20))
+                .gateway(Ipv4Addr::new(10, 0, 0, 254))
+                .subnet_mask(Ipv4Addr::new(255, 255, 0, 0));
+
+            wifi.set_ip_config(&ip_config).unwrap();
+        },
+    },
+    TestVector {
+        name: "GET_IPADDR",
+        exchanges: &[Exchange {
+            request: &[0xe0, 0x21, 0x01, 0x01, 0xff, 0xee, 0xff, 0xff],
+            reply: &[
+                0xe0, 0xa1, 0x03, 0x04, 0xc0, 0xa8, 0x01, 0x2a, 0x04, 0xff, 0xff, 0xff, 0x00, 0x04,
+                0xc0, 0xa8, 0x01, 0xfe, 0xee,
+            ],
+        }],
+        run: |wifi| {
+            let ip_config = wifi.get_ip_config().unwrap();
+
+            assert_eq!(ip_config.ip, Ipv4Addr::new(192, 168, 1, 42));
+            assert_eq!(ip_config.subnet_mask, Ipv4Addr::new(255, 255, 255, 0));
+            assert_eq!(ip_config.gateway, Ipv4Addr::new(192, 168, 1, 254));
+        },
+    },
+    TestVector {
+        name: "GET_MACADDR",
+        exchanges: &[Exchange {
+            request: &[0xe0, 0x22, 0x01, 0x01, 0xff, 0xee, 0xff, 0xff],
+            reply: &[
+                0xe0, 0xa2, 0x01, 0x06, 0x5e, 0x3c, 0x0b, 0x12, 0xcf, 0xa4, 0xee,
+            ],
+        }],
+        run: |wifi| {
+            let mac_address = wifi.get_mac_address().unwrap();
+
+            assert_eq!(mac_address.to_string(), "a4:cf:12:0b:3c:5e");
+        },
+    },
+    TestVector {
+        name: "GET_CURR_SSID + GET_CURR_BSSID + GET_CURR_RSSI + GET_CURR_ENCT",
+        exchanges: &[
+            Exchange {
+                request: &[0xe0, 0x23, 0x01, 0x01, 0xff, 0xee, 0xff, 0xff],
+                reply: &[0xe0, 0xa3, 0x01, 0x04, b'h', b'o', b'm', b'e', 0xee],
+            },
+            Exchange {
+                request: &[0xe0, 0x24, 0x01, 0x01, 0xff, 0xee, 0xff, 0xff],
+                reply: &[0xe0, 0xa4, 0x01, 0x06, 0x5e, 0x3c, 0x0b, 0x12, 0xcf, 0xa4, 0xee],
+            },
+            Exchange {
+                request: &[0xe0, 0x25, 0x01, 0x01, 0xff, 0xee, 0xff, 0xff],
+                reply: &[0xe0, 0xa5, 0x01, 0x04, 0xc4, 0xff, 0xff, 0xff, 0xee],
+            },
+            Exchange {
+                request: &[0xe0, 0x26, 0x01, 0x01, 0xff, 0xee, 0xff, 0xff],
+                reply: &[0xe0, 0xa6, 0x01, 0x01, 0x04, 0xee],
+            },
+        ],
+        run: |wifi| {
+            let connection_info = wifi.connection_info().unwrap();
+
+            assert_eq!(connection_info.ssid(), Some("home"));
+            assert_eq!(
+                connection_info.bssid().to_string(),
+                "a4:cf:12:0b:3c:5e"
+            );
+            assert_eq!(connection_info.rssi(), -60);
+            assert_eq!(connection_info.encryption_type(), EncryptionType::Ccmp);
+        },
+    },
+    TestVector {
+        name: "SET_HOSTNAME",
+        exchanges: &[Exchange {
+            request: &[
+                0xe0, 0x16, 0x01, 0x04, b'p', b'i', b'c', b'o', 0xee, 0xff, 0xff, 0xff,
+            ],
+            reply: &[0xe0, 0x96, 0x01, 0x01, 0x01, 0xee],
+        }],
+        run: |wifi| {
+            wifi.set_hostname("pico").unwrap();
+        },
+    },
+    TestVector {
+        name: "GET_TIME",
+        exchanges: &[Exchange {
+            request: &[0xe0, 0x3b, 0x01, 0x01, 0xff, 0xee, 0xff, 0xff],
+            reply: &[0xe0, 0xbb, 0x01, 0x04, 0x00, 0xe1, 0xf5, 0x65, 0xee],
+        }],
+        run: |wifi| {
+            assert_eq!(wifi.get_time().unwrap(), 1_710_612_736);
+        },
+    },
+    TestVector {
+        name: "SET_DEBUG",
+        exchanges: &[Exchange {
+            request: &[0xe0, 0x1a, 0x01, 0x01, 0x01, 0xee, 0xff, 0xff],
+            reply: &[0xe0, 0x9a, 0x01, 0x01, 0x01, 0xee],
+        }],
+        run: |wifi| {
+            wifi.set_firmware_debug(true).unwrap();
+        },
+    },
+    TestVector {
+        name: "GET_TEMPERATURE",
+        exchanges: &[Exchange {
+            request: &[0xe0, 0x1b, 0x01, 0x01, 0xff, 0xee, 0xff, 0xff],
+            reply: &[0xe0, 0x9b, 0x01, 0x04, 0x00, 0x00, 0xcc, 0x41, 0xee],
+        }],
+        run: |wifi| {
+            assert_eq!(wifi.get_temperature().unwrap(), 2550);
+        },
+    },
+    TestVector {
+        name: "SET_POWER_MODE",
+        exchanges: &[Exchange {
+            request: &[0xe0, 0x17, 0x01, 0x01, 0x01, 0xee, 0xff, 0xff],
+            reply: &[0xe0, 0x97, 0x01, 0x01, 0x01, 0xee],
+        }],
+        run: |wifi| {
+            wifi.set_low_power_mode(true).unwrap();
+        },
+    },
+    TestVector {
+        name: "GET_REMOTE_DATA",
+        exchanges: &[Exchange {
+            request: &[0xe0, 0x3a, 0x01, 0x01, 0x01, 0xee, 0xff, 0xff],
+            reply: &[
+                0xe0, 0xba, 0x02, 0x04, 0xc0, 0xa8, 0x01, 0x07, 0x02, 0xc3, 0x50, 0xee,
+            ],
+        }],
+        run: |wifi| {
+            let mut tcp_server = TcpServer::build(wifi);
+
+            assert_eq!(
+                tcp_server.remote_address(1).unwrap(),
+                SocketAddrV4::new(Ipv4Addr::new(192, 168, 1, 7), 50000)
+            );
+        },
+    },
+    TestVector {
+        name: "SET_PIN_MODE",
+        exchanges: &[Exchange {
+            request: &[0xe0, 0x50, 0x02, 0x01, 0x05, 0x01, 0x01, 0xee],
+            reply: &[0xe0, 0xd0, 0x01, 0x01, 0x01, 0xee],
+        }],
+        run: |wifi| {
+            EspGpio::build(wifi).pin_mode(5, PinMode::Output).unwrap();
+        },
+    },
+    TestVector {
+        name: "SET_DIGITAL_WRITE",
+        exchanges: &[Exchange {
+            request: &[0xe0, 0x51, 0x02, 0x01, 0x05, 0x01, 0x01, 0xee],
+            reply: &[0xe0, 0xd1, 0x01, 0x01, 0x01, 0xee],
+        }],
+        run: |wifi| {
+            EspGpio::build(wifi).digital_write(5, true).unwrap();
+        },
+    },
+    TestVector {
+        name: "SET_ANALOG_WRITE",
+        exchanges: &[Exchange {
+            request: &[0xe0, 0x52, 0x02, 0x01, 0x1a, 0x01, 0x40, 0xee],
+            reply: &[0xe0, 0xd2, 0x01, 0x01, 0x01, 0xee],
+        }],
+        run: |wifi| {
+            EspGpio::build(wifi).analog_write(26, 64).unwrap();
+        },
+    },
+    TestVector {
+        name: "GET_DIGITAL_READ",
+        exchanges: &[Exchange {
+            request: &[0xe0, 0x53, 0x01, 0x01, 0x04, 0xee, 0xff, 0xff],
+            reply: &[0xe0, 0xd3, 0x01, 0x01, 0x01, 0xee],
+        }],
+        run: |wifi| {
+            assert!(EspGpio::build(wifi).digital_read(4).unwrap());
+        },
+    },
+    TestVector {
+        name: "RGB_LED",
+        exchanges: &[
+            Exchange {
+                request: &[0xe0, 0x50, 0x02, 0x01, 0x1b, 0x01, 0x01, 0xee],
+                reply: &[0xe0, 0xd0, 0x01, 0x01, 0x01, 0xee],
+            },
+            Exchange {
+                request: &[0xe0, 0x50, 0x02, 0x01, 0x19, 0x01, 0x01, 0xee],
+                reply: &[0xe0, 0xd0, 0x01, 0x01, 0x01, 0xee],
+            },
+            Exchange {
+                request: &[0xe0, 0x50, 0x02, 0x01, 0x1a, 0x01, 0x01, 0xee],
+                reply: &[0xe0, 0xd0, 0x01, 0x01, 0x01, 0xee],
+            },
+            Exchange {
+                request: &[0xe0, 0x52, 0x02, 0x01, 0x1b, 0x01, 0xff, 0xee],
+                reply: &[0xe0, 0xd2, 0x01, 0x01, 0x01, 0xee],
+            },
+            Exchange {
+                request: &[0xe0, 0x52, 0x02, 0x01, 0x19, 0x01, 0xff, 0xee],
+                reply: &[0xe0, 0xd2, 0x01, 0x01, 0x01, 0xee],
+            },
+            Exchange {
+                request: &[0xe0, 0x52, 0x02, 0x01, 0x1a, 0x01, 0xff, 0xee],
+                reply: &[0xe0, 0xd2, 0x01, 0x01, 0x01, 0xee],
+            },
+            Exchange {
+                request: &[0xe0, 0x52, 0x02, 0x01, 0x1b, 0x01, 0x00, 0xee],
+                reply: &[0xe0, 0xd2, 0x01, 0x01, 0x01, 0xee],
+            },
+            Exchange {
+                request: &[0xe0, 0x52, 0x02, 0x01, 0x19, 0x01, 0x7f, 0xee],
+                reply: &[0xe0, 0xd2, 0x01, 0x01, 0x01, 0xee],
+            },
+            Exchange {
+                request: &[0xe0, 0x52, 0x02, 0x01, 0x1a, 0x01, 0xff, 0xee],
+                reply: &[0xe0, 0xd2, 0x01, 0x01, 0x01, 0xee],
+            },
+        ],
+        run: |wifi| {
+            let pins = RgbLedPins::nano_rp2040_connect().active_low(true);
+            let mut rgb_led = RgbLed::new(wifi, pins).unwrap();
+
+            rgb_led.set_color(255, 128, 0).unwrap();
+        },
+    },
+    TestVector {
+        name: "GET_ANALOG_READ",
+        exchanges: &[Exchange {
+            request: &[0xe0, 0x54, 0x02, 0x01, 0x06, 0x01, 0x03, 0xee],
+            reply: &[0xe0, 0xd4, 0x01, 0x02, 0xd2, 0x04, 0xee],
+        }],
+        run: |wifi| {
+            let mut esp_gpio = EspGpio::build(wifi);
+
+            assert_eq!(
+                esp_gpio
+                    .analog_read(AnalogPin::Gpio34, AdcAttenuation::Db11)
+                    .unwrap(),
+                1234
+            );
+        },
+    },
+    TestVector {
+        name: "SET_DNS_CONFIG",
+        exchanges: &[Exchange {
+            request: &[
+                0xe0, 0x15, 0x03, 0x01, 0x01, 0x04, 0x09, 0x09, 0x09, 0x09, 0x04, 0x08, 0x08, 0x08,
+                0x08, 0xee,
+            ],
+            reply: &[0xe0, 0x95, 0x01, 0x01, 0x01, 0xee],
+        }],
+        run: |wifi| {
+            wifi.set_dns(Ipv4Addr::new(9, 9, 9, 9), Some(Ipv4Addr::new(8, 8, 8, 8))).unwrap();
+        },
+    },
+    TestVector {
+        name: "REQ_HOST_BY_NAME + GET_HOST_BY_NAME",
+        exchanges: &[
+            Exchange {
+                request: &[0xe0, 0x34, 0x01, 0x02, b'a', b'b', 0xee, 0xff],
+                reply: &[0xe0, 0xb4, 0x01, 0x01, 0x01, 0xee],
+            },
+            Exchange {
+                request: &[0xe0, 0x35, 0x00, 0xee],
+                reply: &[0xe0, 0xb5, 0x01, 0x04, 0xc0, 0xa8, 0x01, 0x0a, 0xee],
+            },
+        ],
+        run: |wifi| {
+            assert_eq!(wifi.resolve("ab").unwrap(), Ipv4Addr::new(192, 168, 1, 10));
+        },
+    },
+    TestVector {
+        name: "REQ_HOST_BY_NAME + GET_HOST_BY_NAME with the DNS cache enabled",
+        exchanges: &[
+            Exchange {
+                request: &[0xe0, 0x34, 0x01, 0x02, b'a', b'b', 0xee, 0xff],
+                reply: &[0xe0, 0xb4, 0x01, 0x01, 0x01, 0xee],
+            },
+            Exchange {
+                request: &[0xe0, 0x35, 0x00, 0xee],
+                reply: &[0xe0, 0xb5, 0x01, 0x04, 0xc0, 0xa8, 0x01, 0x0a, 0xee],
+            },
+        ],
+        run: |wifi| {
+            wifi.enable_dns_cache(&FixedClock, DnsCacheConfig::new());
+
+            // Only the first lookup is sent to the ESP32 target
+            assert_eq!(wifi.resolve("ab").unwrap(), Ipv4Addr::new(192, 168, 1, 10));
+            assert_eq!(wifi.resolve("ab").unwrap(), Ipv4Addr::new(192, 168, 1, 10));
+        },
+    },
+    TestVector {
+        name: "START_SCAN_NETWORKS + SCAN_NETWORKS + GET_IDX_RSSI + GET_IDX_ENCT + GET_IDX_BSSID + GET_IDX_CHANNEL",
+        exchanges: &[
+            Exchange {
+                request: &[0xe0, 0x36, 0x00, 0xee],
+                reply: &[0xe0, 0xb6, 0x01, 0x01, 0x01, 0xee],
+            },
+            Exchange {
+                request: &[0xe0, 0x27, 0x00, 0xee],
+                reply: &[0xe0, 0xa7, 0x01, 0x04, b'h', b'o', b'm', b'e', 0xee],
+            },
+            Exchange {
+                request: &[0xe0, 0x32, 0x01, 0x01, 0x00, 0xee, 0xff, 0xff],
+                reply: &[0xe0, 0xb2, 0x01, 0x04, 0xc4, 0xff, 0xff, 0xff, 0xee],
+            },
+            Exchange {
+                request: &[0xe0, 0x33, 0x01, 0x01, 0x00, 0xee, 0xff, 0xff],
+                reply: &[0xe0, 0xb3, 0x01, 0x01, 0x04, 0xee],
+            },
+            Exchange {
+                request: &[0xe0, 0x3c, 0x01, 0x01, 0x00, 0xee, 0xff, 0xff],
+                reply: &[
+                    0xe0, 0xbc, 0x01, 0x06, 0x5e, 0x3c, 0x0b, 0x12, 0xcf, 0xa4, 0xee,
+                ],
+            },
+            Exchange {
+                request: &[0xe0, 0x3d, 0x01, 0x01, 0x00, 0xee, 0xff, 0xff],
+                reply: &[0xe0, 0xbd, 0x01, 0x01, 0x06, 0xee],
+            },
+        ],
+        run: |wifi| {
+            let results: Vec<_> = wifi.scan_networks().unwrap().collect();
+            assert_eq!(results.len(), 1);
+            assert_eq!(results[0].rssi(), -60);
+            assert_eq!(results[0].channel(), 6);
+        },
+    },
+    TestVector {
+        name: "GET_SOCKET + START_SERVER_TCP + AVAIL_DATA_TCP",
+        exchanges: &[
+            Exchange {
+                request: &[0xe0, 0x3f, 0x00, 0xee],
+                reply: &[0xe0, 0xbf, 0x01, 0x01, 0x00, 0xee],
+            },
+            Exchange {
+                request: &[
+                    0xe0, 0x28, 0x03, 0x02, 0x11, 0x11, 0x01, 0x00, 0x01, 0x00, 0xee, 0xff,
+                ],
+                reply: &[0xe0, 0xa8, 0x01, 0x01, 0x01, 0xee],
+            },
+            Exchange {
+                request: &[0xe0, 0x2b, 0x01, 0x01, 0x00, 0xee, 0xff, 0xff],
+                reply: &[0xe0, 0xab, 0x01, 0x02, 0xff, 0x00, 0xee],
+            },
+        ],
+        run: |wifi| {
+            let mut tcp_server = TcpServer::build(wifi);
+            tcp_server.bind(0x1111).unwrap();
+            assert_eq!(tcp_server.accept().unwrap(), None);
+        },
+    },
+    TestVector {
+        name: "GET_DATABUF_TCP",
+        exchanges: &[Exchange {
+            request: &[
+                0xe0, 0x45, 0x02, 0x00, 0x01, 0x00, 0x00, 0x02, 0x02, 0x00, 0xee, 0xff,
+            ],
+            reply: &[0xe0, 0xc5, 0x01, 0x00, 0x02, b'h', b'i', 0xee],
+        }],
+        run: |wifi| {
+            let mut buf = [0u8; 2];
+            let received = TcpServer::build(wifi).receive_data(0, &mut buf).unwrap();
+            assert_eq!(&buf[..received], b"hi");
+        },
+    },
+    TestVector {
+        name: "GET_SOCKET + START_CLIENT_TCP + INSERT_DATABUF + SEND_DATA_UDP",
+        exchanges: &[
+            Exchange {
+                request: &[0xe0, 0x3f, 0x00, 0xee],
+                reply: &[0xe0, 0xbf, 0x01, 0x01, 0x00, 0xee],
+            },
+            Exchange {
+                request: &[
+                    0xe0, 0x2d, 0x04, 0x04, 0xc0, 0xa8, 0x01, 0x0a, 0x02, 0x22, 0xb8, 0x01, 0x00,
+                    0x01, 0x01, 0xee,
+                ],
+                reply: &[0xe0, 0xad, 0x01, 0x01, 0x01, 0xee],
+            },
+            Exchange {
+                request: &[
+                    0xe0, 0x46, 0x02, 0x00, 0x01, 0x00, 0x00, 0x02, b'h', b'i', 0xee, 0xff,
+                ],
+                reply: &[0xe0, 0xc6, 0x01, 0x01, 0x01, 0xee],
+            },
+            Exchange {
+                request: &[0xe0, 0x39, 0x01, 0x01, 0x00, 0xee, 0xff, 0xff],
+                reply: &[0xe0, 0xb9, 0x01, 0x01, 0x01, 0xee],
+            },
+        ],
+        run: |wifi| {
+            UdpClient::build(wifi)
+                .send_to(SocketAddrV4::new(Ipv4Addr::new(192, 168, 1, 10), 8888), b"hi")
+                .unwrap();
+        },
+    },
+    TestVector {
+        name: "SET_CLI_CERT",
+        exchanges: &[Exchange {
+            request: &[
+                0xe0, 0x40, 0x01, 0x00, 0x03, b'-', b'-', b'-', 0xee, 0xff, 0xff, 0xff,
+            ],
+            reply: &[0xe0, 0xc0, 0x01, 0x01, 0x01, 0xee],
+        }],
+        run: |wifi| {
+            wifi.set_client_certificate(b"---").unwrap();
+        },
+    },
+];
+
+fn expectations(exchanges: &[Exchange]) -> Vec<spi::Transaction> {
+    let mut expectations = Vec::new();
+
+    for exchange in exchanges {
+        for byte in exchange.request {
+            expectations.push(spi::Transaction::transfer(vec![*byte], vec![0x0]));
+        }
+        for byte in exchange.reply {
+            expectations.push(spi::Transaction::transfer(vec![0xff], vec![*byte]));
+        }
+    }
+
+    expectations
+}
+
+#[test]
+fn codec_matches_nina_fw_frame_layouts_byte_for_byte() {
+    for vector in TEST_VECTORS {
+        let spi = BytewiseSpiMock::new(&expectations(vector.exchanges));
+
+        let delay = MockNoop::new();
+
+        let pins = EspControlMock {};
+
+        let mut wifi = Wifi::init(spi, pins, delay).ok().unwrap();
+
+        println!("Checking test vector: {}", vector.name);
+        (vector.run)(&mut wifi);
+
+        wifi.destroy().done();
+    }
+}
@@ -0,0 +1,182 @@
+use embedded_hal_mock::delay::MockNoop;
+
+use esp32_wroom_core::diagnostics::{EchoServer, EchoStats};
+use esp32_wroom_core::network::Port;
+use esp32_wroom_core::wifi::Wifi;
+
+pub mod support;
+
+use support::*;
+
+#[test]
+fn echo_server_echoes_received_data_back_to_client() {
+    // ----- get_socket -----
+
+    let get_socket_command = 0x3f;
+    let mut number_of_params = 0x0;
+    let number_of_params_to_receive = 0x1;
+
+    let mut expectations = mock_command(get_socket_command, number_of_params);
+
+    expectations.append(&mut mock_end_byte());
+
+    expectations.append(&mut mock_receive(
+        get_socket_command,
+        number_of_params_to_receive,
+        &[0x0],
+    ));
+
+    // ----- start_server_tcp -----
+
+    let start_server_tcp_command = 0x28;
+    number_of_params = 0x3;
+
+    expectations.append(&mut mock_command(
+        start_server_tcp_command,
+        number_of_params,
+    ));
+    expectations.append(&mut mock_single_byte_size_params(2, 0x11)); // Send fake Port
+    expectations.append(&mut mock_single_byte_size_params(1, 0x0)); // Send fake Socket
+    expectations.append(&mut mock_single_byte_size_params(1, 0x0)); // Send fake Transport Mode
+
+    expectations.append(&mut mock_end_byte());
+
+    expectations.append(&mut mock_padding(1));
+
+    expectations.append(&mut mock_receive(
+        start_server_tcp_command,
+        number_of_params_to_receive,
+        &[0x1],
+    ));
+
+    // ----- avail_data_tcp on the server socket accepts client socket 1 -----
+
+    let avail_data_tcp_command = 0x2b;
+    number_of_params = 0x1;
+
+    expectations.append(&mut mock_command(avail_data_tcp_command, number_of_params));
+    expectations.append(&mut mock_single_byte_size_params(1, 0x0)); // Send server Socket
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_padding(2));
+    expectations.append(&mut mock_receive(
+        avail_data_tcp_command,
+        number_of_params_to_receive,
+        &[0x1, 0x0],
+    ));
+
+    // ----- avail_data_tcp on the client socket reports 4 bytes -----
+
+    expectations.append(&mut mock_command(avail_data_tcp_command, number_of_params));
+    expectations.append(&mut mock_single_byte_size_params(1, 0x1)); // Send client Socket
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_padding(2));
+    expectations.append(&mut mock_receive(
+        avail_data_tcp_command,
+        number_of_params_to_receive,
+        &[0x4, 0x0],
+    ));
+
+    // ----- get_data_buf_tcp -----
+
+    let get_data_buf_tcp_command = 0x45;
+    number_of_params = 0x2;
+
+    expectations.append(&mut mock_command(
+        get_data_buf_tcp_command,
+        number_of_params,
+    ));
+    expectations.append(&mut mock_two_byte_size_params(&[0x1])); // Send client Socket
+    expectations.append(&mut mock_two_byte_size_params(&[0x4, 0x0])); // Send requested length
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_padding(1));
+    expectations.append(&mut mock_receive_data(
+        get_data_buf_tcp_command,
+        &[0x46, 0x46, 0x46, 0x46],
+    ));
+
+    // ----- send_data echoes the same bytes back -----
+
+    let send_data_tcp_command = 0x44;
+
+    expectations.append(&mut mock_command(send_data_tcp_command, number_of_params));
+    expectations.append(&mut mock_two_byte_size_params(&[0x1])); // Send client Socket
+    expectations.append(&mut mock_two_byte_size_params(&[0x46, 0x46, 0x46, 0x46]));
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_padding(3));
+    expectations.append(&mut mock_receive(
+        send_data_tcp_command,
+        number_of_params_to_receive,
+        &[0x4],
+    ));
+
+    // ----- avail_data_tcp on the client socket reports no more data -----
+
+    number_of_params = 0x1;
+
+    expectations.append(&mut mock_command(avail_data_tcp_command, number_of_params));
+    expectations.append(&mut mock_single_byte_size_params(1, 0x1)); // Send client Socket
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_padding(2));
+    expectations.append(&mut mock_receive(
+        avail_data_tcp_command,
+        number_of_params_to_receive,
+        &[0x0, 0x0],
+    ));
+
+    // ----- get_client_state_tcp reports the client closed the connection -----
+
+    let get_client_state_tcp_command = 0x2f;
+
+    expectations.append(&mut mock_command(
+        get_client_state_tcp_command,
+        number_of_params,
+    ));
+    expectations.append(&mut mock_single_byte_size_params(1, 0x1)); // Send client Socket
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_padding(2));
+    expectations.append(&mut mock_receive(
+        get_client_state_tcp_command,
+        number_of_params_to_receive,
+        &[0x0], // ConnectionState::Closed
+    ));
+
+    // ----- stop_client_tcp for the client and then the server socket -----
+
+    let stop_client_tcp_command = 0x2e;
+
+    for socket in [0x1, 0x0] {
+        expectations.append(&mut mock_command(stop_client_tcp_command, number_of_params));
+        expectations.append(&mut mock_single_byte_size_params(1, socket));
+        expectations.append(&mut mock_end_byte());
+        expectations.append(&mut mock_padding(2));
+        expectations.append(&mut mock_receive(
+            stop_client_tcp_command,
+            number_of_params_to_receive,
+            &[0x1],
+        ));
+    }
+
+    let spi = BytewiseSpiMock::new(&expectations);
+
+    let delay = MockNoop::new();
+
+    let pins = EspControlMock {};
+
+    let mut wifi = Wifi::init(spi, pins, delay).ok().unwrap();
+
+    let port: Port = 0x1111;
+
+    let stats = EchoServer::build(&mut wifi)
+        .serve(port, &mut |stats| stats.sessions < 1)
+        .unwrap();
+
+    assert_eq!(
+        stats,
+        EchoStats {
+            sessions: 1,
+            bytes_echoed: 4
+        }
+    );
+
+    wifi.destroy().done();
+}
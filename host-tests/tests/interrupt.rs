@@ -0,0 +1,156 @@
+use std::cell::Cell;
+
+use embedded_hal_mock::delay::MockNoop;
+
+use esp32_wroom_core::gpio::EspControlInterface;
+use esp32_wroom_core::interrupt::{CommandProgress, PendingCommand, Reply, Request};
+use esp32_wroom_core::wifi::{ConnectionStatus, Wifi};
+
+pub mod support;
+
+use support::*;
+
+// Acknowledges only every other check of the ACK pin, like a NINA firmware that is still busy
+// the first time it's polled.
+#[derive(Default)]
+struct SlowAckMock {
+    ack_checks: Cell<u32>,
+}
+
+impl EspControlInterface for SlowAckMock {
+    fn init(&mut self) {}
+
+    fn reset<D>(&mut self, _delay: &mut D) {}
+
+    fn get_esp_ack(&self) -> bool {
+        self.ack_checks.set(self.ack_checks.get() + 1);
+        self.ack_checks.get().is_multiple_of(2)
+    }
+
+    fn wait_for_esp_select(&mut self) {}
+
+    fn wait_for_esp_ack(&self) {}
+
+    fn wait_for_esp_ready(&self) {}
+
+    fn esp_select(&mut self) {}
+
+    fn esp_deselect(&mut self) {}
+
+    fn get_esp_ready(&self) -> bool {
+        true
+    }
+
+    fn hold_in_reset(&mut self) {}
+
+    fn enter_bootloader<D>(&mut self, _delay: &mut D) {}
+}
+
+#[test]
+fn pending_command_returns_pending_until_the_esp32_acknowledges() {
+    let get_conn_status_command = 0x20;
+    let number_of_params_to_receive = 0x1;
+
+    let mut expectations = mock_command(get_conn_status_command, 0x0);
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_receive(
+        get_conn_status_command,
+        number_of_params_to_receive,
+        &[0x3],
+    ));
+
+    let spi = BytewiseSpiMock::new(&expectations);
+
+    let delay = MockNoop::new();
+
+    let mut wifi = Wifi::init(spi, SlowAckMock::default(), delay).ok().unwrap();
+
+    let mut command = PendingCommand::new(&mut wifi, Request::ConnectionStatus);
+
+    // Not acknowledged before sending the command, then before receiving its reply
+    assert_eq!(command.poll().unwrap(), CommandProgress::Pending);
+    assert_eq!(command.poll().unwrap(), CommandProgress::Pending);
+    assert_eq!(
+        command.poll().unwrap(),
+        CommandProgress::Done(Reply::ConnectionStatus(ConnectionStatus::Connected))
+    );
+    drop(command);
+
+    wifi.destroy().done();
+}
+
+#[test]
+fn dropping_a_pending_command_part_way_lets_other_commands_be_sent_again() {
+    let get_client_state_tcp_command = 0x2f;
+    let get_conn_status_command = 0x20;
+    let number_of_params_to_receive = 0x1;
+
+    // ----- get_client_state_tcp, abandoned before its reply is read -----
+
+    let mut expectations = mock_command(get_client_state_tcp_command, 0x1);
+    expectations.append(&mut mock_single_byte_size_params(1, 0x0)); // Send Socket
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_padding(2));
+
+    // ----- get_conn_status -----
+
+    expectations.append(&mut mock_command(get_conn_status_command, 0x0));
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_receive(
+        get_conn_status_command,
+        number_of_params_to_receive,
+        &[0x3],
+    ));
+
+    let spi = BytewiseSpiMock::new(&expectations);
+
+    let delay = MockNoop::new();
+
+    let mut wifi = Wifi::init(spi, SlowAckMock::default(), delay).ok().unwrap();
+
+    let mut command = PendingCommand::new(&mut wifi, Request::ClientState(0));
+
+    assert_eq!(command.poll().unwrap(), CommandProgress::Pending);
+    assert_eq!(command.poll().unwrap(), CommandProgress::Pending);
+    drop(command);
+
+    assert_eq!(
+        wifi.get_connection_status().unwrap(),
+        ConnectionStatus::Connected
+    );
+
+    wifi.destroy().done();
+}
+
+#[test]
+fn pending_command_completes_in_a_single_poll_when_the_esp32_is_not_busy() {
+    let stop_client_tcp_command = 0x2e;
+    let number_of_params_to_receive = 0x1;
+
+    let mut expectations = mock_command(stop_client_tcp_command, 0x1);
+    expectations.append(&mut mock_single_byte_size_params(1, 0x0)); // Send Socket
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_padding(2));
+    expectations.append(&mut mock_receive(
+        stop_client_tcp_command,
+        number_of_params_to_receive,
+        &[0x1],
+    ));
+
+    let spi = BytewiseSpiMock::new(&expectations);
+
+    let delay = MockNoop::new();
+
+    let pins = EspControlMock {};
+
+    let mut wifi = Wifi::init(spi, pins, delay).ok().unwrap();
+
+    assert_eq!(
+        PendingCommand::new(&mut wifi, Request::StopClient(0))
+            .poll()
+            .unwrap(),
+        CommandProgress::Done(Reply::StopClient)
+    );
+
+    wifi.destroy().done();
+}
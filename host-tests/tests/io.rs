@@ -0,0 +1,109 @@
+use embedded_hal_mock::delay::MockNoop;
+
+use embedded_io::{Read, Write};
+
+use esp32_wroom_core::tcp_client::TcpClient;
+use esp32_wroom_core::wifi::Wifi;
+
+pub mod support;
+
+use support::*;
+
+#[test]
+fn tcp_client_writes_and_reads_until_the_connection_is_closed() {
+    let number_of_params_to_receive = 0x1;
+
+    // ----- send_data and check_data_sent -----
+
+    let send_data_tcp_command = 0x44;
+
+    let mut expectations = mock_command(send_data_tcp_command, 0x2);
+    expectations.append(&mut mock_two_byte_size_params(&[0x0])); // Send Socket
+    expectations.append(&mut mock_two_byte_size_params(&[0x41, 0x42, 0x43])); // Send data
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_receive(
+        send_data_tcp_command,
+        number_of_params_to_receive,
+        &[0x3, 0x0],
+    ));
+
+    let check_data_sent_command = 0x2a;
+
+    expectations.append(&mut mock_command(check_data_sent_command, 0x1));
+    expectations.append(&mut mock_single_byte_size_params(1, 0x0)); // Send Socket
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_padding(2));
+    expectations.append(&mut mock_receive(
+        check_data_sent_command,
+        number_of_params_to_receive,
+        &[0x1],
+    ));
+
+    // ----- avail_data_tcp and get_data_buf_tcp -----
+
+    let avail_data_tcp_command = 0x2b;
+
+    expectations.append(&mut mock_command(avail_data_tcp_command, 0x1));
+    expectations.append(&mut mock_single_byte_size_params(1, 0x0)); // Send Socket
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_padding(2));
+    expectations.append(&mut mock_receive(
+        avail_data_tcp_command,
+        number_of_params_to_receive,
+        &[0x2, 0x0],
+    ));
+
+    let get_data_buf_tcp_command = 0x45;
+
+    expectations.append(&mut mock_command(get_data_buf_tcp_command, 0x2));
+    expectations.append(&mut mock_two_byte_size_params(&[0x0])); // Send Socket
+    expectations.append(&mut mock_two_byte_size_params(&[0x2, 0x0])); // Send requested length
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_padding(1));
+    expectations.append(&mut mock_receive_data(
+        get_data_buf_tcp_command,
+        &[0x4f, 0x4b],
+    ));
+
+    // ----- no more data and get_client_state_tcp reports Closed -----
+
+    expectations.append(&mut mock_command(avail_data_tcp_command, 0x1));
+    expectations.append(&mut mock_single_byte_size_params(1, 0x0)); // Send Socket
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_padding(2));
+    expectations.append(&mut mock_receive(
+        avail_data_tcp_command,
+        number_of_params_to_receive,
+        &[0x0, 0x0],
+    ));
+
+    let get_client_state_tcp_command = 0x2f;
+
+    expectations.append(&mut mock_command(get_client_state_tcp_command, 0x1));
+    expectations.append(&mut mock_single_byte_size_params(1, 0x0)); // Send Socket
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_padding(2));
+    expectations.append(&mut mock_receive(
+        get_client_state_tcp_command,
+        number_of_params_to_receive,
+        &[0x0],
+    ));
+
+    let spi = BytewiseSpiMock::new(&expectations);
+
+    let delay = MockNoop::new();
+
+    let pins = EspControlMock {};
+
+    let mut wifi = Wifi::init(spi, pins, delay).ok().unwrap();
+
+    let mut tcp_client = TcpClient::build(&mut wifi);
+    let mut buf = [0u8; 8];
+
+    assert_eq!(tcp_client.write(b"ABC").unwrap(), 3);
+    assert_eq!(tcp_client.read(&mut buf).unwrap(), 2);
+    assert_eq!(&buf[..2], b"OK");
+    assert_eq!(tcp_client.read(&mut buf).unwrap(), 0);
+
+    wifi.destroy().done();
+}
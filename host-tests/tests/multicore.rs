@@ -0,0 +1,191 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+
+use embedded_hal::blocking::spi::Transfer;
+
+use embedded_hal_mock::delay::MockNoop;
+
+use esp32_wroom_core::multicore::{BusChannel, CommandChannel};
+use esp32_wroom_core::wifi::{ConnectionStatus, Wifi};
+use esp32_wroom_core::Error;
+
+pub mod support;
+
+use support::*;
+
+#[test]
+fn bus_server_performs_transfers_requested_from_another_thread() {
+    let get_conn_status_command = 0x20;
+    let number_of_params = 0x0;
+    let number_of_params_to_receive = 0x1;
+
+    let mut expectations = mock_command(get_conn_status_command, number_of_params);
+
+    expectations.append(&mut mock_end_byte());
+
+    expectations.append(&mut mock_receive(
+        get_conn_status_command,
+        number_of_params_to_receive,
+        &[0x3],
+    ));
+
+    let mut bus = BytewiseSpiMock::new(&expectations);
+    let mut bus_channel: BusChannel<4> = BusChannel::new();
+    let (bus_client, mut bus_server) = bus_channel.split();
+    let stop = AtomicBool::new(false);
+
+    thread::scope(|scope| {
+        // Stands in for core1
+        let server = scope.spawn(|| {
+            while !stop.load(Ordering::Acquire) {
+                bus_server.poll(&mut bus).unwrap();
+            }
+            bus.done();
+        });
+
+        let delay = MockNoop::new();
+
+        let pins = EspControlMock {};
+
+        let mut wifi = Wifi::init(bus_client, pins, delay).ok().unwrap();
+
+        assert_eq!(
+            wifi.get_connection_status().unwrap(),
+            ConnectionStatus::Connected
+        );
+
+        stop.store(true, Ordering::Release);
+        server.join().unwrap();
+    });
+}
+
+// A bus whose every transfer fails.
+struct FailingBus;
+
+impl Transfer<u8> for FailingBus {
+    type Error = ();
+
+    fn transfer<'w>(&mut self, _words: &'w mut [u8]) -> Result<&'w [u8], ()> {
+        Err(())
+    }
+}
+
+#[test]
+fn bus_client_transfer_fails_when_the_bus_server_transfer_fails() {
+    let mut bus_channel: BusChannel<4> = BusChannel::new();
+    let (mut bus_client, mut bus_server) = bus_channel.split();
+
+    thread::scope(|scope| {
+        // Stands in for core1
+        let server = scope.spawn(|| {
+            let mut bus = FailingBus;
+            while bus_server.poll(&mut bus).is_ok() {
+                thread::yield_now();
+            }
+        });
+
+        let mut words = [0xe0, 0x20, 0x00, 0xee];
+        assert_eq!(bus_client.transfer(&mut words), Err(Error::Bus));
+
+        server.join().unwrap();
+    });
+}
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    ConnectionStatus,
+}
+
+#[derive(Debug, PartialEq)]
+enum Response {
+    ConnectionStatus(ConnectionStatus),
+}
+
+#[test]
+fn command_server_runs_the_protocol_for_another_thread() {
+    let get_conn_status_command = 0x20;
+    let number_of_params = 0x0;
+    let number_of_params_to_receive = 0x1;
+
+    let mut expectations = mock_command(get_conn_status_command, number_of_params);
+
+    expectations.append(&mut mock_end_byte());
+
+    expectations.append(&mut mock_receive(
+        get_conn_status_command,
+        number_of_params_to_receive,
+        &[0x3],
+    ));
+
+    let spi = BytewiseSpiMock::new(&expectations);
+    let mut command_channel: CommandChannel<Command, Response, 2> = CommandChannel::new();
+    let (mut client, mut server) = command_channel.split();
+    let stop = AtomicBool::new(false);
+
+    thread::scope(|scope| {
+        // Stands in for core1, which owns the driver
+        let server = scope.spawn(|| {
+            let mut wifi = Wifi::init(spi, EspControlMock {}, MockNoop::new())
+                .ok()
+                .unwrap();
+            while !stop.load(Ordering::Acquire) {
+                server.poll(&mut wifi, |wifi, command| match command {
+                    Command::ConnectionStatus => {
+                        Response::ConnectionStatus(wifi.get_connection_status().unwrap())
+                    }
+                });
+            }
+            wifi.destroy().done();
+        });
+
+        client.send(Command::ConnectionStatus).unwrap();
+
+        let response = loop {
+            if let Some(response) = client.try_receive() {
+                break response;
+            }
+            thread::yield_now();
+        };
+        assert_eq!(
+            response,
+            Response::ConnectionStatus(ConnectionStatus::Connected)
+        );
+
+        stop.store(true, Ordering::Release);
+        server.join().unwrap();
+    });
+}
+
+#[test]
+fn command_client_hands_back_commands_while_the_queue_is_full() {
+    let mut command_channel: CommandChannel<u8, u8, 2> = CommandChannel::new();
+    let (mut client, _server) = command_channel.split();
+
+    assert!(client.is_ready());
+    assert_eq!(client.send(1), Ok(()));
+    assert!(!client.is_ready());
+    assert_eq!(client.send(2), Err(2));
+    assert_eq!(client.try_receive(), None);
+}
+
+#[test]
+fn command_server_leaves_commands_queued_while_the_responses_are_full() {
+    let mut command_channel: CommandChannel<u8, u8, 2> = CommandChannel::new();
+    let (mut client, mut server) = command_channel.split();
+    let mut performed = Vec::new();
+    let mut handler = |performed: &mut Vec<u8>, command: u8| {
+        performed.push(command);
+        command * 2
+    };
+
+    client.send(1).unwrap();
+    assert!(server.poll(&mut performed, &mut handler));
+    client.send(2).unwrap();
+    assert!(!server.poll(&mut performed, &mut handler));
+    assert_eq!(performed, vec![1]);
+
+    assert_eq!(client.try_receive(), Some(2));
+    assert!(server.poll(&mut performed, &mut handler));
+    assert_eq!(client.try_receive(), Some(4));
+    assert!(!server.poll(&mut performed, &mut handler));
+}
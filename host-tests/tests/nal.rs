@@ -0,0 +1,345 @@
+use core::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+
+use embedded_hal_mock::delay::MockNoop;
+use embedded_hal_mock::spi;
+
+use embedded_nal::{nb, TcpClientStack, TcpError, TcpErrorKind, UdpClientStack, UdpFullStack};
+
+use esp32_wroom_core::network::NetworkError;
+use esp32_wroom_core::wifi::Wifi;
+
+pub mod support;
+
+use support::*;
+
+#[test]
+fn tcp_client_stack_connects_sends_and_closes() {
+    let number_of_params_to_receive = 0x1;
+
+    let mut expectations = mock_get_socket();
+
+    // ----- start_client_tcp -----
+
+    let start_client_tcp_command = 0x2d;
+
+    expectations.append(&mut mock_command(start_client_tcp_command, 0x4));
+    expectations.append(&mut mock_single_byte_size_params(4, 0x46)); // Send fake IP Address
+    expectations.append(&mut mock_single_byte_size_params(2, 0x11)); // Send fake Port
+    expectations.append(&mut mock_single_byte_size_params(1, 0x0)); // Send fake Socket
+    expectations.append(&mut mock_single_byte_size_params(1, 0x0)); // Send fake Transport Mode
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_receive(
+        start_client_tcp_command,
+        number_of_params_to_receive,
+        &[0x1],
+    ));
+
+    // ----- get_client_state_tcp reports SynSent and then Established -----
+
+    expectations.append(&mut mock_get_client_state(0x2));
+    expectations.append(&mut mock_get_client_state(0x4));
+
+    // ----- send_data -----
+
+    let send_data_tcp_command = 0x44;
+
+    expectations.append(&mut mock_command(send_data_tcp_command, 0x2));
+    expectations.append(&mut mock_two_byte_size_params(&[0x0])); // Send Socket
+    expectations.append(&mut mock_two_byte_size_params(&[0x41, 0x42, 0x43])); // Send data
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_receive(
+        send_data_tcp_command,
+        number_of_params_to_receive,
+        &[0x3, 0x0],
+    ));
+
+    let check_data_sent_command = 0x2a;
+
+    expectations.append(&mut mock_command(check_data_sent_command, 0x1));
+    expectations.append(&mut mock_single_byte_size_params(1, 0x0)); // Send Socket
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_padding(2));
+    expectations.append(&mut mock_receive(
+        check_data_sent_command,
+        number_of_params_to_receive,
+        &[0x1],
+    ));
+
+    // ----- stop_client_tcp -----
+
+    let stop_client_tcp_command = 0x2e;
+
+    expectations.append(&mut mock_command(stop_client_tcp_command, 0x1));
+    expectations.append(&mut mock_single_byte_size_params(1, 0x0)); // Send Socket
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_padding(2));
+    expectations.append(&mut mock_receive(
+        stop_client_tcp_command,
+        number_of_params_to_receive,
+        &[0x1],
+    ));
+
+    let spi = BytewiseSpiMock::new(&expectations);
+
+    let delay = MockNoop::new();
+
+    let pins = EspControlMock {};
+
+    let mut wifi = Wifi::init(spi, pins, delay).ok().unwrap();
+
+    let remote = SocketAddr::from((Ipv4Addr::new(0x46, 0x46, 0x46, 0x46), 0x1111));
+
+    let mut socket = TcpClientStack::socket(&mut wifi).unwrap();
+    assert_eq!(
+        TcpClientStack::connect(&mut wifi, &mut socket, remote),
+        Err(nb::Error::WouldBlock)
+    );
+    assert_eq!(
+        TcpClientStack::connect(&mut wifi, &mut socket, remote),
+        Ok(())
+    );
+    assert_eq!(TcpClientStack::send(&mut wifi, &mut socket, b"ABC"), Ok(3));
+    TcpClientStack::close(&mut wifi, socket).unwrap();
+
+    wifi.destroy().done();
+}
+
+#[test]
+fn tcp_client_stack_receive_reports_closed_connection_as_pipe_closed() {
+    let avail_data_tcp_command = 0x2b;
+
+    let mut expectations = mock_get_socket();
+
+    expectations.append(&mut mock_command(avail_data_tcp_command, 0x1));
+    expectations.append(&mut mock_single_byte_size_params(1, 0x0)); // Send Socket
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_padding(2));
+    expectations.append(&mut mock_receive(avail_data_tcp_command, 0x1, &[0x0, 0x0]));
+
+    expectations.append(&mut mock_get_client_state(0x0)); // ConnectionState::Closed
+
+    let spi = BytewiseSpiMock::new(&expectations);
+
+    let delay = MockNoop::new();
+
+    let pins = EspControlMock {};
+
+    let mut wifi = Wifi::init(spi, pins, delay).ok().unwrap();
+
+    let mut socket = TcpClientStack::socket(&mut wifi).unwrap();
+    let mut buffer = [0u8; 8];
+
+    match TcpClientStack::receive(&mut wifi, &mut socket, &mut buffer) {
+        Err(nb::Error::Other(error)) => {
+            assert_eq!(
+                error,
+                esp32_wroom_core::Error::Network(NetworkError::ConnectionLost)
+            );
+            assert_eq!(error.kind(), TcpErrorKind::PipeClosed);
+        }
+        result => panic!("Unexpected result: {:?}", result),
+    }
+
+    wifi.destroy().done();
+}
+
+#[test]
+fn tcp_client_stack_connect_to_ipv6_address_returns_ipv6_unsupported_error() {
+    let spi = BytewiseSpiMock::new(&mock_get_socket());
+
+    let delay = MockNoop::new();
+
+    let pins = EspControlMock {};
+
+    let mut wifi = Wifi::init(spi, pins, delay).ok().unwrap();
+
+    let mut socket = TcpClientStack::socket(&mut wifi).unwrap();
+    let remote = SocketAddr::from((Ipv6Addr::LOCALHOST, 4000));
+
+    assert_eq!(
+        TcpClientStack::connect(&mut wifi, &mut socket, remote),
+        Err(nb::Error::Other(esp32_wroom_core::Error::Network(
+            NetworkError::Ipv6Unsupported
+        )))
+    );
+
+    wifi.destroy().done();
+}
+
+#[test]
+fn udp_full_stack_sends_from_and_receives_on_bound_port() {
+    let number_of_params_to_receive = 0x1;
+
+    let mut expectations = mock_get_socket();
+
+    // ----- start_server_tcp in UDP mode -----
+
+    let start_server_tcp_command = 0x28;
+
+    expectations.append(&mut mock_command(start_server_tcp_command, 0x3));
+    expectations.append(&mut mock_single_byte_size_params(2, 0x11)); // Send fake Port
+    expectations.append(&mut mock_single_byte_size_params(1, 0x0)); // Send fake Socket
+    expectations.append(&mut mock_single_byte_size_params(1, 0x1)); // Send UDP Transport Mode
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_padding(1));
+    expectations.append(&mut mock_receive(
+        start_server_tcp_command,
+        number_of_params_to_receive,
+        &[0x1],
+    ));
+
+    // ----- start_client_tcp in UDP mode, insert_data_buf and send_udp_data -----
+
+    let start_client_tcp_command = 0x2d;
+
+    expectations.append(&mut mock_command(start_client_tcp_command, 0x4));
+    expectations.append(&mut mock_single_byte_size_params(4, 0x40)); // Send fake IP Address
+    expectations.append(&mut mock_single_byte_size_params(2, 0x11)); // Send fake Port
+    expectations.append(&mut mock_single_byte_size_params(1, 0x0)); // Send fake Socket
+    expectations.append(&mut mock_single_byte_size_params(1, 0x1)); // Send UDP Transport Mode
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_receive(
+        start_client_tcp_command,
+        number_of_params_to_receive,
+        &[0x1],
+    ));
+
+    let insert_data_buf_command = 0x46;
+
+    expectations.append(&mut mock_command(insert_data_buf_command, 0x2));
+    expectations.append(&mut mock_two_byte_size_params(&[0x0])); // Send Socket
+    expectations.append(&mut mock_two_byte_size_params(&[0x46, 0x46, 0x46, 0x46]));
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_padding(3));
+    expectations.append(&mut mock_receive(
+        insert_data_buf_command,
+        number_of_params_to_receive,
+        &[0x1],
+    ));
+
+    let send_udp_data_command = 0x39;
+
+    expectations.append(&mut mock_command(send_udp_data_command, 0x1));
+    expectations.append(&mut mock_single_byte_size_params(1, 0x0)); // Send Socket
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_padding(2));
+    expectations.append(&mut mock_receive(
+        send_udp_data_command,
+        number_of_params_to_receive,
+        &[0x1],
+    ));
+
+    // ----- avail_data_tcp, get_data_buf_tcp and get_remote_data -----
+
+    let avail_data_tcp_command = 0x2b;
+
+    expectations.append(&mut mock_command(avail_data_tcp_command, 0x1));
+    expectations.append(&mut mock_single_byte_size_params(1, 0x0)); // Send Socket
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_padding(2));
+    expectations.append(&mut mock_receive(
+        avail_data_tcp_command,
+        number_of_params_to_receive,
+        &[0x2, 0x0],
+    ));
+
+    let get_data_buf_tcp_command = 0x45;
+
+    expectations.append(&mut mock_command(get_data_buf_tcp_command, 0x2));
+    expectations.append(&mut mock_two_byte_size_params(&[0x0])); // Send Socket
+    expectations.append(&mut mock_two_byte_size_params(&[0x2, 0x0])); // Send requested length
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_padding(1));
+    expectations.append(&mut mock_receive_data(
+        get_data_buf_tcp_command,
+        &[0x4f, 0x4b],
+    ));
+
+    let get_remote_data_command = 0x3a;
+
+    expectations.append(&mut mock_command(get_remote_data_command, 0x1));
+    expectations.append(&mut mock_single_byte_size_params(1, 0x0)); // Send Socket
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_padding(2));
+    expectations.append(&mut mock_receive_params(
+        get_remote_data_command,
+        &[&[0xc0, 0xa8, 0x1, 0xa], &[0x22, 0xb8]], // 192.168.1.10:8888
+    ));
+
+    let spi = BytewiseSpiMock::new(&expectations);
+
+    let delay = MockNoop::new();
+
+    let pins = EspControlMock {};
+
+    let mut wifi = Wifi::init(spi, pins, delay).ok().unwrap();
+
+    let remote = SocketAddr::from((Ipv4Addr::new(0x40, 0x40, 0x40, 0x40), 0x1111));
+
+    let mut socket = UdpClientStack::socket(&mut wifi).unwrap();
+    wifi.bind(&mut socket, 0x1111).unwrap();
+    assert_eq!(socket.local_port(), Some(0x1111));
+
+    wifi.send_to(&mut socket, remote, b"FFFF").unwrap();
+
+    let mut buffer = [0u8; 8];
+    let (length, sender) = UdpClientStack::receive(&mut wifi, &mut socket, &mut buffer).unwrap();
+
+    assert_eq!(&buffer[..length], b"OK");
+    assert_eq!(
+        sender,
+        SocketAddr::from((Ipv4Addr::new(192, 168, 1, 10), 8888))
+    );
+
+    wifi.destroy().done();
+}
+
+#[test]
+fn udp_client_stack_send_on_unconnected_socket_returns_not_connected_error() {
+    let spi = BytewiseSpiMock::new(&mock_get_socket());
+
+    let delay = MockNoop::new();
+
+    let pins = EspControlMock {};
+
+    let mut wifi = Wifi::init(spi, pins, delay).ok().unwrap();
+
+    let mut socket = UdpClientStack::socket(&mut wifi).unwrap();
+
+    assert_eq!(
+        UdpClientStack::send(&mut wifi, &mut socket, b"FFFF"),
+        Err(nb::Error::Other(esp32_wroom_core::Error::Network(
+            NetworkError::UdpSocketNotConnected
+        )))
+    );
+
+    wifi.destroy().done();
+}
+
+// Expectations for requesting a socket, answered with socket 0
+fn mock_get_socket() -> Vec<spi::Transaction> {
+    let get_socket_command = 0x3f;
+
+    let mut expectations = mock_command(get_socket_command, 0x0);
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_receive(get_socket_command, 0x1, &[0x0]));
+
+    expectations
+}
+
+// Expectations for reading the connection state of socket 0, answered with `state`
+fn mock_get_client_state(state: u8) -> Vec<spi::Transaction> {
+    let get_client_state_tcp_command = 0x2f;
+
+    let mut expectations = mock_command(get_client_state_tcp_command, 0x1);
+    expectations.append(&mut mock_single_byte_size_params(1, 0x0)); // Send Socket
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_padding(2));
+    expectations.append(&mut mock_receive(
+        get_client_state_tcp_command,
+        0x1,
+        &[state],
+    ));
+
+    expectations
+}
@@ -0,0 +1,186 @@
+use core::future::Future;
+use core::net::{Ipv4Addr, SocketAddr};
+use core::pin::pin;
+use core::task::{Context, Poll, Waker};
+
+use embedded_hal_mock::delay::MockNoop;
+
+use embedded_io_async::{Read, Write};
+use embedded_nal_async::{AddrType, Dns, TcpConnect};
+
+use esp32_wroom_core::network::NetworkError;
+use esp32_wroom_core::wifi::Wifi;
+
+pub mod support;
+
+use support::*;
+
+// Polls `future` until it completes. The futures under test never wait on anything but the
+// mocked ESP32 target, so they can simply be polled again right away.
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let mut context = Context::from_waker(Waker::noop());
+
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+            return output;
+        }
+    }
+}
+
+#[test]
+fn tcp_connect_writes_reads_and_closes_on_drop() {
+    let number_of_params_to_receive = 0x1;
+
+    // ----- get_socket -----
+
+    let get_socket_command = 0x3f;
+
+    let mut expectations = mock_command(get_socket_command, 0x0);
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_receive(
+        get_socket_command,
+        number_of_params_to_receive,
+        &[0x0],
+    ));
+
+    // ----- start_client_tcp -----
+
+    let start_client_tcp_command = 0x2d;
+
+    expectations.append(&mut mock_command(start_client_tcp_command, 0x4));
+    expectations.append(&mut mock_single_byte_size_params(4, 0x46)); // Send fake IP Address
+    expectations.append(&mut mock_single_byte_size_params(2, 0x11)); // Send fake Port
+    expectations.append(&mut mock_single_byte_size_params(1, 0x0)); // Send fake Socket
+    expectations.append(&mut mock_single_byte_size_params(1, 0x0)); // Send fake Transport Mode
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_receive(
+        start_client_tcp_command,
+        number_of_params_to_receive,
+        &[0x1],
+    ));
+
+    // ----- get_client_state_tcp reports SynSent and then Established -----
+
+    let get_client_state_tcp_command = 0x2f;
+
+    for state in [0x2, 0x4] {
+        expectations.append(&mut mock_command(get_client_state_tcp_command, 0x1));
+        expectations.append(&mut mock_single_byte_size_params(1, 0x0)); // Send Socket
+        expectations.append(&mut mock_end_byte());
+        expectations.append(&mut mock_padding(2));
+        expectations.append(&mut mock_receive(
+            get_client_state_tcp_command,
+            number_of_params_to_receive,
+            &[state],
+        ));
+    }
+
+    // ----- send_data and check_data_sent -----
+
+    let send_data_tcp_command = 0x44;
+
+    expectations.append(&mut mock_command(send_data_tcp_command, 0x2));
+    expectations.append(&mut mock_two_byte_size_params(&[0x0])); // Send Socket
+    expectations.append(&mut mock_two_byte_size_params(&[0x41, 0x42, 0x43])); // Send data
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_receive(
+        send_data_tcp_command,
+        number_of_params_to_receive,
+        &[0x3, 0x0],
+    ));
+
+    let check_data_sent_command = 0x2a;
+
+    expectations.append(&mut mock_command(check_data_sent_command, 0x1));
+    expectations.append(&mut mock_single_byte_size_params(1, 0x0)); // Send Socket
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_padding(2));
+    expectations.append(&mut mock_receive(
+        check_data_sent_command,
+        number_of_params_to_receive,
+        &[0x1],
+    ));
+
+    // ----- avail_data_tcp and get_data_buf_tcp -----
+
+    let avail_data_tcp_command = 0x2b;
+
+    expectations.append(&mut mock_command(avail_data_tcp_command, 0x1));
+    expectations.append(&mut mock_single_byte_size_params(1, 0x0)); // Send Socket
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_padding(2));
+    expectations.append(&mut mock_receive(
+        avail_data_tcp_command,
+        number_of_params_to_receive,
+        &[0x2, 0x0],
+    ));
+
+    let get_data_buf_tcp_command = 0x45;
+
+    expectations.append(&mut mock_command(get_data_buf_tcp_command, 0x2));
+    expectations.append(&mut mock_two_byte_size_params(&[0x0])); // Send Socket
+    expectations.append(&mut mock_two_byte_size_params(&[0x2, 0x0])); // Send requested length
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_padding(1));
+    expectations.append(&mut mock_receive_data(
+        get_data_buf_tcp_command,
+        &[0x4f, 0x4b],
+    ));
+
+    // ----- stop_client_tcp when the connection is dropped -----
+
+    let stop_client_tcp_command = 0x2e;
+
+    expectations.append(&mut mock_command(stop_client_tcp_command, 0x1));
+    expectations.append(&mut mock_single_byte_size_params(1, 0x0)); // Send Socket
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_padding(2));
+    expectations.append(&mut mock_receive(
+        stop_client_tcp_command,
+        number_of_params_to_receive,
+        &[0x1],
+    ));
+
+    let spi = BytewiseSpiMock::new(&expectations);
+
+    let delay = MockNoop::new();
+
+    let pins = EspControlMock {};
+
+    let wifi = Wifi::init(spi, pins, delay).ok().unwrap();
+
+    let remote = SocketAddr::from((Ipv4Addr::new(0x46, 0x46, 0x46, 0x46), 0x1111));
+    let mut buf = [0u8; 8];
+
+    let length = block_on(async {
+        let mut connection = wifi.connect(remote).await.unwrap();
+
+        assert_eq!(connection.write(b"ABC").await.unwrap(), 3);
+        connection.read(&mut buf).await.unwrap()
+    });
+
+    assert_eq!(&buf[..length], b"OK");
+
+    wifi.destroy().done();
+}
+
+#[test]
+fn dns_get_host_by_name_for_ipv6_returns_ipv6_unsupported_error() {
+    let spi = BytewiseSpiMock::new(&[]);
+
+    let delay = MockNoop::new();
+
+    let pins = EspControlMock {};
+
+    let wifi = Wifi::init(spi, pins, delay).ok().unwrap();
+
+    assert_eq!(
+        block_on(wifi.get_host_by_name("example.com", AddrType::IPv6)),
+        Err(esp32_wroom_core::Error::Network(
+            NetworkError::Ipv6Unsupported
+        ))
+    );
+
+    wifi.destroy().done();
+}
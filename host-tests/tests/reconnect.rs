@@ -0,0 +1,117 @@
+use embedded_hal_mock::delay::MockNoop;
+
+use esp32_wroom_core::credentials::StaticCredentials;
+use esp32_wroom_core::offline_queue::OfflineQueue;
+use esp32_wroom_core::reconnect::{ReconnectConfig, ReconnectState, Reconnector};
+use esp32_wroom_core::wifi::Wifi;
+
+pub mod support;
+
+use support::*;
+
+#[test]
+fn poll_and_flush_sends_the_queued_data_once_the_network_is_joined_again() {
+    let get_conn_status_command = 0x20;
+    let set_passphrase_command = 0x11;
+    let get_client_state_tcp_command = 0x2f;
+    let send_data_tcp_command = 0x44;
+    let number_of_params_to_receive = 0x1;
+
+    // ----- get_conn_status, ConnectionStatus::Disconnected -----
+
+    let mut expectations = mock_command(get_conn_status_command, 0x0);
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_receive(
+        get_conn_status_command,
+        number_of_params_to_receive,
+        &[0x6],
+    ));
+
+    // ----- set_passphrase -----
+
+    expectations.append(&mut mock_command(set_passphrase_command, 0x2));
+    expectations.append(&mut mock_single_byte_size_params(4, 0x46)); // SSID is "FFFF"
+    expectations.append(&mut mock_single_byte_size_params(8, 0x46)); // Passphrase is "FFFFFFFF"
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_padding(2));
+    expectations.append(&mut mock_receive(
+        set_passphrase_command,
+        number_of_params_to_receive,
+        &[0x1],
+    ));
+
+    // ----- get_conn_status, ConnectionStatus::Connected -----
+
+    expectations.append(&mut mock_command(get_conn_status_command, 0x0));
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_receive(
+        get_conn_status_command,
+        number_of_params_to_receive,
+        &[0x3],
+    ));
+
+    // ----- get_client_state_tcp, ConnectionState::Established -----
+
+    expectations.append(&mut mock_command(get_client_state_tcp_command, 0x1));
+    expectations.append(&mut mock_single_byte_size_params(1, 0x0)); // Send Socket
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_padding(2));
+    expectations.append(&mut mock_receive(
+        get_client_state_tcp_command,
+        number_of_params_to_receive,
+        &[0x4],
+    ));
+
+    // ----- send_data_tcp -----
+
+    expectations.append(&mut mock_command(send_data_tcp_command, 0x2));
+    expectations.append(&mut mock_two_byte_size_params(&[0x0])); // Send Socket
+    expectations.append(&mut mock_two_byte_size_params(b"hi")); // Send queued data
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_padding(1));
+    expectations.append(&mut mock_receive(
+        send_data_tcp_command,
+        number_of_params_to_receive,
+        &[0x2, 0x0],
+    ));
+
+    // ----- get_conn_status, still connected without flushing again -----
+
+    expectations.append(&mut mock_command(get_conn_status_command, 0x0));
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_receive(
+        get_conn_status_command,
+        number_of_params_to_receive,
+        &[0x3],
+    ));
+
+    let spi = BytewiseSpiMock::new(&expectations);
+
+    let delay = MockNoop::new();
+
+    let pins = EspControlMock {};
+
+    let mut wifi = Wifi::init(spi, pins, delay).ok().unwrap();
+
+    let mut credentials = StaticCredentials::new("FFFF", "FFFFFFFF");
+    let mut reconnector = Reconnector::new(ReconnectConfig::new());
+    let mut queue: OfflineQueue<16> = OfflineQueue::new();
+    queue.push(b"hi").unwrap();
+
+    for (now_ms, state) in [
+        (0, ReconnectState::Joining { attempt: 1 }),
+        (1_000, ReconnectState::Connected),
+        (2_000, ReconnectState::Connected),
+    ] {
+        assert_eq!(
+            reconnector
+                .poll_and_flush(&mut wifi, &mut credentials, now_ms, 0, &mut queue)
+                .unwrap(),
+            state
+        );
+    }
+
+    assert!(queue.is_empty());
+
+    wifi.destroy().done();
+}
@@ -0,0 +1,92 @@
+use core::net::{Ipv4Addr, SocketAddrV4};
+
+use esp32_wroom_core::network::{NetworkError, TransportMode};
+use esp32_wroom_core::sim::{SimulatedBus, SimulatedControlPins, SimulatedDelay, Simulator};
+use esp32_wroom_core::tcp_client::{Connect, TcpClient};
+use esp32_wroom_core::transport::SpiTransport;
+use esp32_wroom_core::wifi::{ConnectionStatus, Wifi};
+use esp32_wroom_core::Error;
+
+fn init(
+    simulator: &Simulator,
+) -> Wifi<SpiTransport<SimulatedBus, SimulatedControlPins>, SimulatedDelay> {
+    Wifi::init(simulator.bus(), simulator.control_pins(), SimulatedDelay).unwrap()
+}
+
+#[test]
+fn simulated_firmware_reports_its_version() {
+    let simulator = Simulator::new();
+    simulator.set_firmware_version("1.8.0");
+    let mut wifi = init(&simulator);
+
+    assert_eq!(wifi.firmware_version().unwrap().to_string(), "1.8.0");
+    assert_eq!(simulator.commands_received(), 1);
+}
+
+#[test]
+fn joining_a_simulated_network_checks_its_passphrase() {
+    let simulator = Simulator::new();
+    simulator.add_network("Home", Some("secret123"));
+    let mut wifi = init(&simulator);
+
+    wifi.join("Home", "wrong-passphrase").unwrap();
+    assert_eq!(
+        wifi.get_connection_status().unwrap(),
+        ConnectionStatus::Failed
+    );
+
+    wifi.join("Home", "secret123").unwrap();
+    assert_eq!(
+        wifi.get_connection_status().unwrap(),
+        ConnectionStatus::Connected
+    );
+
+    wifi.leave().unwrap();
+    assert_eq!(
+        wifi.get_connection_status().unwrap(),
+        ConnectionStatus::Disconnected
+    );
+}
+
+#[test]
+fn resolving_an_unknown_simulated_host_fails() {
+    let simulator = Simulator::new();
+    simulator.add_host("example.com", Ipv4Addr::new(93, 184, 216, 34));
+    let mut wifi = init(&simulator);
+
+    assert_eq!(
+        wifi.resolve("example.com").unwrap(),
+        Ipv4Addr::new(93, 184, 216, 34)
+    );
+    assert_eq!(
+        wifi.resolve("unknown.example.com"),
+        Err(Error::Network(NetworkError::DnsResolveFailed))
+    );
+}
+
+#[test]
+fn simulated_echo_server_sends_back_received_data() {
+    let simulator = Simulator::new();
+    let server = SocketAddrV4::new(Ipv4Addr::new(10, 0, 1, 2), 4000);
+    simulator.add_network("Home", None);
+    simulator.add_echo_server(server);
+    let mut wifi = init(&simulator);
+
+    wifi.join_open("Home").unwrap();
+
+    let mut received = [0u8; 16];
+    let mut length = 0;
+    TcpClient::build(&mut wifi)
+        .connect(
+            *server.ip(),
+            server.port(),
+            TransportMode::Tcp,
+            &mut |tcp_client| {
+                tcp_client.send_data(b"ping").unwrap();
+                length = tcp_client.receive_data(&mut received).unwrap();
+            },
+        )
+        .unwrap();
+
+    assert_eq!(&received[..length], b"ping");
+}
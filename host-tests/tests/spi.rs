@@ -1,7 +1,7 @@
 use embedded_hal_mock::delay::MockNoop;
 use embedded_hal_mock::spi;
 
-use esp32_wroom_rp::wifi::Wifi;
+use esp32_wroom_core::wifi::Wifi;
 
 pub mod support;
 
@@ -9,7 +9,7 @@ use support::*;
 
 #[test]
 fn too_many_parameters_error() {
-    let command = 0x37;
+    let command = 0x20;
     let number_of_params = 0x0;
     let mut expectations = mock_command(command, number_of_params);
 
@@ -30,18 +30,20 @@ fn too_many_parameters_error() {
 
     expectations.append(&mut too_man_parameters_expectations);
 
-    let spi = spi::Mock::new(&expectations);
+    let spi = BytewiseSpiMock::new(&expectations);
 
-    let mut delay = MockNoop::new();
+    let delay = MockNoop::new();
 
     let pins = EspControlMock {};
 
-    let mut wifi = Wifi::init(spi, pins, &mut delay).ok().unwrap();
-    let f = wifi.firmware_version();
+    let mut wifi = Wifi::init(spi, pins, delay).ok().unwrap();
+    let f = wifi.get_connection_status();
 
     assert_eq!(
         f.unwrap_err(),
-        esp32_wroom_rp::Error::Protocol(esp32_wroom_rp::protocol::ProtocolError::TooManyParameters)
+        esp32_wroom_core::Error::Protocol(
+            esp32_wroom_core::protocol::ProtocolError::TooManyParameters
+        )
     );
 
     wifi.destroy().done();
@@ -49,7 +51,7 @@ fn too_many_parameters_error() {
 
 #[test]
 fn invalid_number_of_parameters_error() {
-    let command = 0x37;
+    let command = 0x20;
     let number_of_params = 0x0;
     let mut expectations = mock_command(command, number_of_params);
 
@@ -67,19 +69,19 @@ fn invalid_number_of_parameters_error() {
 
     expectations.append(&mut invalid_number_of_parameters_expactations);
 
-    let spi = spi::Mock::new(&expectations);
+    let spi = BytewiseSpiMock::new(&expectations);
 
-    let mut delay = MockNoop::new();
+    let delay = MockNoop::new();
 
     let pins = EspControlMock {};
 
-    let mut wifi = Wifi::init(spi, pins, &mut delay).ok().unwrap();
-    let f = wifi.firmware_version();
+    let mut wifi = Wifi::init(spi, pins, delay).ok().unwrap();
+    let f = wifi.get_connection_status();
 
     assert_eq!(
         f.unwrap_err(),
-        esp32_wroom_rp::Error::Protocol(
-            esp32_wroom_rp::protocol::ProtocolError::InvalidNumberOfParameters
+        esp32_wroom_core::Error::Protocol(
+            esp32_wroom_core::protocol::ProtocolError::InvalidNumberOfParameters
         )
     );
 
@@ -103,18 +105,20 @@ fn invalid_command_induces_invalid_command_error() {
     ];
     expectations.append(&mut invalid_command_expactations);
 
-    let spi = spi::Mock::new(&expectations);
+    let spi = BytewiseSpiMock::new(&expectations);
 
-    let mut delay = MockNoop::new();
+    let delay = MockNoop::new();
 
     let pins = EspControlMock {};
 
-    let mut wifi = Wifi::init(spi, pins, &mut delay).ok().unwrap();
+    let mut wifi = Wifi::init(spi, pins, delay).ok().unwrap();
     let f = wifi.firmware_version();
 
     assert_eq!(
         f.unwrap_err(),
-        esp32_wroom_rp::Error::Protocol(esp32_wroom_rp::protocol::ProtocolError::InvalidCommand)
+        esp32_wroom_core::Error::Protocol(
+            esp32_wroom_core::protocol::ProtocolError::InvalidCommand
+        )
     );
 
     wifi.destroy().done();
@@ -133,19 +137,19 @@ fn timeout_induces_communication_timeout_error() {
         expectations.push(spi::Transaction::transfer(vec![0xff], vec![0x0]))
     }
 
-    let spi = spi::Mock::new(&expectations);
+    let spi = BytewiseSpiMock::new(&expectations);
 
-    let mut delay = MockNoop::new();
+    let delay = MockNoop::new();
 
     let pins = EspControlMock {};
 
-    let mut wifi = Wifi::init(spi, pins, &mut delay).ok().unwrap();
+    let mut wifi = Wifi::init(spi, pins, delay).ok().unwrap();
     let f = wifi.firmware_version();
 
     assert_eq!(
         f.unwrap_err(),
-        esp32_wroom_rp::Error::Protocol(
-            esp32_wroom_rp::protocol::ProtocolError::CommunicationTimeout
+        esp32_wroom_core::Error::Protocol(
+            esp32_wroom_core::protocol::ProtocolError::CommunicationTimeout
         )
     );
 
@@ -170,21 +174,88 @@ fn invalid_command_induces_nina_protocol_version_mismatch_error() {
     ];
     expectations.append(&mut invalid_command_expactations);
 
-    let spi = spi::Mock::new(&expectations);
+    let spi = BytewiseSpiMock::new(&expectations);
 
-    let mut delay = MockNoop::new();
+    let delay = MockNoop::new();
 
     let pins = EspControlMock {};
 
-    let mut wifi = Wifi::init(spi, pins, &mut delay).ok().unwrap();
+    let mut wifi = Wifi::init(spi, pins, delay).ok().unwrap();
     let f = wifi.firmware_version();
 
     assert_eq!(
         f.unwrap_err(),
-        esp32_wroom_rp::Error::Protocol(
-            esp32_wroom_rp::protocol::ProtocolError::NinaProtocolVersionMismatch
+        esp32_wroom_core::Error::Protocol(
+            esp32_wroom_core::protocol::ProtocolError::NinaProtocolVersionMismatch
         )
     );
 
     wifi.destroy().done();
 }
+
+#[test]
+fn commands_are_written_in_a_single_transfer() {
+    let set_passphrase_command = 0x11;
+    let mut command = vec![0xe0, set_passphrase_command, 0x2, 0x4];
+    command.extend_from_slice(b"ssid");
+    command.push(0x9);
+    command.extend_from_slice(b"password1");
+    command.push(0xee);
+
+    let expectations = vec![
+        spi::Transaction::transfer(command.clone(), vec![0x0; command.len()]),
+        // Pads the 19 byte command to 20 bytes
+        spi::Transaction::transfer(vec![0xff], vec![0x0]),
+        spi::Transaction::transfer(vec![0xff], vec![0xe0]),
+        spi::Transaction::transfer(
+            vec![0xff],
+            vec![command_or_reply_byte(set_passphrase_command)],
+        ),
+        spi::Transaction::transfer(vec![0xff], vec![0x1]),
+        spi::Transaction::transfer(vec![0xff], vec![0x1]),
+        spi::Transaction::transfer(vec![0xff], vec![0x1]),
+        spi::Transaction::transfer(vec![0xff], vec![0xee]),
+    ];
+
+    let spi = spi::Mock::new(&expectations);
+
+    let delay = MockNoop::new();
+
+    let pins = EspControlMock {};
+
+    let mut wifi = Wifi::init(spi, pins, delay).ok().unwrap();
+
+    wifi.join("ssid", "password1").unwrap();
+
+    wifi.destroy().done();
+}
+
+#[test]
+fn response_params_are_read_in_a_single_transfer() {
+    let get_fw_version_command = 0x37;
+
+    let expectations = vec![
+        spi::Transaction::transfer(vec![0xe0, get_fw_version_command, 0x0, 0xee], vec![0x0; 4]),
+        spi::Transaction::transfer(vec![0xff], vec![0xe0]),
+        spi::Transaction::transfer(
+            vec![0xff],
+            vec![command_or_reply_byte(get_fw_version_command)],
+        ),
+        spi::Transaction::transfer(vec![0xff], vec![0x1]),
+        spi::Transaction::transfer(vec![0xff], vec![0x5]),
+        spi::Transaction::transfer(vec![0xff; 5], b"1.7.4".to_vec()),
+        spi::Transaction::transfer(vec![0xff], vec![0xee]),
+    ];
+
+    let spi = spi::Mock::new(&expectations);
+
+    let delay = MockNoop::new();
+
+    let pins = EspControlMock {};
+
+    let mut wifi = Wifi::init(spi, pins, delay).ok().unwrap();
+
+    assert_eq!(wifi.firmware_version().unwrap().to_string(), "1.7.4");
+
+    wifi.destroy().done();
+}
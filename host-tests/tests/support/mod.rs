@@ -1,5 +1,7 @@
+use embedded_hal::blocking::spi::Transfer;
 use embedded_hal_mock::spi;
-use esp32_wroom_rp::gpio::EspControlInterface;
+use embedded_hal_mock::MockError;
+use esp32_wroom_core::gpio::EspControlInterface;
 
 pub(crate) struct EspControlMock {}
 
@@ -25,6 +27,40 @@ impl EspControlInterface for EspControlMock {
     fn get_esp_ready(&self) -> bool {
         true
     }
+
+    fn hold_in_reset(&mut self) {}
+
+    fn enter_bootloader<D>(&mut self, _delay: &mut D) {}
+}
+
+// Performs every transfer on an spi::Mock one byte at a time, so that the expectations below
+// describe the bytes on the wire regardless of how the driver groups them into transfers.
+#[derive(Clone)]
+pub(crate) struct BytewiseSpiMock {
+    spi: spi::Mock,
+}
+
+impl BytewiseSpiMock {
+    pub(crate) fn new(expectations: &[spi::Transaction]) -> Self {
+        Self {
+            spi: spi::Mock::new(expectations),
+        }
+    }
+
+    pub(crate) fn done(&mut self) {
+        self.spi.done();
+    }
+}
+
+impl Transfer<u8> for BytewiseSpiMock {
+    type Error = MockError;
+
+    fn transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8], MockError> {
+        for word in words.iter_mut() {
+            *word = self.spi.transfer(&mut [*word])?[0];
+        }
+        Ok(words)
+    }
 }
 
 pub fn mock_command(command_byte: u8, number_of_params: u8) -> Vec<spi::Transaction> {
@@ -55,6 +91,20 @@ pub fn mock_single_byte_size_params(
     expectations
 }
 
+pub fn mock_two_byte_size_params(param_bytes: &[u8]) -> Vec<spi::Transaction> {
+    let length = param_bytes.len() as u16;
+    let mut expectations = vec![
+        spi::Transaction::transfer(vec![(length >> 8) as u8], vec![0x0]),
+        spi::Transaction::transfer(vec![(length & 0xff) as u8], vec![0x0]),
+    ];
+
+    for byte in param_bytes.iter().cloned() {
+        expectations.push(spi::Transaction::transfer(vec![byte], vec![0x0]));
+    }
+
+    expectations
+}
+
 pub fn mock_padding(number_of_padding_bytes: u8) -> Vec<spi::Transaction> {
     let mut expectations = Vec::new();
     for _ in 0..number_of_padding_bytes {
@@ -71,6 +121,7 @@ pub fn mock_end_byte() -> Vec<spi::Transaction> {
     ]
 }
 
+#[allow(clippy::len_zero)]
 pub fn mock_receive(
     command_byte: u8,
     number_of_params_to_receive: u8,
@@ -110,10 +161,62 @@ pub fn mock_receive(
     expectations
 }
 
+pub fn mock_receive_data(command_byte: u8, values_to_receive: &[u8]) -> Vec<spi::Transaction> {
+    let length = values_to_receive.len() as u16;
+    let mut expectations = vec![
+        // read start command
+        spi::Transaction::transfer(vec![0xff], vec![0xe0]),
+        // read command byte | reply byte
+        spi::Transaction::transfer(vec![0xff], vec![command_or_reply_byte(command_byte)]),
+        // read number of params to receive
+        spi::Transaction::transfer(vec![0xff], vec![0x1]),
+        // read 2 byte length of the single param
+        spi::Transaction::transfer(vec![0xff], vec![(length >> 8) as u8]),
+        spi::Transaction::transfer(vec![0xff], vec![(length & 0xff) as u8]),
+    ];
+
+    for byte in values_to_receive.iter().cloned() {
+        expectations.push(spi::Transaction::transfer(vec![0xff], vec![byte]));
+    }
+
+    // read end byte
+    expectations.push(spi::Transaction::transfer(vec![0xff], vec![0xee]));
+
+    expectations
+}
+
+pub fn mock_receive_params(command_byte: u8, params_to_receive: &[&[u8]]) -> Vec<spi::Transaction> {
+    let mut expectations = vec![
+        // read start command
+        spi::Transaction::transfer(vec![0xff], vec![0xe0]),
+        // read command byte | reply byte
+        spi::Transaction::transfer(vec![0xff], vec![command_or_reply_byte(command_byte)]),
+        // read number of params to receive
+        spi::Transaction::transfer(vec![0xff], vec![params_to_receive.len() as u8]),
+    ];
+
+    for param in params_to_receive {
+        // read 1 byte length of the param followed by its bytes
+        expectations.push(spi::Transaction::transfer(
+            vec![0xff],
+            vec![param.len() as u8],
+        ));
+        for byte in param.iter().cloned() {
+            expectations.push(spi::Transaction::transfer(vec![0xff], vec![byte]));
+        }
+    }
+
+    // read end byte
+    expectations.push(spi::Transaction::transfer(vec![0xff], vec![0xee]));
+
+    expectations
+}
+
 pub fn command_or_reply_byte(command: u8) -> u8 {
     command | 0x80
 }
 
+#[allow(clippy::unnecessary_cast)]
 pub fn command_and_reply_byte(command: u8) -> u8 {
     (command as u8) & !(0x80 as u8)
 }
@@ -0,0 +1,111 @@
+use embedded_hal_mock::delay::MockNoop;
+
+use embedded_svc::wifi::{AccessPointConfiguration, ClientConfiguration, Configuration, Wifi as _};
+
+use esp32_wroom_core::network::NetworkError;
+use esp32_wroom_core::svc::SvcWifi;
+use esp32_wroom_core::wifi::Wifi;
+use esp32_wroom_core::Error;
+
+pub mod support;
+
+use support::*;
+
+#[test]
+fn connect_joins_the_configured_network() {
+    let set_passphrase_command = 0x11;
+    let get_conn_status_command = 0x20;
+    let number_of_params_to_receive = 0x1;
+
+    // ----- set_passphrase -----
+
+    let mut expectations = mock_command(set_passphrase_command, 0x2);
+    expectations.append(&mut mock_single_byte_size_params(2, 0x41)); // Send SSID
+    expectations.append(&mut mock_single_byte_size_params(2, 0x42)); // Send passphrase
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_padding(2));
+    expectations.append(&mut mock_receive(
+        set_passphrase_command,
+        number_of_params_to_receive,
+        &[0x1],
+    ));
+
+    // ----- get_conn_status reports Connected -----
+
+    expectations.append(&mut mock_command(get_conn_status_command, 0x0));
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_receive(
+        get_conn_status_command,
+        number_of_params_to_receive,
+        &[0x3],
+    ));
+
+    let spi = BytewiseSpiMock::new(&expectations);
+
+    let delay = MockNoop::new();
+
+    let pins = EspControlMock {};
+
+    let mut wifi = Wifi::init(spi, pins, delay).ok().unwrap();
+
+    let configuration = Configuration::Client(ClientConfiguration {
+        ssid: "AA".try_into().unwrap(),
+        password: "BB".try_into().unwrap(),
+        ..Default::default()
+    });
+
+    let mut svc_wifi = SvcWifi::build(&mut wifi);
+    svc_wifi.set_configuration(&configuration).unwrap();
+    svc_wifi.start().unwrap();
+    svc_wifi.connect().unwrap();
+
+    assert!(svc_wifi.is_started().unwrap());
+    assert!(svc_wifi.is_connected().unwrap());
+    assert_eq!(svc_wifi.get_configuration().unwrap(), configuration);
+
+    wifi.destroy().done();
+}
+
+#[test]
+fn connect_without_client_configuration_returns_client_not_configured_error() {
+    let spi = BytewiseSpiMock::new(&[]);
+
+    let delay = MockNoop::new();
+
+    let pins = EspControlMock {};
+
+    let mut wifi = Wifi::init(spi, pins, delay).ok().unwrap();
+
+    let mut svc_wifi = SvcWifi::build(&mut wifi);
+
+    assert_eq!(
+        svc_wifi.connect(),
+        Err(Error::Network(NetworkError::ClientNotConfigured))
+    );
+
+    wifi.destroy().done();
+}
+
+#[test]
+fn set_configuration_for_mixed_mode_returns_unsupported_operation_error() {
+    let spi = BytewiseSpiMock::new(&[]);
+
+    let delay = MockNoop::new();
+
+    let pins = EspControlMock {};
+
+    let mut wifi = Wifi::init(spi, pins, delay).ok().unwrap();
+
+    let mut svc_wifi = SvcWifi::build(&mut wifi);
+
+    assert_eq!(
+        svc_wifi.set_configuration(&Configuration::Mixed(
+            ClientConfiguration::default(),
+            AccessPointConfiguration::default()
+        )),
+        Err(Error::UnsupportedOperation)
+    );
+    assert_eq!(svc_wifi.get_configuration().unwrap(), Configuration::None);
+
+    wifi.destroy().done();
+}
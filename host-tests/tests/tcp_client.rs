@@ -1,9 +1,12 @@
 use embedded_hal_mock::delay::MockNoop;
 use embedded_hal_mock::spi;
 
-use esp32_wroom_rp::network::{Hostname, IpAddress, Port, TransportMode};
-use esp32_wroom_rp::tcp_client::{Connect, TcpClient};
-use esp32_wroom_rp::wifi::Wifi;
+use core::net::Ipv4Addr;
+use esp32_wroom_core::keep_alive::KeepAlive;
+
+use esp32_wroom_core::network::{Hostname, IpAddr, NetworkError, Port, TransportMode};
+use esp32_wroom_core::tcp_client::{Connect, ConnectProgress, PollConfig, TcpClient};
+use esp32_wroom_core::wifi::Wifi;
 
 pub mod support;
 
@@ -130,13 +133,13 @@ fn successful_tcp_connection_with_hostname_invokes_closure() {
         &[0x1],
     ));
 
-    let spi = spi::Mock::new(&expectations);
+    let spi = BytewiseSpiMock::new(&expectations);
 
-    let mut delay = MockNoop::new();
+    let delay = MockNoop::new();
 
     let pins = EspControlMock {};
 
-    let mut wifi = Wifi::init(spi, pins, &mut delay).ok().unwrap();
+    let mut wifi = Wifi::init(spi, pins, delay).ok().unwrap();
 
     let hostname: Hostname = "FFFF";
     let port: Port = 0x1111;
@@ -148,9 +151,7 @@ fn successful_tcp_connection_with_hostname_invokes_closure() {
     let test_value = &mut value;
 
     TcpClient::build(&mut wifi)
-        .connect(hostname, port, mode, &mut delay, &mut |_tcp_client| {
-            *test_value = 2
-        })
+        .connect(hostname, port, mode, &mut |_tcp_client| *test_value = 2)
         .unwrap();
 
     assert_eq!(value, 2);
@@ -237,15 +238,15 @@ fn successful_tcp_connection_with_ip_address_invokes_closure() {
         &[0x1],
     ));
 
-    let spi = spi::Mock::new(&expectations);
+    let spi = BytewiseSpiMock::new(&expectations);
 
-    let mut delay = MockNoop::new();
+    let delay = MockNoop::new();
 
     let pins = EspControlMock {};
 
-    let mut wifi = Wifi::init(spi, pins, &mut delay).ok().unwrap();
+    let mut wifi = Wifi::init(spi, pins, delay).ok().unwrap();
 
-    let ip_address: IpAddress = [0x40, 0x40, 0x40, 0x40];
+    let ip_address = Ipv4Addr::new(0x40, 0x40, 0x40, 0x40);
     let port: Port = 0x1111;
     let mode: TransportMode = TransportMode::Tcp;
 
@@ -255,14 +256,114 @@ fn successful_tcp_connection_with_ip_address_invokes_closure() {
     let test_value = &mut value;
 
     TcpClient::build(&mut wifi)
-        .connect(ip_address, port, mode, &mut delay, &mut |_tcp_client| {
-            *test_value = 2
-        })
+        .connect(ip_address, port, mode, &mut |_tcp_client| *test_value = 2)
         .unwrap();
 
     assert_eq!(value, 2);
 }
 
+#[test]
+fn connect_nb_returns_before_the_connection_is_established() {
+    // ----- get_socket -----
+
+    let get_socket_command = 0x3f;
+    let mut number_of_params = 0x0;
+    let number_of_params_to_receive = 0x1;
+
+    let mut expectations = mock_command(get_socket_command, number_of_params);
+
+    expectations.append(&mut mock_end_byte());
+
+    expectations.append(&mut mock_receive(
+        get_socket_command,
+        number_of_params_to_receive,
+        &[0x0],
+    ));
+
+    // ------ start_client_tcp ------
+
+    let start_client_tcp_command = 0x2d;
+    number_of_params = 0x4;
+
+    expectations.append(&mut mock_command(
+        start_client_tcp_command,
+        number_of_params,
+    ));
+    expectations.append(&mut mock_single_byte_size_params(4, 0x40)); // Send fake IP Address
+    expectations.append(&mut mock_single_byte_size_params(2, 0x11)); // Send fake Port
+    expectations.append(&mut mock_single_byte_size_params(1, 0x0)); // Send fake Socket
+    expectations.append(&mut mock_single_byte_size_params(1, 0x0)); // Send fake Transport Mode
+
+    expectations.append(&mut mock_end_byte());
+
+    expectations.append(&mut mock_receive(
+        start_client_tcp_command,
+        number_of_params_to_receive,
+        &[0x1],
+    ));
+
+    // ----- get_client_state_tcp, polled twice -----
+
+    let get_client_state_tcp_command = 0x2f;
+    number_of_params = 0x1;
+
+    // ConnectionState::SynSent, then ConnectionState::Established
+    for state in [0x2, 0x4] {
+        expectations.append(&mut mock_command(
+            get_client_state_tcp_command,
+            number_of_params,
+        ));
+        expectations.append(&mut mock_single_byte_size_params(1, 0x0)); // Send fake Socket
+        expectations.append(&mut mock_end_byte());
+        expectations.append(&mut mock_padding(2));
+        expectations.append(&mut mock_receive(
+            get_client_state_tcp_command,
+            number_of_params_to_receive,
+            &[state],
+        ));
+    }
+
+    // ----- stop_client_tcp -----
+
+    let stop_client_tcp_command = 0x2e;
+
+    expectations.append(&mut mock_command(stop_client_tcp_command, number_of_params));
+    expectations.append(&mut mock_single_byte_size_params(1, 0x0)); // Send fake Socket
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_padding(2));
+    expectations.append(&mut mock_receive(
+        stop_client_tcp_command,
+        number_of_params_to_receive,
+        &[0x1],
+    ));
+
+    let spi = BytewiseSpiMock::new(&expectations);
+
+    let delay = MockNoop::new();
+
+    let pins = EspControlMock {};
+
+    let mut wifi = Wifi::init(spi, pins, delay).ok().unwrap();
+
+    let ip_address = Ipv4Addr::new(0x40, 0x40, 0x40, 0x40);
+    let port: Port = 0x1111;
+
+    let mut tcp_client = TcpClient::build(&mut wifi);
+    tcp_client
+        .connect_nb(ip_address, port, TransportMode::Tcp)
+        .unwrap();
+
+    assert_eq!(tcp_client.poll_connect().unwrap(), ConnectProgress::Pending);
+    assert_eq!(
+        tcp_client.poll_connect().unwrap(),
+        ConnectProgress::Established
+    );
+
+    tcp_client.close().unwrap();
+
+    wifi.destroy().done();
+}
+
 #[test]
 fn tcp_connection_timeout_error() {
     // ----- get_socket -----
@@ -366,28 +467,597 @@ fn tcp_connection_timeout_error() {
         &[0x1],
     ));
 
-    let spi = spi::Mock::new(&expectations);
+    let spi = BytewiseSpiMock::new(&expectations);
 
-    let mut delay = MockNoop::new();
+    let delay = MockNoop::new();
 
     let pins = EspControlMock {};
 
-    let mut wifi = Wifi::init(spi, pins, &mut delay).ok().unwrap();
+    let mut wifi = Wifi::init(spi, pins, delay).ok().unwrap();
 
-    let ip_address: IpAddress = [0x40, 0x40, 0x40, 0x40];
+    let ip_address = Ipv4Addr::new(0x40, 0x40, 0x40, 0x40);
     let port: Port = 0x1111;
     let mode: TransportMode = TransportMode::Tcp;
 
-    let result = TcpClient::build(&mut wifi).connect(
-        ip_address,
-        port,
-        mode,
-        &mut delay,
-        &mut |_tcp_client| {},
+    let result = TcpClient::build(&mut wifi).connect(ip_address, port, mode, &mut |_tcp_client| {});
+
+    assert_eq!(
+        result.unwrap_err(),
+        esp32_wroom_core::Error::Network(
+            esp32_wroom_core::network::NetworkError::ConnectionTimeout
+        )
+    );
+}
+
+#[test]
+fn set_local_port_returns_unsupported_operation_error() {
+    let spi = BytewiseSpiMock::new(&[]);
+
+    let delay = MockNoop::new();
+
+    let pins = EspControlMock {};
+
+    let mut wifi = Wifi::init(spi, pins, delay).ok().unwrap();
+
+    let result = TcpClient::build(&mut wifi).set_local_port(4000);
+
+    assert_eq!(
+        result.unwrap_err(),
+        esp32_wroom_core::Error::UnsupportedOperation
+    );
+
+    wifi.destroy().done();
+}
+
+#[test]
+fn connect_to_ipv6_address_returns_ipv6_unsupported_error() {
+    let spi = BytewiseSpiMock::new(&[]);
+
+    let delay = MockNoop::new();
+
+    let pins = EspControlMock {};
+
+    let mut wifi = Wifi::init(spi, pins, delay).ok().unwrap();
+
+    let ip = IpAddr::V6([0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+    let mut invoked = false;
+
+    let result =
+        TcpClient::build(&mut wifi).connect(ip, 4000, TransportMode::Tcp, &mut |_| invoked = true);
+
+    assert_eq!(
+        result.unwrap_err(),
+        esp32_wroom_core::Error::Network(NetworkError::Ipv6Unsupported)
+    );
+    assert!(!invoked);
+
+    wifi.destroy().done();
+}
+
+struct MqttPing;
+
+impl KeepAlive for MqttPing {
+    fn interval_ms(&self) -> u32 {
+        1000
+    }
+
+    fn ping(&self, buf: &mut [u8]) -> usize {
+        buf[..2].copy_from_slice(&[0xc0, 0x00]);
+        2
+    }
+}
+
+static MQTT_PING: MqttPing = MqttPing;
+
+#[test]
+fn poll_sends_keep_alive_ping_once_interval_has_elapsed() {
+    let send_data_tcp_command = 0x44;
+    let number_of_params = 0x2;
+    let number_of_params_to_receive = 0x1;
+
+    let mut expectations = mock_command(send_data_tcp_command, number_of_params);
+    expectations.append(&mut mock_two_byte_size_params(&[0x0])); // Send Socket
+    expectations.append(&mut mock_two_byte_size_params(&[0xc0, 0x00])); // Send PINGREQ
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_padding(1));
+    expectations.append(&mut mock_receive(
+        send_data_tcp_command,
+        number_of_params_to_receive,
+        &[0x2],
+    ));
+
+    let spi = BytewiseSpiMock::new(&expectations);
+
+    let delay = MockNoop::new();
+
+    let pins = EspControlMock {};
+
+    let mut wifi = Wifi::init(spi, pins, delay).ok().unwrap();
+
+    let mut tcp_client = TcpClient::build(&mut wifi);
+    tcp_client.set_keep_alive(&MQTT_PING).unwrap();
+
+    // The interval starts at the first poll and only the third poll is due
+    for now_ms in [5000, 5999, 6000] {
+        tcp_client.poll(now_ms).unwrap();
+    }
+
+    wifi.destroy().done();
+}
+
+#[test]
+fn receive_data_fails_once_read_timeout_has_elapsed() {
+    let avail_data_tcp_command = 0x2b;
+    let number_of_params = 0x1;
+    let number_of_params_to_receive = 0x1;
+
+    // Polled at 0ms, 50ms and 100ms, after which the timeout has elapsed
+    let mut expectations = vec![];
+    for _ in 0..3 {
+        expectations.append(&mut mock_command(avail_data_tcp_command, number_of_params));
+        expectations.append(&mut mock_single_byte_size_params(1, 0x0)); // Send Socket
+        expectations.append(&mut mock_end_byte());
+        expectations.append(&mut mock_padding(2));
+        expectations.append(&mut mock_receive(
+            avail_data_tcp_command,
+            number_of_params_to_receive,
+            &[0x0, 0x0],
+        ));
+    }
+
+    let spi = BytewiseSpiMock::new(&expectations);
+
+    let delay = MockNoop::new();
+
+    let pins = EspControlMock {};
+
+    let mut wifi = Wifi::init(spi, pins, delay).ok().unwrap();
+
+    let mut tcp_client = TcpClient::build(&mut wifi);
+    tcp_client.set_poll_config(PollConfig::new().initial_interval_ms(50));
+    tcp_client.set_read_timeout(Some(100));
+
+    let mut buf = [0; 16];
+
+    assert_eq!(
+        tcp_client.receive_data(&mut buf).unwrap_err(),
+        esp32_wroom_core::Error::Network(NetworkError::ReadTimeout)
+    );
+
+    wifi.destroy().done();
+}
+
+fn mock_avail_data_tcp_polls(polls: usize) -> Vec<spi::Transaction> {
+    let avail_data_tcp_command = 0x2b;
+    let number_of_params = 0x1;
+    let number_of_params_to_receive = 0x1;
+
+    let mut expectations = vec![];
+    for _ in 0..polls {
+        expectations.append(&mut mock_command(avail_data_tcp_command, number_of_params));
+        expectations.append(&mut mock_single_byte_size_params(1, 0x0)); // Send Socket
+        expectations.append(&mut mock_end_byte());
+        expectations.append(&mut mock_padding(2));
+        expectations.append(&mut mock_receive(
+            avail_data_tcp_command,
+            number_of_params_to_receive,
+            &[0x0, 0x0],
+        ));
+    }
+    expectations
+}
+
+#[test]
+fn receive_data_backs_off_in_between_polls() {
+    // Polled at 0ms, 1ms, 3ms, 7ms and 11ms, doubling the interval up to 4ms, after which the
+    // timeout has elapsed
+    let spi = BytewiseSpiMock::new(&mock_avail_data_tcp_polls(5));
+
+    let delay = MockNoop::new();
+
+    let pins = EspControlMock {};
+
+    let mut wifi = Wifi::init(spi, pins, delay).ok().unwrap();
+
+    let mut tcp_client = TcpClient::build(&mut wifi);
+    tcp_client.set_poll_config(PollConfig::new().max_interval_ms(4));
+    tcp_client.set_read_timeout(Some(10));
+
+    let mut buf = [0; 16];
+
+    assert_eq!(
+        tcp_client.receive_data(&mut buf).unwrap_err(),
+        esp32_wroom_core::Error::Network(NetworkError::ReadTimeout)
+    );
+
+    wifi.destroy().done();
+}
+
+#[test]
+fn receive_data_fails_once_poll_deadline_has_passed() {
+    // Polled at 0ms, 1ms, 2ms and 3ms without a read timeout
+    let spi = BytewiseSpiMock::new(&mock_avail_data_tcp_polls(4));
+
+    let delay = MockNoop::new();
+
+    let pins = EspControlMock {};
+
+    let mut wifi = Wifi::init(spi, pins, delay).ok().unwrap();
+
+    let mut tcp_client = TcpClient::build(&mut wifi);
+    tcp_client.set_poll_config(PollConfig::new().max_interval_ms(1).deadline_ms(Some(3)));
+
+    let mut buf = [0; 16];
+
+    assert_eq!(
+        tcp_client.receive_data(&mut buf).unwrap_err(),
+        esp32_wroom_core::Error::Network(NetworkError::ReadTimeout)
+    );
+
+    wifi.destroy().done();
+}
+
+#[test]
+fn write_all_retries_while_nothing_is_accepted() {
+    let send_data_tcp_command = 0x44;
+    let number_of_params = 0x2;
+    let number_of_params_to_receive = 0x1;
+
+    // The ESP32 target first accepts none and then both bytes
+    let mut expectations = vec![];
+    for written in [0x0, 0x2] {
+        expectations.append(&mut mock_command(send_data_tcp_command, number_of_params));
+        expectations.append(&mut mock_two_byte_size_params(&[0x0])); // Send Socket
+        expectations.append(&mut mock_two_byte_size_params(&[0x41, 0x42])); // Send data
+        expectations.append(&mut mock_end_byte());
+        expectations.append(&mut mock_padding(1));
+        expectations.append(&mut mock_receive(
+            send_data_tcp_command,
+            number_of_params_to_receive,
+            &[written, 0x0],
+        ));
+    }
+
+    expectations.append(&mut mock_check_data_sent(0x1));
+
+    let spi = BytewiseSpiMock::new(&expectations);
+
+    let delay = MockNoop::new();
+
+    let pins = EspControlMock {};
+
+    let mut wifi = Wifi::init(spi, pins, delay).ok().unwrap();
+
+    let mut tcp_client = TcpClient::build(&mut wifi);
+    tcp_client.set_write_timeout(Some(1000));
+
+    tcp_client.write_all(b"AB").unwrap();
+
+    wifi.destroy().done();
+}
+
+#[test]
+fn poll_reports_lost_connection_once_liveness_check_is_due() {
+    let get_client_state_tcp_command = 0x2f;
+    let number_of_params = 0x1;
+    let number_of_params_to_receive = 0x1;
+
+    // Checked at the first poll and again once the interval has elapsed
+    let mut expectations = vec![];
+    for state in [0x4, 0x0] {
+        expectations.append(&mut mock_command(
+            get_client_state_tcp_command,
+            number_of_params,
+        ));
+        expectations.append(&mut mock_single_byte_size_params(1, 0x0)); // Send Socket
+        expectations.append(&mut mock_end_byte());
+        expectations.append(&mut mock_padding(2));
+        expectations.append(&mut mock_receive(
+            get_client_state_tcp_command,
+            number_of_params_to_receive,
+            &[state],
+        ));
+    }
+
+    let spi = BytewiseSpiMock::new(&expectations);
+
+    let delay = MockNoop::new();
+
+    let pins = EspControlMock {};
+
+    let mut wifi = Wifi::init(spi, pins, delay).ok().unwrap();
+
+    let mut tcp_client = TcpClient::build(&mut wifi);
+    tcp_client.set_liveness_check(Some(1000));
+
+    tcp_client.poll(5000).unwrap();
+    tcp_client.poll(5999).unwrap();
+
+    assert_eq!(
+        tcp_client.poll(6000).unwrap_err(),
+        esp32_wroom_core::Error::Network(NetworkError::ConnectionLost)
+    );
+
+    wifi.destroy().done();
+}
+
+#[test]
+fn send_data_sends_binary_data_and_returns_bytes_written() {
+    let send_data_tcp_command = 0x44;
+    let number_of_params = 0x2;
+    let number_of_params_to_receive = 0x1;
+
+    let mut expectations = mock_command(send_data_tcp_command, number_of_params);
+    expectations.append(&mut mock_two_byte_size_params(&[0x0])); // Send Socket
+    expectations.append(&mut mock_two_byte_size_params(&[0x00, 0xff, 0x80])); // Send data
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_receive(
+        send_data_tcp_command,
+        number_of_params_to_receive,
+        &[0x3, 0x0],
+    ));
+
+    expectations.append(&mut mock_check_data_sent(0x1));
+
+    let spi = BytewiseSpiMock::new(&expectations);
+
+    let delay = MockNoop::new();
+
+    let pins = EspControlMock {};
+
+    let mut wifi = Wifi::init(spi, pins, delay).ok().unwrap();
+
+    let written = TcpClient::build(&mut wifi)
+        .send_data(&[0x00, 0xff, 0x80])
+        .unwrap();
+
+    assert_eq!(written, 3);
+
+    wifi.destroy().done();
+}
+
+#[test]
+fn receive_stream_passes_chunks_until_server_closes_connection() {
+    let avail_data_tcp_command = 0x2b;
+    let get_data_buf_tcp_command = 0x45;
+    let get_client_state_tcp_command = 0x2f;
+    let number_of_params_to_receive = 0x1;
+
+    let mut expectations = vec![];
+
+    // ----- two chunks of 3 and 2 bytes arrive -----
+
+    for chunk in [&[0x41, 0x42, 0x43][..], &[0x44, 0x45][..]] {
+        expectations.append(&mut mock_command(avail_data_tcp_command, 0x1));
+        expectations.append(&mut mock_single_byte_size_params(1, 0x0)); // Send Socket
+        expectations.append(&mut mock_end_byte());
+        expectations.append(&mut mock_padding(2));
+        expectations.append(&mut mock_receive(
+            avail_data_tcp_command,
+            number_of_params_to_receive,
+            &[chunk.len() as u8, 0x0],
+        ));
+
+        expectations.append(&mut mock_command(get_data_buf_tcp_command, 0x2));
+        expectations.append(&mut mock_two_byte_size_params(&[0x0])); // Send Socket
+        expectations.append(&mut mock_two_byte_size_params(&[chunk.len() as u8, 0x0])); // Send requested length
+        expectations.append(&mut mock_end_byte());
+        expectations.append(&mut mock_padding(1));
+        expectations.append(&mut mock_receive_data(get_data_buf_tcp_command, chunk));
+    }
+
+    // ----- no more data and the connection is closed -----
+
+    expectations.append(&mut mock_command(avail_data_tcp_command, 0x1));
+    expectations.append(&mut mock_single_byte_size_params(1, 0x0)); // Send Socket
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_padding(2));
+    expectations.append(&mut mock_receive(
+        avail_data_tcp_command,
+        number_of_params_to_receive,
+        &[0x0, 0x0],
+    ));
+
+    expectations.append(&mut mock_command(get_client_state_tcp_command, 0x1));
+    expectations.append(&mut mock_single_byte_size_params(1, 0x0)); // Send Socket
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_padding(2));
+    expectations.append(&mut mock_receive(
+        get_client_state_tcp_command,
+        number_of_params_to_receive,
+        &[0x0],
+    ));
+
+    let spi = BytewiseSpiMock::new(&expectations);
+
+    let delay = MockNoop::new();
+
+    let pins = EspControlMock {};
+
+    let mut wifi = Wifi::init(spi, pins, delay).ok().unwrap();
+
+    let mut buf = [0; 3];
+    let mut body = vec![];
+
+    let received = TcpClient::build(&mut wifi)
+        .receive_stream(&mut buf, |chunk| {
+            body.extend_from_slice(chunk);
+            true
+        })
+        .unwrap();
+
+    assert_eq!(received, 5);
+    assert_eq!(body, b"ABCDE");
+
+    wifi.destroy().done();
+}
+
+#[test]
+fn send_data_fails_when_data_could_not_be_confirmed_sent() {
+    let send_data_tcp_command = 0x44;
+    let number_of_params = 0x2;
+    let number_of_params_to_receive = 0x1;
+
+    let mut expectations = mock_command(send_data_tcp_command, number_of_params);
+    expectations.append(&mut mock_two_byte_size_params(&[0x0])); // Send Socket
+    expectations.append(&mut mock_two_byte_size_params(&[0x41])); // Send data
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_padding(2));
+    expectations.append(&mut mock_receive(
+        send_data_tcp_command,
+        number_of_params_to_receive,
+        &[0x1, 0x0],
+    ));
+    expectations.append(&mut mock_check_data_sent(0x0));
+
+    let spi = BytewiseSpiMock::new(&expectations);
+
+    let delay = MockNoop::new();
+
+    let pins = EspControlMock {};
+
+    let mut wifi = Wifi::init(spi, pins, delay).ok().unwrap();
+
+    assert_eq!(
+        TcpClient::build(&mut wifi).send_data(b"A").unwrap_err(),
+        esp32_wroom_core::Error::Network(NetworkError::SendFailed)
     );
 
+    wifi.destroy().done();
+}
+
+// Expectations for checking whether the data written to socket 0 was sent, answered with `sent`
+fn mock_check_data_sent(sent: u8) -> Vec<spi::Transaction> {
+    let check_data_sent_command = 0x2a;
+
+    let mut expectations = mock_command(check_data_sent_command, 0x1);
+    expectations.append(&mut mock_single_byte_size_params(1, 0x0)); // Send Socket
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_padding(2));
+    expectations.append(&mut mock_receive(check_data_sent_command, 0x1, &[sent]));
+
+    expectations
+}
+
+#[test]
+fn bytes_available_returns_number_of_received_bytes() {
+    let avail_data_tcp_command = 0x2b;
+    let number_of_params = 0x1;
+    let number_of_params_to_receive = 0x1;
+
+    let mut expectations = mock_command(avail_data_tcp_command, number_of_params);
+    expectations.append(&mut mock_single_byte_size_params(1, 0x0)); // Send Socket
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_padding(2));
+    expectations.append(&mut mock_receive(
+        avail_data_tcp_command,
+        number_of_params_to_receive,
+        &[0x2c, 0x01],
+    ));
+
+    let spi = BytewiseSpiMock::new(&expectations);
+
+    let delay = MockNoop::new();
+
+    let pins = EspControlMock {};
+
+    let mut wifi = Wifi::init(spi, pins, delay).ok().unwrap();
+
+    assert_eq!(TcpClient::build(&mut wifi).bytes_available().unwrap(), 300);
+
+    wifi.destroy().done();
+}
+
+#[test]
+fn connect_to_host_starts_tls_connection_by_hostname_and_leaves_it_open() {
+    let get_socket_command = 0x3f;
+    let start_client_tcp_command = 0x2d;
+    let get_client_state_tcp_command = 0x2f;
+    let number_of_params_to_receive = 0x1;
+
+    // ----- get_socket -----
+
+    let mut expectations = mock_command(get_socket_command, 0x0);
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_receive(
+        get_socket_command,
+        number_of_params_to_receive,
+        &[0x0],
+    ));
+
+    // ----- start_client_tcp with hostname in TLS mode -----
+
+    expectations.append(&mut mock_command(start_client_tcp_command, 0x5));
+    expectations.append(&mut mock_single_byte_size_params(4, 0x46)); // hostname is "FFFF"
+    expectations.append(&mut mock_single_byte_size_params(4, 0x0)); // IP Address is resolved by NINA
+    expectations.append(&mut mock_single_byte_size_params(2, 0x11)); // Send fake Port
+    expectations.append(&mut mock_single_byte_size_params(1, 0x0)); // Send fake Socket
+    expectations.append(&mut mock_single_byte_size_params(1, 0x2)); // Send TLS Transport Mode
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_padding(3));
+    expectations.append(&mut mock_receive(
+        start_client_tcp_command,
+        number_of_params_to_receive,
+        &[0x1],
+    ));
+
+    // ----- get_client_state_tcp -----
+
+    expectations.append(&mut mock_command(get_client_state_tcp_command, 0x1));
+    expectations.append(&mut mock_single_byte_size_params(1, 0x0)); // Send fake Socket
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_padding(2));
+    expectations.append(&mut mock_receive(
+        get_client_state_tcp_command,
+        number_of_params_to_receive,
+        &[0x4], // ConnectionState::Established
+    ));
+
+    let spi = BytewiseSpiMock::new(&expectations);
+
+    let delay = MockNoop::new();
+
+    let pins = EspControlMock {};
+
+    let mut wifi = Wifi::init(spi, pins, delay).ok().unwrap();
+
+    let hostname: Hostname = "FFFF";
+    let port: Port = 0x1111;
+
+    let mut tcp_client = TcpClient::build(&mut wifi);
+    tcp_client
+        .connect_to_host(hostname, port, TransportMode::Tls)
+        .unwrap();
+
+    assert_eq!(tcp_client.server_hostname(), "FFFF");
+
+    wifi.destroy().done();
+}
+
+#[test]
+fn connect_to_host_fails_without_requesting_socket_when_hostname_does_not_resolve() {
+    let req_host_by_name_command = 0x34;
+
+    let mut expectations = mock_command(req_host_by_name_command, 0x1);
+    expectations.append(&mut mock_single_byte_size_params(4, 0x46)); // hostname is "FFFF"
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_padding(3));
+    expectations.append(&mut mock_receive(req_host_by_name_command, 0x1, &[0x0]));
+
+    let spi = BytewiseSpiMock::new(&expectations);
+
+    let delay = MockNoop::new();
+
+    let pins = EspControlMock {};
+
+    let mut wifi = Wifi::init(spi, pins, delay).ok().unwrap();
+
+    let result = TcpClient::build(&mut wifi).connect_to_host("FFFF", 80, TransportMode::Tcp);
+
     assert_eq!(
         result.unwrap_err(),
-        esp32_wroom_rp::Error::Network(esp32_wroom_rp::network::NetworkError::ConnectionTimeout)
+        esp32_wroom_core::Error::Network(NetworkError::DnsResolveFailed)
     );
+
+    wifi.destroy().done();
 }
@@ -0,0 +1,130 @@
+use embedded_hal_mock::delay::MockNoop;
+
+use esp32_wroom_core::network::{NetworkError, Port};
+use esp32_wroom_core::tcp_server::TcpServer;
+use esp32_wroom_core::wifi::Wifi;
+use esp32_wroom_core::Error;
+
+pub mod support;
+
+use support::*;
+
+#[test]
+fn accept_returns_client_socket_once_a_client_connects() {
+    // ----- get_socket -----
+
+    let get_socket_command = 0x3f;
+    let mut number_of_params = 0x0;
+    let number_of_params_to_receive = 0x1;
+
+    let mut expectations = mock_command(get_socket_command, number_of_params);
+
+    expectations.append(&mut mock_end_byte());
+
+    expectations.append(&mut mock_receive(
+        get_socket_command,
+        number_of_params_to_receive,
+        &[0x0],
+    ));
+
+    // ----- start_server_tcp -----
+
+    let start_server_tcp_command = 0x28;
+    number_of_params = 0x3;
+
+    expectations.append(&mut mock_command(
+        start_server_tcp_command,
+        number_of_params,
+    ));
+    expectations.append(&mut mock_single_byte_size_params(2, 0x11)); // Send fake Port
+    expectations.append(&mut mock_single_byte_size_params(1, 0x0)); // Send fake Socket
+    expectations.append(&mut mock_single_byte_size_params(1, 0x0)); // Send fake Transport Mode
+
+    expectations.append(&mut mock_end_byte());
+
+    expectations.append(&mut mock_padding(1));
+
+    expectations.append(&mut mock_receive(
+        start_server_tcp_command,
+        number_of_params_to_receive,
+        &[0x1],
+    ));
+
+    // ----- avail_data_tcp first reports no client, then client socket 1 -----
+
+    let avail_data_tcp_command = 0x2b;
+    number_of_params = 0x1;
+
+    for client_socket in [0xff, 0x1] {
+        expectations.append(&mut mock_command(avail_data_tcp_command, number_of_params));
+        expectations.append(&mut mock_single_byte_size_params(1, 0x0)); // Send server Socket
+        expectations.append(&mut mock_end_byte());
+        expectations.append(&mut mock_padding(2));
+        expectations.append(&mut mock_receive(
+            avail_data_tcp_command,
+            number_of_params_to_receive,
+            &[client_socket, 0x0],
+        ));
+    }
+
+    // ----- stop_client_tcp for the client and then the server socket -----
+
+    let stop_client_tcp_command = 0x2e;
+
+    for socket in [0x1, 0x0] {
+        expectations.append(&mut mock_command(stop_client_tcp_command, number_of_params));
+        expectations.append(&mut mock_single_byte_size_params(1, socket));
+        expectations.append(&mut mock_end_byte());
+        expectations.append(&mut mock_padding(2));
+        expectations.append(&mut mock_receive(
+            stop_client_tcp_command,
+            number_of_params_to_receive,
+            &[0x1],
+        ));
+    }
+
+    let spi = BytewiseSpiMock::new(&expectations);
+
+    let delay = MockNoop::new();
+
+    let pins = EspControlMock {};
+
+    let mut wifi = Wifi::init(spi, pins, delay).ok().unwrap();
+
+    let port: Port = 0x1111;
+
+    let mut tcp_server = TcpServer::build(&mut wifi);
+
+    tcp_server.bind(port).unwrap();
+    assert_eq!(tcp_server.socket(), Some(0x0));
+
+    assert_eq!(tcp_server.accept().unwrap(), None);
+
+    let client_socket = tcp_server.accept().unwrap().unwrap();
+    assert_eq!(client_socket, 0x1);
+
+    tcp_server.close(client_socket).unwrap();
+    tcp_server.stop().unwrap();
+
+    wifi.destroy().done();
+}
+
+#[test]
+fn accept_returns_server_not_bound_error_before_bind() {
+    let spi = BytewiseSpiMock::new(&[]);
+
+    let delay = MockNoop::new();
+
+    let pins = EspControlMock {};
+
+    let mut wifi = Wifi::init(spi, pins, delay).ok().unwrap();
+
+    let mut tcp_server = TcpServer::build(&mut wifi);
+
+    assert_eq!(
+        tcp_server.accept().unwrap_err(),
+        Error::Network(NetworkError::ServerNotBound)
+    );
+
+    wifi.destroy().done();
+}
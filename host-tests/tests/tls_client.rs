@@ -0,0 +1,230 @@
+use embedded_hal_mock::delay::MockNoop;
+use embedded_hal_mock::spi;
+
+use esp32_wroom_core::credentials::CredentialProvider;
+use esp32_wroom_core::network::{Hostname, Port};
+use esp32_wroom_core::protocol::ProtocolError;
+use esp32_wroom_core::tls_client::{TlsClient, MAX_CLIENT_CERTIFICATE_LENGTH};
+use esp32_wroom_core::wifi::Wifi;
+use esp32_wroom_core::Error;
+
+pub mod support;
+
+use support::*;
+
+// Expectations for connecting to "FFFF" on port 0x1111 using TLS and closing the connection
+// again.
+fn mock_tls_connection() -> Vec<spi::Transaction> {
+    // ----- get_socket -----
+
+    let get_socket_command = 0x3f;
+    let mut number_of_params = 0x0;
+    let number_of_params_to_receive = 0x1;
+
+    let mut expectations = mock_command(get_socket_command, number_of_params);
+
+    expectations.append(&mut mock_end_byte());
+
+    expectations.append(&mut mock_receive(
+        get_socket_command,
+        number_of_params_to_receive,
+        &[0x0],
+    ));
+
+    // ----- start_client_tcp with hostname in TLS mode -----
+
+    let start_client_tcp_command = 0x2d;
+    number_of_params = 0x5;
+
+    expectations.append(&mut mock_command(
+        start_client_tcp_command,
+        number_of_params,
+    ));
+    expectations.append(&mut mock_single_byte_size_params(4, 0x46)); // hostname is "FFFF"
+    expectations.append(&mut mock_single_byte_size_params(4, 0x0)); // IP Address is resolved by NINA
+    expectations.append(&mut mock_single_byte_size_params(2, 0x11)); // Send fake Port
+    expectations.append(&mut mock_single_byte_size_params(1, 0x0)); // Send fake Socket
+    expectations.append(&mut mock_single_byte_size_params(1, 0x2)); // Send TLS Transport Mode
+
+    expectations.append(&mut mock_end_byte());
+
+    expectations.append(&mut mock_padding(3));
+
+    expectations.append(&mut mock_receive(
+        start_client_tcp_command,
+        number_of_params_to_receive,
+        &[0x1],
+    ));
+
+    // ----- get_client_state_tcp -----
+
+    let get_client_state_tcp_command = 0x2f;
+    number_of_params = 0x1;
+
+    expectations.append(&mut mock_command(
+        get_client_state_tcp_command,
+        number_of_params,
+    ));
+    expectations.append(&mut mock_single_byte_size_params(1, 0x0)); // Send fake Socket
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_padding(2));
+    expectations.append(&mut mock_receive(
+        get_client_state_tcp_command,
+        number_of_params_to_receive,
+        &[0x4], // ConnectionState::Established
+    ));
+
+    // ----- stop_client_tcp -----
+
+    let stop_client_tcp_command = 0x2e;
+
+    expectations.append(&mut mock_command(stop_client_tcp_command, number_of_params));
+    expectations.append(&mut mock_single_byte_size_params(1, 0x0)); // Send fake Socket
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_padding(2));
+    expectations.append(&mut mock_receive(
+        stop_client_tcp_command,
+        number_of_params_to_receive,
+        &[0x1],
+    ));
+
+    expectations
+}
+
+#[test]
+fn successful_tls_connection_sends_hostname_and_invokes_closure() {
+    let expectations = mock_tls_connection();
+
+    let spi = BytewiseSpiMock::new(&expectations);
+
+    let delay = MockNoop::new();
+
+    let pins = EspControlMock {};
+
+    let mut wifi = Wifi::init(spi, pins, delay).ok().unwrap();
+
+    let hostname: Hostname = "FFFF";
+    let port: Port = 0x1111;
+
+    // if the value is successfully updated inside the closure then
+    // we know the closure was invoked.
+    let mut value: u8 = 1;
+    let test_value = &mut value;
+
+    TlsClient::build(&mut wifi)
+        .connect(hostname, port, &mut |_tcp_client| *test_value = 2)
+        .unwrap();
+
+    assert_eq!(value, 2);
+
+    wifi.destroy().done();
+}
+
+#[test]
+fn set_client_certificate_and_private_key_send_data_params() {
+    let number_of_params = 0x1;
+    let number_of_params_to_receive = 0x1;
+
+    let mut expectations = Vec::new();
+
+    // 0x40 is SetCliCert and 0x41 is SetPk
+    for command in [0x40, 0x41] {
+        expectations.append(&mut mock_command(command, number_of_params));
+        expectations.append(&mut mock_two_byte_size_params(&[0x2d, 0x2d, 0x2d]));
+        expectations.append(&mut mock_end_byte());
+        expectations.append(&mut mock_padding(3));
+        expectations.append(&mut mock_receive(
+            command,
+            number_of_params_to_receive,
+            &[0x1],
+        ));
+    }
+
+    let spi = BytewiseSpiMock::new(&expectations);
+
+    let delay = MockNoop::new();
+
+    let pins = EspControlMock {};
+
+    let mut wifi = Wifi::init(spi, pins, delay).ok().unwrap();
+
+    wifi.set_client_certificate(b"---").unwrap();
+    wifi.set_private_key(b"---").unwrap();
+
+    wifi.destroy().done();
+}
+
+struct CertificateProvider {
+    certificate: &'static [u8],
+}
+
+impl CredentialProvider for CertificateProvider {
+    fn ssid(&mut self, _buf: &mut [u8]) -> Result<usize, Error> {
+        Ok(0)
+    }
+
+    fn passphrase(&mut self, _buf: &mut [u8]) -> Result<usize, Error> {
+        Ok(0)
+    }
+
+    fn client_certificate(&mut self, buf: &mut [u8]) -> Result<Option<usize>, Error> {
+        buf[..self.certificate.len()].copy_from_slice(self.certificate);
+        Ok(Some(self.certificate.len()))
+    }
+}
+
+#[test]
+fn connect_with_credentials_installs_the_provided_certificate_before_connecting() {
+    let set_cli_cert_command = 0x40;
+    let number_of_params = 0x1;
+    let number_of_params_to_receive = 0x1;
+
+    let mut expectations = mock_command(set_cli_cert_command, number_of_params);
+    expectations.append(&mut mock_two_byte_size_params(&[0x2d, 0x2d, 0x2d]));
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_padding(3));
+    expectations.append(&mut mock_receive(
+        set_cli_cert_command,
+        number_of_params_to_receive,
+        &[0x1],
+    ));
+    expectations.append(&mut mock_tls_connection());
+
+    let spi = BytewiseSpiMock::new(&expectations);
+
+    let delay = MockNoop::new();
+
+    let pins = EspControlMock {};
+
+    let mut wifi = Wifi::init(spi, pins, delay).ok().unwrap();
+
+    let mut provider = CertificateProvider {
+        certificate: b"---",
+    };
+
+    TlsClient::build(&mut wifi)
+        .connect_with_credentials("FFFF", 0x1111, &mut provider, &mut |_tcp_client| {})
+        .unwrap();
+
+    wifi.destroy().done();
+}
+
+#[test]
+fn set_client_certificate_returns_payload_too_large_error_for_oversized_certificate() {
+    let spi = BytewiseSpiMock::new(&[]);
+
+    let delay = MockNoop::new();
+
+    let pins = EspControlMock {};
+
+    let mut wifi = Wifi::init(spi, pins, delay).ok().unwrap();
+
+    let certificate = [0x2d; MAX_CLIENT_CERTIFICATE_LENGTH + 1];
+
+    assert_eq!(
+        wifi.set_client_certificate(&certificate).unwrap_err(),
+        Error::Protocol(ProtocolError::PayloadTooLarge)
+    );
+
+    wifi.destroy().done();
+}
@@ -0,0 +1,106 @@
+use std::collections::VecDeque;
+
+use embedded_hal_mock::delay::MockNoop;
+
+use esp32_wroom_core::transport::Transport;
+use esp32_wroom_core::wifi::Wifi;
+
+// A transport without SPI's framing that records what the driver writes and replays a canned
+// reply, standing in for e.g. a UART link.
+#[derive(Default)]
+struct ScriptedTransport {
+    written: Vec<u8>,
+    command_lengths: Vec<u16>,
+    reply: VecDeque<u8>,
+}
+
+impl Transport for ScriptedTransport {
+    fn init(&mut self) {}
+
+    fn reset<D>(&mut self, _delay: &mut D) {}
+
+    fn hold_in_reset(&mut self) {}
+
+    fn is_ready(&self) -> bool {
+        true
+    }
+
+    fn select(&mut self) {}
+
+    fn is_acknowledged(&self) -> bool {
+        true
+    }
+
+    fn deselect(&mut self) {}
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.written.extend_from_slice(bytes);
+    }
+
+    fn read(&mut self, buffer: &mut [u8]) {
+        for byte in buffer.iter_mut() {
+            *byte = self.reply.pop_front().unwrap_or(0xff);
+        }
+    }
+
+    fn end_command(&mut self, command_length: u16) {
+        self.command_lengths.push(command_length);
+    }
+}
+
+fn init(reply: &[u8]) -> Wifi<ScriptedTransport, MockNoop> {
+    let transport = ScriptedTransport {
+        reply: reply.iter().copied().collect(),
+        ..Default::default()
+    };
+
+    Wifi::init_with_transport(transport, MockNoop::new()).unwrap()
+}
+
+#[test]
+fn firmware_version_is_read_over_any_transport() {
+    let get_fw_version_command = 0x37;
+    let mut reply = vec![0xe0, get_fw_version_command | 0x80, 0x1, 0x5];
+    reply.extend_from_slice(b"1.7.4");
+    reply.push(0xee);
+
+    let mut wifi = init(&reply);
+
+    let version = wifi.firmware_version().unwrap();
+    assert_eq!(version.to_string(), "1.7.4");
+
+    let transport = wifi.into_transport();
+    assert_eq!(
+        transport.written,
+        vec![0xe0, get_fw_version_command, 0x0, 0xee]
+    );
+    assert_eq!(transport.command_lengths, vec![4]);
+}
+
+#[test]
+fn commands_with_params_are_ended_with_their_length() {
+    let set_net_command = 0x10;
+    let reply = [0xe0, set_net_command | 0x80, 0x1, 0x1, 0x1, 0xee];
+
+    let mut wifi = init(&reply);
+
+    wifi.join_open("ssid").unwrap();
+
+    let transport = wifi.into_transport();
+    assert_eq!(
+        transport.written,
+        vec![
+            0xe0,
+            set_net_command,
+            0x1,
+            0x4,
+            b's',
+            b's',
+            b'i',
+            b'd',
+            0xee
+        ]
+    );
+    // Start, command, number of params and end bytes, a 1 byte length and the SSID
+    assert_eq!(transport.command_lengths, vec![9]);
+}
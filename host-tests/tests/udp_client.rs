@@ -0,0 +1,116 @@
+use embedded_hal_mock::delay::MockNoop;
+
+use core::net::{Ipv4Addr, SocketAddrV4};
+
+use esp32_wroom_core::udp_client::UdpClient;
+use esp32_wroom_core::wifi::Wifi;
+
+pub mod support;
+
+use support::*;
+
+#[test]
+fn send_to_sends_datagram_and_close_releases_socket() {
+    // ----- get_socket -----
+
+    let get_socket_command = 0x3f;
+    let mut number_of_params = 0x0;
+    let number_of_params_to_receive = 0x1;
+
+    let mut expectations = mock_command(get_socket_command, number_of_params);
+
+    expectations.append(&mut mock_end_byte());
+
+    expectations.append(&mut mock_receive(
+        get_socket_command,
+        number_of_params_to_receive,
+        &[0x0],
+    ));
+
+    // ----- start_client_tcp in UDP mode -----
+
+    let start_client_tcp_command = 0x2d;
+    number_of_params = 0x4;
+
+    expectations.append(&mut mock_command(
+        start_client_tcp_command,
+        number_of_params,
+    ));
+    expectations.append(&mut mock_single_byte_size_params(4, 0x40)); // Send fake IP Address
+    expectations.append(&mut mock_single_byte_size_params(2, 0x11)); // Send fake Port
+    expectations.append(&mut mock_single_byte_size_params(1, 0x0)); // Send fake Socket
+    expectations.append(&mut mock_single_byte_size_params(1, 0x1)); // Send UDP Transport Mode
+
+    expectations.append(&mut mock_end_byte());
+
+    expectations.append(&mut mock_receive(
+        start_client_tcp_command,
+        number_of_params_to_receive,
+        &[0x1],
+    ));
+
+    // ----- insert_data_buf -----
+
+    let insert_data_buf_command = 0x46;
+    number_of_params = 0x2;
+
+    expectations.append(&mut mock_command(insert_data_buf_command, number_of_params));
+    expectations.append(&mut mock_two_byte_size_params(&[0x0])); // Send Socket
+    expectations.append(&mut mock_two_byte_size_params(&[0x46, 0x46, 0x46, 0x46]));
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_padding(3));
+    expectations.append(&mut mock_receive(
+        insert_data_buf_command,
+        number_of_params_to_receive,
+        &[0x1],
+    ));
+
+    // ----- send_udp_data -----
+
+    let send_udp_data_command = 0x39;
+    number_of_params = 0x1;
+
+    expectations.append(&mut mock_command(send_udp_data_command, number_of_params));
+    expectations.append(&mut mock_single_byte_size_params(1, 0x0)); // Send Socket
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_padding(2));
+    expectations.append(&mut mock_receive(
+        send_udp_data_command,
+        number_of_params_to_receive,
+        &[0x1],
+    ));
+
+    // ----- stop_client_tcp -----
+
+    let stop_client_tcp_command = 0x2e;
+
+    expectations.append(&mut mock_command(stop_client_tcp_command, number_of_params));
+    expectations.append(&mut mock_single_byte_size_params(1, 0x0)); // Send Socket
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_padding(2));
+    expectations.append(&mut mock_receive(
+        stop_client_tcp_command,
+        number_of_params_to_receive,
+        &[0x1],
+    ));
+
+    let spi = BytewiseSpiMock::new(&expectations);
+
+    let delay = MockNoop::new();
+
+    let pins = EspControlMock {};
+
+    let mut wifi = Wifi::init(spi, pins, delay).ok().unwrap();
+
+    let remote = SocketAddrV4::new(Ipv4Addr::new(0x40, 0x40, 0x40, 0x40), 0x1111);
+
+    let mut udp_client = UdpClient::build(&mut wifi);
+
+    udp_client.send_to(remote, b"FFFF").unwrap();
+    assert_eq!(udp_client.socket(), Some(0x0));
+
+    udp_client.close().unwrap();
+    assert_eq!(udp_client.socket(), None);
+
+    wifi.destroy().done();
+}
@@ -0,0 +1,127 @@
+use core::net::{Ipv4Addr, SocketAddrV4};
+
+use embedded_hal_mock::delay::MockNoop;
+
+use esp32_wroom_core::network::Port;
+use esp32_wroom_core::udp_server::{Datagram, UdpServer};
+use esp32_wroom_core::wifi::Wifi;
+
+pub mod support;
+
+use support::*;
+
+#[test]
+fn recv_from_returns_payload_and_sender_address() {
+    // ----- get_socket -----
+
+    let get_socket_command = 0x3f;
+    let mut number_of_params = 0x0;
+    let number_of_params_to_receive = 0x1;
+
+    let mut expectations = mock_command(get_socket_command, number_of_params);
+
+    expectations.append(&mut mock_end_byte());
+
+    expectations.append(&mut mock_receive(
+        get_socket_command,
+        number_of_params_to_receive,
+        &[0x0],
+    ));
+
+    // ----- start_server_tcp in UDP mode -----
+
+    let start_server_tcp_command = 0x28;
+    number_of_params = 0x3;
+
+    expectations.append(&mut mock_command(
+        start_server_tcp_command,
+        number_of_params,
+    ));
+    expectations.append(&mut mock_single_byte_size_params(2, 0x11)); // Send fake Port
+    expectations.append(&mut mock_single_byte_size_params(1, 0x0)); // Send fake Socket
+    expectations.append(&mut mock_single_byte_size_params(1, 0x1)); // Send UDP Transport Mode
+
+    expectations.append(&mut mock_end_byte());
+
+    expectations.append(&mut mock_padding(1));
+
+    expectations.append(&mut mock_receive(
+        start_server_tcp_command,
+        number_of_params_to_receive,
+        &[0x1],
+    ));
+
+    // ----- avail_data_tcp reports a 4 byte datagram -----
+
+    let avail_data_tcp_command = 0x2b;
+    number_of_params = 0x1;
+
+    expectations.append(&mut mock_command(avail_data_tcp_command, number_of_params));
+    expectations.append(&mut mock_single_byte_size_params(1, 0x0)); // Send Socket
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_padding(2));
+    expectations.append(&mut mock_receive(
+        avail_data_tcp_command,
+        number_of_params_to_receive,
+        &[0x4, 0x0],
+    ));
+
+    // ----- get_data_buf_tcp -----
+
+    let get_data_buf_tcp_command = 0x45;
+    number_of_params = 0x2;
+
+    expectations.append(&mut mock_command(
+        get_data_buf_tcp_command,
+        number_of_params,
+    ));
+    expectations.append(&mut mock_two_byte_size_params(&[0x0])); // Send Socket
+    expectations.append(&mut mock_two_byte_size_params(&[0x4, 0x0])); // Send requested length
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_padding(1));
+    expectations.append(&mut mock_receive_data(
+        get_data_buf_tcp_command,
+        &[0x46, 0x46, 0x46, 0x46],
+    ));
+
+    // ----- get_remote_data -----
+
+    let get_remote_data_command = 0x3a;
+    number_of_params = 0x1;
+
+    expectations.append(&mut mock_command(get_remote_data_command, number_of_params));
+    expectations.append(&mut mock_single_byte_size_params(1, 0x0)); // Send Socket
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_padding(2));
+    expectations.append(&mut mock_receive_params(
+        get_remote_data_command,
+        &[&[0xc0, 0xa8, 0x1, 0xa], &[0x22, 0xb8]], // 192.168.1.10:8888
+    ));
+
+    let spi = BytewiseSpiMock::new(&expectations);
+
+    let delay = MockNoop::new();
+
+    let pins = EspControlMock {};
+
+    let mut wifi = Wifi::init(spi, pins, delay).ok().unwrap();
+
+    let port: Port = 0x1111;
+
+    let mut udp_server = UdpServer::build(&mut wifi);
+    udp_server.bind(port).unwrap();
+
+    let mut buf = [0u8; 16];
+    let datagram = udp_server.recv_from(&mut buf).unwrap();
+
+    assert_eq!(
+        datagram,
+        Some(Datagram {
+            length: 4,
+            sender: SocketAddrV4::new(Ipv4Addr::new(192, 168, 1, 10), 8888)
+        })
+    );
+    assert_eq!(&buf[..4], b"FFFF");
+
+    wifi.destroy().done();
+}
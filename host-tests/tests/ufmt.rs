@@ -0,0 +1,27 @@
+use esp32_wroom_core::network::{MacAddress, NetworkError, TransportMode};
+use esp32_wroom_core::Error;
+
+#[test]
+fn mac_address_udisplay_formats_lowercase_colon_separated_octets() {
+    let mac_address = MacAddress::new([0xa4, 0xcf, 0x12, 0x0b, 0x3c, 0x5e]);
+    let mut s = String::new();
+
+    ufmt::uwrite!(s, "{}", mac_address).unwrap();
+
+    assert_eq!(s, "a4:cf:12:0b:3c:5e");
+}
+
+#[test]
+fn public_types_udebug_formats_variant_names() {
+    let mut s = String::new();
+
+    ufmt::uwrite!(
+        s,
+        "{:?} {:?}",
+        Error::Network(NetworkError::SsidTooLong),
+        TransportMode::Udp
+    )
+    .unwrap();
+
+    assert_eq!(s, "Network(SsidTooLong) Udp");
+}
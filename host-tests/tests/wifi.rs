@@ -0,0 +1,928 @@
+use core::net::Ipv4Addr;
+
+use embedded_hal_mock::delay::MockNoop;
+use embedded_hal_mock::spi;
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+
+use esp32_wroom_core::activity::{Activity, ActivityIndicator};
+use esp32_wroom_core::credentials::{CredentialProvider, EnterpriseConfig, MAX_SSID_LENGTH};
+use esp32_wroom_core::events::{WifiEvent, WifiEventHandler};
+use esp32_wroom_core::gpio::EspControlInterface;
+use esp32_wroom_core::network::{MacAddress, NetworkError};
+use esp32_wroom_core::protocol::ProtocolError;
+use esp32_wroom_core::scheduler::Yield;
+use esp32_wroom_core::stats::RecoveryStats;
+use esp32_wroom_core::tcp_server::TcpServer;
+use esp32_wroom_core::transport::{SpiClockError, SpiConfig, MAX_SPI_FREQUENCY_HZ};
+use esp32_wroom_core::wifi::{ConnectionStatus, EncryptionType, InitStage, Wifi};
+use esp32_wroom_core::Error;
+
+pub mod support;
+
+use support::*;
+
+#[test]
+fn init_with_progress_reports_every_completed_stage() {
+    let get_fw_version_command = 0x37;
+    let number_of_params = 0x0;
+    let number_of_params_to_receive = 0x1;
+
+    let mut expectations = mock_command(get_fw_version_command, number_of_params);
+
+    expectations.append(&mut mock_end_byte());
+
+    expectations.append(&mut mock_receive(
+        get_fw_version_command,
+        number_of_params_to_receive,
+        &[0x31, 0x2e, 0x37, 0x2e, 0x34], // "1.7.4"
+    ));
+
+    let spi = BytewiseSpiMock::new(&expectations);
+
+    let delay = MockNoop::new();
+
+    let pins = EspControlMock {};
+
+    let mut stages: Vec<InitStage> = Vec::new();
+
+    let wifi =
+        Wifi::init_with_progress(spi, pins, delay, 100, &mut |stage| stages.push(stage)).unwrap();
+
+    assert_eq!(
+        stages,
+        vec![
+            InitStage::Reset,
+            InitStage::Handshake,
+            InitStage::FirmwareVersion
+        ]
+    );
+
+    wifi.destroy().done();
+}
+
+#[test]
+fn init_with_progress_reports_firmware_version_stage_timeout() {
+    let get_fw_version_command = 0x37;
+    let number_of_params = 0x0;
+
+    let mut expectations = mock_command(get_fw_version_command, number_of_params);
+
+    expectations.append(&mut mock_end_byte());
+
+    // simulate reading 1000 bytes which will exhaust the retry limit.
+    for _ in 0..1000 {
+        expectations.push(spi::Transaction::transfer(vec![0xff], vec![0x0]))
+    }
+
+    let spi = BytewiseSpiMock::new(&expectations);
+
+    let delay = MockNoop::new();
+
+    let pins = EspControlMock {};
+
+    let mut stages: Vec<InitStage> = Vec::new();
+
+    let result = Wifi::init_with_progress(spi, pins, delay, 100, &mut |stage| stages.push(stage));
+
+    assert_eq!(
+        result.err().unwrap(),
+        esp32_wroom_core::Error::InitTimeout(InitStage::FirmwareVersion)
+    );
+    assert_eq!(stages, vec![InitStage::Reset, InitStage::Handshake]);
+}
+
+// Signals it's ready but never acknowledges being selected, like an ESP32 target whose NINA
+// firmware has hung.
+struct UnacknowledgingControlMock {}
+
+impl EspControlInterface for UnacknowledgingControlMock {
+    fn init(&mut self) {}
+
+    fn reset<D>(&mut self, _delay: &mut D) {}
+
+    fn get_esp_ack(&self) -> bool {
+        false
+    }
+
+    fn wait_for_esp_select(&mut self) {
+        panic!("waited for the ESP32 target to be selected without a timeout");
+    }
+
+    fn wait_for_esp_ack(&self) {}
+
+    fn wait_for_esp_ready(&self) {}
+
+    fn esp_select(&mut self) {}
+
+    fn esp_deselect(&mut self) {}
+
+    fn get_esp_ready(&self) -> bool {
+        true
+    }
+
+    fn hold_in_reset(&mut self) {}
+
+    fn enter_bootloader<D>(&mut self, _delay: &mut D) {}
+}
+
+#[test]
+fn init_with_progress_times_out_when_the_firmware_never_acknowledges_the_version_command() {
+    let spi = BytewiseSpiMock::new(&[]);
+
+    let delay = MockNoop::new();
+
+    let pins = UnacknowledgingControlMock {};
+
+    let mut stages: Vec<InitStage> = Vec::new();
+
+    let result = Wifi::init_with_progress(spi, pins, delay, 100, &mut |stage| stages.push(stage));
+
+    assert_eq!(
+        result.err().unwrap(),
+        esp32_wroom_core::Error::InitTimeout(InitStage::FirmwareVersion)
+    );
+    assert_eq!(stages, vec![InitStage::Reset, InitStage::Handshake]);
+}
+
+fn mock_get_fw_version(reply: Vec<spi::Transaction>) -> Vec<spi::Transaction> {
+    let get_fw_version_command = 0x37;
+    let number_of_params = 0x0;
+
+    let mut expectations = mock_command(get_fw_version_command, number_of_params);
+    expectations.append(&mut mock_end_byte());
+    expectations.extend(reply);
+
+    expectations
+}
+
+fn mock_fw_version_reply() -> Vec<spi::Transaction> {
+    mock_receive_params(0x37, &[b"1.7.4"])
+}
+
+// A reply whose command byte came through garbled
+fn mock_garbled_reply() -> Vec<spi::Transaction> {
+    vec![
+        spi::Transaction::transfer(vec![0xff], vec![0xe0]),
+        spi::Transaction::transfer(vec![0xff], vec![0x1b]),
+    ]
+}
+
+#[test]
+fn init_with_spi_config_rejects_frequencies_above_the_maximum() {
+    let spi = BytewiseSpiMock::new(&[]);
+
+    let config = SpiConfig::new().frequency_hz(MAX_SPI_FREQUENCY_HZ + 1);
+    let result = Wifi::init_with_spi_config(spi, EspControlMock {}, MockNoop::new(), config);
+
+    assert_eq!(
+        result.err().unwrap(),
+        Error::SpiClock(SpiClockError::AboveMaximum)
+    );
+}
+
+#[test]
+fn init_with_spi_config_checks_the_replies_of_the_nina_firmware() {
+    let mut expectations = mock_get_fw_version(mock_fw_version_reply());
+    expectations.append(&mut mock_get_fw_version(mock_fw_version_reply()));
+
+    let spi = BytewiseSpiMock::new(&expectations);
+
+    let wifi =
+        Wifi::init_with_spi_config(spi, EspControlMock {}, MockNoop::new(), SpiConfig::new())
+            .unwrap();
+
+    wifi.destroy().done();
+}
+
+#[test]
+fn init_with_spi_config_reports_garbled_replies_as_an_unreliable_clock() {
+    let mut expectations = mock_get_fw_version(mock_fw_version_reply());
+    expectations.append(&mut mock_get_fw_version(mock_garbled_reply()));
+
+    let mut spi = BytewiseSpiMock::new(&expectations);
+
+    let result = Wifi::init_with_spi_config(
+        spi.clone(),
+        EspControlMock {},
+        MockNoop::new(),
+        SpiConfig::new(),
+    );
+
+    assert_eq!(
+        result.err().unwrap(),
+        Error::SpiClock(SpiClockError::Unreliable)
+    );
+    spi.done();
+}
+
+#[test]
+fn init_negotiating_spi_clock_steps_the_frequency_down_until_replies_are_intact() {
+    let mut expectations = mock_get_fw_version(mock_garbled_reply());
+    expectations.append(&mut mock_get_fw_version(mock_garbled_reply()));
+    expectations.append(&mut mock_get_fw_version(mock_fw_version_reply()));
+    expectations.append(&mut mock_get_fw_version(mock_fw_version_reply()));
+
+    let spi = BytewiseSpiMock::new(&expectations);
+
+    let mut frequencies: Vec<u32> = Vec::new();
+
+    let (wifi, frequency_hz) = Wifi::init_negotiating_spi_clock(
+        spi,
+        EspControlMock {},
+        MockNoop::new(),
+        SpiConfig::new(),
+        &mut |_, frequency_hz| frequencies.push(frequency_hz),
+    )
+    .unwrap();
+
+    assert_eq!(frequency_hz, 2_000_000);
+    assert_eq!(frequencies, vec![8_000_000, 4_000_000, 2_000_000]);
+
+    wifi.destroy().done();
+}
+
+#[test]
+fn init_negotiating_spi_clock_gives_up_at_the_minimum_frequency() {
+    let mut expectations = mock_get_fw_version(mock_garbled_reply());
+    expectations.append(&mut mock_get_fw_version(mock_garbled_reply()));
+
+    let mut spi = BytewiseSpiMock::new(&expectations);
+
+    let mut frequencies: Vec<u32> = Vec::new();
+
+    let config = SpiConfig::new().min_frequency_hz(4_000_000);
+    let result = Wifi::init_negotiating_spi_clock(
+        spi.clone(),
+        EspControlMock {},
+        MockNoop::new(),
+        config,
+        &mut |_, frequency_hz| frequencies.push(frequency_hz),
+    );
+
+    assert_eq!(
+        result.err().unwrap(),
+        Error::SpiClock(SpiClockError::Unreliable)
+    );
+    assert_eq!(frequencies, vec![8_000_000, 4_000_000]);
+    spi.done();
+}
+
+#[test]
+fn shutdown_leaves_network_when_requested() {
+    let disconnect_command = 0x30;
+    let number_of_params = 0x1;
+    let number_of_params_to_receive = 0x1;
+
+    let mut expectations = mock_command(disconnect_command, number_of_params);
+
+    expectations.append(&mut mock_single_byte_size_params(1, 0xff)); // Send dummy param
+
+    expectations.append(&mut mock_end_byte());
+
+    expectations.append(&mut mock_padding(2));
+
+    expectations.append(&mut mock_receive(
+        disconnect_command,
+        number_of_params_to_receive,
+        &[0x1],
+    ));
+
+    let spi = BytewiseSpiMock::new(&expectations);
+
+    let delay = MockNoop::new();
+
+    let pins = EspControlMock {};
+
+    let wifi = Wifi::init(spi, pins, delay).ok().unwrap();
+
+    let (mut spi, _, _) = wifi.shutdown(true).ok().unwrap();
+
+    spi.done();
+}
+
+#[test]
+fn recovery_stats_count_hard_resets_and_protocol_resyncs() {
+    let command = 0x37;
+    let number_of_params = 0x0;
+    let mut expectations = mock_command(command, number_of_params);
+
+    expectations.append(&mut mock_end_byte());
+
+    // NINA Firmware sends an error byte (0xef) followed by 0x00 and end 0xee
+    expectations.append(&mut vec![
+        spi::Transaction::transfer(vec![0xff], vec![0xef]),
+        spi::Transaction::transfer(vec![0xff], vec![0x00]),
+        spi::Transaction::transfer(vec![0xff], vec![0xee]),
+    ]);
+
+    let spi = BytewiseSpiMock::new(&expectations);
+
+    let delay = MockNoop::new();
+
+    let pins = EspControlMock {};
+
+    let mut wifi = Wifi::init(spi, pins, delay).ok().unwrap();
+    wifi.firmware_version().ok();
+
+    assert_eq!(
+        wifi.recovery_stats(),
+        RecoveryStats {
+            hard_resets: 1,
+            protocol_resyncs: 1,
+            rejoins: 0
+        }
+    );
+
+    wifi.destroy().done();
+}
+
+#[test]
+fn scan_networks_with_invokes_closure_for_every_network() {
+    let start_scan_networks_command = 0x36;
+    let number_of_params = 0x0;
+    let number_of_params_to_receive = 0x1;
+
+    let mut expectations = mock_command(start_scan_networks_command, number_of_params);
+
+    expectations.append(&mut mock_end_byte());
+
+    expectations.append(&mut mock_receive(
+        start_scan_networks_command,
+        number_of_params_to_receive,
+        &[0x1],
+    ));
+
+    let scan_networks_command = 0x27;
+
+    // The first attempt hasn't found any networks yet
+    for params in [&[][..], &[&b"home"[..], &b"guest"[..]][..]] {
+        expectations.append(&mut mock_command(scan_networks_command, number_of_params));
+        expectations.append(&mut mock_end_byte());
+        expectations.append(&mut mock_receive_params(scan_networks_command, params));
+    }
+
+    for index in 0..2 {
+        expectations.append(&mut mock_scan_result_details(
+            index,
+            &[0xc4, 0xff, 0xff, 0xff],
+            &[0x4],
+            &[0x0, 0x0, 0x0, 0x0, 0x0, 0x0],
+            &[0x1],
+        ));
+    }
+
+    let spi = BytewiseSpiMock::new(&expectations);
+
+    let delay = MockNoop::new();
+
+    let pins = EspControlMock {};
+
+    let mut wifi = Wifi::init(spi, pins, delay).ok().unwrap();
+
+    let mut ssids: Vec<String> = Vec::new();
+
+    let number_of_networks = wifi
+        .scan_networks_with(&mut |result| ssids.push(result.ssid().unwrap().to_string()))
+        .unwrap();
+
+    assert_eq!(number_of_networks, 2);
+    assert_eq!(ssids, vec!["home", "guest"]);
+
+    wifi.destroy().done();
+}
+
+#[test]
+fn scan_networks_returns_details_of_every_network() {
+    let start_scan_networks_command = 0x36;
+    let number_of_params = 0x0;
+    let number_of_params_to_receive = 0x1;
+
+    let mut expectations = mock_command(start_scan_networks_command, number_of_params);
+
+    expectations.append(&mut mock_end_byte());
+
+    expectations.append(&mut mock_receive(
+        start_scan_networks_command,
+        number_of_params_to_receive,
+        &[0x1],
+    ));
+
+    let scan_networks_command = 0x27;
+
+    expectations.append(&mut mock_command(scan_networks_command, number_of_params));
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_receive_params(scan_networks_command, &[b"home"]));
+
+    expectations.append(&mut mock_scan_result_details(
+        0,
+        &[0xc4, 0xff, 0xff, 0xff],             // -60 dBm
+        &[0x4],                                // CCMP
+        &[0x5e, 0x3c, 0x0b, 0x12, 0xcf, 0xa4], // Reversed BSSID
+        &[0x6],
+    ));
+
+    let spi = BytewiseSpiMock::new(&expectations);
+
+    let delay = MockNoop::new();
+
+    let pins = EspControlMock {};
+
+    let mut wifi = Wifi::init(spi, pins, delay).ok().unwrap();
+
+    let mut results = wifi.scan_networks().unwrap();
+
+    let result = results.next().unwrap();
+    assert_eq!(result.ssid(), Some("home"));
+    assert_eq!(result.rssi(), -60);
+    assert_eq!(result.encryption_type(), EncryptionType::Ccmp);
+    assert_eq!(
+        result.bssid(),
+        MacAddress::new([0xa4, 0xcf, 0x12, 0x0b, 0x3c, 0x5e])
+    );
+    assert_eq!(result.channel(), 6);
+    assert_eq!(results.next(), None);
+
+    wifi.destroy().done();
+}
+
+#[test]
+fn scan_networks_with_reports_networks_that_did_not_fit() {
+    let start_scan_networks_command = 0x36;
+    let number_of_params = 0x0;
+    let number_of_params_to_receive = 0x1;
+
+    let mut expectations = mock_command(start_scan_networks_command, number_of_params);
+
+    expectations.append(&mut mock_end_byte());
+
+    expectations.append(&mut mock_receive(
+        start_scan_networks_command,
+        number_of_params_to_receive,
+        &[0x1],
+    ));
+
+    let scan_networks_command = 0x27;
+
+    // One more network than MAX_SCAN_RESULTS
+    let ssids: Vec<String> = (0..11).map(|index| format!("net{index}")).collect();
+    let params: Vec<&[u8]> = ssids.iter().map(|ssid| ssid.as_bytes()).collect();
+
+    expectations.append(&mut mock_command(scan_networks_command, number_of_params));
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_receive_params(scan_networks_command, &params));
+
+    for index in 0..10 {
+        expectations.append(&mut mock_scan_result_details(
+            index,
+            &[0xc4, 0xff, 0xff, 0xff],
+            &[0x4],
+            &[0x0, 0x0, 0x0, 0x0, 0x0, 0x0],
+            &[0x1],
+        ));
+    }
+
+    let spi = BytewiseSpiMock::new(&expectations);
+
+    let delay = MockNoop::new();
+
+    let pins = EspControlMock {};
+
+    let mut wifi = Wifi::init(spi, pins, delay).ok().unwrap();
+
+    let mut seen = 0;
+
+    assert_eq!(
+        wifi.scan_networks_with(&mut |_| seen += 1),
+        Err(Error::Network(NetworkError::ScanResultsTruncated))
+    );
+    assert_eq!(seen, 10);
+
+    wifi.destroy().done();
+}
+
+// Mocks looking up the RSSI, encryption type, BSSID and channel of the scanned network at `index`
+fn mock_scan_result_details(
+    index: u8,
+    rssi: &[u8],
+    encryption_type: &[u8],
+    bssid: &[u8],
+    channel: &[u8],
+) -> Vec<spi::Transaction> {
+    let mut expectations = Vec::new();
+
+    for (command, values) in [
+        (0x32, rssi),
+        (0x33, encryption_type),
+        (0x3c, bssid),
+        (0x3d, channel),
+    ] {
+        expectations.append(&mut mock_command(command, 0x1));
+        expectations.append(&mut mock_single_byte_size_params(1, index));
+        expectations.append(&mut mock_end_byte());
+        expectations.append(&mut mock_padding(2));
+        expectations.append(&mut mock_receive(command, 0x1, values));
+    }
+
+    expectations
+}
+
+#[test]
+fn join_too_long_utf8_ssid_returns_ssid_too_long_error() {
+    let spi = BytewiseSpiMock::new(&[]);
+
+    let delay = MockNoop::new();
+
+    let pins = EspControlMock {};
+
+    let mut wifi = Wifi::init(spi, pins, delay).ok().unwrap();
+
+    // 11 CJK characters encode to 33 bytes
+    assert_eq!(
+        wifi.join("無線網路無線網路無線網", "passphrase")
+            .unwrap_err(),
+        esp32_wroom_core::Error::Network(NetworkError::SsidTooLong)
+    );
+
+    wifi.destroy().done();
+}
+
+// Supplies credentials like a secure element would, reporting `reported_ssid_length` instead of
+// the actual length of the SSID if it's set.
+struct ProvidedCredentials {
+    ssid: &'static [u8],
+    passphrase: &'static [u8],
+    reported_ssid_length: Option<usize>,
+}
+
+impl CredentialProvider for ProvidedCredentials {
+    fn ssid(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        buf[..self.ssid.len()].copy_from_slice(self.ssid);
+        Ok(self.reported_ssid_length.unwrap_or(self.ssid.len()))
+    }
+
+    fn passphrase(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        buf[..self.passphrase.len()].copy_from_slice(self.passphrase);
+        Ok(self.passphrase.len())
+    }
+}
+
+#[test]
+fn join_with_sends_the_provided_credentials() {
+    let set_passphrase_command = 0x11;
+    let number_of_params = 0x2;
+    let number_of_params_to_receive = 0x1;
+
+    let mut expectations = mock_command(set_passphrase_command, number_of_params);
+    expectations.append(&mut mock_single_byte_size_params(4, 0x46)); // SSID is "FFFF"
+    expectations.append(&mut mock_single_byte_size_params(8, 0x46)); // Passphrase is "FFFFFFFF"
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_padding(2));
+    expectations.append(&mut mock_receive(
+        set_passphrase_command,
+        number_of_params_to_receive,
+        &[0x1],
+    ));
+
+    let spi = BytewiseSpiMock::new(&expectations);
+
+    let delay = MockNoop::new();
+
+    let pins = EspControlMock {};
+
+    let mut wifi = Wifi::init(spi, pins, delay).ok().unwrap();
+
+    let mut credentials = ProvidedCredentials {
+        ssid: b"FFFF",
+        passphrase: b"FFFFFFFF",
+        reported_ssid_length: None,
+    };
+
+    wifi.join_with(&mut credentials).unwrap();
+
+    wifi.destroy().done();
+}
+
+#[test]
+fn join_with_provider_length_beyond_the_buffer_returns_credentials_unavailable_error() {
+    let spi = BytewiseSpiMock::new(&[]);
+
+    let delay = MockNoop::new();
+
+    let pins = EspControlMock {};
+
+    let mut wifi = Wifi::init(spi, pins, delay).ok().unwrap();
+
+    let mut credentials = ProvidedCredentials {
+        ssid: b"FFFF",
+        passphrase: b"FFFFFFFF",
+        reported_ssid_length: Some(MAX_SSID_LENGTH + 1),
+    };
+
+    assert_eq!(
+        wifi.join_with(&mut credentials).unwrap_err(),
+        Error::Network(NetworkError::CredentialsUnavailable)
+    );
+
+    wifi.destroy().done();
+}
+
+#[test]
+fn join_enterprise_with_ca_certificate_returns_unsupported_operation_error() {
+    let spi = BytewiseSpiMock::new(&[]);
+
+    let delay = MockNoop::new();
+
+    let pins = EspControlMock {};
+
+    let mut wifi = Wifi::init(spi, pins, delay).ok().unwrap();
+
+    let config = EnterpriseConfig::new("alice", "secret").ca_certificate(b"-----BEGIN");
+
+    assert_eq!(
+        wifi.join_enterprise("campus", &config).unwrap_err(),
+        esp32_wroom_core::Error::UnsupportedOperation
+    );
+
+    wifi.destroy().done();
+}
+
+struct CountingYield {
+    count: AtomicU32,
+}
+
+impl Yield for CountingYield {
+    fn yield_now(&self) {
+        self.count.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+static COUNTING_YIELD: CountingYield = CountingYield {
+    count: AtomicU32::new(0),
+};
+
+#[test]
+fn set_yield_invokes_yield_before_sending_and_receiving_a_command() {
+    let get_fw_version_command = 0x37;
+    let number_of_params = 0x0;
+    let number_of_params_to_receive = 0x1;
+
+    let mut expectations = mock_command(get_fw_version_command, number_of_params);
+
+    expectations.append(&mut mock_end_byte());
+
+    expectations.append(&mut mock_receive(
+        get_fw_version_command,
+        number_of_params_to_receive,
+        &[0x31, 0x2e, 0x37, 0x2e, 0x34], // "1.7.4"
+    ));
+
+    let spi = BytewiseSpiMock::new(&expectations);
+
+    let delay = MockNoop::new();
+
+    let pins = EspControlMock {};
+
+    let mut wifi = Wifi::init(spi, pins, delay).ok().unwrap();
+    wifi.set_yield(&COUNTING_YIELD);
+
+    wifi.firmware_version().unwrap();
+
+    assert_eq!(COUNTING_YIELD.count.load(Ordering::Relaxed), 2);
+
+    wifi.destroy().done();
+}
+
+struct CountingActivityIndicator {
+    transmits: AtomicU32,
+    receives: AtomicU32,
+}
+
+impl ActivityIndicator for CountingActivityIndicator {
+    fn on_activity(&self, activity: Activity) {
+        match activity {
+            Activity::Transmit => self.transmits.fetch_add(1, Ordering::Relaxed),
+            Activity::Receive => self.receives.fetch_add(1, Ordering::Relaxed),
+            _ => 0,
+        };
+    }
+}
+
+static COUNTING_ACTIVITY_INDICATOR: CountingActivityIndicator = CountingActivityIndicator {
+    transmits: AtomicU32::new(0),
+    receives: AtomicU32::new(0),
+};
+
+#[test]
+fn set_activity_indicator_is_notified_about_socket_data_activity() {
+    // ----- get_data_buf_tcp -----
+
+    let get_data_buf_tcp_command = 0x45;
+    let number_of_params = 0x2;
+
+    let mut expectations = mock_command(get_data_buf_tcp_command, number_of_params);
+    expectations.append(&mut mock_two_byte_size_params(&[0x0])); // Send Socket
+    expectations.append(&mut mock_two_byte_size_params(&[0x2, 0x0])); // Send requested length
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_padding(1));
+    expectations.append(&mut mock_receive_data(get_data_buf_tcp_command, b"hi"));
+
+    // ----- send_data_tcp -----
+
+    let send_data_tcp_command = 0x44;
+
+    expectations.append(&mut mock_command(send_data_tcp_command, number_of_params));
+    expectations.append(&mut mock_two_byte_size_params(&[0x0])); // Send Socket
+    expectations.append(&mut mock_two_byte_size_params(b"hi")); // Send data
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_padding(1));
+    expectations.append(&mut mock_receive(send_data_tcp_command, 0x1, &[0x2]));
+
+    let spi = BytewiseSpiMock::new(&expectations);
+
+    let delay = MockNoop::new();
+
+    let pins = EspControlMock {};
+
+    let mut wifi = Wifi::init(spi, pins, delay).ok().unwrap();
+    wifi.set_activity_indicator(&COUNTING_ACTIVITY_INDICATOR);
+
+    let mut tcp_server = TcpServer::build(&mut wifi);
+    let mut buf = [0u8; 2];
+    tcp_server.receive_data(0, &mut buf).unwrap();
+    tcp_server.send_data(0, &buf).unwrap();
+
+    assert_eq!(
+        COUNTING_ACTIVITY_INDICATOR.receives.load(Ordering::Relaxed),
+        1
+    );
+    assert_eq!(
+        COUNTING_ACTIVITY_INDICATOR
+            .transmits
+            .load(Ordering::Relaxed),
+        1
+    );
+
+    wifi.destroy().done();
+}
+
+#[test]
+fn init_with_buffer_discards_the_excess_of_an_oversized_reply_through_the_lent_buffer() {
+    // ----- get_data_buf_tcp, with more data than was requested -----
+
+    let get_data_buf_tcp_command = 0x45;
+    let number_of_params = 0x2;
+
+    let mut expectations = mock_command(get_data_buf_tcp_command, number_of_params);
+    expectations.append(&mut mock_two_byte_size_params(&[0x0])); // Send Socket
+    expectations.append(&mut mock_two_byte_size_params(&[0x2, 0x0])); // Send requested length
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_padding(1));
+    expectations.append(&mut mock_receive_data(get_data_buf_tcp_command, b"hello"));
+
+    // ----- get_conn_status, still aligned with the firmware -----
+
+    let get_conn_status_command = 0x20;
+
+    expectations.append(&mut mock_command(get_conn_status_command, 0x0));
+    expectations.append(&mut mock_end_byte());
+    expectations.append(&mut mock_receive(get_conn_status_command, 0x1, &[0x3]));
+
+    let spi = BytewiseSpiMock::new(&expectations);
+
+    let delay = MockNoop::new();
+
+    let pins = EspControlMock {};
+
+    let buffer: &'static mut [u8] = Box::leak(Box::new([0u8; 2]));
+    let mut wifi = Wifi::init_with_buffer(spi, pins, delay, buffer)
+        .ok()
+        .unwrap();
+
+    let mut buf = [0u8; 2];
+    assert_eq!(
+        TcpServer::build(&mut wifi).receive_data(0, &mut buf),
+        Err(Error::Protocol(ProtocolError::PayloadTooLarge))
+    );
+    assert_eq!(buf, *b"he");
+
+    assert_eq!(
+        wifi.get_connection_status().unwrap(),
+        ConnectionStatus::Connected
+    );
+
+    wifi.destroy().done();
+}
+
+struct RecordingEventHandler {
+    events: Mutex<Vec<WifiEvent>>,
+}
+
+impl WifiEventHandler for RecordingEventHandler {
+    fn on_event(&self, event: WifiEvent) {
+        self.events.lock().unwrap().push(event);
+    }
+}
+
+static RECORDING_EVENT_HANDLER: RecordingEventHandler = RecordingEventHandler {
+    events: Mutex::new(Vec::new()),
+};
+
+#[test]
+fn set_event_handler_is_notified_about_connection_status_transitions() {
+    let get_conn_status_command = 0x20;
+    let get_ip_addr_command = 0x21;
+    let number_of_params_to_receive = 0x1;
+
+    let mut expectations = Vec::new();
+
+    // Connected, still connected, then ConnectionStatus::Lost
+    for (index, status) in [0x3, 0x3, 0x5].into_iter().enumerate() {
+        // ----- get_conn_status -----
+
+        expectations.append(&mut mock_command(get_conn_status_command, 0x0));
+        expectations.append(&mut mock_end_byte());
+        expectations.append(&mut mock_receive(
+            get_conn_status_command,
+            number_of_params_to_receive,
+            &[status],
+        ));
+
+        // ----- get_ip_config, only right after connecting -----
+
+        if index == 0 {
+            expectations.append(&mut mock_command(get_ip_addr_command, 0x1));
+            expectations.append(&mut mock_single_byte_size_params(1, 0xff)); // Send dummy
+            expectations.append(&mut mock_end_byte());
+            expectations.append(&mut mock_padding(2));
+            expectations.append(&mut mock_receive_params(
+                get_ip_addr_command,
+                &[&[192, 168, 1, 42], &[255, 255, 255, 0], &[192, 168, 1, 1]],
+            ));
+        }
+    }
+
+    let spi = BytewiseSpiMock::new(&expectations);
+
+    let delay = MockNoop::new();
+
+    let pins = EspControlMock {};
+
+    let mut wifi = Wifi::init(spi, pins, delay).ok().unwrap();
+    wifi.set_event_handler(&RECORDING_EVENT_HANDLER);
+
+    for _ in 0..3 {
+        wifi.get_connection_status().unwrap();
+    }
+
+    assert_eq!(
+        *RECORDING_EVENT_HANDLER.events.lock().unwrap(),
+        vec![
+            WifiEvent::Connected,
+            WifiEvent::GotIp(Ipv4Addr::new(192, 168, 1, 42)),
+            WifiEvent::Disconnected
+        ]
+    );
+
+    wifi.destroy().done();
+}
+
+#[test]
+fn two_wifi_instances_communicate_independently() {
+    let get_conn_status_command = 0x20;
+    let number_of_params = 0x0;
+    let number_of_params_to_receive = 0x1;
+
+    // Each target replies with a different connection status
+    let mock_spi = |status: u8| {
+        let mut expectations = mock_command(get_conn_status_command, number_of_params);
+        expectations.append(&mut mock_end_byte());
+        expectations.append(&mut mock_receive(
+            get_conn_status_command,
+            number_of_params_to_receive,
+            &[status],
+        ));
+        BytewiseSpiMock::new(&expectations)
+    };
+
+    let uplink_spi = mock_spi(0x3); // ConnectionStatus::Connected
+    let access_point_spi = mock_spi(0x7); // ConnectionStatus::ApListening
+
+    let mut uplink = Wifi::init(uplink_spi, EspControlMock {}, MockNoop::new()).unwrap();
+    let mut access_point =
+        Wifi::init(access_point_spi, EspControlMock {}, MockNoop::new()).unwrap();
+
+    assert_eq!(
+        access_point.get_connection_status().unwrap(),
+        ConnectionStatus::ApListening
+    );
+    assert_eq!(
+        uplink.get_connection_status().unwrap(),
+        ConnectionStatus::Connected
+    );
+    assert_eq!(uplink.recovery_stats(), access_point.recovery_stats());
+
+    uplink.destroy().done();
+    access_point.destroy().done();
+}
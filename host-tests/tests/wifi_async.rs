@@ -0,0 +1,132 @@
+use core::convert::Infallible;
+use core::future::Future;
+use core::pin::pin;
+use core::task::{Context, Poll, Waker};
+
+use std::collections::VecDeque;
+
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_async::spi::{ErrorType, SpiBus};
+
+use esp32_wroom_core::gpio::AsyncEspControlInterface;
+use esp32_wroom_core::wifi::ConnectionStatus;
+use esp32_wroom_core::wifi_async::AsyncWifi;
+
+// Records every byte sent over the bus and answers reads with the queued reply bytes.
+#[derive(Default)]
+struct SpiBusMock {
+    sent: Vec<u8>,
+    replies: VecDeque<u8>,
+}
+
+impl ErrorType for SpiBusMock {
+    type Error = Infallible;
+}
+
+impl SpiBus<u8> for SpiBusMock {
+    async fn read(&mut self, words: &mut [u8]) -> Result<(), Infallible> {
+        self.transfer_in_place(words).await
+    }
+
+    async fn write(&mut self, words: &[u8]) -> Result<(), Infallible> {
+        self.sent.extend_from_slice(words);
+        Ok(())
+    }
+
+    async fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), Infallible> {
+        self.write(write).await?;
+        self.read(read).await
+    }
+
+    async fn transfer_in_place(&mut self, words: &mut [u8]) -> Result<(), Infallible> {
+        self.sent.extend_from_slice(words);
+        for word in words.iter_mut() {
+            *word = self.replies.pop_front().unwrap_or(0xff);
+        }
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+}
+
+struct AsyncEspControlMock {}
+
+impl AsyncEspControlInterface for AsyncEspControlMock {
+    fn init(&mut self) {}
+
+    async fn reset<D: DelayNs>(&mut self, _delay: &mut D) {}
+
+    async fn wait_for_esp_select(&mut self) {}
+
+    fn esp_deselect(&mut self) {}
+}
+
+struct DelayMock {}
+
+impl DelayNs for DelayMock {
+    async fn delay_ns(&mut self, _ns: u32) {}
+}
+
+// Polls `future` until it completes. The futures under test never wait on anything but the
+// mocked ESP32 target, so they can simply be polled again right away.
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let mut context = Context::from_waker(Waker::noop());
+
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+            return output;
+        }
+    }
+}
+
+#[test]
+fn get_connection_status_sends_command_and_parses_reply() {
+    let spi = SpiBusMock {
+        replies: VecDeque::from([0xe0, 0xa0, 0x01, 0x01, 0x03, 0xee]),
+        ..Default::default()
+    };
+
+    let mut wifi = block_on(AsyncWifi::init(
+        spi,
+        AsyncEspControlMock {},
+        &mut DelayMock {},
+    ))
+    .unwrap();
+
+    assert_eq!(
+        block_on(wifi.get_connection_status()).unwrap(),
+        ConnectionStatus::Connected
+    );
+
+    let spi = wifi.destroy();
+    assert_eq!(spi.sent[..4], [0xe0, 0x20, 0x00, 0xee]);
+    assert!(spi.replies.is_empty());
+}
+
+#[test]
+fn join_pads_command_and_reads_reply() {
+    let spi = SpiBusMock {
+        // The 2 bytes padding the command are answered with dummies ahead of the reply
+        replies: VecDeque::from([0xff, 0xff, 0xe0, 0x91, 0x01, 0x01, 0x01, 0xee]),
+        ..Default::default()
+    };
+
+    let mut wifi = block_on(AsyncWifi::init(
+        spi,
+        AsyncEspControlMock {},
+        &mut DelayMock {},
+    ))
+    .unwrap();
+
+    block_on(wifi.join("ab", "cd")).unwrap();
+
+    let spi = wifi.destroy();
+    assert_eq!(
+        spi.sent[..12],
+        [0xe0, 0x11, 0x02, 0x02, b'a', b'b', 0x02, b'c', b'd', 0xee, 0xff, 0xff]
+    );
+    assert!(spi.replies.is_empty());
+}